@@ -0,0 +1,186 @@
+//! Serves a web page showing the answers to each day's puzzle, which
+//! are computed when the page for that day is requested.
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{value_parser, Arg, Command};
+use tiny_http::{Header, Request, Response, Server};
+
+use lib::cpu::parse_program;
+use lib::days::day15::{explore, RepairDroid};
+use lib::days::{input_path, solver, DAYS};
+use lib::error::Fail;
+use lib::grid::Position;
+use lib::input::read_file_as_string;
+use lib::usage::measure;
+
+fn escape_html(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n",
+            "<body><h1>{0}</h1>\n{1}</body></html>\n"
+        ),
+        escape_html(title),
+        body
+    )
+}
+
+/// Show an answer; answers of more than one line (such as the
+/// images of days 8 and 11) are shown preformatted.
+fn format_answer(answer: &str) -> String {
+    if answer.contains('\n') {
+        format!("<pre>{}</pre>", escape_html(answer))
+    } else {
+        format!("<code>{}</code>", escape_html(answer))
+    }
+}
+
+fn index_page(inputs: &Path) -> String {
+    let mut body = String::from("<table>\n<tr><th>Day</th><th>Input</th></tr>\n");
+    for day in DAYS.iter() {
+        let status = if day.has_input(inputs) {
+            "present"
+        } else {
+            "missing"
+        };
+        // Writing to a String cannot fail.
+        let _ = writeln!(
+            body,
+            "<tr><td><a href=\"/day/{0}\">Day {0}</a></td><td>{1}</td></tr>",
+            day.number, status
+        );
+    }
+    body.push_str("</table>\n");
+    page("Advent of Code 2019", &body)
+}
+
+fn day_page(inputs: &Path, day: u8) -> Result<String, Fail> {
+    let solve = solver(day).ok_or_else(|| Fail(format!("day {} is not solved", day)))?;
+    let input = read_file_as_string(&input_path(inputs, day))?;
+    let (result, usage) = measure(|| solve(&input));
+    let mut body = String::new();
+    match result {
+        Ok((part1, part2)) => {
+            let _ = writeln!(body, "<h2>Part 1</h2>\n{}", format_answer(&part1));
+            let part2 = match part2 {
+                Some(answer) => format_answer(&answer),
+                None => "<em>not solved</em>".to_string(),
+            };
+            let _ = writeln!(body, "<h2>Part 2</h2>\n{}", part2);
+        }
+        Err(e) => {
+            let _ = writeln!(body, "<p>Failed: {}</p>", escape_html(&e.to_string()));
+        }
+    }
+    let _ = writeln!(body, "<p>Time: {:.3}s</p>", usage.elapsed.as_secs_f64());
+    if let Some(bytes) = usage.peak_rss {
+        let _ = writeln!(
+            body,
+            "<p>Peak memory: {:.1} MiB</p>",
+            bytes as f64 / (1024.0 * 1024.0)
+        );
+    }
+    if day == 15 {
+        body.push_str("<p><a href=\"/day/15/map\">Map of the ship</a></p>\n");
+    }
+    body.push_str("<p><a href=\"/\">All days</a></p>\n");
+    Ok(page(&format!("Day {}", day), &body))
+}
+
+fn ship_map_page(inputs: &Path) -> Result<String, Fail> {
+    let program = parse_program(&read_file_as_string(&input_path(inputs, 15))?)?;
+    let droid = RepairDroid::new(&program)?;
+    let (ship_map, _) = explore(&Position { x: 0, y: 0 }, droid, |_, _| ())?;
+    let body = format!(
+        "<pre>{}</pre>\n<p><a href=\"/day/15\">Day 15</a></p>\n",
+        escape_html(&ship_map.to_string())
+    );
+    Ok(page("Day 15: map of the ship", &body))
+}
+
+fn html_response(status: u16, html: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "text/html; charset=utf-8")
+        .expect("the Content-Type header should be valid");
+    Response::from_string(html)
+        .with_status_code(status)
+        .with_header(content_type)
+}
+
+fn handle(request: Request, inputs: &Path) {
+    let path: Vec<&str> = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or("")
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect();
+    let result: Option<Result<String, Fail>> = match path.as_slice() {
+        [] => Some(Ok(index_page(inputs))),
+        ["day", "15", "map"] => Some(ship_map_page(inputs)),
+        ["day", day] => day.parse().ok().map(|day| day_page(inputs, day)),
+        _ => None,
+    };
+    let response = match result {
+        Some(Ok(html)) => html_response(200, html),
+        Some(Err(e)) => html_response(
+            500,
+            page("Error", &format!("<p>{}</p>", escape_html(&e.to_string()))),
+        ),
+        None => html_response(404, page("Not found", "<p><a href=\"/\">All days</a></p>")),
+    };
+    if let Err(e) = request.respond(response) {
+        eprintln!("failed to send response: {}", e);
+    }
+}
+
+fn main() -> Result<(), Fail> {
+    let matches = Command::new("aoc-serve")
+        .author("James Youngman, james@youngman.org")
+        .about("Serves the answers to the Advent of Code 2019 puzzles as a web page")
+        .arg(
+            Arg::new("port")
+                .long("port")
+                .value_name("PORT")
+                .takes_value(true)
+                .value_parser(value_parser!(u16))
+                .default_value("8019")
+                .help("Listen on PORT"),
+        )
+        .arg(
+            Arg::new("inputs")
+                .long("inputs")
+                .value_name("DIR")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .default_value("inputs")
+                .help("Read the input of day N from DIR/dayNN.txt"),
+        )
+        .get_matches();
+    let port: u16 = *matches.get_one::<u16>("port").expect("port has a default");
+    let inputs: PathBuf = matches
+        .value_of_os("inputs")
+        .map(PathBuf::from)
+        .expect("inputs has a default");
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| Fail(format!("failed to listen on port {}: {}", port, e)))?;
+    println!("Serving on http://127.0.0.1:{}/", port);
+    for request in server.incoming_requests() {
+        handle(request, &inputs);
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Tools for working on the whole set of puzzles, rather than one
+//! day at a time.
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, Command};
+
+use lib::days::{day, input_path, LAST_DAY};
+use lib::error::Fail;
+use lib::scaffold::create_day;
+
+/// Show which days are implemented, how many parts of each are
+/// solved and whether each has an input file in `inputs`.
+fn list(inputs: &Path) {
+    println!("{:>3}  {:<36}  {:<5}  Input", "Day", "Title", "Parts");
+    for number in 1..=LAST_DAY {
+        match day(number) {
+            Some(d) => {
+                let input = if d.has_input(inputs) {
+                    "present"
+                } else {
+                    "missing"
+                };
+                println!(
+                    "{:>3}  {:<36}  {:<5}  {}",
+                    number,
+                    d.title,
+                    format!("{}/2", d.parts_solved),
+                    input
+                );
+            }
+            None => println!("{:>3}  (not implemented)", number),
+        }
+    }
+}
+
+/// Create and register the files of day `number` in the crate at
+/// `root`.
+fn new_day(root: &Path, number: u8, title: &str) -> Result<(), Fail> {
+    for path in create_day(root, number, title)? {
+        println!("wrote {}", path.display());
+    }
+    println!(
+        "Put the puzzle input in {}",
+        input_path(Path::new("inputs"), number).display()
+    );
+    Ok(())
+}
+
+fn inputs_dir(matches: &ArgMatches) -> &Path {
+    matches
+        .value_of_os("inputs")
+        .map(Path::new)
+        .expect("inputs has a default")
+}
+
+fn main() -> Result<(), Fail> {
+    let inputs_arg = Arg::new("inputs")
+        .long("inputs")
+        .value_name("DIR")
+        .takes_value(true)
+        .allow_invalid_utf8(true)
+        .default_value("inputs")
+        .help("Look for the input of day N in DIR/dayNN.txt");
+    let matches = Command::new("aoc")
+        .author("James Youngman, james@youngman.org")
+        .about("Tools for the Advent of Code 2019 puzzles as a whole")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("list")
+                .about("List the days, showing which are solved")
+                .arg(inputs_arg),
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Create the files for a new day and register it")
+                .arg(
+                    Arg::new("day")
+                        .required(true)
+                        .value_parser(clap::value_parser!(u8).range(1..=i64::from(LAST_DAY)))
+                        .help("The number of the day"),
+                )
+                .arg(
+                    Arg::new("title")
+                        .long("title")
+                        .value_name("TITLE")
+                        .takes_value(true)
+                        .default_value("Untitled")
+                        .help("The title of the day's puzzle"),
+                )
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .allow_invalid_utf8(true)
+                        .default_value(".")
+                        .help("The top directory of the crate (containing Cargo.toml)"),
+                ),
+        )
+        .get_matches();
+    match matches.subcommand() {
+        Some(("list", m)) => {
+            list(inputs_dir(m));
+            Ok(())
+        }
+        Some(("new", m)) => new_day(
+            m.value_of_os("root")
+                .map(Path::new)
+                .expect("root has a default"),
+            *m.get_one::<u8>("day").expect("day is required"),
+            m.get_one::<String>("title").expect("title has a default"),
+        ),
+        _ => unreachable!("clap requires a known subcommand"),
+    }
+}
@@ -1,50 +1,16 @@
-use lib::cpu::Word;
-use lib::cpu::{read_program_from_file, InputOutputError, Processor};
-use lib::input::run_with_input;
+use lib::days::day05::solve;
+use lib::error::{Fail, ResultExt};
+use lib::input::{read_file_as_string, run_with_input};
 
-use lib::error::Fail;
-
-fn run_program(program: &[Word], input_word: Word) -> Result<Vec<Word>, Fail> {
-    let mut cpu = Processor::new(Word(0));
-    cpu.load(Word(0), program)
-        .map_err(|e| Fail(e.to_string()))?;
-    let mut output_words = Vec::new();
-    let mut output = |w: Word| -> Result<(), InputOutputError> {
-        output_words.push(w);
-        Ok(())
-    };
-    let input: Vec<Word> = vec![input_word];
-    if let Err(e) = cpu.run_with_fixed_input(&input, &mut output) {
-        Err(Fail(format!("program should be valid: {:?}", e)))
-    } else {
-        Ok(output_words)
-    }
-}
-
-fn part1(program: &[Word]) -> Result<(), Fail> {
-    print!("Day 2 part 1:");
-    for w in run_program(program, Word(1))? {
-        print!(" {}", w);
+fn run(input: String) -> Result<(), Fail> {
+    let (part1, part2) = solve(&input).context("failed to solve day 5")?;
+    println!("Day 2 part 1: {}", part1);
+    if let Some(part2) = part2 {
+        println!("Day 2 part 2: {}", part2);
     }
-    println!();
-    Ok(())
-}
-
-fn part2(program: &[Word]) -> Result<(), Fail> {
-    print!("Day 2 part 2:");
-    for w in run_program(program, Word(5))? {
-        print!(" {}", w);
-    }
-    println!();
-    Ok(())
-}
-
-fn run(words: Vec<Word>) -> Result<(), Fail> {
-    part1(&words)?;
-    part2(&words)?;
     Ok(())
 }
 
 fn main() -> Result<(), Fail> {
-    run_with_input(5, read_program_from_file, run)
+    run_with_input(5, read_file_as_string, run)
 }
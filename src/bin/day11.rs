@@ -1,257 +1,102 @@
-use std::collections::HashMap;
-use std::fmt::Display;
-use std::sync::{Arc, Mutex};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
-use lib::cpu::{read_program_from_file, CpuFault, InputOutputError, Processor, Word};
-use lib::error::Fail;
-use lib::input::run_with_input;
+use clap::{Arg, ArgMatches};
 
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone)]
-struct Panel {
-    x: i32,
-    y: i32,
-}
+use lib::cpu::parse_program;
+use lib::days::day11::{paint, solve, PaintColour, ShipSurface};
+use lib::error::{Fail, ResultExt};
+use lib::input::{read_file_as_string, run_with_input_and_options};
 
-impl Panel {
-    fn up(&self) -> Panel {
-        Panel {
-            y: self.y - 1,
-            ..*self
-        }
-    }
-    fn down(&self) -> Panel {
-        Panel {
-            y: self.y + 1,
-            ..*self
-        }
-    }
-    fn right(&self) -> Panel {
-        Panel {
-            x: self.x + 1,
-            ..*self
-        }
-    }
-    fn left(&self) -> Panel {
-        Panel {
-            x: self.x - 1,
-            ..*self
-        }
-    }
-}
-
-impl Display for Panel {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{},{}", self.x, self.y)
-    }
-}
-
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
-enum PaintColour {
-    White,
-    Black,
-}
-
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
-enum PaintStatus {
-    PaintedWhite,
-    PaintedBlack,
-}
-
-#[derive(Debug)]
-struct ShipSurface {
-    panels: HashMap<Panel, PaintStatus>,
-    total_painted_panels: usize,
-}
-
-impl ShipSurface {
-    fn new() -> ShipSurface {
-        ShipSurface {
-            panels: HashMap::new(),
-            total_painted_panels: 0,
-        }
-    }
-
-    fn get_painted_panel_count(&self) -> usize {
-        self.total_painted_panels
-    }
-
-    fn paint_panel(&mut self, location: Panel, colour: PaintColour) {
-        let new_state: PaintStatus = match colour {
-            PaintColour::White => PaintStatus::PaintedWhite,
-            PaintColour::Black => PaintStatus::PaintedBlack,
-        };
-        if self.panels.insert(location, new_state).is_none() {
-            self.total_painted_panels += 1;
-        }
-    }
+/// Each panel is drawn as a square this many pixels wide in PNG
+/// output.
+const PNG_SCALE: u32 = 8;
 
-    fn get_panel_colour(&self, location: &Panel) -> PaintColour {
-        match self.panels.get(location) {
-            Some(PaintStatus::PaintedWhite) => PaintColour::White,
-            _ => PaintColour::Black,
-        }
-    }
+/// Options which make the robot paint just once, instead of solving
+/// both parts of the puzzle.
+struct PaintOptions<'a> {
+    start_colour: PaintColour,
+    out: Option<&'a Path>,
+    count_only: bool,
 }
 
-impl Display for ShipSurface {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let max_x = self.panels.keys().map(|p| p.x).max();
-        let max_y = self.panels.keys().map(|p| p.y).max();
-        let (max_x, max_y) = match (max_x, max_y) {
-            (None, _) | (_, None) => {
-                // Nothing to display: empty!
-                return Ok(());
-            }
-            (Some(max_x), Some(max_y)) => (max_x, max_y),
-        };
-
-        for y in 0..=max_y {
-            for x in 0..=max_x {
-                let colour = self.get_panel_colour(&Panel { x, y });
-                write!(
-                    f,
-                    "{}",
-                    match colour {
-                        PaintColour::Black => ' ',
-                        PaintColour::White => '#',
-                    }
-                )?;
-            }
-            f.write_str("\n")?;
-        }
-        Ok(())
+fn save_surface(surface: &ShipSurface, path: &Path) -> Result<(), Fail> {
+    let is_png = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let fail = |e: std::io::Error| Fail(format!("failed to write {}: {}", path.display(), e));
+    if is_png {
+        let mut out = BufWriter::new(File::create(path).map_err(fail)?);
+        surface
+            .write_png(&mut out, PNG_SCALE)
+            .and_then(|()| out.flush())
+            .map_err(fail)
+    } else {
+        fs::write(path, surface.to_string()).map_err(fail)
     }
 }
 
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
-enum Heading {
-    Up,
-    Right,
-    Down,
-    Left,
-}
-
-fn perform_turn_and_move(
-    w: Word,
-    heading: &mut Heading,
-    location: &mut Panel,
-) -> Result<(), InputOutputError> {
-    let right: bool = w.0 != 0;
-    use Heading::*;
-    match heading {
-        Heading::Up => {
-            *heading = if right { Right } else { Left };
-            *location = if right {
-                location.right()
-            } else {
-                location.left()
-            };
-        }
-        Heading::Right => {
-            *heading = if right { Down } else { Up };
-            *location = if right {
-                location.down()
-            } else {
-                location.up()
-            };
-        }
-        Heading::Down => {
-            *heading = if right { Left } else { Right };
-            *location = if right {
-                location.left()
-            } else {
-                location.right()
-            };
-        }
-        Heading::Left => {
-            *heading = if right { Up } else { Down };
-            *location = if right {
-                location.up()
-            } else {
-                location.down()
-            };
-        }
+fn paint_once(input: &str, options: &PaintOptions) -> Result<(), Fail> {
+    let program = parse_program(input).context("failed to load the program")?;
+    let surface = paint(&program, options.start_colour).context("failed to run the robot")?;
+    println!("panels painted: {}", surface.get_painted_panel_count());
+    if let Some(path) = options.out {
+        save_surface(&surface, path)?;
+    } else if !options.count_only {
+        print!("{}", surface);
     }
     Ok(())
 }
 
-fn run_robot(
-    start: Panel,
-    start_colour: PaintColour,
-    surface: &mut ShipSurface,
-    program: &[Word],
-) -> Result<Panel, CpuFault> {
-    let panel_colour = Arc::new(Mutex::new(start_colour));
-
-    let mut get_input = || -> Result<Word, InputOutputError> {
-        match *panel_colour.lock().unwrap() {
-            PaintColour::Black => Ok(Word(0)),
-            PaintColour::White => Ok(Word(1)),
-        }
-    };
-
-    let mut moving: bool = false;
-    let mut location: Panel = start;
-    let mut heading = Heading::Up;
-
-    let mut do_output = |w: Word| -> Result<(), InputOutputError> {
-        let new_colour = if moving {
-            perform_turn_and_move(w, &mut heading, &mut location)?;
-            surface.get_panel_colour(&location)
-        } else {
-            let new_colour: PaintColour = match w {
-                Word(0) => PaintColour::Black,
-                Word(1) => PaintColour::White,
-                _ => {
-                    // invalid; ignore it.
-                    return Ok(());
-                }
-            };
-            surface.paint_panel(location.clone(), new_colour);
-            new_colour
-        };
-        moving = !moving;
-        *panel_colour.lock().unwrap() = new_colour;
-        Ok(())
-    };
-
-    let mut cpu: Processor = Processor::new(Word(0));
-    cpu.load(Word(0), program)?;
-    cpu.run_with_io(&mut get_input, &mut do_output)?;
-    Ok(location)
-}
-
-fn part1(program: &[Word]) -> Result<(), Fail> {
-    let start = Panel { x: 0, y: 0 };
-    let mut surface = ShipSurface::new();
-    if let Err(e) = run_robot(start, PaintColour::Black, &mut surface, program) {
-        Err(e.into())
-    } else {
-        println!(
-            "Day 11 part 1: panels painted: {}",
-            surface.get_painted_panel_count()
-        );
-        Ok(())
+fn run(input: String, options: Option<&PaintOptions>) -> Result<(), Fail> {
+    if let Some(options) = options {
+        return paint_once(&input, options);
+    }
+    let (part1, part2) = solve(&input).context("failed to solve day 11")?;
+    println!("Day 11 part 1: panels painted: {}", part1);
+    if let Some(part2) = part2 {
+        println!("Day 11 part 2\n{}", part2);
     }
+    Ok(())
 }
 
-fn part2(program: &[Word]) -> Result<(), Fail> {
-    let start = Panel { x: 0, y: 0 };
-    let mut surface = ShipSurface::new();
-    if let Err(e) = run_robot(start, PaintColour::White, &mut surface, program) {
-        Err(e.into())
-    } else {
-        println!("Day 11 part 2\n{}", surface);
-        Ok(())
+fn paint_options(matches: &ArgMatches) -> Option<PaintOptions<'_>> {
+    let start_colour = matches.get_one::<String>("start-colour");
+    let out = matches.value_of_os("out").map(Path::new);
+    let count_only = matches.is_present("count-only");
+    if start_colour.is_none() && out.is_none() && !count_only {
+        return None;
     }
+    Some(PaintOptions {
+        start_colour: match start_colour.map(String::as_str) {
+            Some("white") => PaintColour::White,
+            _ => PaintColour::Black,
+        },
+        out,
+        count_only,
+    })
 }
 
 fn main() -> Result<(), Fail> {
-    fn run(words: Vec<Word>) -> Result<(), Fail> {
-        part1(&words)?;
-        part2(&words)?;
-        Ok(())
-    }
-
-    run_with_input(11, read_program_from_file, run)
+    let options = vec![
+        Arg::new("start-colour")
+            .long("start-colour")
+            .value_name("COLOUR")
+            .takes_value(true)
+            .value_parser(["black", "white"])
+            .help("Paint once, starting on a panel of this colour (black if not given), instead of solving the puzzle"),
+        Arg::new("out")
+            .long("out")
+            .value_name("FILE")
+            .takes_value(true)
+            .allow_invalid_utf8(true)
+            .help("Paint once and save the picture in FILE, as a PNG image if FILE ends in .png and as text otherwise"),
+        Arg::new("count-only")
+            .long("count-only")
+            .help("Paint once and show only the number of panels painted"),
+    ];
+    run_with_input_and_options(11, options, read_file_as_string, |input, matches| {
+        run(input, paint_options(matches).as_ref())
+    })
 }
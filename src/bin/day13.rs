@@ -1,242 +1,238 @@
-use pancurses::{endwin, initscr, Window};
-use std::cmp::Ordering;
-use std::collections::HashSet;
-use std::fmt::{self, Display, Formatter};
-use std::fs::OpenOptions;
-use std::sync::Arc;
-use std::sync::Mutex;
+use clap::Arg;
+use pancurses::{endwin, initscr, noecho, Input, Window};
+use std::path::Path;
 use std::{thread, time};
 
-use lib::cpu::{read_program_from_file, CpuFault, InputOutputError, Processor, Word};
-use lib::error::Fail;
-use lib::input::run_with_input;
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
-struct Position {
-    x: Word,
-    y: Word,
+use lib::cpu::{read_program_from_file, Word};
+use lib::days::day13::{
+    indicator, part1, play, strategy_named, tile_symbol, Controller, Move, Outcome, Player,
+    SavedGame, Strategy, STRATEGY_NAMES,
+};
+use lib::error::{Fail, ResultExt};
+use lib::grid::Position;
+use lib::input::run_with_input_and_options;
+use lib::screen::{Screen, Viewport};
+
+/// How long to wait for a key press in each frame of a game played
+/// with `--play`.
+const PLAY_FRAME_MILLIS: i32 = 150;
+
+/// The number of rows below the game kept for the score and hints.
+const INFO_ROWS: i32 = 3;
+
+/// If the game does not fit the terminal, the display scrolls to
+/// keep the ball at least this far from its edge.
+const FOLLOW_MARGIN: usize = 3;
+
+/// Shows the game screen on the terminal.
+struct Terminal {
+    window: Window,
+    /// The part of the game screen which fits on the terminal.
+    view: Viewport,
+    reads_keys: bool,
+    hint: String,
+    /// The user can ask for the game to be saved by pressing 's'.
+    pausable: bool,
+    /// The user moves the paddle with the arrow keys.
+    play: bool,
+    /// Moves the paddle when the user is not playing.
+    strategy: Box<dyn Strategy>,
+    /// How many times the joystick has been read.
+    moves: usize,
 }
 
-impl Display for Position {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{},{}", self.x, self.y)
+impl Terminal {
+    fn new(pausable: bool, play: bool, strategy: Box<dyn Strategy>) -> Terminal {
+        let window = initscr();
+        let reads_keys = pausable || play;
+        if reads_keys {
+            noecho();
+            window.keypad(true);
+            window.timeout(if play { PLAY_FRAME_MILLIS } else { 0 });
+        }
+        let mut hints: Vec<&str> = Vec::new();
+        if play {
+            hints.push("arrow keys move the paddle");
+        }
+        if pausable {
+            hints.push("press s to save and quit");
+        }
+        Terminal {
+            window,
+            view: Viewport::new(Position { x: 0, y: 0 }, 0, 0),
+            reads_keys,
+            hint: hints.join("; "),
+            pausable,
+            play,
+            strategy,
+            moves: 0,
+        }
     }
-}
-
-#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
-enum DrawCommand {
-    DrawTile { pos: Position, tile: Word },
-    UpdateScore(Word),
-}
 
-struct DisplayCommandInterpreter {
-    pending: Vec<Word>,
-}
-
-impl DisplayCommandInterpreter {
-    fn new() -> DisplayCommandInterpreter {
-        DisplayCommandInterpreter {
-            pending: Vec::with_capacity(3),
-        }
+    fn done(self) {
+        thread::sleep(time::Duration::from_millis(4000));
+        endwin();
     }
 
-    fn put(&mut self, item: Word) -> Option<DrawCommand> {
-        assert!(self.pending.len() < 3);
-        self.pending.push(item);
-        match self.pending.as_slice() {
-            [Word(-1), Word(0), score] => {
-                let result = DrawCommand::UpdateScore(*score);
-                self.pending.clear();
-                Some(result)
-            }
-            [x, y, tile] => {
-                let result = DrawCommand::DrawTile {
-                    pos: Position { x: *x, y: *y },
-                    tile: *tile,
-                };
-                self.pending.clear();
-                Some(result)
+    /// Show the game, scrolling if necessary to keep `focus` (the
+    /// ball) in view.
+    fn show(&mut self, screen: &mut Screen, indicator: &str, focus: Option<Position>) {
+        let (rows, columns) = self.window.get_max_yx();
+        let changed = self.view.update(
+            usize::try_from(columns).unwrap_or(0),
+            usize::try_from(rows - INFO_ROWS).unwrap_or(0),
+            focus,
+            FOLLOW_MARGIN,
+        );
+        let dirty = screen.take_dirty();
+        let to_draw = if changed {
+            self.window.clear();
+            self.view.rect()
+        } else {
+            dirty
+        };
+        if let Some(rect) = to_draw {
+            for pos in rect.positions() {
+                if let Some((row, column)) = self.view.to_screen(&pos) {
+                    self.window
+                        .mvaddch(row, column, tile_symbol(screen.get(&pos)));
+                }
             }
-            _ => None,
         }
+        let info_row: i32 = screen.height().min(self.view.height()) as i32 + 1;
+        self.window.mvprintw(info_row, 0, indicator);
+        if screen.take_score_dirty() || changed {
+            let score = format!("{:>10}", screen.score().unwrap_or(0));
+            self.window.mvprintw(info_row, 20, score);
+        }
+        if !self.hint.is_empty() {
+            self.window.mvprintw(info_row + 1, 0, &self.hint);
+        }
+        self.window.refresh();
     }
-}
 
-fn part1(program: &[Word]) -> Result<(), CpuFault> {
-    fn run(program: &[Word], disp: &mut DisplayCommandInterpreter) -> Result<usize, CpuFault> {
-        let mut blocks: HashSet<Position> = HashSet::new();
-        let mut get_input = || Ok(Word(0));
-        let mut do_output = |w: Word| -> Result<(), InputOutputError> {
-            if let Some(DrawCommand::DrawTile { pos, tile: Word(2) }) = disp.put(w) {
-                blocks.insert(pos);
-            }
-            Ok(())
-        };
-        let mut cpu = Processor::new(Word(0));
-        cpu.load(Word(0), program)?;
-        cpu.run_with_io(&mut get_input, &mut do_output)?;
-        Ok(blocks.len())
+    /// The key pressed by the user, if any.
+    fn read_key(&mut self) -> Option<Input> {
+        if self.reads_keys {
+            self.window.getch()
+        } else {
+            None
+        }
     }
-
-    let mut disp_interp = DisplayCommandInterpreter::new();
-    let block_count = run(program, &mut disp_interp)?;
-    println!("Day 13 part 1: block count is {}", block_count);
-    Ok(())
 }
 
-struct GameState {
-    bat: Word,
-    ball: Word,
-    score: Word,
-    window: Option<Window>,
-}
-
-impl GameState {
-    fn new() -> GameState {
-        GameState {
-            bat: Word(0),
-            ball: Word(0),
-            score: Word(0),
-            window: None,
+impl Controller for Terminal {
+    fn next_move(&mut self, screen: &mut Screen, player: &Player) -> Move {
+        let key = self.read_key();
+        let joystick = if self.play {
+            human_joystick(key.as_ref())
+        } else {
+            self.strategy.joystick(player)
+        };
+        self.moves += 1;
+        self.show(screen, indicator(joystick), Some(player.ball));
+        Move {
+            joystick,
+            indicator: indicator(joystick),
+            save: self.pausable && key == Some(Input::Character('s')),
         }
     }
 
-    fn init(&mut self) {
-        let w = initscr();
-        self.window = Some(w);
-    }
-
-    fn done(&mut self) {
-        if self.window.is_some() {
-            thread::sleep(time::Duration::from_millis(4000));
-            endwin();
-        }
+    fn finished(&mut self, screen: &mut Screen) {
+        self.show(screen, " ", None);
     }
+}
 
-    fn update_from(&mut self, update: Option<DrawCommand>) {
-        match update {
-            Some(DrawCommand::UpdateScore(newscore)) => {
-                self.score = newscore;
-            }
-            Some(DrawCommand::DrawTile { pos, tile: Word(3) }) => {
-                self.bat = pos.x;
-            }
-            Some(DrawCommand::DrawTile { pos, tile: Word(4) }) => {
-                self.ball = pos.x;
-            }
-            _ => (),
-        }
-        if let Some(w) = self.window.as_mut() {
-            match update {
-                None | Some(DrawCommand::UpdateScore(_)) => (),
-                Some(DrawCommand::DrawTile { pos, tile }) => {
-                    let symbol: &str = match tile.0 {
-                        0 => " ", // empty
-                        1 => "|", // wall
-                        2 => "#", // block
-                        3 => "=", // paddle
-                        4 => "o", // ball
-                        _ => unreachable!(),
-                    };
-                    w.mvprintw(pos.y.0 as i32, pos.x.0 as i32, symbol);
-                    w.refresh();
-                }
-            }
-        }
+/// The joystick position chosen by a human player pressing `key`.
+fn human_joystick(key: Option<&Input>) -> Word {
+    match key {
+        Some(Input::KeyLeft) => Word(-1),
+        Some(Input::KeyRight) => Word(1),
+        _ => Word(0),
     }
 }
 
-fn part2(program: &[Word]) -> Result<(), CpuFault> {
-    fn run(
-        program: &[Word],
-        disp: &mut DisplayCommandInterpreter,
-        state: &Arc<Mutex<GameState>>,
-    ) -> Result<Word, CpuFault> {
-        let mut get_input = || -> Result<Word, InputOutputError> {
-            let mut state = state.lock().unwrap();
-            let score = format!("{:>10}", state.score);
-            let (joystick_pos, indicator) = match state.bat.cmp(&state.ball) {
-                Ordering::Less => {
-                    // move joystick right
-                    (Word(1), ">")
-                }
-                Ordering::Equal => {
-                    // neutral
-                    (Word(0), "^")
-                }
-                Ordering::Greater => {
-                    // move joystick left
-                    (Word(-1), "<")
-                }
-            };
-            if let Some(w) = state.window.as_mut() {
-                const INFO_ROW: i32 = 26;
-                w.mvprintw(INFO_ROW, 0, indicator);
-                w.mvprintw(INFO_ROW, 20, score);
-            }
-            //thread::sleep(time::Duration::from_millis(100));
-            Ok(joystick_pos)
-        };
-        let mut do_output = |w: Word| -> Result<(), InputOutputError> {
-            match state.lock() {
-                Ok(mut state) => {
-                    state.update_from(disp.put(w));
-                }
-                Err(e) => {
-                    panic!("lock poisoned: {}", e);
-                }
-            }
-            Ok(())
-        };
-        let mut cpu = Processor::new(Word(0));
-        cpu.load(Word(0), program)?;
-        //println!("Memory before inserting coin:\n{:?}", &cpu.ram());
-        cpu.load(Word(0), &[Word(2)])?; // insert coin.
-                                        //println!("Memory after inserting coin:\n{:?}", &cpu.ram());
-
-        const TRACE_FILE_NAME: &str = "/tmp/aoc-2019-day13-part2-trace-Rust.txt";
-        match OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(TRACE_FILE_NAME)
-        {
-            Ok(file) => {
-                cpu.enable_tracing(file);
-            }
-            Err(e) => {
-                return Err(CpuFault::TraceError(format!(
-                    "failed to open trace file {} for writing: {}",
-                    TRACE_FILE_NAME, e
-                )));
-            }
-        }
-        cpu.run_with_io(&mut get_input, &mut do_output)?;
-        Ok(state.lock().unwrap().score)
-    }
+/// Options for part 2.
+struct GameOptions<'a> {
+    /// Resume this game instead of starting a new one.
+    saved: Option<SavedGame>,
+    /// Allow the game to be saved in this file.
+    save_to: Option<&'a Path>,
+    /// A human controls the paddle instead of the computer.
+    play: bool,
+    /// How the computer moves the paddle.
+    strategy: Box<dyn Strategy>,
+}
 
-    let state: Arc<Mutex<GameState>> = Arc::new(Mutex::new(GameState::new()));
-    state.lock().unwrap().init();
-    let mut disp_interp = DisplayCommandInterpreter::new();
-    let result = run(program, &mut disp_interp, &state);
-    state.lock().unwrap().done();
+fn part2(program: &[Word], options: GameOptions) -> Result<(), Fail> {
+    let save_to = options.save_to;
+    let mut display = Terminal::new(save_to.is_some(), options.play, options.strategy);
+    let result = play(program, options.saved, &mut display).context("failed to play the game");
+    let moves = display.moves;
+    display.done();
     match result {
-        Ok(score) => {
-            println!("Day 13 part 2: score is {}", score);
+        Ok(Outcome::Finished(score)) => {
+            println!("Day 13 part 2: score is {} after {} moves", score, moves);
             Ok(())
         }
-        Err(e) => {
-            eprintln!("part2: cpu fault: {}", e);
-            Err(e)
+        Ok(Outcome::Lost(score, loss)) => Err(Fail(format!(
+            "Day 13 part 2: game lost with score {} after {} moves: {}",
+            score, moves, loss
+        ))),
+        Ok(Outcome::Saved(game)) => {
+            if let Some(path) = save_to {
+                game.save(path)?;
+                println!("Day 13 part 2: game saved in {}", path.display());
+            }
+            Ok(())
         }
+        Err(e) => Err(e.into()),
     }
 }
 
 fn main() -> Result<(), Fail> {
-    fn run(words: Vec<Word>) -> Result<(), Fail> {
-        part1(&words)?;
-        part2(&words)?;
-        Ok(())
-    }
-
-    run_with_input(13, read_program_from_file, run)
+    let options = vec![
+        Arg::new("save-state")
+            .long("save-state")
+            .value_name("FILE")
+            .takes_value(true)
+            .allow_invalid_utf8(true)
+            .help("Allow the part 2 game to be paused with 's', saving it in FILE"),
+        Arg::new("load-state")
+            .long("load-state")
+            .value_name("FILE")
+            .takes_value(true)
+            .allow_invalid_utf8(true)
+            .help("Resume a part 2 game previously saved in FILE"),
+        Arg::new("play")
+            .long("play")
+            .help("Play part 2 yourself, using the arrow keys"),
+        Arg::new("strategy")
+            .long("strategy")
+            .value_name("NAME")
+            .takes_value(true)
+            .value_parser(STRATEGY_NAMES)
+            .default_value(STRATEGY_NAMES[0])
+            .help("How the computer moves the paddle in part 2"),
+    ];
+    run_with_input_and_options(13, options, read_program_from_file, |words, matches| {
+        println!(
+            "Day 13 part 1: block count is {}",
+            part1(&words).context("failed to solve day 13 part 1")?
+        );
+        let saved: Option<SavedGame> = match matches.value_of_os("load-state") {
+            Some(name) => Some(SavedGame::load(Path::new(name))?),
+            None => None,
+        };
+        let options = GameOptions {
+            saved,
+            save_to: matches.value_of_os("save-state").map(Path::new),
+            play: matches.is_present("play"),
+            strategy: matches
+                .get_one::<String>("strategy")
+                .and_then(|name| strategy_named(name))
+                .ok_or_else(|| Fail("unknown strategy".to_string()))?,
+        };
+        part2(&words, options)
+    })
 }
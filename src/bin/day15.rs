@@ -1,558 +1,292 @@
-use pancurses::{endwin, initscr, Window};
-use std::collections::HashMap;
+use pancurses::{endwin, flushinp, initscr, noecho, Input, Window};
 use std::collections::HashSet;
-use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
 use std::thread;
-use std::time::Duration;
-
-use lib::cpu::Processor;
-use lib::cpu::Word;
-use lib::cpu::{read_program_from_file, CpuFault, CpuStatus, InputOutputError, ProgramLoadError};
-use lib::grid;
-use lib::input::{run_with_input, InputError};
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
-enum RoomType {
-    Wall,
-    Open(bool),
-    Goal,
-    Start,
-}
-
-impl From<RoomType> for char {
-    fn from(rt: RoomType) -> char {
-        match rt {
-            RoomType::Start => 'S',
-            RoomType::Wall => '#',
-            RoomType::Open(filled) => {
-                if filled {
-                    'O'
-                } else {
-                    '.'
-                }
-            }
-            RoomType::Goal => 'X',
-        }
-    }
-}
-
-#[derive(Debug)]
-struct BadMap(String);
-
-impl TryFrom<char> for RoomType {
-    type Error = BadMap;
-    fn try_from(ch: char) -> Result<RoomType, BadMap> {
-        match ch {
-            'S' => Ok(RoomType::Start),
-            '#' => Ok(RoomType::Wall),
-            'O' => Ok(RoomType::Open(true)),
-            '.' => Ok(RoomType::Open(false)),
-            ' ' => Ok(RoomType::Wall),
-            'X' => Ok(RoomType::Goal),
-            _ => Err(BadMap(format!("unexpected character '{}'", ch))),
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-struct Movements {
-    steps: Vec<CompassDirection>,
+use std::time::{Duration, Instant};
+
+use clap::{value_parser, Arg};
+
+use lib::cpu::{read_program_from_file, Word};
+use lib::days::day15::{explore, part2, replay, Movements, RepairDroid, RoomType, ShipMap};
+use lib::error::{Fail, ResultExt};
+use lib::grid::Position;
+use lib::input::run_with_input_and_options;
+use lib::screen::{quadrant_glyph, Viewport};
+use lib::warning;
+
+/// The number of rows at the top of the terminal kept for messages.
+const MESSAGE_ROWS: i32 = 1;
+
+/// The display scrolls to keep the droid at least this far from its
+/// edge.
+const FOLLOW_MARGIN: usize = 5;
+
+/// How far the arrow keys move the view, in characters.
+const PAN_STEP: i64 = 4;
+
+/// Shown when the map is complete, while the user inspects it.
+const FINISHED_MESSAGE: &str =
+    "** FINISHED: ARROWS PAN, Z ZOOMS, F FOLLOWS THE DROID, Q CONTINUES **";
+
+/// Start curses, reading keys without waiting for them.
+fn open_terminal() -> Window {
+    let window = initscr();
+    noecho();
+    window.keypad(true);
+    window.nodelay(true);
+    window
 }
 
-impl Movements {
-    fn empty() -> Movements {
-        Movements { steps: Vec::new() }
-    }
-
-    fn len(&self) -> usize {
-        self.steps.len()
-    }
-
-    fn push_step(&mut self, step: &CompassDirection) {
-        self.steps.push(*step);
-    }
-
-    fn pop(&mut self) -> Option<CompassDirection> {
-        self.steps.pop()
-    }
-
-    fn compute_path_locations(&self, origin: &Position) -> Vec<Position> {
-        self.steps
-            .iter()
-            .fold((*origin, vec![*origin]), |(here, mut path), direction| {
-                let next = here.move_direction(direction);
-                path.push(next);
-                (next, path)
-            })
-            .1
-    }
+/// The width and height of the part of `w` which shows the map.
+fn map_area(w: &Window) -> (usize, usize) {
+    let (rows, columns) = w.get_max_yx();
+    (
+        usize::try_from(columns).unwrap_or(0),
+        usize::try_from(rows - MESSAGE_ROWS).unwrap_or(0),
+    )
 }
 
-use grid::{CompassDirection, Position, ALL_MOVE_OPTIONS};
-
-struct ShipMap {
-    tiles: HashMap<grid::Position, RoomType>,
-    goal: Option<Position>,
+/// The part of the map which is shown.  The user can pan the view
+/// with the arrow keys and zoom out with 'z', which shows four
+/// positions of the map in each character.
+struct MapView {
+    view: Viewport,
+    /// Whether the view follows the droid.  Panning stops this, and
+    /// 'f' starts it again.
+    following: bool,
 }
 
-impl ShipMap {
-    fn new(start: Position) -> ShipMap {
-        let mut tiles = HashMap::new();
-        tiles.insert(start, RoomType::Start);
-        ShipMap { tiles, goal: None }
-    }
-
-    fn add_location(&mut self, pos: Position, t: RoomType) {
-        if t == RoomType::Goal {
-            self.goal = Some(pos);
+impl MapView {
+    /// A view of the map centred on `centre`, which fits the terminal.
+    fn new(w: &Window, centre: Position) -> MapView {
+        let (width, height) = map_area(w);
+        MapView {
+            view: Viewport::centred_on(centre, width, height),
+            following: true,
         }
-        self.tiles.insert(pos, t);
     }
 
-    fn oxygen_fill(&mut self, pos: Position) {
-        if let Some(RoomType::Open(filled)) = self.tiles.get_mut(&pos) {
-            *filled = true;
+    /// Act on `key`, returning false if it is not one of ours.
+    fn handle_key(&mut self, key: &Input) -> bool {
+        let step = PAN_STEP * self.view.zoom() as i64;
+        match key {
+            Input::KeyLeft => self.view.pan(-step, 0),
+            Input::KeyRight => self.view.pan(step, 0),
+            Input::KeyUp => self.view.pan(0, -step),
+            Input::KeyDown => self.view.pan(0, step),
+            Input::Character('z') => {
+                self.view
+                    .set_zoom(if self.view.zoom() == 1 { 2 } else { 1 });
+                return true;
+            }
+            Input::Character('f') => {
+                self.following = true;
+                return true;
+            }
+            _ => {
+                return false;
+            }
         }
+        self.following = false;
+        true
     }
 
-    fn get_location_type(&self, pos: &Position) -> Option<&RoomType> {
-        match self.goal.as_ref() {
-            Some(g) if g == pos => Some(&RoomType::Goal),
-            _ => self.tiles.get(pos),
+    /// Show the part of `map` in view in `w`, with `path` (which
+    /// starts at `start`) highlighted.  The view follows the droid,
+    /// which is at the end of the path, unless the user has panned
+    /// away from it.  Keys pressed since the last call are acted on
+    /// first.
+    fn show(&mut self, map: &ShipMap, w: &mut Window, start: &Position, path: &Movements) {
+        while let Some(key) = w.getch() {
+            self.handle_key(&key);
         }
-    }
-
-    fn get_open_rooms(&self) -> HashSet<Position> {
-        self.tiles
-            .iter()
-            .filter_map(|(pos, room_type)| match room_type {
-                RoomType::Start | RoomType::Open(_) | RoomType::Goal => Some(*pos),
-                RoomType::Wall => None,
-            })
-            .collect()
-    }
-
-    fn options_from(&self, pos: &Position) -> Vec<CompassDirection> {
-        ALL_MOVE_OPTIONS
-            .iter()
-            .filter(|direction| !self.tiles.contains_key(&pos.move_direction(direction)))
-            .copied()
-            .collect()
-    }
-
-    fn is_known_to_be_the_goal(&self, pos: &Position) -> bool {
-        self.goal.as_ref().map(|p| p == pos).unwrap_or(false)
-    }
-
-    fn display(&self, w: &mut Window, start: &Position, path: &Movements) {
-        const HALF_WIDTH: i64 = 30;
-        const HALF_HEIGHT: i64 = 30;
-        let path_locations: HashSet<Position> =
-            path.compute_path_locations(start).into_iter().collect();
-        for y in (-HALF_HEIGHT)..(HALF_HEIGHT - 1) {
-            let row: String = ((-HALF_WIDTH)..(HALF_WIDTH - 1))
-                .map(|x: i64| -> char {
-                    let here = Position { x, y };
-                    if x == 0 && y == 0 {
-                        '@' // the droid
-                    } else if path_locations.contains(&here) {
+        let path_locations: Vec<Position> = path.compute_path_locations(start);
+        let droid: Position = path_locations.last().copied().unwrap_or(*start);
+        let path_locations: HashSet<Position> = path_locations.into_iter().collect();
+        let (width, height) = map_area(w);
+        let focus = if self.following { Some(droid) } else { None };
+        self.view.update(width, height, focus, FOLLOW_MARGIN);
+        let is_wall = |pos: &Position| matches!(map.get_location_type(pos), Some(RoomType::Wall));
+        for row in 0..self.view.height() {
+            let line: String = (0..self.view.width())
+                .map(|column| -> char {
+                    let cells = self.view.cells_at(row, column);
+                    if cells.contains(&droid) {
+                        '@'
+                    } else if cells.positions().any(|p| path_locations.contains(&p)) {
                         '*'
-                    } else {
-                        self.get_location_type(&here)
+                    } else if self.view.zoom() == 1 {
+                        map.get_location_type(&cells.top_left)
                             .map(|t| (*t).into())
                             .unwrap_or(' ')
+                    } else {
+                        let walls: Vec<bool> = cells.positions().map(|p| is_wall(&p)).collect();
+                        quadrant_glyph(walls[0], walls[1], walls[2], walls[3])
                     }
                 })
                 .collect();
-            match (y + HALF_HEIGHT + 1).try_into() {
-                Ok(screen_row) => {
-                    w.mvprintw(screen_row, 0, row);
-                }
-                Err(_) => {
-                    panic!("unexpected screen_row overflow");
-                }
-            }
+            w.mvprintw(row as i32 + MESSAGE_ROWS, 0, line);
         }
         w.refresh();
     }
-}
 
-impl Display for ShipMap {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match grid::bounds(self.tiles.keys().chain(self.goal.iter())) {
-            Some((min, max)) => {
-                for y in min.y..=max.y {
-                    let row: String = (min.x..=max.x)
-                        .map(|x: i64| -> char {
-                            let here = Position { x, y };
-                            if x == 0 && y == 0 {
-                                '@' // the droid
-                            } else {
-                                self.get_location_type(&here)
-                                    .map(|t| (*t).into())
-                                    .unwrap_or(' ')
-                            }
-                        })
-                        .collect();
-                    writeln!(f, "{}", row)?;
-                }
-                Ok(())
-            }
-            None => {
-                // Empty; nothing to display.
-                Ok(())
+    /// Let the user look around the finished map until they press a
+    /// key which is not one of ours.
+    fn inspect(&mut self, map: &ShipMap, w: &mut Window, start: &Position, path: &Movements) {
+        flushinp();
+        w.nodelay(false);
+        loop {
+            self.show(map, w, start, path);
+            w.mvprintw(0, 0, FINISHED_MESSAGE);
+            w.refresh();
+            match w.getch() {
+                Some(key) if self.handle_key(&key) => (),
+                _ => break,
             }
         }
+        w.nodelay(true);
     }
 }
 
-impl TryFrom<&str> for ShipMap {
-    type Error = BadMap;
-    fn try_from(s: &str) -> Result<ShipMap, BadMap> {
-        let mut result = ShipMap::new(Position { x: 0, y: 0 });
-        for (y, line) in s.split('\n').enumerate() {
-            for (x, ch) in line.chars().enumerate() {
-                let t: RoomType = RoomType::try_from(ch)?;
-                result.add_location(
-                    Position {
-                        x: x as i64,
-                        y: y as i64,
-                    },
-                    t,
-                );
-            }
-        }
-        Ok(result)
-    }
-}
-
-struct MoveResult {
-    moved: bool,
-    new_location: Position,
-    cpu_status: CpuStatus,
+/// Limits the rate at which the map is redrawn.
+struct FramePacer {
+    interval: Option<Duration>,
+    last_frame: Option<Instant>,
 }
 
-struct RepairDroid {
-    cpu: Processor,
-}
-
-impl RepairDroid {
-    fn new(program: &[Word]) -> Result<RepairDroid, CpuFault> {
-        let mut cpu = Processor::new(Word(0));
-        cpu.load(Word(0), program)?;
-        Ok(RepairDroid { cpu })
-    }
-
-    fn move_droid(
-        &mut self,
-        current_position: &Position,
-        which_way: &CompassDirection,
-        ship_map: &mut ShipMap,
-    ) -> Result<MoveResult, CpuFault> {
-        enum RunResult {
-            Running(Word),
-            Stopped,
-        }
-        let mut run_until_output = |which_way: &CompassDirection| -> Result<RunResult, CpuFault> {
-            let mut input_word: Option<Word> = Some(match which_way {
-                CompassDirection::North => Word(1),
-                CompassDirection::South => Word(2),
-                CompassDirection::West => Word(3),
-                CompassDirection::East => Word(4),
-            });
-            let mut do_input = || -> Result<Word, InputOutputError> {
-                match input_word.take() {
-                    Some(w) => Ok(w),
-                    None => {
-                        panic!("RepairDroid::move: program read more than one input word");
-                    }
-                }
-            };
-
-            loop {
-                let mut output_word: Option<Word> = None;
-                let mut do_output = |w: Word| -> Result<(), InputOutputError> {
-                    output_word = Some(w);
-                    Ok(())
-                };
-                match self.cpu.execute_instruction(&mut do_input, &mut do_output) {
-                    Err(e) => return Err(e),
-                    Ok(CpuStatus::Halt) => return Ok(RunResult::Stopped),
-                    Ok(CpuStatus::Run) => (),
-                }
-                if let Some(w) = output_word.as_ref() {
-                    return Ok(RunResult::Running(*w));
-                }
-            }
-        };
-
-        let target = current_position.move_direction(which_way);
-        match run_until_output(which_way) {
-            Err(e) => Err(e),
-            Ok(RunResult::Stopped) => Ok(MoveResult {
-                cpu_status: CpuStatus::Halt,
-                moved: false,
-                new_location: *current_position,
-            }),
-            Ok(RunResult::Running(w)) => match w {
-                Word(0) => {
-                    ship_map.add_location(target, RoomType::Wall);
-                    Ok(MoveResult {
-                        cpu_status: CpuStatus::Run,
-                        moved: false,
-                        new_location: *current_position,
-                    })
-                }
-                Word(1 | 2) => {
-                    ship_map.add_location(
-                        target,
-                        if w == Word(1) {
-                            RoomType::Open(false)
-                        } else {
-                            RoomType::Goal
-                        },
-                    );
-                    Ok(MoveResult {
-                        cpu_status: CpuStatus::Run,
-                        moved: true,
-                        new_location: target,
-                    })
-                }
-                other => {
-                    panic!("program generated unexpected output {}", other);
-                }
-            },
+impl FramePacer {
+    /// Show at most `fps` frames per second; if `fps` is None, don't
+    /// limit the frame rate.
+    fn new(fps: Option<u32>) -> FramePacer {
+        FramePacer {
+            interval: fps.filter(|n| *n > 0).map(|n| Duration::from_secs(1) / n),
+            last_frame: None,
         }
     }
-}
 
-fn shortest_path_to_goal(
-    start: &Position,
-    current_position: &Position,
-    mut current_path: Movements,
-    droid: &mut RepairDroid,
-    ship_map: &mut ShipMap,
-    window: &mut Window,
-) -> Result<Option<Movements>, CpuFault> {
-    ship_map.display(window, start, &current_path);
-    if ship_map.is_known_to_be_the_goal(current_position) {
-        return Ok(Some(current_path.clone()));
-    }
-    let mut best_path: Option<Movements> = None;
-    for direction in ship_map.options_from(current_position) {
-        match droid.move_droid(current_position, &direction, ship_map)? {
-            MoveResult {
-                cpu_status: CpuStatus::Halt,
-                ..
-            } => {
-                panic!("droid CPU halted during move");
-            }
-            MoveResult {
-                moved: false,
-                cpu_status: CpuStatus::Run,
-                ..
-            } => (),
-            MoveResult {
-                moved: true,
-                new_location,
-                cpu_status: CpuStatus::Run,
-            } => {
-                current_path.push_step(&direction);
-                match (
-                    best_path.as_ref(),
-                    shortest_path_to_goal(
-                        start,
-                        &new_location,
-                        current_path.clone(),
-                        droid,
-                        ship_map,
-                        window,
-                    )?,
-                ) {
-                    (_, None) => (),
-                    (None, Some(new_path)) => {
-                        best_path = Some(new_path);
-                    }
-                    (Some(existing), Some(new_path)) => {
-                        if new_path.len() < existing.len() {
-                            best_path = Some(new_path);
-                        }
-                    }
-                }
-                let before_retracing_steps: Position = new_location;
-                match droid.move_droid(&new_location, &direction.reversed(), ship_map)? {
-                    MoveResult {
-                        cpu_status: CpuStatus::Halt,
-                        ..
-                    } => {
-                        panic!("droid CPU halted while retracing steps");
-                    }
-                    MoveResult {
-                        cpu_status: CpuStatus::Run,
-                        new_location,
-                        ..
-                    } => {
-                        current_path.pop();
-                        if new_location == before_retracing_steps {
-                            panic!("droid hit a wall where we don't think there is a wall");
-                        } else if &new_location != current_position {
-                            panic!("droid went in an unexpected direction when retracing steps");
-                        }
-                    }
-                }
+    /// Wait until it is time to show the next frame.
+    fn wait(&mut self) {
+        if let (Some(interval), Some(last)) = (self.interval, self.last_frame) {
+            if let Some(remaining) = interval.checked_sub(last.elapsed()) {
+                thread::sleep(remaining);
             }
         }
+        self.last_frame = Some(Instant::now());
     }
-    Ok(best_path)
 }
 
 fn part1(
     start: &Position,
-    droid: &mut RepairDroid,
+    droid: RepairDroid,
     window: &mut Window,
-) -> Result<Option<(ShipMap, usize)>, CpuFault> {
-    let mut ship_map = ShipMap::new(*start);
-    let result = shortest_path_to_goal(
-        start,
-        start,
-        Movements::empty(),
-        droid,
-        &mut ship_map,
-        window,
-    );
-    if let Ok(Some(shortest)) = result.as_ref() {
-        ship_map.display(window, start, shortest);
+    pacer: &mut FramePacer,
+) -> Result<Option<(ShipMap, Movements)>, Fail> {
+    let empty_movements: Movements = Movements::empty();
+    let mut view = MapView::new(window, *start);
+    let result = explore(start, droid, |ship_map, path| {
+        pacer.wait();
+        view.show(ship_map, window, start, path.unwrap_or(&empty_movements));
+    });
+    match result.as_ref() {
+        Ok((ship_map, Some(shortest))) => {
+            view.inspect(ship_map, window, start, shortest);
+        }
+        Ok((ship_map, None)) => {
+            view.inspect(ship_map, window, start, &empty_movements);
+        }
+        Err(_) => (),
     }
-    window.mvprintw(0, 0, "** FINISHED : PRESS A KEY TO CONTINUE **");
-    window.refresh();
-    thread::sleep(Duration::from_millis(4000));
-    window.getch();
     match result {
-        Err(e) => Err(e),
-        Ok(Some(path)) => Ok(Some((ship_map, path.len()))),
-        Ok(None) => {
-            eprintln!("Day 15 part 1: did not find a solution");
+        Err(e) => Err(e.into()),
+        Ok((ship_map, Some(path))) => Ok(Some((ship_map, path))),
+        Ok((_, None)) => {
+            warning!("Day 15 part 1: did not find a solution");
             Ok(None)
         }
     }
 }
 
-fn advance(boundary: &HashSet<Position>, open_rooms: &HashSet<Position>) -> HashSet<Position> {
-    boundary
-        .iter()
-        .flat_map(|pos| {
-            ALL_MOVE_OPTIONS.iter().filter_map(|direction| {
-                let next_pos = pos.move_direction(direction);
-                if open_rooms.contains(&next_pos) {
-                    Some(next_pos)
-                } else {
-                    None
-                }
-            })
-        })
-        .collect()
+/// Command-line options.
+struct Options<'a> {
+    /// Limit the display to this many frames per second.
+    fps: Option<u32>,
+    /// Save the map of the ship in this file once it is explored.
+    save_map: Option<&'a Path>,
+    /// Instead of exploring the ship, drive the droid along this path.
+    replay_path: Option<Movements>,
 }
 
-fn part2<F>(start: &Position, ship_map: &mut ShipMap, mut display_state: F) -> usize
-where
-    F: FnMut(usize, usize, &ShipMap),
-{
-    let mut boundary: HashSet<Position> = HashSet::new();
-    let mut to_fill: HashSet<Position> = ship_map.get_open_rooms();
-    boundary.insert(*start);
-    to_fill.remove(start);
-    let mut fill_count: usize = 1;
-    for step_number in 0.. {
-        display_state(step_number, fill_count, ship_map);
-        if to_fill.is_empty() {
-            return step_number;
-        }
-        let new_boundary: HashSet<Position> = advance(&boundary, &to_fill);
-        for filled_pos in new_boundary.iter() {
-            ship_map.oxygen_fill(*filled_pos);
-            to_fill.remove(filled_pos);
-            fill_count += 1;
-        }
-        boundary = new_boundary;
+/// Show the droid following `path`.
+fn replay_path(program: &[Word], path: &Movements, options: &Options) -> Result<(), Fail> {
+    let start = Position { x: 0, y: 0 };
+    let droid = RepairDroid::new(program).context("failed to start the repair droid")?;
+    let mut pacer = FramePacer::new(options.fps);
+    let mut window = open_terminal();
+    let mut view = MapView::new(&window, start);
+    let result = replay(&start, droid, path, |ship_map, followed| {
+        pacer.wait();
+        view.show(ship_map, &mut window, &start, followed);
+    });
+    if let Ok((ship_map, _)) = result.as_ref() {
+        view.inspect(ship_map, &mut window, &start, path);
     }
-    unreachable!()
-}
-
-#[test]
-fn test_part2() {
-    let mut sm = ShipMap::try_from(concat!(
-        " ##   \n", "#..## \n", "#.#..#\n", "#.X.# \n", " ###  \n",
-    ))
-    .expect("test input should be valid");
-    let oxy = Position { x: 2, y: 3 };
-    let display_map = |step: usize, occupied: usize, sm: &ShipMap| {
-        println!("Step {}: {} cells occupied:\n{}", step, occupied, sm);
+    endwin();
+    let (ship_map, end) = result?;
+    let what = if ship_map.goal() == Some(end) {
+        " (the oxygen system)"
+    } else {
+        ""
     };
-    assert_eq!(part2(&oxy, &mut sm, display_map), 4);
-}
-
-#[derive(Debug)]
-enum Fail {
-    CpuFault(CpuFault),
-    InputError(InputError),
-    ProgramLoadError(ProgramLoadError),
-}
-
-impl Display for Fail {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            Fail::CpuFault(e) => write!(f, "cpu fault: {}", e),
-            Fail::InputError(e) => write!(f, "input error: {}", e),
-            Fail::ProgramLoadError(e) => write!(f, "failed to load program: {}", e),
-        }
-    }
-}
-
-impl From<CpuFault> for Fail {
-    fn from(e: CpuFault) -> Fail {
-        Fail::CpuFault(e)
-    }
-}
-
-impl From<InputError> for Fail {
-    fn from(e: InputError) -> Fail {
-        Fail::InputError(e)
-    }
-}
-
-impl From<ProgramLoadError> for Fail {
-    fn from(e: ProgramLoadError) -> Fail {
-        Fail::ProgramLoadError(e)
-    }
+    println!(
+        "Day 15: after {} steps the droid is at {}{}",
+        path.len(),
+        end,
+        what
+    );
+    Ok(())
 }
 
-impl std::error::Error for Fail {}
-
-fn run(words: Vec<Word>) -> Result<(), Fail> {
+fn run(words: Vec<Word>, options: &Options) -> Result<(), Fail> {
     let program = &words;
+    if let Some(path) = options.replay_path.as_ref() {
+        return replay_path(program, path, options);
+    }
     let start = Position { x: 0, y: 0 };
-    let mut droid = RepairDroid::new(program)?;
-    let mut window = initscr();
-    let result_msg: Result<String, CpuFault> = match part1(&start, &mut droid, &mut window) {
-        Ok(Some((mut ship_map, part1_path_len))) => match ship_map.goal {
+    let droid = RepairDroid::new(program).context("failed to start the repair droid")?;
+    let mut pacer = FramePacer::new(options.fps);
+    let mut window = open_terminal();
+    let result_msg: Result<String, Fail> = match part1(&start, droid, &mut window, &mut pacer) {
+        Ok(Some((mut ship_map, part1_path))) => match ship_map.goal() {
             Some(g) => {
+                let saved = match options.save_map {
+                    Some(path) => fs::write(path, ship_map.to_string()).map_err(|e| {
+                        Fail(format!("failed to save map to {}: {}", path.display(), e))
+                    }),
+                    None => Ok(()),
+                };
                 let empty_movements: Movements = Movements::empty();
+                let mut view = MapView::new(&window, g);
                 let step = part2(
                     &g,
                     &mut ship_map,
                     |_step: usize, _occ: usize, map: &ShipMap| {
-                        map.display(&mut window, &g, &empty_movements)
+                        pacer.wait();
+                        view.show(map, &mut window, &g, &empty_movements)
                     },
                 );
+                view.inspect(&ship_map, &mut window, &g, &empty_movements);
                 endwin();
-                Ok(format!(
-                    "Day 15 part 1: path length is {}\nDay 15 part 2: fill at step {}",
-                    part1_path_len, step
-                ))
-            }
-            None => {
-                panic!("no oxygen system");
+                saved.map(|()| {
+                    format!(
+                        "Day 15 part 1: path length is {}: {}\nDay 15 part 2: fill at step {}",
+                        part1_path.len(),
+                        part1_path,
+                        step
+                    )
+                })
             }
+            None => Err(Fail("no oxygen system".to_string())),
         },
         Ok(None) => Ok("Day 15: no solution found to part 1".to_string()),
         Err(e) => Err(e),
@@ -563,10 +297,45 @@ fn run(words: Vec<Word>) -> Result<(), Fail> {
             println!("{}", msg);
             Ok(())
         }
-        Err(e) => Err(Fail::CpuFault(e)),
+        Err(e) => Err(e),
     }
 }
 
 fn main() -> Result<(), Fail> {
-    run_with_input(15, read_program_from_file, run)
+    let options = vec![
+        Arg::new("fps")
+            .long("fps")
+            .value_name("N")
+            .takes_value(true)
+            .value_parser(value_parser!(u32))
+            .help("Redraw the map at most N times per second"),
+        Arg::new("save-map")
+            .long("save-map")
+            .value_name("FILE")
+            .takes_value(true)
+            .allow_invalid_utf8(true)
+            .help("Save the map of the ship in FILE"),
+        Arg::new("replay-path")
+            .long("replay-path")
+            .value_name("MOVES")
+            .takes_value(true)
+            .help(
+                "Instead of exploring the ship, show the droid following MOVES (for example NNWS)",
+            ),
+    ];
+    run_with_input_and_options(15, options, read_program_from_file, |words, matches| {
+        let options = Options {
+            fps: matches.get_one::<u32>("fps").copied(),
+            save_map: matches.value_of_os("save-map").map(Path::new),
+            replay_path: match matches.value_of("replay-path") {
+                Some(moves) => Some(
+                    moves
+                        .parse()
+                        .with_context(|| format!("invalid --replay-path {}", moves))?,
+                ),
+                None => None,
+            },
+        };
+        run(words, &options)
+    })
 }
@@ -1,149 +1,173 @@
-use std::collections::HashMap;
+use std::{thread, time};
 
-use lib::cpu::{read_program_from_file, InputOutputError, Processor, Word};
-use lib::error::Fail;
-use lib::grid::{bounds, Position};
-use lib::input::run_with_input;
+use clap::{Arg, ArgMatches};
+use pancurses::{endwin, initscr, Window};
 
-use ndarray::prelude::*;
+use lib::cpu::{read_program_from_file, InputOutputError, Word};
+use lib::days::day17::{alignment_parameter, capture, intersections};
+use lib::error::{Fail, ResultExt};
+use lib::grid::Position;
+use lib::input::run_with_input_and_options;
+use lib::screen::{OffScreen, Screen, Viewport};
+use lib::verbose;
+use lib::verbosity::{verbosity, Verbosity};
 
-struct ImageBuilder {
-    pos: Position,
-    pixels: HashMap<Position, char>,
+/// The camera view as the program draws it.  Each character is a
+/// tile of a `Screen`; a blank line ends a frame, and the next frame
+/// is drawn over it.
+struct CameraView {
+    screen: Screen,
+    cursor: Position,
 }
 
-impl ImageBuilder {
-    fn new() -> ImageBuilder {
-        ImageBuilder {
-            pos: Position { x: 0, y: 0 },
-            pixels: HashMap::new(),
+impl CameraView {
+    fn new() -> CameraView {
+        CameraView {
+            screen: Screen::new(),
+            cursor: Position { x: 0, y: 0 },
         }
     }
 
-    fn emit(&mut self, ch: char) {
+    fn emit(&mut self, ch: char) -> Result<(), OffScreen> {
         match ch {
+            '\n' if self.cursor.x == 0 => {
+                self.cursor.y = 0;
+            }
             '\n' => {
-                self.pos.y += 1;
-                self.pos.x = 0;
+                self.cursor.y += 1;
+                self.cursor.x = 0;
             }
             _ => {
-                self.pixels.insert(self.pos, ch);
-                self.pos.x += 1;
+                self.screen.draw(self.cursor, i64::from(u32::from(ch)))?;
+                self.cursor.x += 1;
             }
         }
+        Ok(())
     }
 
-    fn getter(&self, r: usize, c: usize) -> char {
-        match (c.try_into(), r.try_into()) {
-            (Ok(x), Ok(y)) => match self.pixels.get(&Position { x, y }) {
-                Some(ch) => *ch,
-                None => '?',
-            },
-            _ => '!',
+    fn symbol(tile: i64) -> char {
+        match u32::try_from(tile).map(char::try_from) {
+            Ok(Ok(ch)) if tile != 0 => ch,
+            _ => ' ',
         }
     }
+}
 
-    fn build(&self) -> Array2<char> {
-        match bounds(self.pixels.keys()) {
-            Some((min, max)) => {
-                let w = max.x - min.x;
-                let h = max.y - min.y;
-                let shape = (h as usize, w as usize);
-                Array2::from_shape_fn(shape, |(r, c)| self.getter(r, c))
-            }
-            None => Array2::from_shape_fn((0, 0), |(_, _)| '^'),
-        }
+#[test]
+fn test_camera_view() {
+    let mut view = CameraView::new();
+    for ch in "..#..\n.#^#.\n\n".chars() {
+        view.emit(ch).expect("emit should succeed");
     }
+    assert_eq!(view.screen.render(CameraView::symbol), "..#..\n.#^#.\n");
+    // The next frame is drawn over the first.
+    for ch in "#".chars() {
+        view.emit(ch).expect("emit should succeed");
+    }
+    assert_eq!(view.screen.render(CameraView::symbol), "#.#..\n.#^#.\n");
 }
 
-fn is_scaffold(arr: &Array2<char>, pos: &(usize, usize)) -> bool {
-    matches!(arr[*pos], '#' | '^' | 'v' | '>' | '<')
-}
+/// The characters which show the vacuum robot in the camera view.
+const ROBOT_SYMBOLS: [char; 5] = ['^', 'v', '<', '>', 'X'];
 
-fn is_scaffold_intersection(arr: &Array2<char>, pos: &(usize, usize)) -> bool {
-    let (h, w) = match arr.shape() {
-        &[h, w] => (h, w),
-        _ => {
-            panic!("unexpected shape in array");
-        }
-    };
+/// If the camera view does not fit the terminal, the display scrolls
+/// to keep the robot at least this far from its edge.
+const FOLLOW_MARGIN: usize = 5;
 
-    // check centre
-    if !is_scaffold(arr, pos) {
-        return false;
-    }
-    if pos.0 > 0 {
-        // check north neighbour (note, y axis points down the page)
-        if !is_scaffold(arr, &(pos.0 - 1, pos.1)) {
-            return false;
-        }
-    }
-    if pos.1 > 0 {
-        // check west neighbour
-        if !is_scaffold(arr, &(pos.0, pos.1 - 1)) {
-            return false;
-        }
-    }
-    if pos.0 < h {
-        // check south neighbour
-        if !is_scaffold(arr, &(pos.0 + 1, pos.1)) {
-            return false;
+/// Shows the camera view in the terminal as it is drawn.
+struct LiveView {
+    window: Window,
+    /// The part of the camera view which fits on the terminal.
+    viewport: Viewport,
+}
+
+impl LiveView {
+    fn new() -> LiveView {
+        LiveView {
+            window: initscr(),
+            viewport: Viewport::new(Position { x: 0, y: 0 }, 0, 0),
         }
     }
-    if pos.1 < w {
-        // check east neighbour
-        if !is_scaffold(arr, &(pos.0, pos.1 + 1)) {
-            return false;
+
+    fn show(&mut self, view: &mut CameraView) {
+        let (rows, columns) = self.window.get_max_yx();
+        let robot: Option<Position> = ROBOT_SYMBOLS
+            .iter()
+            .find_map(|ch| view.screen.find(i64::from(u32::from(*ch))));
+        let changed = self.viewport.update(
+            usize::try_from(columns).unwrap_or(0),
+            usize::try_from(rows).unwrap_or(0),
+            robot,
+            FOLLOW_MARGIN,
+        );
+        let dirty = view.screen.take_dirty();
+        let to_draw = if changed {
+            self.window.clear();
+            self.viewport.rect()
+        } else {
+            dirty
+        };
+        if let Some(rect) = to_draw {
+            for pos in rect.positions() {
+                if let Some((row, column)) = self.viewport.to_screen(&pos) {
+                    self.window
+                        .mvaddch(row, column, CameraView::symbol(view.screen.get(&pos)));
+                }
+            }
+            self.window.refresh();
         }
     }
-    true
-}
-
-fn find_matches<F>(array: &Array2<char>, pred: F) -> Vec<Position>
-where
-    F: Fn(&Array2<char>, &(usize, usize)) -> bool,
-{
-    array
-        .indexed_iter()
-        .filter(|(pos, _)| pred(array, &(pos.0, pos.1)))
-        .map(|(pos, _)| Position {
-            y: pos.0 as i64,
-            x: pos.1 as i64,
-        })
-        .collect()
-}
 
-fn alignment_parameter(pos: &Position) -> i64 {
-    pos.x * pos.y
+    fn done(self) {
+        thread::sleep(time::Duration::from_millis(2000));
+        endwin();
+    }
 }
 
-fn part1(program: &[Word]) -> Result<(), Fail> {
-    let mut cpu: Processor = Processor::new(Word(0));
-    cpu.load(Word(0), program)?;
-    let mut imb = ImageBuilder::new();
-    let mut get_input = || -> Result<Word, InputOutputError> { Err(InputOutputError::NoInput) };
-    let mut do_output = |w: Word| -> Result<(), InputOutputError> {
-        if let Ok(Ok(ch)) = u32::try_from(w.0).map(char::try_from) {
-            print!("{}", ch);
-            imb.emit(ch);
-            Ok(())
-        } else {
-            Err(InputOutputError::Unprintable(w))
+fn part1(program: &[Word], live: bool) -> Result<(), Fail> {
+    let mut camera = CameraView::new();
+    let mut display: Option<LiveView> = if live { Some(LiveView::new()) } else { None };
+    let result = capture(program, |ch| {
+        match display.as_mut() {
+            Some(display) => {
+                if camera.emit(ch).is_err() {
+                    return Err(InputOutputError::Unprintable(Word(u32::from(ch).into())));
+                }
+                if ch == '\n' {
+                    display.show(&mut camera);
+                }
+            }
+            None => {
+                if verbosity() == Verbosity::Verbose {
+                    print!("{}", ch);
+                }
+            }
         }
-    };
-    cpu.run_with_io(&mut get_input, &mut do_output)?;
-    let array = imb.build();
-    let matches = find_matches(&array, is_scaffold_intersection);
-    println!("{:?}", &matches);
+        Ok(())
+    });
+    if let Some(display) = display {
+        display.done();
+    }
+    let array = result.context("failed to capture the camera image")?;
+    let matches = intersections(&array);
+    verbose!("intersections: {:?}", &matches);
     let tot: i64 = matches.iter().map(alignment_parameter).sum();
     println!("Day 17 part 1: count is {}, sum is {}", matches.len(), tot);
     Ok(())
 }
 
-fn run(words: Vec<Word>) -> Result<(), Fail> {
-    part1(&words)
+fn run(words: Vec<Word>, live: bool) -> Result<(), Fail> {
+    part1(&words, live)
 }
 
 fn main() -> Result<(), Fail> {
-    run_with_input(17, read_program_from_file, run)
+    let extra_args = vec![Arg::new("live")
+        .long("live")
+        .help("Show the camera view in the terminal as the program draws it")];
+    run_with_input_and_options(
+        17,
+        extra_args,
+        read_program_from_file,
+        |words, matches: &ArgMatches| run(words, matches.is_present("live")),
+    )
 }
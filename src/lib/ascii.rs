@@ -0,0 +1,104 @@
+//! Pictures drawn by Intcode programs as lines of ASCII text, such as
+//! the camera view of day 17.
+use std::collections::HashMap;
+
+use ndarray::Array2;
+
+use crate::grid::{bounds, Position};
+
+/// A complete picture, indexed by (row, column).
+pub type Frame = Array2<char>;
+
+/// Shown in a frame where a row is shorter than the longest one.
+pub const MISSING: char = '?';
+
+/// Assembles the characters written by a program into frames.  Each
+/// line of text is a row of the frame, and an empty line ends the
+/// frame.
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    pos: Position,
+    pixels: HashMap<Position, char>,
+}
+
+impl FrameParser {
+    pub fn new() -> FrameParser {
+        FrameParser::default()
+    }
+
+    /// Adds `ch` to the frame in progress.  Returns the frame if
+    /// `ch` completed it.
+    pub fn push(&mut self, ch: char) -> Option<Frame> {
+        match ch {
+            '\n' if self.pos.x == 0 && !self.pixels.is_empty() => {
+                let frame = self.build();
+                *self = FrameParser::new();
+                Some(frame)
+            }
+            '\n' if self.pixels.is_empty() => {
+                // A blank line before the frame starts.
+                None
+            }
+            '\n' => {
+                self.pos.y += 1;
+                self.pos.x = 0;
+                None
+            }
+            _ => {
+                self.pixels.insert(self.pos, ch);
+                self.pos.x += 1;
+                None
+            }
+        }
+    }
+
+    /// Completes the frame in progress, if it has anything in it.
+    /// This is for programs which do not end their last frame with
+    /// an empty line.
+    pub fn finish(self) -> Option<Frame> {
+        if self.pixels.is_empty() {
+            None
+        } else {
+            Some(self.build())
+        }
+    }
+
+    fn build(&self) -> Frame {
+        match bounds(self.pixels.keys()) {
+            Some((min, max)) => {
+                let shape = ((max.y - min.y + 1) as usize, (max.x - min.x + 1) as usize);
+                Array2::from_shape_fn(shape, |(r, c)| {
+                    let pos = Position {
+                        x: min.x + c as i64,
+                        y: min.y + r as i64,
+                    };
+                    self.pixels.get(&pos).copied().unwrap_or(MISSING)
+                })
+            }
+            None => Array2::from_elem((0, 0), MISSING),
+        }
+    }
+}
+
+#[cfg(test)]
+fn rows(frame: &Frame) -> Vec<String> {
+    frame
+        .rows()
+        .into_iter()
+        .map(|row| row.iter().collect())
+        .collect()
+}
+
+#[test]
+fn test_frame_parser() {
+    let mut parser = FrameParser::new();
+    let frames: Vec<Frame> = "#..\n.#.\n\n\n##\n#\n"
+        .chars()
+        .filter_map(|ch| parser.push(ch))
+        .collect();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].dim(), (2, 3));
+    assert_eq!(rows(&frames[0]), vec!["#..", ".#."]);
+    let last = parser.finish().expect("a frame is in progress");
+    assert_eq!(rows(&last), vec!["##", "#?"]);
+}
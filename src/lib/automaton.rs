@@ -0,0 +1,392 @@
+//! A cellular automaton on a rectangular grid, in which each cell
+//! lives or dies according to how many of its four orthogonal
+//! neighbours are alive.  This is the machinery of day 24; the
+//! puzzle itself only supplies a `Rule`.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::Fail;
+use crate::grid::{CompassDirection, Position, ALL_MOVE_OPTIONS};
+
+/// How the edges of the grid are connected.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Topology {
+    /// Cells beyond the edge of the grid are always dead.
+    Bounded,
+    /// The left edge is adjacent to the right edge, and the top to
+    /// the bottom.
+    Wrapping,
+    /// Day 24 part 2: the centre cell of each grid is itself a whole
+    /// grid one level down, and each grid sits in the centre cell of
+    /// the grid one level up.  The grid must have odd dimensions.
+    Recursive,
+}
+
+/// A cell of the automaton.  `level` is always zero unless the
+/// topology is `Recursive`; higher levels are nested inside lower
+/// ones.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Cell {
+    pub level: i64,
+    pub pos: Position,
+}
+
+/// Decides whether a cell is alive in the next generation, given
+/// whether it is alive now and how many of its neighbours are.  A
+/// dead cell with no live neighbours must stay dead.
+pub trait Rule {
+    fn alive(&self, alive: bool, live_neighbours: usize) -> bool;
+}
+
+impl<F> Rule for F
+where
+    F: Fn(bool, usize) -> bool,
+{
+    fn alive(&self, alive: bool, live_neighbours: usize) -> bool {
+        self(alive, live_neighbours)
+    }
+}
+
+/// The grid could not be built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadGrid(pub String);
+
+impl Display for BadGrid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad automaton grid: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadGrid {}
+
+impl From<BadGrid> for Fail {
+    fn from(e: BadGrid) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Automaton {
+    width: i64,
+    height: i64,
+    topology: Topology,
+    live: BTreeSet<Cell>,
+}
+
+/// The point at which a sequence of generations starts repeating.
+#[derive(Clone, Debug)]
+pub struct Cycle {
+    /// The generation which first has the repeated state.
+    pub start: usize,
+    /// How many generations pass before the state recurs.
+    pub length: usize,
+    /// The repeated state.
+    pub state: Automaton,
+}
+
+impl Automaton {
+    pub fn new(width: i64, height: i64, topology: Topology) -> Result<Automaton, BadGrid> {
+        if width < 1 || height < 1 {
+            return Err(BadGrid(format!("grid size {}x{} is empty", width, height)));
+        }
+        if topology == Topology::Recursive && (width % 2 == 0 || height % 2 == 0) {
+            return Err(BadGrid(format!(
+                "a recursive grid must have odd dimensions, not {}x{}",
+                width, height
+            )));
+        }
+        Ok(Automaton {
+            width,
+            height,
+            topology,
+            live: BTreeSet::new(),
+        })
+    }
+
+    /// Reads a grid drawn with `#` for live cells and `.` for dead
+    /// ones, placing it at level zero.  In a recursive grid the centre
+    /// cell may also be drawn as `?`, and is dead whatever it shows.
+    pub fn from_text(text: &str, topology: Topology) -> Result<Automaton, BadGrid> {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows.first().map(|row| row.chars().count()).unwrap_or(0);
+        let mut result = Automaton::new(width as i64, rows.len() as i64, topology)?;
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(BadGrid(format!(
+                    "row {} has length {} but row 0 has length {}",
+                    y,
+                    row.chars().count(),
+                    width
+                )));
+            }
+            for (x, ch) in row.chars().enumerate() {
+                let pos = Position {
+                    x: x as i64,
+                    y: y as i64,
+                };
+                match ch {
+                    '#' => {
+                        result.set(Cell { level: 0, pos }, true);
+                    }
+                    '.' => (),
+                    '?' if result.is_centre(&pos) => (),
+                    _ => {
+                        return Err(BadGrid(format!("unexpected '{}' at {}", ch, pos)));
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    fn in_bounds(&self, pos: &Position) -> bool {
+        (0..self.width).contains(&pos.x) && (0..self.height).contains(&pos.y)
+    }
+
+    fn centre(&self) -> Position {
+        Position {
+            x: self.width / 2,
+            y: self.height / 2,
+        }
+    }
+
+    fn is_centre(&self, pos: &Position) -> bool {
+        self.topology == Topology::Recursive && *pos == self.centre()
+    }
+
+    pub fn is_alive(&self, cell: &Cell) -> bool {
+        self.live.contains(cell)
+    }
+
+    /// Makes `cell` live or dead.  Cells which are not part of the
+    /// grid (such as the centre of a recursive grid) are ignored.
+    pub fn set(&mut self, cell: Cell, alive: bool) {
+        if !self.in_bounds(&cell.pos) || self.is_centre(&cell.pos) {
+            return;
+        }
+        if alive {
+            self.live.insert(cell);
+        } else {
+            self.live.remove(&cell);
+        }
+    }
+
+    /// The live cells, in order of level, then row, then column.
+    pub fn live_cells(&self) -> impl Iterator<Item = &Cell> {
+        self.live.iter()
+    }
+
+    pub fn population(&self) -> usize {
+        self.live.len()
+    }
+
+    /// The lowest and highest levels having any live cells.
+    pub fn levels(&self) -> Option<(i64, i64)> {
+        let first = self.live.iter().next()?;
+        let last = self.live.iter().next_back()?;
+        Some((first.level, last.level))
+    }
+
+    /// The neighbours of `cell`.  In a recursive grid a cell next to
+    /// the centre has a whole edge of the level below as neighbours.
+    pub fn neighbours(&self, cell: &Cell) -> Vec<Cell> {
+        let mut result = Vec::with_capacity(4);
+        for direction in ALL_MOVE_OPTIONS.iter() {
+            let pos = cell.pos.move_direction(direction);
+            match self.topology {
+                Topology::Bounded => {
+                    if self.in_bounds(&pos) {
+                        result.push(Cell { pos, ..*cell });
+                    }
+                }
+                Topology::Wrapping => {
+                    let pos = Position {
+                        x: pos.x.rem_euclid(self.width),
+                        y: pos.y.rem_euclid(self.height),
+                    };
+                    result.push(Cell { pos, ..*cell });
+                }
+                Topology::Recursive => {
+                    if !self.in_bounds(&pos) {
+                        result.push(Cell {
+                            level: cell.level - 1,
+                            pos: self.centre().move_direction(direction),
+                        });
+                    } else if self.is_centre(&pos) {
+                        let level = cell.level + 1;
+                        result.extend(
+                            self.inner_edge(direction)
+                                .into_iter()
+                                .map(|pos| Cell { level, pos }),
+                        );
+                    } else {
+                        result.push(Cell { pos, ..*cell });
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// The edge of the inner grid which is reached by moving in
+    /// `direction` into the centre cell.
+    fn inner_edge(&self, direction: &CompassDirection) -> Vec<Position> {
+        let (w, h) = (self.width, self.height);
+        match direction {
+            CompassDirection::North => (0..w).map(|x| Position { x, y: h - 1 }).collect(),
+            CompassDirection::South => (0..w).map(|x| Position { x, y: 0 }).collect(),
+            CompassDirection::East => (0..h).map(|y| Position { x: 0, y }).collect(),
+            CompassDirection::West => (0..h).map(|y| Position { x: w - 1, y }).collect(),
+        }
+    }
+
+    /// Computes the next generation.
+    pub fn step<R: Rule>(&self, rule: &R) -> Automaton {
+        let mut counts: HashMap<Cell, usize> = self.live.iter().map(|cell| (*cell, 0)).collect();
+        for cell in self.live.iter() {
+            for neighbour in self.neighbours(cell) {
+                *counts.entry(neighbour).or_insert(0) += 1;
+            }
+        }
+        let live = counts
+            .into_iter()
+            .filter(|(cell, count)| rule.alive(self.is_alive(cell), *count))
+            .map(|(cell, _)| cell)
+            .collect();
+        Automaton { live, ..*self }
+    }
+
+    /// Computes the state after `generations` steps.
+    pub fn run<R: Rule>(&self, rule: &R, generations: usize) -> Automaton {
+        (0..generations).fold(self.clone(), |state, _| state.step(rule))
+    }
+
+    /// Steps the automaton until it reaches a state it has been in
+    /// before.  This never returns if the automaton grows without
+    /// limit, as a recursive one can.
+    pub fn find_cycle<R: Rule>(&self, rule: &R) -> Cycle {
+        let mut seen: HashMap<Automaton, usize> = HashMap::new();
+        let mut state = self.clone();
+        for generation in 0.. {
+            if let Some(start) = seen.get(&state) {
+                return Cycle {
+                    start: *start,
+                    length: generation - start,
+                    state,
+                };
+            }
+            let next = state.step(rule);
+            seen.insert(state, generation);
+            state = next;
+        }
+        unreachable!("the generation count is unbounded")
+    }
+}
+
+/// Draws each level which has live cells, using the same notation
+/// as `from_text`.  Levels are labelled only in recursive grids.
+impl Display for Automaton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (low, high) = self.levels().unwrap_or((0, 0));
+        for level in low..=high {
+            if self.topology == Topology::Recursive {
+                if level != low {
+                    writeln!(f)?;
+                }
+                writeln!(f, "Depth {}:", level)?;
+            }
+            for y in 0..self.height {
+                let row: String = (0..self.width)
+                    .map(|x| {
+                        let pos = Position { x, y };
+                        if self.is_centre(&pos) {
+                            '?'
+                        } else if self.is_alive(&Cell { level, pos }) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                writeln!(f, "{}", row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn bug_rule(alive: bool, live_neighbours: usize) -> bool {
+    // The rule of day 24.
+    live_neighbours == 1 || (!alive && live_neighbours == 2)
+}
+
+#[cfg(test)]
+const DAY24_EXAMPLE: &str = "....#\n#..#.\n#..##\n..#..\n#....\n";
+
+#[test]
+fn test_bounded_cycle() {
+    let initial = Automaton::from_text(DAY24_EXAMPLE, Topology::Bounded).expect("valid grid");
+    let cycle = initial.find_cycle(&bug_rule);
+    assert_eq!(
+        cycle.state.to_string(),
+        ".....\n.....\n.....\n#....\n.#...\n"
+    );
+    let biodiversity: i64 = cycle
+        .state
+        .live_cells()
+        .map(|cell| 1 << (cell.pos.y * 5 + cell.pos.x))
+        .sum();
+    assert_eq!(biodiversity, 2129920);
+}
+
+#[test]
+fn test_recursive() {
+    let initial = Automaton::from_text(DAY24_EXAMPLE, Topology::Recursive).expect("valid grid");
+    let after = initial.run(&bug_rule, 10);
+    assert_eq!(after.population(), 99);
+    assert_eq!(after.levels(), Some((-5, 5)));
+}
+
+#[test]
+fn test_wrapping_neighbours() {
+    let grid = Automaton::new(3, 3, Topology::Wrapping).expect("valid size");
+    let corner = Cell {
+        level: 0,
+        pos: Position { x: 0, y: 0 },
+    };
+    let mut got: Vec<Position> = grid.neighbours(&corner).iter().map(|c| c.pos).collect();
+    got.sort();
+    assert_eq!(
+        got,
+        vec![
+            Position { x: 0, y: 1 },
+            Position { x: 0, y: 2 },
+            Position { x: 1, y: 0 },
+            Position { x: 2, y: 0 },
+        ]
+    );
+}
+
+#[test]
+fn test_bad_grid() {
+    assert!(Automaton::from_text("#.\n#", Topology::Bounded).is_err());
+    assert!(Automaton::from_text("##\n##\n", Topology::Recursive).is_err());
+    assert!(Automaton::from_text("#x#\n", Topology::Bounded).is_err());
+}
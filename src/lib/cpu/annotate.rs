@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{ProgramBundle, Word, WordInt};
+use crate::error::Fail;
+use crate::input::read_file_as_string;
+
+/// What is known about one address of a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    pub name: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// Names and comments for the addresses of a program, typically
+/// the notes made while working out what a program does.  These are
+/// shown in traces and disassembly.
+///
+/// Annotations are stored as text, one address per line, as in
+///
+/// ```text
+/// # Day 13
+/// 386 = ball_x    # x coordinate of the ball
+/// 392 # the score is updated here
+/// ```
+///
+/// A '#' starts a comment, which is attached to the address on the
+/// same line, if there is one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotations(BTreeMap<Word, Annotation>);
+
+/// An annotations file could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadAnnotation(pub String);
+
+impl Display for BadAnnotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad annotation: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadAnnotation {}
+
+impl From<BadAnnotation> for Fail {
+    fn from(e: BadAnnotation) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations::default()
+    }
+
+    /// Read annotations from the file `path`.
+    pub fn from_file(path: &Path) -> Result<Annotations, Fail> {
+        let text = read_file_as_string(path)?;
+        text.parse::<Annotations>()
+            .map_err(|e| Fail(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn set_name(&mut self, addr: Word, name: &str) {
+        self.0.entry(addr).or_default().name = Some(name.to_string());
+    }
+
+    pub fn set_comment(&mut self, addr: Word, comment: &str) {
+        self.0.entry(addr).or_default().comment = Some(comment.to_string());
+    }
+
+    pub fn get(&self, addr: Word) -> Option<&Annotation> {
+        self.0.get(&addr)
+    }
+
+    pub fn name(&self, addr: Word) -> Option<&str> {
+        self.get(addr).and_then(|a| a.name.as_deref())
+    }
+
+    pub fn comment(&self, addr: Word) -> Option<&str> {
+        self.get(addr).and_then(|a| a.comment.as_deref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `addr` followed by its name in parentheses, if it has one.
+    pub fn describe(&self, addr: Word) -> String {
+        match self.name(addr) {
+            Some(name) => format!("{} ({})", addr, name),
+            None => addr.to_string(),
+        }
+    }
+}
+
+impl ProgramBundle {
+    /// The bundle's symbols, as annotations.
+    pub fn annotations(&self) -> Annotations {
+        let mut result = Annotations::new();
+        for (addr, name) in self.symbols.iter() {
+            result.set_name(*addr, name);
+        }
+        result
+    }
+}
+
+impl FromStr for Annotations {
+    type Err = BadAnnotation;
+
+    fn from_str(s: &str) -> Result<Annotations, BadAnnotation> {
+        let mut result = Annotations::new();
+        for (i, line) in s.lines().enumerate() {
+            let (item, comment) = match line.split_once('#') {
+                Some((item, comment)) => (item.trim(), Some(comment.trim())),
+                None => (line.trim(), None),
+            };
+            if item.is_empty() {
+                continue;
+            }
+            let bad = |msg: String| BadAnnotation(format!("line {}: {}", i + 1, msg));
+            let (addr, name) = match item.split_once('=') {
+                Some((addr, name)) => (addr.trim(), Some(name.trim())),
+                None => (item, None),
+            };
+            let addr = addr
+                .parse::<WordInt>()
+                .map(Word)
+                .map_err(|e| bad(format!("bad address '{}': {}", addr, e)))?;
+            match name {
+                Some(name) if name.is_empty() || name.contains(char::is_whitespace) => {
+                    return Err(bad(format!("bad name '{}'", name)));
+                }
+                Some(name) => result.set_name(addr, name),
+                None => (),
+            }
+            match comment {
+                Some(comment) if !comment.is_empty() => result.set_comment(addr, comment),
+                _ => (),
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Display for Annotations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (addr, annotation) in self.0.iter() {
+            write!(f, "{}", addr)?;
+            if let Some(name) = annotation.name.as_ref() {
+                write!(f, " = {}", name)?;
+            }
+            if let Some(comment) = annotation.comment.as_ref() {
+                write!(f, " # {}", comment)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_annotations() {
+    let text = "# Day 13\n386 = ball_x    # x coordinate of the ball\n392 # score\n\n12=paddle\n";
+    let annotations: Annotations = text.parse().expect("annotations are valid");
+    assert_eq!(annotations.name(Word(386)), Some("ball_x"));
+    assert_eq!(
+        annotations.comment(Word(386)),
+        Some("x coordinate of the ball")
+    );
+    assert_eq!(annotations.name(Word(392)), None);
+    assert_eq!(annotations.comment(Word(392)), Some("score"));
+    assert_eq!(annotations.describe(Word(12)), "12 (paddle)");
+    assert_eq!(annotations.describe(Word(13)), "13");
+    assert_eq!(
+        annotations.to_string(),
+        "12 = paddle\n386 = ball_x # x coordinate of the ball\n392 # score\n"
+    );
+    assert_eq!(
+        annotations.to_string().parse::<Annotations>(),
+        Ok(annotations)
+    );
+    assert!("x = y\n".parse::<Annotations>().is_err());
+    assert!("1 = two words\n".parse::<Annotations>().is_err());
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+
+use super::{
+    CpuFault, CpuStatus, InputDevice, InputOutputError, OutputDevice, Processor, Word, WordInt,
+};
+use crate::error::Fail;
+
+/// Something a program which speaks ASCII wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiOutput {
+    /// A complete line of text, without its newline.
+    Line(String),
+    /// A word which is not an ASCII character, such as the amount of
+    /// dust collected on day 17.
+    Value(Word),
+}
+
+/// Text which cannot be sent to a program because it is not ASCII.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotAscii(pub char);
+
+impl Display for NotAscii {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is not an ASCII character", self.0)
+    }
+}
+
+impl std::error::Error for NotAscii {}
+
+impl From<NotAscii> for Fail {
+    fn from(e: NotAscii) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// Input and output for programs which speak ASCII (such as those of
+/// days 17, 21 and 25).  Lines of input are queued up in advance, and
+/// output is gathered into whole lines.  Output words which are not
+/// ASCII characters are kept separately, in order with the lines.
+#[derive(Debug, Clone, Default)]
+pub struct AsciiIo {
+    input: VecDeque<Word>,
+    output: LineBuffer,
+}
+
+/// Gathers output into lines.
+#[derive(Debug, Clone, Default)]
+struct LineBuffer {
+    line: String,
+    complete: Vec<AsciiOutput>,
+}
+
+impl OutputDevice for LineBuffer {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        match u8::try_from(w.0) {
+            Ok(b'\n') => {
+                let line = std::mem::take(&mut self.line);
+                self.complete.push(AsciiOutput::Line(line));
+            }
+            Ok(b) if b.is_ascii() => self.line.push(char::from(b)),
+            _ => self.complete.push(AsciiOutput::Value(w)),
+        }
+        Ok(())
+    }
+}
+
+impl AsciiIo {
+    pub fn new() -> AsciiIo {
+        AsciiIo::default()
+    }
+
+    /// Queue `line`, followed by a newline, as input.  Nothing is
+    /// queued if `line` is not all ASCII.
+    pub fn queue_line(&mut self, line: &str) -> Result<(), NotAscii> {
+        if let Some(ch) = line.chars().find(|ch| !ch.is_ascii()) {
+            return Err(NotAscii(ch));
+        }
+        self.input.extend(
+            line.bytes()
+                .chain(Some(b'\n'))
+                .map(|b| Word(WordInt::from(b))),
+        );
+        Ok(())
+    }
+
+    /// Queue each line of `script` as input (see `queue_line`).
+    /// Nothing is queued if `script` is not all ASCII.
+    pub fn queue_script(&mut self, script: &str) -> Result<(), NotAscii> {
+        if let Some(ch) = script.chars().find(|ch| !ch.is_ascii()) {
+            return Err(NotAscii(ch));
+        }
+        for line in script.lines() {
+            self.queue_line(line)?;
+        }
+        Ok(())
+    }
+
+    /// The number of input characters not yet read by the program.
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// The text of the line the program is part way through writing.
+    pub fn partial_line(&self) -> &str {
+        &self.output.line
+    }
+
+    /// The lines and values written so far, which are then
+    /// forgotten.  Any partial line is kept until it is completed.
+    pub fn take_output(&mut self) -> Vec<AsciiOutput> {
+        std::mem::take(&mut self.output.complete)
+    }
+
+    /// Like `take_output`, but also includes the partial line (if
+    /// any) as if it were complete.  This suits programs whose last
+    /// line has no newline.
+    pub fn finish(&mut self) -> Vec<AsciiOutput> {
+        if !self.output.line.is_empty() {
+            let line = std::mem::take(&mut self.output.line);
+            self.output.complete.push(AsciiOutput::Line(line));
+        }
+        self.take_output()
+    }
+}
+
+impl InputDevice for AsciiIo {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.input.pop_front().ok_or(InputOutputError::NoInput)
+    }
+}
+
+impl OutputDevice for AsciiIo {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.output.put_output(w)
+    }
+}
+
+impl Processor {
+    /// Run a program which speaks ASCII, reading the input queued in
+    /// `io` and gathering its output there, until it halts
+    /// (`CpuStatus::Halt`), wants more input than was queued
+    /// (`CpuStatus::AwaitingInput`) or reaches a breakpoint
+    /// (`CpuStatus::BreakpointHit`).  In the second case, more input
+    /// can be queued and the program run again, so this suits
+    /// interactive programs such as the text adventure of day 25.
+    pub fn run_ascii(&mut self, io: &mut AsciiIo) -> Result<CpuStatus, CpuFault> {
+        loop {
+            match self.step(&mut io.output)? {
+                CpuStatus::Run => (),
+                CpuStatus::AwaitingInput => match io.input.pop_front() {
+                    Some(w) => self.provide_input(w),
+                    None => return Ok(CpuStatus::AwaitingInput),
+                },
+                status => return Ok(status),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ascii_io() {
+    use super::{encode_program, Instr, Param};
+
+    // Echo input until it reads a '.', then write 1000000.
+    let program = encode_program(&[
+        Instr::read(20),
+        Instr::write(Param::Pos(20)),
+        Instr::equals(Param::Pos(20), Param::Imm(46), 21),
+        Instr::jump_if_false(Param::Pos(21), Param::Imm(0)),
+        Instr::write(Param::Imm(1000000)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let mut io = AsciiIo::new();
+    io.queue_script("WALK\nNOT A J").expect("script is ASCII");
+    assert_eq!(io.pending_input(), 13);
+    assert_eq!(io.queue_line("café"), Err(NotAscii('é')));
+    assert_eq!(
+        cpu.run_ascii(&mut io).expect("no fault"),
+        CpuStatus::AwaitingInput
+    );
+    assert_eq!(io.partial_line(), "");
+    io.queue_line(".").expect("line is ASCII");
+    assert_eq!(cpu.run_ascii(&mut io).expect("no fault"), CpuStatus::Halt);
+    assert_eq!(io.pending_input(), 1);
+    assert_eq!(io.partial_line(), ".");
+    assert_eq!(
+        io.take_output(),
+        vec![
+            AsciiOutput::Line("WALK".to_string()),
+            AsciiOutput::Line("NOT A J".to_string()),
+            AsciiOutput::Value(Word(1000000)),
+        ]
+    );
+    assert_eq!(io.finish(), vec![AsciiOutput::Line(".".to_string())]);
+}
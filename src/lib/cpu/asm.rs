@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use super::instr::{OpcodeInfo, OPCODES};
+use super::{Annotations, Instr, Param, Word, WordInt};
+use crate::error::Fail;
+
+/// Assembly source could not be assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadAssembly {
+    /// The line (counting from 1) where the problem is.
+    pub line: usize,
+    pub problem: String,
+}
+
+impl Display for BadAssembly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad assembly at line {}: {}", self.line, self.problem)
+    }
+}
+
+impl std::error::Error for BadAssembly {}
+
+impl From<BadAssembly> for Fail {
+    fn from(e: BadAssembly) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// A number, or the name of a label standing for its address.
+#[derive(Debug, Clone)]
+enum Value {
+    Number(WordInt),
+    Label(String),
+}
+
+/// An operand, whose value may not be known until every label has
+/// been seen.
+#[derive(Debug, Clone)]
+enum Operand {
+    Pos(Value),
+    Imm(Value),
+    Rel(Value),
+}
+
+/// One line of source, after the first pass.
+#[derive(Debug)]
+enum Statement {
+    Instruction(&'static OpcodeInfo, Vec<Operand>),
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    /// The number of words the statement occupies.
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction(info, _) => 1 + info.params,
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+fn parse_number(text: &str) -> Result<WordInt, String> {
+    text.trim()
+        .parse()
+        .map_err(|e| format!("bad number '{}': {}", text.trim(), e))
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    if is_label(text) {
+        Ok(Value::Label(text.to_string()))
+    } else {
+        parse_number(text).map(Value::Number)
+    }
+}
+
+/// Parse an operand written as `Param` displays it: `5` (immediate),
+/// `[5]` (positional) or `[rb+5]` (relative).  A label may be used
+/// in place of the number, except after `rb-`.
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    let text = text.trim();
+    match text.strip_prefix('[').map(|rest| rest.strip_suffix(']')) {
+        None => parse_value(text).map(Operand::Imm),
+        Some(None) => Err(format!("operand '{}' lacks a closing ']'", text)),
+        Some(Some(inner)) => match inner.trim().strip_prefix("rb") {
+            Some(offset) => match offset.trim_start() {
+                "" => Ok(Operand::Rel(Value::Number(0))),
+                offset => match offset.strip_prefix('+') {
+                    Some(positive) => parse_value(positive).map(Operand::Rel),
+                    None => parse_number(offset).map(|n| Operand::Rel(Value::Number(n))),
+                },
+            },
+            None => parse_value(inner).map(Operand::Pos),
+        },
+    }
+}
+
+/// Parse an instruction (such as `add [0], 5, [rb-2]`) or a data
+/// directive (such as `data 1, 2, end`).
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let operands: Vec<&str> = if operands.trim().is_empty() {
+        Vec::new()
+    } else {
+        operands.split(',').collect()
+    };
+    if mnemonic == "data" {
+        if operands.is_empty() {
+            return Err("data needs at least one value".to_string());
+        }
+        return operands
+            .into_iter()
+            .map(parse_value)
+            .collect::<Result<_, _>>()
+            .map(Statement::Data);
+    }
+    let info = OPCODES
+        .iter()
+        .find(|info| info.mnemonic == mnemonic)
+        .ok_or_else(|| format!("unknown instruction '{}'", mnemonic))?;
+    let operands: Vec<Operand> = operands
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<_, _>>()?;
+    if operands.len() != info.params {
+        return Err(format!(
+            "{} takes {} operands, not {}",
+            mnemonic,
+            info.params,
+            operands.len()
+        ));
+    }
+    if let Some(Operand::Imm(_)) = info.writes.map(|i| &operands[i]) {
+        return Err(format!(
+            "{} cannot store into an immediate operand",
+            mnemonic
+        ));
+    }
+    Ok(Statement::Instruction(info, operands))
+}
+
+fn resolve(value: &Value, labels: &BTreeMap<String, WordInt>) -> Result<WordInt, String> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        Value::Label(name) => labels
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("undefined label '{}'", name)),
+    }
+}
+
+/// Assemble `source`, returning the program and a symbol table
+/// giving the address of each label.  The symbol table can be given
+/// to `Memory::disassemble` (or used as a program's annotations) so
+/// that the labels are shown.
+///
+/// Each line holds an instruction, in the syntax used by `Instr`'s
+/// `Display` implementation, or a data directive, for example
+///
+/// ```text
+/// start:
+///     in [value]          # read a number
+///     add [value], 10, [value]
+///     out [value]
+///     jt [value], start
+///     halt
+/// value:
+///     data 0
+/// ```
+///
+/// Operands are written `5` (immediate), `[5]` (positional) or
+/// `[rb+5]` (relative), and a label can be used in place of the
+/// number.  `data` places its values (numbers or labels) in
+/// consecutive words.  A line may start with a label followed by a
+/// ':', which names the address of what follows.  It may instead
+/// start with an address followed by a ':', as in the output of the
+/// disassembler, which is checked.  A '#' starts a comment, and
+/// blank lines are ignored.  The first statement is placed at
+/// address 0.
+pub fn assemble_with_labels(source: &str) -> Result<(Vec<Word>, Annotations), BadAssembly> {
+    let mut labels: BTreeMap<String, WordInt> = BTreeMap::new();
+    let mut statements: Vec<(usize, Statement)> = Vec::new();
+    let mut addr: WordInt = 0;
+    for (i, line) in source.lines().enumerate() {
+        let bad = |problem: String| BadAssembly {
+            line: i + 1,
+            problem,
+        };
+        let mut text = line.split('#').next().unwrap_or("").trim();
+        if let Some((prefix, rest)) = text.split_once(':') {
+            let prefix = prefix.trim();
+            if is_label(prefix) {
+                if labels.insert(prefix.to_string(), addr).is_some() {
+                    return Err(bad(format!("label '{}' is defined twice", prefix)));
+                }
+            } else if parse_number(prefix).map_err(bad)? != addr {
+                return Err(bad(format!("this is address {}, not {}", addr, prefix)));
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let statement = parse_statement(text).map_err(bad)?;
+        addr += statement.size() as WordInt;
+        statements.push((i + 1, statement));
+    }
+
+    let mut program: Vec<Word> = Vec::with_capacity(addr as usize);
+    for (line, statement) in statements.iter() {
+        let bad = |problem: String| BadAssembly {
+            line: *line,
+            problem,
+        };
+        match statement {
+            Statement::Instruction(info, operands) => {
+                let params: Vec<Param> = operands
+                    .iter()
+                    .map(|operand| match operand {
+                        Operand::Pos(v) => resolve(v, &labels).map(Param::Pos),
+                        Operand::Imm(v) => resolve(v, &labels).map(Param::Imm),
+                        Operand::Rel(v) => resolve(v, &labels).map(Param::Rel),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map_err(bad)?;
+                Instr::new(info.op, &params).encode_into(&mut program);
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(Word(resolve(value, &labels).map_err(bad)?));
+                }
+            }
+        }
+    }
+
+    let mut symbols = Annotations::new();
+    for (name, addr) in labels.iter() {
+        symbols.set_name(Word(*addr), name);
+    }
+    Ok((program, symbols))
+}
+
+/// Assemble `source` (see `assemble_with_labels`), placing the first
+/// instruction at address 0.
+pub fn assemble(source: &str) -> Result<Vec<Word>, BadAssembly> {
+    assemble_with_labels(source).map(|(program, _)| program)
+}
+
+#[test]
+fn test_assemble() {
+    use super::encode_program;
+
+    let source = "
+        # Read a word, add 10 to it and print it.
+        in [9]
+        add [9], 10, [rb-2]   # comment
+        mul [rb], [rb+3], [rb + 4]
+        jf 0, -1
+        halt
+    ";
+    assert_eq!(
+        assemble(source),
+        Ok(encode_program(&[
+            Instr::read(9),
+            Instr::add(Param::Pos(9), Param::Imm(10), Param::Rel(-2)),
+            Instr::mul(Param::Rel(0), Param::Rel(3), Param::Rel(4)),
+            Instr::jump_if_false(Param::Imm(0), Param::Imm(-1)),
+            Instr::stop(),
+        ]))
+    );
+    // What the disassembler shows can be assembled again.
+    let instr = Instr::equals(Param::Rel(-7), Param::Imm(3), Param::Pos(100));
+    assert_eq!(assemble(&instr.to_string()), Ok(instr.encode()));
+
+    let problem = |source: &str| assemble(source).map_err(|e| e.to_string());
+    assert_eq!(
+        problem("halt\nfrob 3"),
+        Err("bad assembly at line 2: unknown instruction 'frob'".to_string())
+    );
+    assert_eq!(
+        problem("out 1, 2"),
+        Err("bad assembly at line 1: out takes 1 operands, not 2".to_string())
+    );
+    assert_eq!(
+        problem("in 4"),
+        Err("bad assembly at line 1: in cannot store into an immediate operand".to_string())
+    );
+    assert!(problem("out [4").is_err());
+    assert!(problem("out [rb*4]").is_err());
+}
+
+#[test]
+fn test_assemble_labels() {
+    use super::Memory;
+
+    let source = "
+        start:
+            in [value]          # read a number
+            jt [value], done
+            data -1, done, done
+            out 1
+        done: out [rb+value]
+            halt
+        value:
+            data 0
+    ";
+    let (program, symbols) = assemble_with_labels(source).expect("source should assemble");
+    let words = |ns: &[WordInt]| -> Vec<Word> { ns.iter().map(|n| Word(*n)).collect() };
+    assert_eq!(
+        program,
+        words(&[3, 13, 1005, 13, 10, -1, 10, 10, 104, 1, 204, 13, 99, 0])
+    );
+    assert_eq!(symbols.name(Word(0)), Some("start"));
+    assert_eq!(symbols.name(Word(10)), Some("done"));
+    assert_eq!(symbols.name(Word(13)), Some("value"));
+
+    // The disassembler shows the labels, and its output can be
+    // assembled again.
+    let mut memory = Memory::new();
+    memory.load(Word(0), &program).expect("program should load");
+    let listing = memory.disassemble(0..14, &symbols);
+    assert!(listing.starts_with("start:\n 0: in [value]\n 2: jt [value], done\n"));
+    assert_eq!(assemble(&listing), Ok(program));
+
+    let problem = |source: &str| assemble(source).map_err(|e| e.to_string());
+    assert_eq!(
+        problem("jt 1, nowhere"),
+        Err("bad assembly at line 1: undefined label 'nowhere'".to_string())
+    );
+    assert_eq!(
+        problem("a: halt\na: halt"),
+        Err("bad assembly at line 2: label 'a' is defined twice".to_string())
+    );
+    assert_eq!(
+        problem("0: halt\n2: halt"),
+        Err("bad assembly at line 2: this is address 1, not 2".to_string())
+    );
+    assert!(problem("data").is_err());
+}
@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use super::{Word, WordInt};
+
+/// The way a `Memory` stores words.  Different programs suit
+/// different strategies, so the backend can be chosen with
+/// `Memory::with_backend` (or `Processor::with_backend`) without
+/// changing anything else.  Addresses passed to a backend are never
+/// negative; `Memory` checks them first.
+pub trait MemoryBackend: Debug + Send + Sync {
+    /// The word at `addr`, if it has ever been written.
+    fn fetch(&self, addr: Word) -> Option<Word>;
+
+    /// Store `value` at `addr`.
+    fn store(&mut self, addr: Word, value: Word);
+
+    /// Forget the word at `addr`, as if it had never been written.
+    fn erase(&mut self, addr: Word);
+
+    /// Store `words` at consecutive addresses starting at `base`.
+    fn load(&mut self, base: Word, words: &[Word]) {
+        for (addr, w) in (base.0..).zip(words) {
+            self.store(Word(addr), *w);
+        }
+    }
+
+    /// The addresses which have been written, with their content, in
+    /// address order.
+    fn dump(&self) -> Box<dyn Iterator<Item = (Word, Word)> + '_>;
+
+    /// The number of addresses which have been written.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A copy of this backend, with the same content.
+    fn clone_box(&self) -> Box<dyn MemoryBackend>;
+
+    /// An empty backend of the same kind as this one.
+    fn empty(&self) -> Box<dyn MemoryBackend>;
+}
+
+impl Clone for Box<dyn MemoryBackend> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Addresses below this may be kept in the dense part of a
+/// `DenseMemory`.
+const DENSE_LIMIT: usize = 1 << 20;
+
+/// The dense part of a `DenseMemory` grows to at least this size, so
+/// that small programs do not grow it repeatedly.
+const MIN_DENSE_GROWTH: usize = 1024;
+
+/// The default backend.  Intcode programs mostly use a contiguous
+/// block of low addresses, which are kept in a vector so that they
+/// can be found quickly.  Addresses far beyond the end of the vector
+/// are kept in a map instead, so that a program writing to (say)
+/// address 10^9 does not need gigabytes of memory.  The vector grows
+/// to include addresses just beyond its end, taking over any of them
+/// which were in the map.
+#[derive(Debug, Clone, Default)]
+pub struct DenseMemory {
+    /// The words at addresses 0 to `dense.len() - 1`, with `None`
+    /// for those which have never been written.
+    dense: Vec<Option<Word>>,
+    /// The words at addresses beyond the end of `dense`.
+    sparse: BTreeMap<Word, Word>,
+    /// The number of addresses which have been written.
+    count: usize,
+}
+
+impl DenseMemory {
+    pub fn new() -> DenseMemory {
+        DenseMemory::default()
+    }
+
+    /// Extend the dense part to at least `len` addresses.
+    fn grow(&mut self, len: usize) {
+        let len = len
+            .max(2 * self.dense.len())
+            .clamp(MIN_DENSE_GROWTH, DENSE_LIMIT);
+        let beyond = self.sparse.split_off(&Word(len as WordInt));
+        let taken_over = std::mem::replace(&mut self.sparse, beyond);
+        self.dense.resize(len, None);
+        for (addr, value) in taken_over {
+            self.dense[addr.0 as usize] = Some(value);
+        }
+    }
+}
+
+impl MemoryBackend for DenseMemory {
+    fn fetch(&self, addr: Word) -> Option<Word> {
+        match usize::try_from(addr.0) {
+            Ok(i) if i < self.dense.len() => self.dense[i],
+            _ => self.sparse.get(&addr).copied(),
+        }
+    }
+
+    fn store(&mut self, addr: Word, value: Word) {
+        let i = match usize::try_from(addr.0) {
+            Ok(i) => i,
+            Err(_) => panic!("negative address {}", addr),
+        };
+        if i >= self.dense.len() && i < DENSE_LIMIT && i <= 2 * self.dense.len() + MIN_DENSE_GROWTH
+        {
+            self.grow(i + 1);
+        }
+        let previous = match self.dense.get_mut(i) {
+            Some(cell) => cell.replace(value),
+            None => self.sparse.insert(addr, value),
+        };
+        if previous.is_none() {
+            self.count += 1;
+        }
+    }
+
+    fn erase(&mut self, addr: Word) {
+        let previous = match usize::try_from(addr.0) {
+            Ok(i) if i < self.dense.len() => self.dense[i].take(),
+            _ => self.sparse.remove(&addr),
+        };
+        if previous.is_some() {
+            self.count -= 1;
+        }
+    }
+
+    fn dump(&self) -> Box<dyn Iterator<Item = (Word, Word)> + '_> {
+        Box::new(
+            self.dense
+                .iter()
+                .enumerate()
+                .filter_map(|(i, cell)| cell.map(|w| (Word(i as WordInt), w)))
+                .chain(self.sparse.iter().map(|(addr, w)| (*addr, *w))),
+        )
+    }
+
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    fn clone_box(&self) -> Box<dyn MemoryBackend> {
+        Box::new(self.clone())
+    }
+
+    fn empty(&self) -> Box<dyn MemoryBackend> {
+        Box::new(DenseMemory::new())
+    }
+}
+
+/// A backend which keeps every word in a map.  This is slower than
+/// `DenseMemory` for most programs, but uses memory only for the
+/// addresses actually written, however scattered they are.
+#[derive(Debug, Clone, Default)]
+pub struct SparseMemory {
+    words: BTreeMap<Word, Word>,
+}
+
+impl SparseMemory {
+    pub fn new() -> SparseMemory {
+        SparseMemory::default()
+    }
+}
+
+impl MemoryBackend for SparseMemory {
+    fn fetch(&self, addr: Word) -> Option<Word> {
+        self.words.get(&addr).copied()
+    }
+
+    fn store(&mut self, addr: Word, value: Word) {
+        self.words.insert(addr, value);
+    }
+
+    fn erase(&mut self, addr: Word) {
+        self.words.remove(&addr);
+    }
+
+    fn dump(&self) -> Box<dyn Iterator<Item = (Word, Word)> + '_> {
+        Box::new(self.words.iter().map(|(addr, w)| (*addr, *w)))
+    }
+
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn clone_box(&self) -> Box<dyn MemoryBackend> {
+        Box::new(self.clone())
+    }
+
+    fn empty(&self) -> Box<dyn MemoryBackend> {
+        Box::new(SparseMemory::new())
+    }
+}
+
+/// The number of words in a page of a `PagedMemory`.
+const PAGE_SIZE: usize = 4096;
+
+/// A backend which keeps words in fixed-size pages, allocated when
+/// something is first written to them.  This suits programs which
+/// use several widely separated blocks of memory.
+#[derive(Debug, Clone, Default)]
+pub struct PagedMemory {
+    pages: BTreeMap<WordInt, Box<[Option<Word>]>>,
+    /// The number of addresses which have been written.
+    count: usize,
+}
+
+impl PagedMemory {
+    pub fn new() -> PagedMemory {
+        PagedMemory::default()
+    }
+
+    /// The number of the page holding `addr`, and the offset of
+    /// `addr` within it.
+    fn locate(addr: Word) -> (WordInt, usize) {
+        let size = PAGE_SIZE as WordInt;
+        (addr.0 / size, (addr.0 % size) as usize)
+    }
+}
+
+impl MemoryBackend for PagedMemory {
+    fn fetch(&self, addr: Word) -> Option<Word> {
+        let (page, offset) = PagedMemory::locate(addr);
+        self.pages.get(&page).and_then(|words| words[offset])
+    }
+
+    fn store(&mut self, addr: Word, value: Word) {
+        let (page, offset) = PagedMemory::locate(addr);
+        let words = self
+            .pages
+            .entry(page)
+            .or_insert_with(|| vec![None; PAGE_SIZE].into_boxed_slice());
+        if words[offset].replace(value).is_none() {
+            self.count += 1;
+        }
+    }
+
+    fn erase(&mut self, addr: Word) {
+        let (page, offset) = PagedMemory::locate(addr);
+        if let Some(words) = self.pages.get_mut(&page) {
+            if words[offset].take().is_some() {
+                self.count -= 1;
+            }
+        }
+    }
+
+    fn dump(&self) -> Box<dyn Iterator<Item = (Word, Word)> + '_> {
+        Box::new(self.pages.iter().flat_map(|(page, words)| {
+            let base = page * PAGE_SIZE as WordInt;
+            words
+                .iter()
+                .enumerate()
+                .filter_map(move |(i, cell)| cell.map(|w| (Word(base + i as WordInt), w)))
+        }))
+    }
+
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    fn clone_box(&self) -> Box<dyn MemoryBackend> {
+        Box::new(self.clone())
+    }
+
+    fn empty(&self) -> Box<dyn MemoryBackend> {
+        Box::new(PagedMemory::new())
+    }
+}
+
+#[test]
+fn test_dense_memory() {
+    let mut memory = DenseMemory::new();
+    assert!(memory.is_empty());
+    memory.store(Word(1_000_000_000), Word(1));
+    memory.store(Word(5000), Word(2));
+    memory.store(Word(3), Word(3));
+    memory.store(Word(3), Word(4));
+    assert_eq!(memory.len(), 3);
+    assert_eq!(memory.fetch(Word(3)), Some(Word(4)));
+    assert_eq!(memory.fetch(Word(4)), None);
+    assert_eq!(memory.fetch(Word(2_000_000_000)), None);
+    // Address 5000 was too far beyond the dense part at first, but
+    // is taken over as the dense part grows.
+    assert!(memory.dense.len() < 5000);
+    memory.store(Word(2040), Word(5));
+    memory.store(Word(5100), Word(6));
+    assert!(memory.dense.len() > 5000);
+    assert_eq!(memory.fetch(Word(5000)), Some(Word(2)));
+    assert_eq!(
+        memory.dump().collect::<Vec<_>>(),
+        vec![
+            (Word(3), Word(4)),
+            (Word(2040), Word(5)),
+            (Word(5000), Word(2)),
+            (Word(5100), Word(6)),
+            (Word(1_000_000_000), Word(1)),
+        ]
+    );
+    assert_eq!(memory.len(), 5);
+}
+
+#[test]
+fn test_backends_agree() {
+    let mut backends: Vec<Box<dyn MemoryBackend>> = vec![
+        Box::new(DenseMemory::new()),
+        Box::new(SparseMemory::new()),
+        Box::new(PagedMemory::new()),
+    ];
+    for backend in backends.iter_mut() {
+        backend.load(Word(4094), &[Word(1), Word(2), Word(3)]);
+        backend.store(Word(7_000_000_000), Word(4));
+        backend.store(Word(4095), Word(5));
+    }
+    for backend in backends.iter() {
+        assert_eq!(backend.len(), 4, "{:?}", backend);
+        assert_eq!(backend.fetch(Word(4096)), Some(Word(3)), "{:?}", backend);
+        assert_eq!(backend.fetch(Word(4097)), None, "{:?}", backend);
+        assert_eq!(
+            backend.clone().dump().collect::<Vec<_>>(),
+            vec![
+                (Word(4094), Word(1)),
+                (Word(4095), Word(5)),
+                (Word(4096), Word(3)),
+                (Word(7_000_000_000), Word(4)),
+            ]
+        );
+    }
+    for backend in backends.iter_mut() {
+        backend.erase(Word(4095));
+        backend.erase(Word(4097));
+        backend.erase(Word(9_000_000_000));
+        assert_eq!(backend.len(), 3, "{:?}", backend);
+        assert_eq!(backend.fetch(Word(4095)), None, "{:?}", backend);
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+
+use super::{Processor, Word};
+
+/// The addresses at which a program is paused before executing the
+/// instruction there.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Breakpoints {
+    addresses: BTreeSet<Word>,
+    /// The breakpoint which was just hit.  The instruction there is
+    /// executed when the program is resumed, instead of hitting the
+    /// breakpoint again.
+    resume_at: Option<Word>,
+}
+
+impl Processor {
+    /// Pause the program whenever it is about to execute the
+    /// instruction at `addr`.  The methods which run the program
+    /// then return `CpuStatus::BreakpointHit` (or
+    /// `MachineEvent::BreakpointHit`) without executing it.  Running
+    /// the program again executes it and carries on.
+    pub fn add_breakpoint(&mut self, addr: Word) {
+        self.breakpoints.addresses.insert(addr);
+    }
+
+    /// Remove the breakpoint at `addr`, returning whether there was
+    /// one.
+    pub fn remove_breakpoint(&mut self, addr: Word) -> bool {
+        self.breakpoints.addresses.remove(&addr)
+    }
+
+    /// The addresses of the breakpoints, in order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = Word> + '_ {
+        self.breakpoints.addresses.iter().copied()
+    }
+
+    /// The address of the breakpoint at which the program should
+    /// pause now, if any.
+    pub(super) fn check_breakpoint(&mut self) -> Option<Word> {
+        if self.breakpoints.resume_at == Some(self.pc)
+            || !self.breakpoints.addresses.contains(&self.pc)
+        {
+            return None;
+        }
+        self.breakpoints.resume_at = Some(self.pc);
+        Some(self.pc)
+    }
+
+    /// Note that the program is about to execute an instruction, so
+    /// that a breakpoint at the same address is hit next time.
+    pub(super) fn leave_breakpoint(&mut self) {
+        self.breakpoints.resume_at = None;
+    }
+}
+
+#[test]
+fn test_breakpoints() {
+    use super::{encode_program, CpuStatus, Instr, MachineEvent, Param};
+
+    // Count down from 3, writing each number.
+    let program = encode_program(&[
+        Instr::add(Param::Imm(3), Param::Imm(0), 20),
+        Instr::write(Param::Pos(20)),
+        Instr::add(Param::Pos(20), Param::Imm(-1), 20),
+        Instr::jump_if_true(Param::Pos(20), Param::Imm(4)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.add_breakpoint(Word(6));
+    cpu.add_breakpoint(Word(13));
+    assert_eq!(
+        cpu.breakpoints().collect::<Vec<_>>(),
+        vec![Word(6), Word(13)]
+    );
+    let mut output: Vec<Word> = Vec::new();
+    let mut no_input = || Err(super::InputOutputError::NoInput);
+    assert_eq!(
+        cpu.run_with_io(&mut no_input, &mut output)
+            .expect("no fault"),
+        CpuStatus::BreakpointHit(Word(6))
+    );
+    assert_eq!(output, vec![Word(3)]);
+    // Resuming executes the instruction at the breakpoint.
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::ProducedOutput(Word(2))
+    );
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::BreakpointHit(Word(6))
+    );
+    assert!(cpu.remove_breakpoint(Word(6)));
+    assert!(!cpu.remove_breakpoint(Word(6)));
+    assert_eq!(
+        cpu.run_with_io(&mut no_input, &mut output)
+            .expect("no fault"),
+        CpuStatus::BreakpointHit(Word(13))
+    );
+    assert_eq!(output, vec![Word(3), Word(1)]);
+    assert_eq!(
+        cpu.run_with_io(&mut no_input, &mut output)
+            .expect("no fault"),
+        CpuStatus::Halt
+    );
+}
@@ -0,0 +1,242 @@
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::factory::apply_global_options;
+use super::{
+    Annotations, CpuFault, DenseMemory, MemoryBackend, OverflowMode, Processor, TraceConfig,
+    TraceOutput, TraceSink, Word,
+};
+
+/// Sets up a `Processor` step by step; see `Processor::builder`.
+/// For example,
+///
+/// ```text
+/// let mut cpu = Processor::builder()
+///     .program(&program)
+///     .input(&[Word(5)])
+///     .strict_reads()
+///     .with_global_options()
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct ProcessorBuilder {
+    backend: Option<Box<dyn MemoryBackend>>,
+    loads: Vec<(Word, Vec<Word>)>,
+    initial_pc: Word,
+    sink: Option<Box<dyn TraceSink>>,
+    trace_config: TraceConfig,
+    annotations: Option<Arc<Annotations>>,
+    input: Vec<Word>,
+    strict_reads: bool,
+    overflow: OverflowMode,
+    protected: Vec<Range<Word>>,
+    journal: Option<usize>,
+    global_options: bool,
+}
+
+impl Debug for ProcessorBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessorBuilder")
+            .field("backend", &self.backend)
+            .field("loads", &self.loads)
+            .field("initial_pc", &self.initial_pc)
+            .field("sink", &self.sink.is_some())
+            .field("trace_config", &self.trace_config)
+            .field("annotations", &self.annotations)
+            .field("input", &self.input)
+            .field("strict_reads", &self.strict_reads)
+            .field("overflow", &self.overflow)
+            .field("protected", &self.protected)
+            .field("journal", &self.journal)
+            .field("global_options", &self.global_options)
+            .finish()
+    }
+}
+
+impl ProcessorBuilder {
+    /// Load `program` at address 0.
+    pub fn program(self, program: &[Word]) -> ProcessorBuilder {
+        self.load(Word(0), program)
+    }
+
+    /// Load `words` at `base`.  Loads happen in the order given.
+    pub fn load(mut self, base: Word, words: &[Word]) -> ProcessorBuilder {
+        self.loads.push((base, words.to_vec()));
+        self
+    }
+
+    /// Start the program at `pc` rather than at address 0.
+    pub fn initial_pc(mut self, pc: Word) -> ProcessorBuilder {
+        self.initial_pc = pc;
+        self
+    }
+
+    /// Keep memory in `backend` rather than a `DenseMemory`.
+    pub fn backend(mut self, backend: Box<dyn MemoryBackend>) -> ProcessorBuilder {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Give the trace to `sink` (see `Processor::enable_tracing_to_sink`).
+    pub fn trace<S>(mut self, sink: S) -> ProcessorBuilder
+    where
+        S: TraceSink + 'static,
+    {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Trace as specified by `config`, rather than every event.
+    pub fn trace_config(mut self, config: TraceConfig) -> ProcessorBuilder {
+        self.trace_config = config;
+        self
+    }
+
+    /// Show the names of addresses given in `annotations` in the
+    /// trace.
+    pub fn annotate(mut self, annotations: Arc<Annotations>) -> ProcessorBuilder {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Queue `words` for the program to read (see
+    /// `Processor::provide_input`).
+    pub fn input(mut self, words: &[Word]) -> ProcessorBuilder {
+        self.input.extend_from_slice(words);
+        self
+    }
+
+    /// See `Processor::set_strict_reads`.
+    pub fn strict_reads(mut self) -> ProcessorBuilder {
+        self.strict_reads = true;
+        self
+    }
+
+    /// See `Processor::set_overflow_mode`.
+    pub fn overflow_mode(mut self, mode: OverflowMode) -> ProcessorBuilder {
+        self.overflow = mode;
+        self
+    }
+
+    /// See `Processor::protect`.
+    pub fn protect(mut self, range: Range<Word>) -> ProcessorBuilder {
+        self.protected.push(range);
+        self
+    }
+
+    /// See `Processor::enable_journal`.
+    pub fn journal(mut self, limit: usize) -> ProcessorBuilder {
+        self.journal = Some(limit);
+        self
+    }
+
+    /// Also apply the options given for every processor, such as
+    /// `--trace` and `--strict-reads`, as `new_processor` does.
+    /// Settings made with this builder take precedence.
+    pub fn with_global_options(mut self) -> ProcessorBuilder {
+        self.global_options = true;
+        self
+    }
+
+    /// Make the processor.  This fails if something cannot be loaded
+    /// where it was asked to be.
+    pub fn build(self) -> Result<Processor, CpuFault> {
+        let backend = self.backend.unwrap_or_else(|| Box::new(DenseMemory::new()));
+        let mut cpu = Processor::with_backend(self.initial_pc, backend);
+        for (base, words) in self.loads.iter() {
+            cpu.load(*base, words)?;
+        }
+        if self.global_options {
+            apply_global_options(&mut cpu)?;
+        }
+        if let Some(sink) = self.sink {
+            cpu.tracer
+                .enable(TraceOutput::Sink(sink, Vec::new()), self.trace_config);
+        }
+        if let Some(annotations) = self.annotations {
+            cpu.annotate(annotations);
+        }
+        for w in self.input {
+            cpu.provide_input(w);
+        }
+        if self.strict_reads {
+            cpu.set_strict_reads(true);
+        }
+        cpu.set_overflow_mode(self.overflow);
+        for range in self.protected {
+            cpu.protect(range);
+        }
+        if let Some(limit) = self.journal {
+            cpu.enable_journal(limit);
+        }
+        Ok(cpu)
+    }
+}
+
+impl Processor {
+    /// Start setting up a processor.  By default, it has empty
+    /// memory and starts at address 0.
+    pub fn builder() -> ProcessorBuilder {
+        ProcessorBuilder::default()
+    }
+}
+
+#[test]
+fn test_builder() {
+    use super::{encode_program, Instr, MachineEvent, Param, SparseMemory};
+    use std::sync::Mutex;
+
+    // Read a word into 20, and write twice its value; then halt.
+    let program = encode_program(&[
+        Instr::read(20),
+        Instr::mul(Param::Pos(20), Param::Imm(2), 20),
+        Instr::write(Param::Pos(20)),
+        Instr::stop(),
+    ]);
+    let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&lines);
+    let mut cpu = Processor::builder()
+        .load(Word(100), &program)
+        .initial_pc(Word(100))
+        .backend(Box::new(SparseMemory::new()))
+        .input(&[Word(21)])
+        .trace(move |line: &str| log.lock().expect("not poisoned").push(line.to_string()))
+        .trace_config(TraceConfig::sample_every(100))
+        .build()
+        .expect("processor should build");
+    assert_eq!(cpu.pc(), Word(100));
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::ProducedOutput(Word(42))
+    );
+    assert_eq!(
+        lines.lock().expect("not poisoned")[1..],
+        [
+            "0 @100: execute 3",
+            "1 io-read:21",
+            "2 @20: store 21",
+            "9 io-write:42"
+        ]
+    );
+
+    // Strict reads and protection do not stop a well-behaved
+    // program.
+    let mut strict = Processor::builder()
+        .program(&program)
+        .strict_reads()
+        .protect(Word(0)..Word(20))
+        .journal(1)
+        .build()
+        .expect("processor should build");
+    strict.provide_input(Word(1));
+    assert_eq!(
+        strict.run_until_blocked().expect("no fault"),
+        MachineEvent::ProducedOutput(Word(2))
+    );
+    assert_eq!(strict.undoable_steps(), 1);
+    assert!(matches!(
+        Processor::builder().load(Word(-1), &program).build(),
+        Err(CpuFault::MemoryFault)
+    ));
+}
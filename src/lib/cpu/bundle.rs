@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{new_processor, parse_program_line, CpuFault, Processor, Word};
+use crate::error::Fail;
+use crate::input::open_decompressed;
+
+/// An Intcode program together with what is known about it: where
+/// it starts, what input it should be given, what it should output
+/// and the names of interesting addresses.
+///
+/// Bundles are stored as text.  A header of `key = value` lines is
+/// followed by an optional `[symbols]` section of `address = "name"`
+/// lines, then a `[program]` section containing the program in the
+/// usual comma-separated form:
+///
+/// ```text
+/// name = "echo"
+/// entry = 0
+/// inputs = [7]
+/// expected_outputs = [7]
+///
+/// [symbols]
+/// 5 = "buffer"
+///
+/// [program]
+/// 3,5,4,5,99,0
+/// ```
+///
+/// All the header fields are optional.  Lines starting with '#' are
+/// ignored, except within the program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramBundle {
+    pub name: Option<String>,
+    pub entry: Word,
+    pub inputs: Vec<Word>,
+    pub expected_outputs: Option<Vec<Word>>,
+    pub symbols: BTreeMap<Word, String>,
+    pub program: Vec<Word>,
+}
+
+/// A program bundle could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadBundle(pub String);
+
+impl Display for BadBundle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad program bundle: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadBundle {}
+
+impl From<BadBundle> for Fail {
+    fn from(e: BadBundle) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+fn bad(line_number: usize, msg: String) -> BadBundle {
+    BadBundle(format!("line {}: {}", line_number, msg))
+}
+
+fn parse_word(line_number: usize, value: &str) -> Result<Word, BadBundle> {
+    value
+        .trim()
+        .parse()
+        .map(Word)
+        .map_err(|e| bad(line_number, format!("bad number '{}': {}", value.trim(), e)))
+}
+
+fn parse_word_list(line_number: usize, value: &str) -> Result<Vec<Word>, BadBundle> {
+    let inner = value
+        .trim()
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .ok_or_else(|| bad(line_number, format!("expected a list but got '{}'", value)))?;
+    inner
+        .split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| parse_word(line_number, item))
+        .collect()
+}
+
+fn parse_string(line_number: usize, value: &str) -> Result<String, BadBundle> {
+    let inner = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| {
+            bad(
+                line_number,
+                format!("expected a string but got '{}'", value),
+            )
+        })?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(escaped @ ('\\' | '"')) => result.push(escaped),
+                _ => {
+                    return Err(bad(
+                        line_number,
+                        format!("bad escape sequence in {}", value.trim()),
+                    ));
+                }
+            },
+            '"' => {
+                return Err(bad(
+                    line_number,
+                    format!("unescaped quote in {}", value.trim()),
+                ));
+            }
+            _ => result.push(ch),
+        }
+    }
+    Ok(result)
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn write_word_list(f: &mut Formatter<'_>, words: &[Word]) -> fmt::Result {
+    f.write_str("[")?;
+    for (i, w) in words.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", w)?;
+    }
+    f.write_str("]")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Header,
+    Symbols,
+    Program,
+}
+
+impl FromStr for ProgramBundle {
+    type Err = BadBundle;
+
+    fn from_str(s: &str) -> Result<ProgramBundle, BadBundle> {
+        let mut bundle = ProgramBundle::default();
+        let mut section = Section::Header;
+        for (i, line) in s.lines().enumerate() {
+            let line_number = i + 1;
+            let trimmed = line.trim();
+            if section == Section::Program {
+                parse_program_line(line, line_number, &mut bundle.program)
+                    .map_err(|e| BadBundle(e.to_string()))?;
+                continue;
+            }
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            match trimmed {
+                "[symbols]" => {
+                    section = Section::Symbols;
+                    continue;
+                }
+                "[program]" => {
+                    section = Section::Program;
+                    continue;
+                }
+                _ => (),
+            }
+            let (key, value) = trimmed.split_once('=').ok_or_else(|| {
+                bad(
+                    line_number,
+                    format!("expected key = value but got '{}'", trimmed),
+                )
+            })?;
+            let key = key.trim();
+            match (section, key) {
+                (Section::Symbols, _) => {
+                    let addr = parse_word(line_number, key)?;
+                    bundle
+                        .symbols
+                        .insert(addr, parse_string(line_number, value)?);
+                }
+                (_, "name") => {
+                    bundle.name = Some(parse_string(line_number, value)?);
+                }
+                (_, "entry") => {
+                    bundle.entry = parse_word(line_number, value)?;
+                }
+                (_, "inputs") => {
+                    bundle.inputs = parse_word_list(line_number, value)?;
+                }
+                (_, "expected_outputs") => {
+                    bundle.expected_outputs = Some(parse_word_list(line_number, value)?);
+                }
+                _ => {
+                    return Err(bad(line_number, format!("unknown key '{}'", key)));
+                }
+            }
+        }
+        if section != Section::Program {
+            return Err(BadBundle("there is no [program] section".to_string()));
+        }
+        Ok(bundle)
+    }
+}
+
+impl Display for ProgramBundle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name.as_ref() {
+            writeln!(f, "name = {}", quote(name))?;
+        }
+        writeln!(f, "entry = {}", self.entry)?;
+        if !self.inputs.is_empty() {
+            f.write_str("inputs = ")?;
+            write_word_list(f, &self.inputs)?;
+            writeln!(f)?;
+        }
+        if let Some(expected) = self.expected_outputs.as_ref() {
+            f.write_str("expected_outputs = ")?;
+            write_word_list(f, expected)?;
+            writeln!(f)?;
+        }
+        if !self.symbols.is_empty() {
+            writeln!(f, "\n[symbols]")?;
+            for (addr, name) in self.symbols.iter() {
+                writeln!(f, "{} = {}", addr, quote(name))?;
+            }
+        }
+        writeln!(f, "\n[program]")?;
+        for (i, w) in self.program.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", w)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl ProgramBundle {
+    /// A bundle containing just `program`.
+    pub fn new(program: Vec<Word>) -> ProgramBundle {
+        ProgramBundle {
+            program,
+            ..ProgramBundle::default()
+        }
+    }
+
+    /// Read a bundle from the file `path`, which may be compressed.
+    pub fn from_file(path: &Path) -> Result<ProgramBundle, Fail> {
+        let mut text = String::new();
+        open_decompressed(path)
+            .and_then(|mut r| r.read_to_string(&mut text))
+            .map_err(|e| Fail(format!("failed to read {}: {}", path.display(), e)))?;
+        Ok(text.parse()?)
+    }
+
+    /// The name of the address `addr`, if it has one.
+    pub fn symbol(&self, addr: Word) -> Option<&str> {
+        self.symbols.get(&addr).map(String::as_str)
+    }
+
+    /// A processor with the program loaded, ready to start at its
+    /// entry point with the bundle's inputs queued up (see
+    /// `Processor::provide_input`).
+    pub fn processor(&self) -> Result<Processor, CpuFault> {
+        let mut cpu = new_processor(&self.program)?;
+        cpu.set_pc(self.entry);
+        for w in self.inputs.iter() {
+            cpu.provide_input(*w);
+        }
+        Ok(cpu)
+    }
+
+    /// Run the program until it halts or needs more input than the
+    /// bundle provides, and return its output.
+    pub fn run(&self) -> Result<Vec<Word>, CpuFault> {
+        let mut cpu = self.processor()?;
+        let outputs = cpu.outputs().collect();
+        outputs
+    }
+
+    /// Run the program and compare its output with the expected
+    /// output.  Returns `None` if no output is expected.
+    pub fn check(&self) -> Result<Option<bool>, CpuFault> {
+        match self.expected_outputs.as_ref() {
+            Some(expected) => Ok(Some(self.run()? == *expected)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[test]
+fn test_bundle() {
+    let text = concat!(
+        "# Echo the input, twice.\n",
+        "name = \"echo \\\"twice\\\"\"\n",
+        "entry = 2\n",
+        "inputs = [7]\n",
+        "expected_outputs = [7, 7]\n",
+        "\n",
+        "[symbols]\n",
+        "9 = \"buffer\"\n",
+        "\n",
+        "[program]\n",
+        "0,0,3,9,\n",
+        "4,9,4,9,99,0\n",
+    );
+    let bundle: ProgramBundle = text.parse().expect("the bundle is valid");
+    assert_eq!(bundle.name.as_deref(), Some("echo \"twice\""));
+    assert_eq!(bundle.entry, Word(2));
+    assert_eq!(bundle.symbol(Word(9)), Some("buffer"));
+    assert_eq!(bundle.program.len(), 10);
+    assert_eq!(bundle.run().expect("no fault"), vec![Word(7), Word(7)]);
+    assert_eq!(bundle.check().expect("no fault"), Some(true));
+    let again: ProgramBundle = bundle.to_string().parse().expect("round trip");
+    assert_eq!(again, bundle);
+    assert_eq!(
+        ProgramBundle::new(vec![Word(99)])
+            .check()
+            .expect("no fault"),
+        None
+    );
+}
+
+#[test]
+fn test_bad_bundle() {
+    assert_eq!(
+        "entry = 0\n".parse::<ProgramBundle>(),
+        Err(BadBundle("there is no [program] section".to_string()))
+    );
+    assert_eq!(
+        "colour = 3\n[program]\n99\n".parse::<ProgramBundle>(),
+        Err(BadBundle("line 1: unknown key 'colour'".to_string()))
+    );
+    assert!("[symbols]\nx = \"y\"\n[program]\n99\n"
+        .parse::<ProgramBundle>()
+        .is_err());
+    assert!("[program]\n1,,2\n".parse::<ProgramBundle>().is_err());
+}
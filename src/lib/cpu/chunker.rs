@@ -0,0 +1,129 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{InputOutputError, Word};
+use crate::error::Fail;
+
+/// The program's output stopped part-way through a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompleteChunk {
+    pub expected: usize,
+    pub pending: Vec<Word>,
+}
+
+impl Display for IncompleteChunk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "program output ended with an incomplete group of {} words (expected {}): {:?}",
+            self.pending.len(),
+            self.expected,
+            self.pending
+        )
+    }
+}
+
+impl std::error::Error for IncompleteChunk {}
+
+impl From<IncompleteChunk> for Fail {
+    fn from(e: IncompleteChunk) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// Groups the words written by a program into arrays of `N` words
+/// and passes each complete group to a callback.  This suits
+/// programs whose output is a sequence of fixed-size records, such
+/// as the (x, y, tile) draw commands of day 13.
+pub struct OutputChunker<const N: usize, F>
+where
+    F: FnMut([Word; N]) -> Result<(), InputOutputError>,
+{
+    pending: [Word; N],
+    len: usize,
+    callback: F,
+}
+
+impl<const N: usize, F> OutputChunker<N, F>
+where
+    F: FnMut([Word; N]) -> Result<(), InputOutputError>,
+{
+    pub fn new(callback: F) -> OutputChunker<N, F> {
+        assert!(N > 0, "chunks must contain at least one word");
+        OutputChunker {
+            pending: [Word(0); N],
+            len: 0,
+            callback,
+        }
+    }
+
+    /// Accept one output word, invoking the callback if this
+    /// completes a chunk.  The signature suits use as (or within)
+    /// the output function passed to `Processor::run_with_io`.
+    pub fn put(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.pending[self.len] = w;
+        self.len += 1;
+        if self.len == N {
+            self.len = 0;
+            (self.callback)(self.pending)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The words of the current chunk received so far.  This is
+    /// empty when the output so far consists only of whole chunks.
+    pub fn pending(&self) -> &[Word] {
+        &self.pending[0..self.len]
+    }
+
+    /// Indicate that the program's output is complete.  Returns an
+    /// error if the output ended part-way through a chunk.
+    pub fn finish(self) -> Result<(), IncompleteChunk> {
+        if self.len == 0 {
+            Ok(())
+        } else {
+            Err(IncompleteChunk {
+                expected: N,
+                pending: self.pending[0..self.len].to_vec(),
+            })
+        }
+    }
+}
+
+#[test]
+fn test_chunker_complete() {
+    let mut got: Vec<[Word; 3]> = Vec::new();
+    let mut chunker = OutputChunker::new(|chunk: [Word; 3]| {
+        got.push(chunk);
+        Ok(())
+    });
+    for n in 1..=6 {
+        chunker.put(Word(n)).expect("callback should not fail");
+    }
+    assert_eq!(chunker.finish(), Ok(()));
+    assert_eq!(
+        got,
+        vec![[Word(1), Word(2), Word(3)], [Word(4), Word(5), Word(6)]]
+    );
+}
+
+#[test]
+fn test_chunker_incomplete() {
+    let mut count: usize = 0;
+    let mut chunker = OutputChunker::new(|_: [Word; 2]| {
+        count += 1;
+        Ok(())
+    });
+    for n in 1..=3 {
+        chunker.put(Word(n)).expect("callback should not fail");
+    }
+    assert_eq!(chunker.pending(), &[Word(3)]);
+    assert_eq!(
+        chunker.finish(),
+        Err(IncompleteChunk {
+            expected: 2,
+            pending: vec![Word(3)],
+        })
+    );
+    assert_eq!(count, 1);
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{CpuFault, InputOutputError, Processor, Word};
+
+/// The number of recently-executed instructions kept for inclusion
+/// in a core dump.
+const RECENT_INSTRUCTIONS: usize = 32;
+
+/// Where to write a core dump, and the recent history of the program
+/// to include in it.
+#[derive(Debug)]
+pub(super) struct CoreDumper {
+    path: PathBuf,
+    recent: VecDeque<(Word, Word)>,
+}
+
+impl CoreDumper {
+    pub(super) fn record(&mut self, pc: Word, instruction: Word) {
+        if self.recent.len() == RECENT_INSTRUCTIONS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((pc, instruction));
+    }
+}
+
+impl Processor {
+    /// If the program faults, write a core dump to `path`.  The core
+    /// dump is a saved `MachineState`, preceded by comments giving
+    /// the fault and the most recently executed instructions, so it
+    /// can be read back with `MachineState::from_str` and resumed
+    /// with `restore`.  Running out of input is not a fault for this
+    /// purpose.
+    pub fn dump_core_on_fault(&mut self, path: &Path) {
+        self.core_dumper = Some(CoreDumper {
+            path: path.to_path_buf(),
+            recent: VecDeque::with_capacity(RECENT_INSTRUCTIONS),
+        });
+    }
+
+    /// Write a core dump describing `fault` to `out`.
+    pub fn write_core_dump<W: Write>(&self, out: &mut W, fault: &CpuFault) -> io::Result<()> {
+        writeln!(out, "# fault: {}", fault)?;
+        if let Some(dumper) = self.core_dumper.as_ref() {
+            writeln!(out, "# recently executed instructions:")?;
+            for (pc, instruction) in dumper.recent.iter() {
+                writeln!(out, "#   @{}: {}", pc, instruction)?;
+            }
+        }
+        write!(out, "{}", self.snapshot())?;
+        out.flush()
+    }
+
+    /// Called when the program faults; writes a core dump if one was
+    /// requested.
+    pub(super) fn handle_fault(&self, fault: &CpuFault) {
+        if matches!(fault, CpuFault::IOError(InputOutputError::NoInput)) {
+            return;
+        }
+        if let Some(dumper) = self.core_dumper.as_ref() {
+            let written = File::create(&dumper.path)
+                .and_then(|f| self.write_core_dump(&mut BufWriter::new(f), fault));
+            match written {
+                Ok(()) => {
+                    eprintln!("core dumped to {}", dumper.path.display());
+                }
+                Err(e) => {
+                    eprintln!(
+                        "failed to write core dump to {}: {}",
+                        dumper.path.display(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_core_dump() {
+    use super::MachineState;
+
+    // Add 1 to location 7 and then execute it; 42 is not a valid opcode.
+    let program: Vec<Word> = [1001, 7, 1, 7, 1105, 1, 7, 41]
+        .iter()
+        .map(|n| Word(*n))
+        .collect();
+    let path = std::env::temp_dir().join(format!("test_core_dump.{}", std::process::id()));
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.dump_core_on_fault(&path);
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    assert!(cpu.run_with_fixed_input(&[], &mut discard).is_err());
+    let dump = std::fs::read_to_string(&path).expect("core dump should exist");
+    std::fs::remove_file(&path).expect("core dump should be removable");
+    assert!(dump.starts_with("# fault: "), "dump is {:?}", dump);
+    assert!(dump.contains("#   @0: 1001\n#   @4: 1105\n#   @7: 42\n"));
+    let state: MachineState = dump.parse().expect("core dump should parse");
+    assert_eq!(state.pc, Word(7));
+    assert_eq!(state.fetch(Word(7)), Word(42));
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use super::instr::opcode_info;
+use super::{AccessKind, Opcode, Processor, Word, WordInt};
+
+/// How the program has used one address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// The address is part of an instruction which was executed.
+    pub executed: bool,
+    /// An operand was loaded from the address.
+    pub loaded: bool,
+    /// A result was stored into the address.
+    pub stored: bool,
+}
+
+impl Usage {
+    /// Whether the address was loaded from or stored into, but never
+    /// executed.
+    pub fn is_data(&self) -> bool {
+        !self.executed && (self.loaded || self.stored)
+    }
+}
+
+/// The use a program has made of each address, in address order.
+/// Addresses which are not in the map were never used.  Together
+/// with the disassembler, this shows which parts of a program are
+/// code, which are data and which were never reached.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage(BTreeMap<Word, Usage>);
+
+impl Coverage {
+    /// How the program has used `addr`.
+    pub fn get(&self, addr: Word) -> Usage {
+        self.0.get(&addr).copied().unwrap_or_default()
+    }
+
+    /// The addresses which have been used, with how, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (Word, Usage)> + '_ {
+        self.0.iter().map(|(addr, usage)| (*addr, *usage))
+    }
+
+    /// The addresses which are part of instructions which were
+    /// executed, in order.
+    pub fn executed(&self) -> impl Iterator<Item = Word> + '_ {
+        self.iter()
+            .filter(|(_, usage)| usage.executed)
+            .map(|(addr, _)| addr)
+    }
+
+    /// The addresses which were loaded from or stored into, but never
+    /// executed, in order.
+    pub fn data(&self) -> impl Iterator<Item = Word> + '_ {
+        self.iter()
+            .filter(|(_, usage)| usage.is_data())
+            .map(|(addr, _)| addr)
+    }
+
+    /// The runs of addresses in `range` which were never used at all,
+    /// in order.  For the range of the loaded program, these are
+    /// typically code which was never reached.
+    pub fn unused(&self, range: Range<Word>) -> Vec<Range<Word>> {
+        let mut result: Vec<Range<Word>> = Vec::new();
+        let mut start = range.start;
+        for addr in self.0.range(range.clone()).map(|(addr, _)| *addr) {
+            if start < addr {
+                result.push(start..addr);
+            }
+            start = Word(addr.0 + 1);
+        }
+        if start < range.end {
+            result.push(start..range.end);
+        }
+        result
+    }
+
+    fn usage(&mut self, addr: Word) -> &mut Usage {
+        self.0.entry(addr).or_default()
+    }
+}
+
+impl Processor {
+    /// Start recording which addresses the program uses, and how,
+    /// from scratch.  The result can be retrieved with `coverage`.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::default());
+    }
+
+    /// The use the program has made of each address so far, if
+    /// coverage is being recorded.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Account for the execution of the instruction at `pc`, with
+    /// opcode `op`.
+    pub(super) fn cover_instruction(&mut self, pc: Word, op: Opcode) {
+        if let Some(coverage) = self.coverage.as_mut() {
+            let size = 1 + opcode_info(op).params as WordInt;
+            for addr in pc.0..pc.0 + size {
+                coverage.usage(Word(addr)).executed = true;
+            }
+        }
+    }
+
+    /// Account for an operand being loaded from, or a result stored
+    /// into, `addr`.
+    pub(super) fn cover_access(&mut self, kind: AccessKind, addr: Word) {
+        if let Some(coverage) = self.coverage.as_mut() {
+            let usage = coverage.usage(addr);
+            match kind {
+                AccessKind::Load => usage.loaded = true,
+                AccessKind::Store => usage.stored = true,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_coverage() {
+    use super::{encode_program, InputOutputError, Instr, Param};
+
+    // Copy the word at 14 to 15, skipping the output at 7; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::add(Param::Pos(14), Param::Imm(0), 15),
+        Instr::jump_if_true(Param::Imm(1), Param::Imm(9)),
+        Instr::write(Param::Imm(5)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.load(Word(14), &[Word(42)]).expect("data should load");
+    assert!(cpu.coverage().is_none());
+    cpu.enable_coverage();
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[], &mut discard)
+        .expect("program should run");
+    let coverage = cpu.coverage().expect("coverage is being recorded");
+    assert_eq!(
+        coverage.executed().collect::<Vec<_>>(),
+        [0, 1, 2, 3, 4, 5, 6, 9].map(Word).to_vec()
+    );
+    assert_eq!(
+        coverage.data().collect::<Vec<_>>(),
+        vec![Word(14), Word(15)]
+    );
+    assert_eq!(
+        coverage.get(Word(15)),
+        Usage {
+            executed: false,
+            loaded: false,
+            stored: true,
+        }
+    );
+    // Immediate operands are loaded from the instruction itself.
+    assert!(coverage.get(Word(2)).executed && coverage.get(Word(2)).loaded);
+    assert_eq!(
+        coverage.unused(Word(0)..Word(16)),
+        vec![Word(7)..Word(9), Word(10)..Word(14)]
+    );
+}
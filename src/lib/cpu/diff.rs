@@ -0,0 +1,183 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter, Write};
+
+use super::{MachineState, Memory, Processor, Word, WordInt};
+
+impl Memory {
+    /// The locations whose content differs between `self` and
+    /// `other`, in address order, as (address, value in `self`, value
+    /// in `other`).  Locations never written hold 0.
+    pub fn diff(&self, other: &Memory) -> Vec<(Word, Word, Word)> {
+        let addresses: BTreeSet<Word> = self
+            .content
+            .dump()
+            .chain(other.content.dump())
+            .map(|(addr, _)| addr)
+            .collect();
+        addresses
+            .into_iter()
+            .filter_map(|addr| {
+                let old = self.fetch(addr).ok()?;
+                let new = other.fetch(addr).ok()?;
+                if old == new {
+                    None
+                } else {
+                    Some((addr, old, new))
+                }
+            })
+            .collect()
+    }
+}
+
+/// What changed between two `MachineState`s.  Each change is given
+/// as the old value and the new one.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StateDiff {
+    pub pc: Option<(Word, Word)>,
+    pub relative_base: Option<(WordInt, WordInt)>,
+    /// The locations whose content changed, in address order, as
+    /// for `Memory::diff`.
+    pub memory: Vec<(Word, Word, Word)>,
+}
+
+impl StateDiff {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.pc.is_none() && self.relative_base.is_none() && self.memory.is_empty()
+    }
+}
+
+/// Shows one change per line, registers first.
+impl Display for StateDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some((old, new)) = self.pc {
+            writeln!(f, "pc: {} -> {}", old, new)?;
+        }
+        if let Some((old, new)) = self.relative_base {
+            writeln!(f, "relative_base: {} -> {}", old, new)?;
+        }
+        f.write_str(&format_memory_diff(&self.memory))
+    }
+}
+
+fn changed<T: PartialEq + Copy>(old: T, new: T) -> Option<(T, T)> {
+    if old == new {
+        None
+    } else {
+        Some((old, new))
+    }
+}
+
+impl MachineState {
+    /// What changed between `self` and the later state `other`.
+    /// Locations never written hold 0.
+    pub fn diff(&self, other: &MachineState) -> StateDiff {
+        let addresses: BTreeSet<Word> = self
+            .ram
+            .iter()
+            .chain(other.ram.iter())
+            .map(|(addr, _)| *addr)
+            .collect();
+        StateDiff {
+            pc: changed(self.pc, other.pc),
+            relative_base: changed(self.relative_base, other.relative_base),
+            memory: addresses
+                .into_iter()
+                .filter_map(|addr| {
+                    let (old, new) = changed(self.fetch(addr), other.fetch(addr))?;
+                    Some((addr, old, new))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Processor {
+    pub fn memory(&self) -> &Memory {
+        &self.ram
+    }
+}
+
+/// Format the output of `Memory::diff` for people to read, one
+/// changed location per line.
+pub fn format_memory_diff(diff: &[(Word, Word, Word)]) -> String {
+    let width = diff
+        .iter()
+        .map(|(addr, _, _)| addr.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut result = String::new();
+    for (addr, old, new) in diff {
+        // Writing to a String cannot fail.
+        let _ = writeln!(
+            result,
+            "{:>width$}: {} -> {}",
+            addr,
+            old,
+            new,
+            width = width
+        );
+    }
+    result
+}
+
+#[test]
+fn test_memory_diff() {
+    use super::{run_patched, InputOutputError};
+
+    // Add locations 5 and 6, storing the result in 7.
+    let program: Vec<Word> = [1, 5, 6, 7, 99, 20, 22, 0]
+        .iter()
+        .map(|n| Word(*n))
+        .collect();
+    let mut before = Processor::new(Word(0));
+    before.load(Word(0), &program).expect("program should load");
+    let mut after = before.clone();
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    after
+        .run_with_fixed_input(&[], &mut discard)
+        .expect("program should run");
+    let diff = before.memory().diff(after.memory());
+    assert_eq!(diff, vec![(Word(7), Word(0), Word(42))]);
+    assert_eq!(format_memory_diff(&diff), "7: 0 -> 42\n");
+
+    let patched = run_patched(&program, &[(Word(10), Word(-1))]).expect("program should run");
+    let mut patched_memory = Memory::new();
+    patched_memory
+        .load(Word(0), &patched)
+        .expect("memory should load");
+    assert_eq!(
+        format_memory_diff(&before.memory().diff(&patched_memory)),
+        concat!(" 7: 0 -> 42\n", "10: 0 -> -1\n")
+    );
+    assert!(after.memory().diff(after.memory()).is_empty());
+}
+
+#[test]
+fn test_state_diff() {
+    // Store 7 at 9, adjust the relative base by 3; then halt.
+    let program: Vec<Word> = [1101, 3, 4, 9, 109, 3, 99]
+        .iter()
+        .map(|n| Word(*n))
+        .collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let before = cpu.snapshot();
+    assert!(before.diff(&before).is_empty());
+    let mut output: Vec<Word> = Vec::new();
+    cpu.run_with_fixed_input(&[], &mut output)
+        .expect("program should run");
+    let diff = before.diff(&cpu.snapshot());
+    assert_eq!(
+        diff,
+        StateDiff {
+            pc: Some((Word(0), Word(6))),
+            relative_base: Some((0, 3)),
+            memory: vec![(Word(9), Word(0), Word(7))],
+        }
+    );
+    assert_eq!(
+        diff.to_string(),
+        "pc: 0 -> 6\nrelative_base: 0 -> 3\n9: 0 -> 7\n"
+    );
+}
@@ -0,0 +1,103 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::{Annotations, Instr, Memory, Param, Word, WordInt};
+
+/// The text of `param`, using the name of the address it refers to
+/// if there is one.  The immediate target of a jump refers to an
+/// address too.
+fn param_text(param: &Param, is_target: bool, annotations: &Annotations) -> String {
+    match param {
+        Param::Pos(n) => match annotations.name(Word(*n)) {
+            Some(name) => format!("[{}]", name),
+            None => param.to_string(),
+        },
+        Param::Imm(n) if is_target => match annotations.name(Word(*n)) {
+            Some(name) => name.to_string(),
+            None => param.to_string(),
+        },
+        _ => param.to_string(),
+    }
+}
+
+fn instruction_text(instr: &Instr, annotations: &Annotations) -> String {
+    let mut result = instr.mnemonic().to_string();
+    for (i, param) in instr.params().iter().enumerate() {
+        result.push_str(if i == 0 { " " } else { ", " });
+        result.push_str(&param_text(param, instr.is_jump() && i == 1, annotations));
+    }
+    result
+}
+
+impl Memory {
+    /// Show the content of the addresses in `range` as instructions,
+    /// one per line.  Words which are not valid instructions are
+    /// shown as `data`.  Addresses with names in `annotations` get a
+    /// label, references to them are shown by name, and comments are
+    /// shown at the end of the line.
+    pub fn disassemble(&self, range: Range<WordInt>, annotations: &Annotations) -> String {
+        let fetch = |addr: WordInt| self.fetch(Word(addr)).unwrap_or(Word(0));
+        let address_width = range.end.saturating_sub(1).to_string().len();
+        let mut result = String::new();
+        let mut addr = range.start;
+        while addr < range.end {
+            let words: Vec<Word> = (addr..addr.saturating_add(4)).map(fetch).collect();
+            let (text, size) = match Instr::decode(&words) {
+                Some(instr) => (instruction_text(&instr, annotations), instr.size()),
+                None => (format!("data {}", words[0]), 1),
+            };
+            if let Some(name) = annotations.name(Word(addr)) {
+                result.push_str(name);
+                result.push_str(":\n");
+            }
+            // Writing to a String cannot fail.
+            let _ = write!(result, "{:>width$}: {}", addr, text, width = address_width);
+            let comments: Vec<&str> = (addr..addr + size as WordInt)
+                .filter_map(|a| annotations.comment(Word(a)))
+                .collect();
+            if !comments.is_empty() {
+                let _ = write!(result, "  # {}", comments.join("; "));
+            }
+            result.push('\n');
+            addr += size as WordInt;
+        }
+        result
+    }
+}
+
+#[test]
+fn test_disassemble() {
+    use super::encode_program;
+
+    let mut memory = Memory::new();
+    let mut program = encode_program(&[
+        Instr::read(10),
+        Instr::mul(Param::Pos(10), Param::Imm(-255), Param::Rel(1)),
+        Instr::jump_if_true(Param::Pos(10), Param::Imm(0)),
+        Instr::stop(),
+    ]);
+    program.push(Word(42));
+    memory.load(Word(0), &program).expect("program should load");
+    let mut annotations = Annotations::new();
+    annotations.set_name(Word(0), "start");
+    annotations.set_name(Word(10), "value");
+    annotations.set_comment(Word(4), "scale it");
+    annotations.set_comment(Word(10), "read here");
+    assert_eq!(
+        memory.disassemble(0..12, &annotations),
+        concat!(
+            "start:\n",
+            " 0: in [value]\n",
+            " 2: mul [value], -255, [rb+1]  # scale it\n",
+            " 6: jt [value], start\n",
+            " 9: halt\n",
+            "value:\n",
+            "10: data 42  # read here\n",
+            "11: data 0\n",
+        )
+    );
+    assert_eq!(
+        memory.disassemble(9..11, &Annotations::new()),
+        " 9: halt\n10: data 42\n"
+    );
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::instr::opcode_info;
+use super::{Memory, Opcode, Word, WordInt};
+
+/// The base in which a memory dump shows numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Decimal,
+    Hex,
+}
+
+impl Radix {
+    fn format(&self, n: WordInt) -> String {
+        let sign = if n < 0 { "-" } else { "" };
+        match self {
+            Radix::Decimal => n.to_string(),
+            Radix::Hex => format!("{}{:x}", sign, n.unsigned_abs()),
+        }
+    }
+}
+
+/// How a memory dump is divided into rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowAlignment {
+    /// Each row holds `words_per_row` words.
+    Fixed,
+    /// Each row holds one instruction (as decoded from its first
+    /// word); words which are not valid instructions get a row each.
+    Instructions,
+}
+
+/// The layout of a memory dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpFormat {
+    pub radix: Radix,
+    /// The minimum width of each word; 0 means as wide as the widest
+    /// word in the dump, so that the columns line up.
+    pub column_width: usize,
+    pub words_per_row: usize,
+    pub alignment: RowAlignment,
+}
+
+impl Default for DumpFormat {
+    fn default() -> DumpFormat {
+        DumpFormat {
+            radix: Radix::Decimal,
+            column_width: 0,
+            words_per_row: 8,
+            alignment: RowAlignment::Fixed,
+        }
+    }
+}
+
+/// The number of words in the instruction which starts with
+/// `instruction`, or 1 if it is not a valid instruction.
+fn instruction_size(instruction: &Word) -> usize {
+    match Opcode::try_from(instruction) {
+        Ok(op) => 1 + opcode_info(op).params,
+        Err(_) => 1,
+    }
+}
+
+impl Memory {
+    /// Show the content of the addresses in `range`, one row per line
+    /// with the address of its first word at the start.
+    pub fn dump_with(&self, range: Range<WordInt>, format: &DumpFormat) -> String {
+        let words: Vec<(WordInt, String)> = range
+            .clone()
+            .map(|addr| {
+                let w = self.fetch(Word(addr)).unwrap_or(Word(0));
+                (addr, format.radix.format(w.0))
+            })
+            .collect();
+        let column_width = match format.column_width {
+            0 => words.iter().map(|(_, s)| s.len()).max().unwrap_or(0),
+            n => n,
+        };
+        let address_width = range
+            .clone()
+            .map(|addr| format.radix.format(addr).len())
+            .max()
+            .unwrap_or(0);
+        let mut result = String::new();
+        let mut i: usize = 0;
+        while i < words.len() {
+            let row_len = match format.alignment {
+                RowAlignment::Fixed => format.words_per_row.max(1),
+                RowAlignment::Instructions => {
+                    instruction_size(&self.fetch(Word(words[i].0)).unwrap_or(Word(0)))
+                }
+            };
+            let row = &words[i..(i + row_len).min(words.len())];
+            write_row(
+                &mut result,
+                &format.radix.format(row[0].0),
+                row.iter().map(|(_, word)| word.as_str()),
+                address_width,
+                column_width,
+            );
+            i += row.len();
+        }
+        result
+    }
+
+    /// Show the content of the addresses in `range` in decimal,
+    /// `width` words to a row, with the columns lined up.
+    pub fn format_dump(&self, range: Range<WordInt>, width: usize) -> String {
+        self.dump_with(
+            range,
+            &DumpFormat {
+                words_per_row: width,
+                ..DumpFormat::default()
+            },
+        )
+    }
+
+    /// Like `format_dump`, but leave out the rows in which every word
+    /// is zero.  Only the rows holding words which have been written
+    /// are examined, so `range` can be very large.
+    pub fn format_sparse_dump(&self, range: Range<WordInt>, width: usize) -> String {
+        let width = width.max(1) as WordInt;
+        let row_start = |addr: WordInt| range.start + (addr - range.start) / width * width;
+        let mut starts: BTreeSet<WordInt> = self
+            .content
+            .dump()
+            .map(|(addr, _)| addr.0)
+            .filter(|addr| range.contains(addr))
+            .map(row_start)
+            .collect();
+        if let Some((shared, _)) = self.shared.as_ref() {
+            let first = shared.start.0.max(range.start);
+            let last = shared.end.0.min(range.end);
+            if first < last {
+                starts.extend((row_start(first)..last).step_by(width as usize));
+            }
+        }
+        let rows: Vec<(WordInt, Vec<String>)> = starts
+            .into_iter()
+            .map(|start| {
+                let words: Vec<Word> = (start..(start + width).min(range.end))
+                    .map(|addr| self.fetch(Word(addr)).unwrap_or(Word(0)))
+                    .collect();
+                (start, words)
+            })
+            .filter(|(_, words)| words.iter().any(|w| w.0 != 0))
+            .map(|(start, words)| (start, words.iter().map(Word::to_string).collect()))
+            .collect();
+        let address_width = rows
+            .iter()
+            .map(|(start, _)| start.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let column_width = rows
+            .iter()
+            .flat_map(|(_, words)| words.iter().map(String::len))
+            .max()
+            .unwrap_or(0);
+        let mut result = String::new();
+        for (start, words) in rows.iter() {
+            write_row(
+                &mut result,
+                &start.to_string(),
+                words.iter().map(String::as_str),
+                address_width,
+                column_width,
+            );
+        }
+        result
+    }
+}
+
+/// Append a row of a dump, starting at `addr`, to `result`.
+fn write_row<'a>(
+    result: &mut String,
+    addr: &str,
+    words: impl Iterator<Item = &'a str>,
+    address_width: usize,
+    column_width: usize,
+) {
+    // Writing to a String cannot fail.
+    let _ = write!(result, "{:>width$}:", addr, width = address_width);
+    for word in words {
+        let _ = write!(result, " {:>width$}", word, width = column_width);
+    }
+    result.push('\n');
+}
+
+#[test]
+fn test_dump_with() {
+    use super::{encode_program, Instr, Param};
+
+    let mut memory = Memory::new();
+    let program = encode_program(&[
+        Instr::read(11),
+        Instr::mul(Param::Pos(11), Param::Imm(-255), 12),
+        Instr::write(Param::Pos(12)),
+        Instr::stop(),
+    ]);
+    memory.load(Word(0), &program).expect("program should load");
+    assert_eq!(
+        memory.dump_with(0..11, &DumpFormat::default()),
+        concat!(
+            " 0:    3   11 1002   11 -255   12    4   12\n",
+            " 8:   99    0    0\n"
+        )
+    );
+    let hex = DumpFormat {
+        radix: Radix::Hex,
+        column_width: 4,
+        words_per_row: 4,
+        alignment: RowAlignment::Fixed,
+    };
+    assert_eq!(
+        memory.dump_with(2..10, &hex),
+        concat!("2:  3ea    b  -ff    c\n", "6:    4    c   63    0\n")
+    );
+    let by_instruction = DumpFormat {
+        alignment: RowAlignment::Instructions,
+        ..DumpFormat::default()
+    };
+    assert_eq!(
+        memory.dump_with(0..12, &by_instruction),
+        concat!(
+            " 0:    3   11\n",
+            " 2: 1002   11 -255   12\n",
+            " 6:    4   12\n",
+            " 8:   99\n",
+            " 9:    0\n",
+            "10:    0\n",
+            "11:    0\n"
+        )
+    );
+}
+
+#[test]
+fn test_format_dump() {
+    let mut memory = Memory::new();
+    memory
+        .load(Word(0), &[Word(1), Word(-20), Word(300)])
+        .expect("program should load");
+    memory
+        .store(Word(1_000_000_007), Word(5))
+        .expect("store should succeed");
+    memory
+        .store(Word(20), Word(0))
+        .expect("store should succeed");
+    assert_eq!(
+        memory.format_dump(0..6, 4),
+        concat!("0:   1 -20 300   0\n", "4:   0   0\n")
+    );
+    assert_eq!(
+        memory.format_sparse_dump(0..2_000_000_000, 4),
+        concat!(
+            "         0:   1 -20 300   0\n",
+            "1000000004:   0   0   0   5\n"
+        )
+    );
+    // Rows start at the start of the range.
+    assert_eq!(memory.format_sparse_dump(1..5, 2), "1: -20 300\n");
+    assert_eq!(memory.format_sparse_dump(10..30, 8), "");
+}
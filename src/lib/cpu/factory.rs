@@ -0,0 +1,220 @@
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use super::{Annotations, CostModel, CpuFault, InputOutputError, Processor, TraceConfig, Word};
+
+/// The file which receives the trace of every processor made by
+/// `new_processor`, if tracing was requested, and how to trace.
+static TRACE_FILE: OnceLock<(File, TraceConfig)> = OnceLock::new();
+
+/// Trace every processor subsequently made by `new_processor` to the
+/// file `path`, as specified by `config`.  All the processors share
+/// the same trace file.
+pub fn trace_all_processors(path: &Path, config: TraceConfig) -> Result<(), CpuFault> {
+    let already_specified =
+        || CpuFault::BadOption("a trace file was already specified".to_string());
+    // Check before opening the file, so that it is not truncated.
+    if TRACE_FILE.get().is_some() {
+        return Err(already_specified());
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| {
+            CpuFault::TraceError(format!(
+                "failed to open trace file {} for writing: {}",
+                path.display(),
+                e
+            ))
+        })?;
+    TRACE_FILE
+        .set((file, config))
+        .map_err(|_| already_specified())
+}
+
+/// The file to which a processor made by `new_processor` writes a
+/// core dump if its program faults.
+static CORE_DUMP_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Make every processor subsequently made by `new_processor` write a
+/// core dump to `path` if its program faults.
+pub fn dump_core_of_all_processors(path: &Path) -> Result<(), CpuFault> {
+    CORE_DUMP_FILE
+        .set(path.to_path_buf())
+        .map_err(|_| CpuFault::BadOption("a core dump file was already specified".to_string()))
+}
+
+/// If set, every processor made by `new_processor` reports its
+/// progress on the standard error after this many instructions.
+static PROGRESS_INTERVAL: OnceLock<u64> = OnceLock::new();
+
+/// Make every processor subsequently made by `new_processor` report
+/// its metrics on the standard error every `interval` instructions.
+pub fn report_progress_of_all_processors(interval: u64) -> Result<(), CpuFault> {
+    if interval == 0 {
+        return Err(CpuFault::BadOption(
+            "the progress interval must be positive".to_string(),
+        ));
+    }
+    PROGRESS_INTERVAL
+        .set(interval)
+        .map_err(|_| CpuFault::BadOption("a progress interval was already specified".to_string()))
+}
+
+/// If set, every processor made by `new_processor` counts the cycles
+/// used by its program according to this cost model.
+static COST_MODEL: OnceLock<CostModel> = OnceLock::new();
+
+/// Make every processor subsequently made by `new_processor` count
+/// the cycles used by its program according to `model`, and report
+/// the total on the standard error when it is finished with.
+pub fn count_cycles_of_all_processors(model: CostModel) -> Result<(), CpuFault> {
+    COST_MODEL
+        .set(model)
+        .map_err(|_| CpuFault::BadOption("a cost model was already specified".to_string()))
+}
+
+/// Annotations shown in the traces of processors made by
+/// `new_processor`.
+static ANNOTATIONS: OnceLock<Arc<Annotations>> = OnceLock::new();
+
+/// Show `annotations` in the traces of every processor subsequently
+/// made by `new_processor`.
+pub fn annotate_all_processors(annotations: Annotations) -> Result<(), CpuFault> {
+    ANNOTATIONS
+        .set(Arc::new(annotations))
+        .map_err(|_| CpuFault::BadOption("annotations were already specified".to_string()))
+}
+
+/// Whether processors made by `new_processor` fault on reads of
+/// uninitialised memory.
+static STRICT_READS: OnceLock<()> = OnceLock::new();
+
+/// Make every processor subsequently made by `new_processor` fault if
+/// its program reads memory which was never written (see
+/// `Processor::set_strict_reads`).
+pub fn strict_reads_of_all_processors() -> Result<(), CpuFault> {
+    STRICT_READS
+        .set(())
+        .map_err(|_| CpuFault::BadOption("strict reads were already requested".to_string()))
+}
+
+/// If set, processors made by `new_processor` profile their
+/// programs.
+static PROFILE: OnceLock<()> = OnceLock::new();
+
+/// Make every processor subsequently made by `new_processor` profile
+/// its program, and show the profile on the standard error when it is
+/// finished with (see `Processor::enable_profiling`).
+pub fn profile_all_processors() -> Result<(), CpuFault> {
+    PROFILE
+        .set(())
+        .map_err(|_| CpuFault::BadOption("profiling was already requested".to_string()))
+}
+
+/// If set, processors made by `new_processor` check for infinite
+/// loops this often (in instructions).
+static LOOP_CHECK_INTERVAL: OnceLock<u64> = OnceLock::new();
+
+/// Make every processor subsequently made by `new_processor` fault if
+/// its program gets stuck in a loop which does no I/O, checking every
+/// `interval` instructions (see `Processor::detect_loops`).
+pub fn detect_loops_of_all_processors(interval: u64) -> Result<(), CpuFault> {
+    if interval == 0 {
+        return Err(CpuFault::BadOption(
+            "the loop detection interval must be positive".to_string(),
+        ));
+    }
+    LOOP_CHECK_INTERVAL
+        .set(interval)
+        .map_err(|_| CpuFault::BadOption("loop detection was already requested".to_string()))
+}
+
+/// If this environment variable is set to 1, processors made by
+/// `new_processor` trace to standard error (unless `--trace` was
+/// given).
+const TRACE_ENV_VAR: &str = "AOC_CPU_TRACE";
+
+/// Make a processor with `program` loaded at address 0, ready to
+/// start there.  Day binaries should obtain their processors in this
+/// way so that `--trace`, `--annotations`, `--core-dump`,
+/// `--progress`, `--cycle-costs`, `--strict-reads`, `--profile`,
+/// `--detect-loops` and `AOC_CPU_TRACE=1` apply to them.  To
+/// configure a processor further, use `Processor::builder` with
+/// `with_global_options` instead.
+pub fn new_processor(program: &[Word]) -> Result<Processor, CpuFault> {
+    Processor::builder()
+        .program(program)
+        .with_global_options()
+        .build()
+}
+
+/// Apply the options which were given for every processor (usually
+/// on the command line) to `cpu`.
+pub(super) fn apply_global_options(cpu: &mut Processor) -> Result<(), CpuFault> {
+    if let Some((file, config)) = TRACE_FILE.get() {
+        cpu.enable_tracing_with(file.try_clone()?, *config);
+    } else if std::env::var_os(TRACE_ENV_VAR).is_some_and(|v| v == "1") {
+        cpu.enable_tracing_stderr();
+    }
+    if let Some(annotations) = ANNOTATIONS.get() {
+        cpu.annotate(Arc::clone(annotations));
+    }
+    if let Some(path) = CORE_DUMP_FILE.get() {
+        cpu.dump_core_on_fault(path);
+    }
+    if let Some(interval) = PROGRESS_INTERVAL.get() {
+        cpu.report_metrics_every(*interval, |metrics| eprintln!("progress: {}", metrics));
+    }
+    if let Some(model) = COST_MODEL.get() {
+        cpu.set_cost_model(model.clone());
+        cpu.report_cycles_when_done();
+    }
+    if STRICT_READS.get().is_some() {
+        cpu.set_strict_reads(true);
+    }
+    if PROFILE.get().is_some() {
+        cpu.enable_profiling();
+        cpu.report_profile_when_done();
+    }
+    if let Some(interval) = LOOP_CHECK_INTERVAL.get() {
+        cpu.detect_loops(*interval);
+    }
+    Ok(())
+}
+
+/// Load `program`, change the words at the given addresses to the
+/// given values, and run the result until it halts.  The program is
+/// given no input and its output is discarded.  Returns the final
+/// content of memory.
+pub fn run_patched(program: &[Word], patches: &[(Word, Word)]) -> Result<Vec<Word>, CpuFault> {
+    let mut cpu = new_processor(program)?;
+    for (addr, value) in patches {
+        cpu.poke(*addr, *value)?;
+    }
+    let mut discard_output = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[], &mut discard_output)?;
+    Ok(cpu.ram())
+}
+
+#[test]
+fn test_run_patched() {
+    let program: Vec<Word> = [1, 0, 0, 0, 99].iter().map(|n| Word(*n)).collect();
+    // Patch the program to compute 99 + 1 instead of 1 + 1.
+    assert_eq!(
+        run_patched(&program, &[(Word(1), Word(4)), (Word(2), Word(0))])
+            .expect("patched program should run"),
+        [100, 4, 0, 0, 99]
+            .iter()
+            .map(|n| Word(*n))
+            .collect::<Vec<Word>>()
+    );
+    // The unpatched program is unaffected.
+    assert_eq!(program[1], Word(0));
+    // A program which reads input fails.
+    let reader: Vec<Word> = [3, 0, 99].iter().map(|n| Word(*n)).collect();
+    assert!(run_patched(&reader, &[]).is_err());
+}
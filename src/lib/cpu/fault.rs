@@ -0,0 +1,197 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{CpuFault, InputOutputError, Instr, Processor, Word};
+
+/// The number of recently executed instructions whose addresses are
+/// given in a `FaultContext`.
+const RECENT_PCS: usize = 8;
+
+/// Where in the program a fault happened.
+#[derive(Debug, Clone)]
+pub struct FaultContext {
+    pub fault: CpuFault,
+    /// The address of the instruction which faulted, or (if the
+    /// fault was found between instructions) of the next instruction.
+    pub pc: Word,
+    /// The instruction which faulted, if it could be decoded.  This
+    /// is `None` if the fault was found between instructions.
+    pub instruction: Option<Instr>,
+    /// The addresses of the instructions executed before the fault,
+    /// oldest first, not including any which faulted.
+    pub recent: Vec<Word>,
+    /// Whether the fault was found between instructions, rather than
+    /// being caused by the instruction at `pc`.
+    pub between_instructions: bool,
+}
+
+impl Display for FaultContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.fault)?;
+        if !self.between_instructions {
+            write!(f, " at address {}", self.pc)?;
+            if let Some(instruction) = self.instruction.as_ref() {
+                write!(f, " ({})", instruction)?;
+            }
+        }
+        if !self.recent.is_empty() {
+            let recent: Vec<String> = self.recent.iter().map(Word::to_string).collect();
+            write!(
+                f,
+                ", after executing the instructions at {}",
+                recent.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl CpuFault {
+    /// The fault itself, without any context.
+    pub fn root(&self) -> &CpuFault {
+        match self {
+            CpuFault::At(context) => context.fault.root(),
+            fault => fault,
+        }
+    }
+
+    /// Like `root`, but taking ownership.
+    pub fn into_root(self) -> CpuFault {
+        match self {
+            CpuFault::At(context) => context.fault.into_root(),
+            fault => fault,
+        }
+    }
+
+    /// Where in the program the fault happened, if known.
+    pub fn context(&self) -> Option<&FaultContext> {
+        match self {
+            CpuFault::At(context) => Some(context),
+            _ => None,
+        }
+    }
+}
+
+/// The addresses of the most recently executed instructions, the
+/// current one last.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct RecentPcs {
+    pcs: [Word; RECENT_PCS],
+    /// The number of instructions executed, of which the last
+    /// `RECENT_PCS` are kept.
+    count: usize,
+}
+
+impl RecentPcs {
+    pub(super) fn record(&mut self, pc: Word) {
+        self.pcs[self.count % RECENT_PCS] = pc;
+        self.count += 1;
+    }
+
+    fn to_vec(self) -> Vec<Word> {
+        let start = self.count.saturating_sub(RECENT_PCS);
+        (start..self.count)
+            .map(|i| self.pcs[i % RECENT_PCS])
+            .collect()
+    }
+}
+
+impl Processor {
+    /// Add to `fault`, which the instruction at the program counter
+    /// has just caused, where it happened.  Running out of input is
+    /// left alone, since callers use it to tell when to provide more.
+    pub(super) fn add_fault_context(&self, fault: CpuFault) -> CpuFault {
+        if matches!(
+            fault,
+            CpuFault::At(_) | CpuFault::IOError(InputOutputError::NoInput)
+        ) {
+            return fault;
+        }
+        let words: Vec<Word> = (self.pc.0..self.pc.0.saturating_add(4))
+            .map(|addr| self.ram.fetch(Word(addr)).unwrap_or(Word(0)))
+            .collect();
+        let mut recent = self.recent_pcs.to_vec();
+        // The last is the instruction which faulted.
+        recent.pop();
+        CpuFault::At(Box::new(FaultContext {
+            fault,
+            pc: self.pc,
+            instruction: Instr::decode(&words),
+            recent,
+            between_instructions: false,
+        }))
+    }
+
+    /// Add to `fault`, which was found between instructions (for
+    /// example because the program is stuck in a loop), what the
+    /// program had been doing.  No instruction is blamed for it.
+    pub(super) fn add_context_between_instructions(&self, fault: CpuFault) -> CpuFault {
+        CpuFault::At(Box::new(FaultContext {
+            fault,
+            pc: self.pc,
+            instruction: None,
+            recent: self.recent_pcs.to_vec(),
+            between_instructions: true,
+        }))
+    }
+}
+
+#[test]
+fn test_fault_context() {
+    use super::{encode_program, Param};
+
+    // Count down from 9, adding 1 to the word at -1 once 0 is reached.
+    let program = encode_program(&[
+        Instr::add(Param::Imm(9), Param::Imm(0), 30),
+        Instr::add(Param::Pos(30), Param::Imm(-1), 30),
+        Instr::jump_if_true(Param::Pos(30), Param::Imm(4)),
+        Instr::add(Param::Pos(-1), Param::Imm(1), 31),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    let fault = cpu
+        .run_with_fixed_input(&[], &mut discard)
+        .expect_err("program should fault");
+    assert!(matches!(fault.root(), CpuFault::MemoryFault));
+    let context = fault.context().expect("the fault has context");
+    assert_eq!(context.pc, Word(11));
+    assert_eq!(context.recent.len(), RECENT_PCS - 1);
+    assert_eq!(
+        fault.to_string(),
+        concat!(
+            "memory fault at address 11 (add [-1], 1, [31]), after executing ",
+            "the instructions at 8, 4, 8, 4, 8, 4, 8"
+        )
+    );
+    assert!(matches!(fault.into_root(), CpuFault::MemoryFault));
+
+    // A loop is found after the instruction which completes it, so
+    // no instruction is blamed and every executed one is listed.
+    let looping = encode_program(&[Instr::jump_if_true(Param::Imm(1), Param::Imm(0))]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &looping).expect("program should load");
+    cpu.detect_loops(1);
+    let fault = cpu
+        .run_with_fixed_input(&[], &mut discard)
+        .expect_err("program should be stuck");
+    let context = fault.context().expect("the fault has context");
+    assert_eq!(context.instruction, None);
+    assert_eq!(context.recent, vec![Word(0), Word(0)]);
+    assert_eq!(
+        fault.to_string(),
+        concat!(
+            "infinite loop without I/O detected at address 0, after executing ",
+            "the instructions at 0, 0"
+        )
+    );
+
+    // Running out of input is not given context.
+    let mut reader = Processor::new(Word(0));
+    reader
+        .load(Word(0), &Instr::read(5).encode())
+        .expect("program should load");
+    assert!(matches!(
+        reader.run_with_fixed_input(&[], &mut discard),
+        Err(CpuFault::IOError(InputOutputError::NoInput))
+    ));
+}
@@ -0,0 +1,167 @@
+use super::instr::opcode_info;
+use super::{AddressingMode, Opcode, Word, WordInt};
+
+/// A small deterministic pseudo-random number generator (xorshift64*),
+/// so that a generated program can be reproduced from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // The state must not be zero.
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// The shape of the programs made by `random_program`.
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratorOptions {
+    /// The number of instructions before the final Stop.
+    pub instructions: usize,
+    /// The number of data words after the code.
+    pub data_words: usize,
+    /// Whether the program may contain Read and Write instructions.
+    pub allow_io: bool,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> GeneratorOptions {
+        GeneratorOptions {
+            instructions: 20,
+            data_words: 8,
+            allow_io: true,
+        }
+    }
+}
+
+fn instruction_length(op: Opcode) -> usize {
+    1 + opcode_info(op).params
+}
+
+/// Make a random but well-behaved Intcode program from `seed`.
+///
+/// The program is a sequence of instructions, then Stop, then some
+/// data words.  Every instruction is valid.  Positional parameters
+/// refer only to the data words, so the code is never modified, and
+/// jumps only go forward to the start of an instruction, so the
+/// program always reaches the Stop after executing at most
+/// `options.instructions` instructions.  Arithmetic may still
+/// overflow.  Relative addressing is not used.
+pub fn random_program(seed: u64, options: &GeneratorOptions) -> Vec<Word> {
+    let mut rng = Rng::new(seed);
+    let mut ops: Vec<Opcode> = vec![
+        Opcode::Add,
+        Opcode::Multiply,
+        Opcode::JumpTrue,
+        Opcode::JumpFalse,
+        Opcode::CmpLess,
+        Opcode::CmpEq,
+    ];
+    if options.allow_io {
+        ops.extend([Opcode::Read, Opcode::Write]);
+    }
+    let code: Vec<Opcode> = (0..options.instructions)
+        .map(|_| ops[rng.below(ops.len())])
+        .chain(std::iter::once(Opcode::Stop))
+        .collect();
+    let starts: Vec<usize> = code
+        .iter()
+        .scan(0, |addr, op| {
+            let start = *addr;
+            *addr += instruction_length(*op);
+            Some(start)
+        })
+        .collect();
+    let data_start: usize = starts[starts.len() - 1] + instruction_length(Opcode::Stop);
+    let data_words = options.data_words.max(1);
+
+    let mut program: Vec<Word> = Vec::with_capacity(data_start + data_words);
+    for (i, op) in code.iter().enumerate() {
+        let param_count = instruction_length(*op) - 1;
+        let mut modes = [AddressingMode::POSITIONAL; 3];
+        let mut params = [0; 3];
+        for (p, (mode, param)) in modes
+            .iter_mut()
+            .zip(params.iter_mut())
+            .enumerate()
+            .take(param_count)
+        {
+            let writes = opcode_info(*op).writes == Some(p);
+            let is_jump_target = matches!(op, Opcode::JumpTrue | Opcode::JumpFalse) && p == 1;
+            if is_jump_target {
+                *mode = AddressingMode::IMMEDIATE;
+                let later = &starts[i + 1..];
+                *param = later[rng.below(later.len())] as WordInt;
+            } else if !writes && rng.below(2) == 0 {
+                *mode = AddressingMode::IMMEDIATE;
+                *param = rng.below(21) as WordInt - 10;
+            } else {
+                *param = (data_start + rng.below(data_words)) as WordInt;
+            }
+        }
+        let mode_digits: WordInt = modes
+            .iter()
+            .rev()
+            .fold(0, |acc, mode| acc * 10 + *mode as WordInt);
+        program.push(Word(mode_digits * 100 + *op as WordInt));
+        program.extend(params.iter().take(param_count).map(|p| Word(*p)));
+    }
+    assert_eq!(program.len(), data_start);
+    program.extend((0..data_words).map(|_| Word(rng.below(201) as WordInt - 100)));
+    program
+}
+
+#[test]
+fn test_random_programs_terminate() {
+    use super::{CpuFault, CpuStatus, InputOutputError, Processor};
+
+    let options = GeneratorOptions::default();
+    assert_eq!(
+        random_program(7, &options),
+        random_program(7, &options),
+        "programs should be reproducible"
+    );
+    for seed in 0..500 {
+        let program = random_program(seed, &options);
+        let mut cpu = Processor::new(Word(0));
+        cpu.load(Word(0), &program).expect("program should load");
+        let mut get_input = || -> Result<Word, InputOutputError> { Ok(Word(seed as WordInt)) };
+        let mut discard_output = |_| -> Result<(), InputOutputError> { Ok(()) };
+        let mut halted = false;
+        for _ in 0..=options.instructions {
+            match cpu
+                .execute_instruction(&mut get_input, &mut discard_output)
+                .map_err(CpuFault::into_root)
+            {
+                Ok(CpuStatus::Run) => (),
+                Ok(CpuStatus::AwaitingInput) => unreachable!("input is always available"),
+                Ok(CpuStatus::BreakpointHit(_) | CpuStatus::WatchpointHit(_)) => {
+                    unreachable!("there are no breakpoints or watchpoints")
+                }
+                Ok(CpuStatus::Halt) => {
+                    halted = true;
+                    break;
+                }
+                Err(CpuFault::Overflow) => {
+                    halted = true;
+                    break;
+                }
+                Err(e) => {
+                    panic!("seed {}: program {:?} faulted: {}", seed, program, e);
+                }
+            }
+        }
+        assert!(halted, "seed {}: program {:?} did not halt", seed, program);
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{AddressingMode, Opcode, Word, WordInt};
+
+/// What there is to know about encoding an opcode.  This table is
+/// shared by everything which writes Intcode.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct OpcodeInfo {
+    pub(super) op: Opcode,
+    pub(super) mnemonic: &'static str,
+    /// The number of parameters the instruction takes.
+    pub(super) params: usize,
+    /// The index (counting from 0) of the parameter which the
+    /// instruction stores a result into, if any.
+    pub(super) writes: Option<usize>,
+}
+
+pub(super) const OPCODES: [OpcodeInfo; 10] = [
+    OpcodeInfo {
+        op: Opcode::Add,
+        mnemonic: "add",
+        params: 3,
+        writes: Some(2),
+    },
+    OpcodeInfo {
+        op: Opcode::Multiply,
+        mnemonic: "mul",
+        params: 3,
+        writes: Some(2),
+    },
+    OpcodeInfo {
+        op: Opcode::Read,
+        mnemonic: "in",
+        params: 1,
+        writes: Some(0),
+    },
+    OpcodeInfo {
+        op: Opcode::Write,
+        mnemonic: "out",
+        params: 1,
+        writes: None,
+    },
+    OpcodeInfo {
+        op: Opcode::JumpTrue,
+        mnemonic: "jt",
+        params: 2,
+        writes: None,
+    },
+    OpcodeInfo {
+        op: Opcode::JumpFalse,
+        mnemonic: "jf",
+        params: 2,
+        writes: None,
+    },
+    OpcodeInfo {
+        op: Opcode::CmpLess,
+        mnemonic: "lt",
+        params: 3,
+        writes: Some(2),
+    },
+    OpcodeInfo {
+        op: Opcode::CmpEq,
+        mnemonic: "eq",
+        params: 3,
+        writes: Some(2),
+    },
+    OpcodeInfo {
+        op: Opcode::DeltaRelBase,
+        mnemonic: "arb",
+        params: 1,
+        writes: None,
+    },
+    OpcodeInfo {
+        op: Opcode::Stop,
+        mnemonic: "halt",
+        params: 0,
+        writes: None,
+    },
+];
+
+pub(super) fn opcode_info(op: Opcode) -> &'static OpcodeInfo {
+    OPCODES
+        .iter()
+        .find(|info| info.op == op)
+        .expect("every opcode should be in the table")
+}
+
+/// An instruction parameter, with its addressing mode.  A plain
+/// number converts to a positional parameter, which is convenient for
+/// the parameters which instructions store into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Pos(WordInt),
+    Imm(WordInt),
+    Rel(WordInt),
+}
+
+impl From<WordInt> for Param {
+    fn from(addr: WordInt) -> Param {
+        Param::Pos(addr)
+    }
+}
+
+/// Parameters are shown as `5` (immediate), `[5]` (positional) or
+/// `[rb+5]` (relative).
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::Pos(n) => write!(f, "[{}]", n),
+            Param::Imm(n) => write!(f, "{}", n),
+            Param::Rel(n) if *n < 0 => write!(f, "[rb{}]", n),
+            Param::Rel(n) => write!(f, "[rb+{}]", n),
+        }
+    }
+}
+
+impl Param {
+    fn mode(&self) -> AddressingMode {
+        match self {
+            Param::Pos(_) => AddressingMode::POSITIONAL,
+            Param::Imm(_) => AddressingMode::IMMEDIATE,
+            Param::Rel(_) => AddressingMode::RELATIVE,
+        }
+    }
+
+    fn value(&self) -> WordInt {
+        match self {
+            Param::Pos(n) | Param::Imm(n) | Param::Rel(n) => *n,
+        }
+    }
+}
+
+/// A single Intcode instruction, for building programs in tests
+/// without writing out the encoded words by hand.  For example
+/// `Instr::add(Param::Pos(0), Param::Imm(5), 7)` adds 5 to the word
+/// at address 0 and stores the result at address 7.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instr {
+    op: Opcode,
+    params: Vec<Param>,
+}
+
+impl Instr {
+    pub(super) fn new(op: Opcode, params: &[Param]) -> Instr {
+        debug_assert_eq!(opcode_info(op).params, params.len());
+        Instr {
+            op,
+            params: params.to_vec(),
+        }
+    }
+
+    pub fn add<P: Into<Param>>(a: Param, b: Param, dest: P) -> Instr {
+        Instr::new(Opcode::Add, &[a, b, dest.into()])
+    }
+
+    pub fn mul<P: Into<Param>>(a: Param, b: Param, dest: P) -> Instr {
+        Instr::new(Opcode::Multiply, &[a, b, dest.into()])
+    }
+
+    pub fn read<P: Into<Param>>(dest: P) -> Instr {
+        Instr::new(Opcode::Read, &[dest.into()])
+    }
+
+    pub fn write(a: Param) -> Instr {
+        Instr::new(Opcode::Write, &[a])
+    }
+
+    pub fn jump_if_true(condition: Param, target: Param) -> Instr {
+        Instr::new(Opcode::JumpTrue, &[condition, target])
+    }
+
+    pub fn jump_if_false(condition: Param, target: Param) -> Instr {
+        Instr::new(Opcode::JumpFalse, &[condition, target])
+    }
+
+    pub fn less_than<P: Into<Param>>(a: Param, b: Param, dest: P) -> Instr {
+        Instr::new(Opcode::CmpLess, &[a, b, dest.into()])
+    }
+
+    pub fn equals<P: Into<Param>>(a: Param, b: Param, dest: P) -> Instr {
+        Instr::new(Opcode::CmpEq, &[a, b, dest.into()])
+    }
+
+    pub fn adjust_relative_base(delta: Param) -> Instr {
+        Instr::new(Opcode::DeltaRelBase, &[delta])
+    }
+
+    pub fn stop() -> Instr {
+        Instr::new(Opcode::Stop, &[])
+    }
+
+    /// Decode the instruction at the start of `words`.  Returns
+    /// `None` if the first word is not a valid instruction or there
+    /// are too few words for its parameters.
+    pub fn decode(words: &[Word]) -> Option<Instr> {
+        let first = words.first()?;
+        let op = Opcode::try_from(first).ok()?;
+        let info = opcode_info(op);
+        let params: Option<Vec<Param>> = (0..info.params)
+            .map(|i| {
+                let value = words.get(i + 1)?.0;
+                match (first.0 / WordInt::pow(10, i as u32 + 2)) % 10 {
+                    0 => Some(Param::Pos(value)),
+                    1 if info.writes != Some(i) => Some(Param::Imm(value)),
+                    2 => Some(Param::Rel(value)),
+                    _ => None,
+                }
+            })
+            .collect();
+        Some(Instr {
+            op,
+            params: params?,
+        })
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        opcode_info(self.op).mnemonic
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// Whether the instruction is a jump, whose second parameter is
+    /// the jump target.
+    pub fn is_jump(&self) -> bool {
+        matches!(self.op, Opcode::JumpTrue | Opcode::JumpFalse)
+    }
+
+    /// The number of words the instruction occupies.
+    pub fn size(&self) -> usize {
+        1 + self.params.len()
+    }
+
+    /// Append the encoded instruction to `out`.
+    pub fn encode_into(&self, out: &mut Vec<Word>) {
+        let modes: WordInt = self
+            .params
+            .iter()
+            .rev()
+            .fold(0, |acc, param| acc * 10 + param.mode() as WordInt);
+        out.push(Word(modes * 100 + self.op as WordInt));
+        out.extend(self.params.iter().map(|param| Word(param.value())));
+    }
+
+    pub fn encode(&self) -> Vec<Word> {
+        let mut result = Vec::with_capacity(self.size());
+        self.encode_into(&mut result);
+        result
+    }
+}
+
+impl Display for Instr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(opcode_info(self.op).mnemonic)?;
+        for (i, param) in self.params.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, param)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encode a sequence of instructions, placing the first at address 0.
+pub fn encode_program(instructions: &[Instr]) -> Vec<Word> {
+    let mut result = Vec::new();
+    for instruction in instructions {
+        instruction.encode_into(&mut result);
+    }
+    result
+}
+
+#[test]
+fn test_instr_encoding() {
+    use Param::*;
+    let words = |ns: &[WordInt]| -> Vec<Word> { ns.iter().map(|n| Word(*n)).collect() };
+    assert_eq!(
+        Instr::add(Pos(0), Imm(5), 7).encode(),
+        words(&[1001, 0, 5, 7])
+    );
+    assert_eq!(
+        Instr::mul(Rel(-1), Imm(3), Rel(2)).encode(),
+        words(&[21202, -1, 3, 2])
+    );
+    assert_eq!(
+        encode_program(&[
+            Instr::read(9),
+            Instr::jump_if_false(Pos(9), Imm(8)),
+            Instr::write(Imm(1)),
+            Instr::adjust_relative_base(Imm(4)),
+            Instr::stop(),
+        ]),
+        words(&[3, 9, 1006, 9, 8, 104, 1, 109, 4, 99])
+    );
+    assert_eq!(
+        Instr::add(Pos(0), Imm(5), Rel(-2)).to_string(),
+        "add [0], 5, [rb-2]"
+    );
+    assert_eq!(Instr::stop().to_string(), "halt");
+}
+
+#[test]
+fn test_instr_decode() {
+    use Param::*;
+    let words = |ns: &[WordInt]| -> Vec<Word> { ns.iter().map(|n| Word(*n)).collect() };
+    let instr = Instr::mul(Rel(-1), Imm(3), Rel(2));
+    assert_eq!(Instr::decode(&instr.encode()), Some(instr));
+    assert_eq!(
+        Instr::decode(&words(&[1006, 9, 8, 104])),
+        Some(Instr::jump_if_false(Pos(9), Imm(8)))
+    );
+    // Bad opcode, bad mode, immediate destination, too short.
+    assert_eq!(Instr::decode(&words(&[42])), None);
+    assert_eq!(Instr::decode(&words(&[304, 1])), None);
+    assert_eq!(Instr::decode(&words(&[10001, 1, 2, 3])), None);
+    assert_eq!(Instr::decode(&words(&[1, 1, 2])), None);
+}
@@ -0,0 +1,374 @@
+//! The devices which Intcode programs read their input from and
+//! write their output to.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender};
+
+use super::{CpuFault, InputOutputError, MachineEvent, Processor, Word};
+
+/// Something a program can read its input from.  Devices can be
+/// chained with `then`, so that for example the phase setting of a
+/// day 7 amplifier and the signals arriving on its input wire look
+/// to the program like one stream:
+///
+/// `once(phase).then(wire.clone())`
+///
+/// A closure returning the next word is also an input device.
+pub trait InputDevice {
+    /// The next input word, or `InputOutputError::NoInput` if there
+    /// is none available yet.
+    fn next_input(&mut self) -> Result<Word, InputOutputError>;
+
+    /// A device which reads from `self` until it is exhausted, then
+    /// from `next`.  Once `next` has been read from, `self` is not
+    /// consulted again.
+    fn then<S: InputDevice>(self, next: S) -> Then<Self, S>
+    where
+        Self: Sized,
+    {
+        Then {
+            first: Some(self),
+            second: next,
+        }
+    }
+}
+
+impl<F> InputDevice for F
+where
+    F: FnMut() -> Result<Word, InputOutputError>,
+{
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self()
+    }
+}
+
+/// Something a program can write its output to.  A closure taking
+/// each word is also an output device.
+pub trait OutputDevice {
+    /// Accept `w`, the next word written by the program.
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError>;
+}
+
+impl<F> OutputDevice for F
+where
+    F: FnMut(Word) -> Result<(), InputOutputError>,
+{
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self(w)
+    }
+}
+
+/// Collects the output.
+impl OutputDevice for Vec<Word> {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.push(w);
+        Ok(())
+    }
+}
+
+/// See `InputDevice::then`.
+#[derive(Debug, Clone)]
+pub struct Then<A, B> {
+    first: Option<A>,
+    second: B,
+}
+
+impl<A: InputDevice, B: InputDevice> InputDevice for Then<A, B> {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        if let Some(first) = self.first.as_mut() {
+            match first.next_input() {
+                Err(InputOutputError::NoInput) => {
+                    self.first = None;
+                }
+                result => {
+                    return result;
+                }
+            }
+        }
+        self.second.next_input()
+    }
+}
+
+/// A device which yields a single word.
+#[derive(Debug, Clone)]
+pub struct Once(Option<Word>);
+
+pub fn once(w: Word) -> Once {
+    Once(Some(w))
+}
+
+impl InputDevice for Once {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.0.take().ok_or(InputOutputError::NoInput)
+    }
+}
+
+/// A device which yields the words of an iterator.
+#[derive(Debug, Clone)]
+pub struct IterSource<I>(I);
+
+pub fn from_iter<I>(words: I) -> IterSource<I::IntoIter>
+where
+    I: IntoIterator<Item = Word>,
+{
+    IterSource(words.into_iter())
+}
+
+impl<I: Iterator<Item = Word>> InputDevice for IterSource<I> {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.0.next().ok_or(InputOutputError::NoInput)
+    }
+}
+
+impl InputDevice for VecDeque<Word> {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.pop_front().ok_or(InputOutputError::NoInput)
+    }
+}
+
+impl OutputDevice for VecDeque<Word> {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.push_back(w);
+        Ok(())
+    }
+}
+
+/// A queue of words which one part of a program writes into and
+/// another reads from.  Clones of a `Wire` share the same queue, so
+/// one clone can be given to the reading program as its input
+/// device while the writing program is given another as its output
+/// device.
+#[derive(Debug, Clone, Default)]
+pub struct Wire(Rc<RefCell<VecDeque<Word>>>);
+
+impl Wire {
+    pub fn new() -> Wire {
+        Wire::default()
+    }
+
+    pub fn send(&self, w: Word) {
+        self.0.borrow_mut().push_back(w);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+impl InputDevice for Wire {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.0
+            .borrow_mut()
+            .pop_front()
+            .ok_or(InputOutputError::NoInput)
+    }
+}
+
+impl OutputDevice for Wire {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.send(w);
+        Ok(())
+    }
+}
+
+/// Reads input from a channel, waiting for each word to be sent.
+/// See `channel_input`.
+#[derive(Debug)]
+pub struct ChannelInput(Receiver<Word>);
+
+/// An input device which receives words from `receiver`, so that the
+/// program can run on another thread from whatever sends its input.
+/// Once every sender has gone away and the words already sent have
+/// been read, the program runs out of input.
+pub fn channel_input(receiver: Receiver<Word>) -> ChannelInput {
+    ChannelInput(receiver)
+}
+
+impl InputDevice for ChannelInput {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.0.recv().map_err(|_| InputOutputError::NoInput)
+    }
+}
+
+/// Writes output to a channel.  See `channel_output`.
+#[derive(Debug, Clone)]
+pub struct ChannelOutput(Sender<Word>);
+
+/// An output device which sends each word to `sender`.  If the
+/// receiver has gone away, the word is rejected.
+pub fn channel_output(sender: Sender<Word>) -> ChannelOutput {
+    ChannelOutput(sender)
+}
+
+impl OutputDevice for ChannelOutput {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.0.send(w).map_err(|_| InputOutputError::Rejected(w))
+    }
+}
+
+/// The words written by a program, as it runs.  See
+/// `Processor::outputs`.
+#[derive(Debug)]
+pub struct Outputs<'a> {
+    cpu: &'a mut Processor,
+    done: bool,
+}
+
+impl Iterator for Outputs<'_> {
+    type Item = Result<Word, CpuFault>;
+
+    fn next(&mut self) -> Option<Result<Word, CpuFault>> {
+        if self.done {
+            return None;
+        }
+        match self.cpu.run_until_blocked() {
+            Ok(MachineEvent::ProducedOutput(w)) => Some(Ok(w)),
+            Ok(
+                MachineEvent::AwaitingInput
+                | MachineEvent::Halted
+                | MachineEvent::BreakpointHit(_)
+                | MachineEvent::WatchpointHit(_),
+            ) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Processor {
+    /// Run the program as the words it writes are needed, reading
+    /// the input given to `provide_input`.  The iteration ends when
+    /// the program halts, needs more input or is paused by a
+    /// breakpoint or watchpoint (in which cases `outputs` can be
+    /// called again to resume it), or after yielding a fault.
+    pub fn outputs(&mut self) -> Outputs<'_> {
+        Outputs {
+            cpu: self,
+            done: false,
+        }
+    }
+}
+
+#[test]
+fn test_chained_sources() {
+    let wire = Wire::new();
+    let mut source = once(Word(9)).then(from_iter([Word(1), Word(2)]).then(wire.clone()));
+    assert_eq!(source.next_input(), Ok(Word(9)));
+    assert_eq!(source.next_input(), Ok(Word(1)));
+    assert_eq!(source.next_input(), Ok(Word(2)));
+    assert_eq!(source.next_input(), Err(InputOutputError::NoInput));
+    // Words sent on the wire later are still delivered.
+    wire.send(Word(3));
+    wire.send(Word(4));
+    assert_eq!(source.next_input(), Ok(Word(3)));
+    assert_eq!(source.next_input(), Ok(Word(4)));
+    assert_eq!(source.next_input(), Err(InputOutputError::NoInput));
+    assert!(wire.is_empty());
+}
+
+#[test]
+fn test_devices() {
+    use super::{encode_program, Instr, Param, Processor};
+
+    // Copy two words from input to output, then halt.
+    let program = encode_program(&[
+        Instr::read(20),
+        Instr::write(Param::Pos(20)),
+        Instr::read(20),
+        Instr::write(Param::Pos(20)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let mut input = once(Word(5)).then(from_iter([Word(6)]));
+    let mut output: Vec<Word> = Vec::new();
+    cpu.run_with_io(&mut input, &mut output)
+        .expect("program should run");
+    assert_eq!(output, vec![Word(5), Word(6)]);
+}
+
+#[test]
+fn test_channels() {
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    use super::{encode_program, Instr, Param, Processor};
+
+    // Add 1 to each of two words, then halt.  Two of these programs
+    // run on their own threads, one feeding the other.
+    let program = encode_program(&[
+        Instr::read(20),
+        Instr::add(Param::Pos(20), Param::Imm(1), 20),
+        Instr::write(Param::Pos(20)),
+        Instr::read(20),
+        Instr::add(Param::Pos(20), Param::Imm(1), 20),
+        Instr::write(Param::Pos(20)),
+        Instr::stop(),
+    ]);
+    let (to_a, from_main) = channel();
+    let (to_b, from_a) = channel();
+    let (to_main, from_b) = channel();
+    let run = |input, output| {
+        let program = program.clone();
+        thread::spawn(move || {
+            let mut cpu = Processor::new(Word(0));
+            cpu.load(Word(0), &program).expect("program should load");
+            cpu.run_with_io(&mut channel_input(input), &mut channel_output(output))
+        })
+    };
+    let a = run(from_main, to_b);
+    let b = run(from_a, to_main);
+    to_a.send(Word(10)).expect("a should be listening");
+    to_a.send(Word(20)).expect("a should be listening");
+    assert_eq!(
+        from_b.iter().collect::<Vec<Word>>(),
+        vec![Word(12), Word(22)]
+    );
+    a.join()
+        .expect("a should not panic")
+        .expect("a should halt");
+    b.join()
+        .expect("b should not panic")
+        .expect("b should halt");
+    // Once the sender has gone, the program runs out of input.
+    let (sender, receiver) = channel::<Word>();
+    drop(sender);
+    assert_eq!(
+        channel_input(receiver).next_input(),
+        Err(InputOutputError::NoInput)
+    );
+}
+
+#[test]
+fn test_outputs() {
+    use super::{encode_program, Instr, Param, WordInt};
+
+    // Write 1, then write twice each word read.
+    let program = encode_program(&[
+        Instr::write(Param::Imm(1)),
+        Instr::read(30),
+        Instr::mul(Param::Pos(30), Param::Imm(2), 31),
+        Instr::write(Param::Pos(31)),
+        Instr::jump_if_true(Param::Imm(1), Param::Imm(2)),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.provide_input(Word(4));
+    let output: Vec<Word> = cpu.outputs().collect::<Result<_, _>>().expect("no fault");
+    assert_eq!(output, vec![Word(1), Word(8)]);
+    cpu.provide_input(Word(3));
+    cpu.provide_input(Word(WordInt::MAX));
+    let mut outputs = cpu.outputs();
+    assert_eq!(outputs.next().map(|r| r.ok()), Some(Some(Word(6))));
+    assert!(matches!(
+        outputs.next().map(|r| r.map_err(CpuFault::into_root)),
+        Some(Err(CpuFault::Overflow))
+    ));
+    assert!(outputs.next().is_none());
+}
@@ -0,0 +1,283 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::{Memory, Processor, Word, WordInt};
+
+/// What is needed to undo one instruction.
+#[derive(Debug, Clone)]
+struct Undo {
+    pc: Word,
+    relative_base: WordInt,
+    /// The addresses stored into, with what they held before (`None`
+    /// if they had never been written), in order.
+    stores: Vec<(Word, Option<Word>)>,
+    /// The input the instruction read, if any.
+    input: Option<Word>,
+}
+
+/// The record of the most recent instructions executed, which allows
+/// them to be undone.
+#[derive(Debug, Clone)]
+pub(super) struct Journal {
+    entries: VecDeque<Undo>,
+    limit: usize,
+    /// The record which was dropped to make room for that of the
+    /// current instruction.  It is put back if the instruction
+    /// faults.
+    evicted: Option<Undo>,
+}
+
+impl Journal {
+    /// Forget every recorded instruction.
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.evicted = None;
+    }
+}
+
+impl Memory {
+    /// What `addr` holds before a store into it, or `None` if it is
+    /// held in a `SharedMemory` (where stores are not undone, since
+    /// other programs may already have seen them).
+    fn before_store(&self, addr: Word) -> Option<Option<Word>> {
+        match self.shared_at(addr) {
+            Some(_) => None,
+            None => Some(self.content.fetch(addr)),
+        }
+    }
+
+    /// Put back what `addr` held before a store.
+    fn undo_store(&mut self, addr: Word, previous: Option<Word>) {
+        let content = Arc::make_mut(&mut self.content);
+        match previous {
+            Some(w) => content.store(addr, w),
+            None => content.erase(addr),
+        }
+    }
+}
+
+impl Processor {
+    /// Record enough about each instruction executed to undo it with
+    /// `step_back`, keeping the record of the last `limit`
+    /// instructions.  Any existing record is discarded.
+    pub fn enable_journal(&mut self, limit: usize) {
+        self.journal = Some(Journal {
+            entries: VecDeque::new(),
+            limit,
+            evicted: None,
+        });
+    }
+
+    /// The number of instructions which `step_back` can currently
+    /// undo.
+    pub fn undoable_steps(&self) -> usize {
+        self.journal
+            .as_ref()
+            .map_or(0, |journal| journal.entries.len())
+    }
+
+    /// Undo the most recently executed instruction, returning `false`
+    /// if there is no record of it (see `enable_journal`).  Memory,
+    /// the program counter and the relative base are put back as they
+    /// were, and any input the instruction read is queued (as by
+    /// `provide_input`) to be read again.  Output cannot be taken
+    /// back, stores into shared memory are not undone, and metrics,
+    /// cycle counts, profiles and coverage still count the
+    /// instruction.
+    ///
+    /// A fault leaves the program counter at the instruction which
+    /// caused it, so stepping back from a fault undoes the
+    /// instruction before that one.  `CpuFault::LoopDetected` is
+    /// found after an instruction has completed, so stepping back
+    /// from it undoes that instruction.
+    pub fn step_back(&mut self) -> bool {
+        let undo = match self
+            .journal
+            .as_mut()
+            .and_then(|journal| journal.entries.pop_back())
+        {
+            Some(undo) => undo,
+            None => return false,
+        };
+        for (addr, previous) in undo.stores.into_iter().rev() {
+            self.ram.undo_store(addr, previous);
+        }
+        self.pc = undo.pc;
+        self.relative_base = undo.relative_base;
+        if let Some(w) = undo.input {
+            self.pending_input.push_front(w);
+        }
+        self.take_watchpoint_hit();
+        self.leave_breakpoint();
+        true
+    }
+
+    /// Start the record of an instruction about to be executed.
+    pub(super) fn journal_instruction(&mut self) {
+        if let Some(journal) = self.journal.as_mut() {
+            if journal.limit == 0 {
+                return;
+            }
+            journal.evicted = if journal.entries.len() == journal.limit {
+                journal.entries.pop_front()
+            } else {
+                None
+            };
+            journal.entries.push_back(Undo {
+                pc: self.pc,
+                relative_base: self.relative_base,
+                stores: Vec::new(),
+                input: None,
+            });
+        }
+    }
+
+    /// Account for a store into `addr` by the current instruction,
+    /// before it happens.
+    pub(super) fn journal_store(&mut self, addr: Word) {
+        if let Some(undo) = self
+            .journal
+            .as_mut()
+            .and_then(|journal| journal.entries.back_mut())
+        {
+            if let Some(previous) = self.ram.before_store(addr) {
+                undo.stores.push((addr, previous));
+            }
+        }
+    }
+
+    /// Account for the current instruction having read `input`.
+    pub(super) fn journal_input(&mut self, input: Word) {
+        if let Some(undo) = self
+            .journal
+            .as_mut()
+            .and_then(|journal| journal.entries.back_mut())
+        {
+            undo.input = Some(input);
+        }
+    }
+
+    /// Forget the record of an instruction which faulted, undoing any
+    /// stores it made.  The program counter was not advanced.
+    pub(super) fn abandon_journal_entry(&mut self) {
+        let journal = match self.journal.as_mut() {
+            Some(journal) => journal,
+            None => return,
+        };
+        let undo = journal.entries.pop_back();
+        if let Some(evicted) = journal.evicted.take() {
+            journal.entries.push_front(evicted);
+        }
+        for (addr, previous) in undo.into_iter().flat_map(|undo| undo.stores).rev() {
+            self.ram.undo_store(addr, previous);
+        }
+    }
+}
+
+#[test]
+fn test_step_back() {
+    use super::{encode_program, CpuFault, CpuStatus, Instr, Param};
+
+    // Read a word into 20, adjust the relative base by it and copy
+    // the word at rb+0 to 21; then load from a negative address.
+    let program = encode_program(&[
+        Instr::read(20),
+        Instr::adjust_relative_base(Param::Pos(20)),
+        Instr::add(Param::Rel(0), Param::Imm(0), 21),
+        Instr::write(Param::Pos(-1)),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.set_strict_reads(true);
+    cpu.enable_journal(10);
+    assert!(!cpu.step_back());
+    cpu.provide_input(Word(1));
+    let mut output: Vec<Word> = Vec::new();
+    for _ in 0..3 {
+        assert_eq!(cpu.step(&mut output).expect("no fault"), CpuStatus::Run);
+    }
+    assert_eq!(cpu.ram.fetch(Word(21)).ok(), Some(program[1]));
+    assert!(matches!(
+        cpu.step(&mut output).map_err(CpuFault::into_root),
+        Err(CpuFault::MemoryFault)
+    ));
+    assert_eq!(cpu.undoable_steps(), 3);
+
+    // Undo the copy; 21 was never written before.
+    assert!(cpu.step_back());
+    assert_eq!(cpu.pc, Word(4));
+    assert!(matches!(
+        cpu.ram.fetch(Word(21)),
+        Err(CpuFault::UninitializedRead(Word(21)))
+    ));
+    // Undo the adjustment and the read, which makes the input
+    // available again.
+    assert!(cpu.step_back());
+    assert_eq!(cpu.relative_base, 0);
+    assert!(cpu.step_back());
+    assert_eq!(cpu.pc, Word(0));
+    assert!(matches!(
+        cpu.ram.fetch(Word(20)),
+        Err(CpuFault::UninitializedRead(Word(20)))
+    ));
+    assert!(!cpu.step_back());
+    let mut replayed = cpu.clone();
+    for _ in 0..3 {
+        assert_eq!(
+            replayed.step(&mut output).expect("no fault"),
+            CpuStatus::Run
+        );
+    }
+    assert_eq!(replayed.ram.fetch(Word(21)).ok(), Some(program[1]));
+
+    // Only the last `limit` instructions are kept.  The input which
+    // was put back is read again.
+    cpu.enable_journal(2);
+    assert!(cpu.run_until_blocked().is_err());
+    assert_eq!(cpu.undoable_steps(), 2);
+    assert!(cpu.step_back() && cpu.step_back());
+    assert!(!cpu.step_back());
+    assert_eq!(cpu.pc, Word(2));
+    assert_eq!(cpu.relative_base, 0);
+}
+
+#[test]
+fn test_journal_with_loop_detection() {
+    use super::{encode_program, CpuFault, InputOutputError, Instr, Param};
+
+    // Flip the sign of the word at 20 for ever.
+    let program = encode_program(&[
+        Instr::mul(Param::Pos(20), Param::Imm(-1), 20),
+        Instr::jump_if_true(Param::Imm(1), Param::Imm(0)),
+    ]);
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    let mut run = |journal: bool| -> Processor {
+        let mut cpu = Processor::new(Word(0));
+        cpu.load(Word(0), &program).expect("program should load");
+        cpu.load(Word(20), &[Word(1)]).expect("data should load");
+        cpu.detect_loops(1);
+        if journal {
+            cpu.enable_journal(10);
+        }
+        assert!(matches!(
+            cpu.run_with_fixed_input(&[], &mut discard)
+                .map_err(CpuFault::into_root),
+            Err(CpuFault::LoopDetected(_))
+        ));
+        cpu
+    };
+    // The third multiplication returns the program to the state it
+    // was in after the first.  With or without the journal, it stops
+    // just after that instruction, whose store is kept.
+    let plain = run(false);
+    let mut journalled = run(true);
+    for cpu in [&plain, &journalled] {
+        assert_eq!(cpu.pc, Word(4));
+        assert_eq!(cpu.ram.fetch(Word(20)).ok(), Some(Word(-1)));
+    }
+    // The instruction which completed the loop can be undone.
+    assert_eq!(journalled.undoable_steps(), 5);
+    assert!(journalled.step_back());
+    assert_eq!(journalled.pc, Word(0));
+    assert_eq!(journalled.ram.fetch(Word(20)).ok(), Some(Word(1)));
+}
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use super::trace_header::fnv1a64;
+use super::{CpuFault, Memory, Opcode, Processor, Word, WordInt};
+
+/// Remembers the states a program has been in since it last did any
+/// I/O.  A deterministic program which returns to an earlier state
+/// without reading or writing anything in between will go on doing
+/// so for ever.
+#[derive(Debug, Clone)]
+pub(super) struct LoopDetector {
+    interval: u64,
+    countdown: u64,
+    seen: HashSet<u64>,
+}
+
+impl LoopDetector {
+    /// Forget the states seen so far.
+    pub(super) fn reset(&mut self) {
+        self.seen.clear();
+        self.countdown = self.interval;
+    }
+}
+
+impl Memory {
+    /// A hash of everything stored in this memory (not including any
+    /// `SharedMemory`).
+    fn digest(&self) -> u64 {
+        fnv1a64(self.content.dump().flat_map(|(addr, value)| [addr, value]))
+    }
+}
+
+impl Processor {
+    /// Make the program fault with `CpuFault::LoopDetected` if it
+    /// gets stuck in an infinite loop which does no I/O.  Every
+    /// `interval` instructions, a hash of the program counter,
+    /// relative base and memory is recorded; if the same hash is
+    /// seen twice with no I/O in between, the program is looping.
+    /// Hashing the memory takes time proportional to its size, so a
+    /// large interval keeps this cheap, at the cost of noticing the
+    /// loop later.  Processors using `SharedMemory` are never
+    /// considered to be stuck, since another processor may change
+    /// the shared memory.
+    pub fn detect_loops(&mut self, interval: u64) {
+        let interval = interval.max(1);
+        self.loop_detector = Some(LoopDetector {
+            interval,
+            countdown: interval,
+            seen: HashSet::new(),
+        });
+    }
+
+    /// Account for the execution of an instruction with opcode `op`,
+    /// which left the program counter at `self.pc`.
+    pub(super) fn check_for_loop(&mut self, op: Opcode) -> Result<(), CpuFault> {
+        let detector = match self.loop_detector.as_mut() {
+            Some(detector) => detector,
+            None => return Ok(()),
+        };
+        if matches!(op, Opcode::Read | Opcode::Write) {
+            detector.reset();
+            return Ok(());
+        }
+        detector.countdown -= 1;
+        if detector.countdown > 0 || self.ram.shared.is_some() {
+            return Ok(());
+        }
+        detector.countdown = detector.interval;
+        let state = fnv1a64(
+            [
+                self.pc,
+                Word(self.relative_base),
+                Word(self.ram.digest() as WordInt),
+            ]
+            .into_iter(),
+        );
+        if detector.seen.insert(state) {
+            Ok(())
+        } else {
+            Err(CpuFault::LoopDetected(self.pc))
+        }
+    }
+}
+
+#[test]
+fn test_detect_loops() {
+    use super::{encode_program, InputOutputError, Instr, Param};
+
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    // Count to 100, then print the count and spin for ever.
+    let program = encode_program(&[
+        Instr::add(Param::Pos(20), Param::Imm(1), 20),
+        Instr::less_than(Param::Pos(20), Param::Imm(100), 21),
+        Instr::jump_if_true(Param::Pos(21), Param::Imm(0)),
+        Instr::write(Param::Pos(20)),
+        Instr::jump_if_true(Param::Imm(1), Param::Imm(13)),
+    ]);
+    for interval in [1, 7, 1000] {
+        let mut cpu = Processor::new(Word(0));
+        cpu.load(Word(0), &program).expect("program should load");
+        cpu.detect_loops(interval);
+        match cpu
+            .run_with_fixed_input(&[], &mut discard)
+            .map_err(CpuFault::into_root)
+        {
+            Err(CpuFault::LoopDetected(Word(13))) => (),
+            other => panic!("expected the loop to be detected, got {:?}", other),
+        }
+    }
+
+    // A program which prints the same thing for ever is not stuck.
+    let program = encode_program(&[
+        Instr::write(Param::Imm(7)),
+        Instr::jump_if_true(Param::Imm(1), Param::Imm(0)),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.detect_loops(1);
+    let mut input = || Err(InputOutputError::NoInput);
+    for _ in 0..100 {
+        cpu.execute_instruction(&mut input, &mut discard)
+            .expect("program should not be considered stuck");
+    }
+}
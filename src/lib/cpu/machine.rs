@@ -0,0 +1,117 @@
+use super::{new_processor, CpuFault, MachineEvent, Processor, Word};
+
+/// Whether a `Machine` can make further progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineStatus {
+    /// The program has not yet been started.
+    Ready,
+    /// The program stopped because it wants another input word.
+    AwaitingInput,
+    /// The program has executed the Stop instruction.
+    Halted,
+}
+
+/// A Processor which is run in stages, being given one input word
+/// at each stage.  This suits programs which read an input, produce
+/// a response and then wait for the next input (for example the
+/// amplifiers of day 7 or the repair droid of day 15).
+///
+/// Cloning a `Machine` forks it; see `Processor`.
+#[derive(Debug, Clone)]
+pub struct Machine {
+    cpu: Processor,
+    status: MachineStatus,
+}
+
+impl Machine {
+    pub fn new(program: &[Word]) -> Result<Machine, CpuFault> {
+        Ok(Machine {
+            cpu: new_processor(program)?,
+            status: MachineStatus::Ready,
+        })
+    }
+
+    pub fn status(&self) -> MachineStatus {
+        self.status
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.status == MachineStatus::Halted
+    }
+
+    /// Run the program, giving it `input` as its next input word,
+    /// until it needs more input or halts.  Returns the last word
+    /// the program wrote in that time, if any.
+    pub fn run_until_output(&mut self, input: Word) -> Result<Option<Word>, CpuFault> {
+        assert!(!self.is_halted(), "cannot resume a halted machine");
+        let mut the_output: Option<Word> = None;
+        self.cpu.provide_input(input);
+        loop {
+            match self.cpu.run_until_blocked()? {
+                MachineEvent::ProducedOutput(w) => {
+                    the_output = Some(w);
+                }
+                MachineEvent::AwaitingInput => {
+                    self.status = MachineStatus::AwaitingInput;
+                    return Ok(the_output);
+                }
+                MachineEvent::Halted => {
+                    self.status = MachineStatus::Halted;
+                    return Ok(the_output);
+                }
+                MachineEvent::BreakpointHit(_) | MachineEvent::WatchpointHit(_) => {
+                    unreachable!("there are no breakpoints or watchpoints")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn words(input: &[super::WordInt]) -> Vec<Word> {
+    input.iter().map(|n| Word(*n)).collect()
+}
+
+#[test]
+fn test_machine_doubler() {
+    // Repeatedly read a word and output twice its value; halt on reading 0.
+    let program = words(&[
+        3, 15, 1006, 15, 14, 1002, 15, 2, 16, 4, 16, 1105, 1, 0, 99, 0, 0,
+    ]);
+    let mut m = Machine::new(&program).expect("program should load");
+    assert_eq!(m.status(), MachineStatus::Ready);
+    assert_eq!(
+        m.run_until_output(Word(4)).expect("no fault"),
+        Some(Word(8))
+    );
+    assert_eq!(m.status(), MachineStatus::AwaitingInput);
+    assert_eq!(
+        m.run_until_output(Word(-3)).expect("no fault"),
+        Some(Word(-6))
+    );
+    assert_eq!(m.status(), MachineStatus::AwaitingInput);
+    assert_eq!(m.run_until_output(Word(0)).expect("no fault"), None);
+    assert_eq!(m.status(), MachineStatus::Halted);
+    assert!(m.is_halted());
+}
+
+#[test]
+fn test_machine_fork() {
+    let program = words(&[
+        3, 15, 1006, 15, 14, 1002, 15, 2, 16, 4, 16, 1105, 1, 0, 99, 0, 0,
+    ]);
+    let mut m = Machine::new(&program).expect("program should load");
+    assert_eq!(
+        m.run_until_output(Word(4)).expect("no fault"),
+        Some(Word(8))
+    );
+    let mut fork = m.clone();
+    assert_eq!(m.run_until_output(Word(0)).expect("no fault"), None);
+    assert!(m.is_halted());
+    // The fork is unaffected by the original halting.
+    assert_eq!(fork.status(), MachineStatus::AwaitingInput);
+    assert_eq!(
+        fork.run_until_output(Word(5)).expect("no fault"),
+        Some(Word(10))
+    );
+}
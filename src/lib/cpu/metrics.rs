@@ -0,0 +1,117 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
+use super::Processor;
+
+/// Counts of the work a `Processor` has done since it was made.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub instructions: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// The number of memory locations which have been written.
+    pub memory_words: usize,
+}
+
+impl Display for Metrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} instructions, {} inputs, {} outputs, {} words of memory",
+            self.instructions, self.inputs, self.outputs, self.memory_words
+        )
+    }
+}
+
+/// A callback which is given the `Metrics` of a `Processor` every
+/// `interval` instructions.
+pub(super) struct MetricsSink {
+    interval: u64,
+    report: Box<dyn FnMut(&Metrics) + Send + Sync>,
+}
+
+impl Debug for MetricsSink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "MetricsSink {{ interval: {} }}", self.interval)
+    }
+}
+
+impl Processor {
+    /// Call `report` with the processor's metrics every `interval`
+    /// instructions, replacing any previous callback.  This allows a
+    /// long-running program to show that it is making progress.
+    pub fn report_metrics_every<F>(&mut self, interval: u64, report: F)
+    where
+        F: FnMut(&Metrics) + Send + Sync + 'static,
+    {
+        assert!(interval > 0, "the metrics interval must be positive");
+        self.metrics_sink = Some(MetricsSink {
+            interval,
+            report: Box::new(report),
+        });
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            memory_words: self.ram.size(),
+            ..self.metrics
+        }
+    }
+
+    /// Account for the execution of one more instruction.
+    pub(super) fn count_instruction(&mut self) {
+        self.metrics.instructions += 1;
+        let due = match self.metrics_sink.as_ref() {
+            Some(sink) => self.metrics.instructions.is_multiple_of(sink.interval),
+            None => false,
+        };
+        if due {
+            let metrics = self.metrics();
+            if let Some(sink) = self.metrics_sink.as_mut() {
+                (sink.report)(&metrics);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_metrics() {
+    use super::{encode_program, InputOutputError, Instr, Param, Word};
+    use std::sync::{Arc, Mutex};
+
+    // Read a word, add 10 to it and print it; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::read(9),
+        Instr::add(Param::Pos(9), Param::Imm(10), 9),
+        Instr::write(Param::Pos(9)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let reports: Arc<Mutex<Vec<Metrics>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink_reports = Arc::clone(&reports);
+    cpu.report_metrics_every(2, move |m| {
+        sink_reports.lock().unwrap().push(*m);
+    });
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[Word(5)], &mut discard)
+        .expect("program should run");
+    let expected_final = Metrics {
+        instructions: 4,
+        inputs: 1,
+        outputs: 1,
+        memory_words: 10,
+    };
+    assert_eq!(cpu.metrics(), expected_final);
+    assert_eq!(
+        *reports.lock().unwrap(),
+        vec![
+            Metrics {
+                instructions: 2,
+                inputs: 1,
+                outputs: 0,
+                memory_words: 10,
+            },
+            expected_final
+        ]
+    );
+}
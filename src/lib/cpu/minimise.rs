@@ -0,0 +1,169 @@
+use std::mem::discriminant;
+
+use super::{CpuFault, CpuStatus, InputOutputError, Processor, Word};
+
+/// A program and the input it is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    pub program: Vec<Word>,
+    pub input: Vec<Word>,
+}
+
+/// How hard `minimise` should try.
+#[derive(Debug, Clone, Copy)]
+pub struct MinimiseOptions {
+    /// Also try to remove words from the end of the program.
+    pub truncate_program: bool,
+    /// Shrinking a case can produce one which never halts, so each
+    /// attempt is abandoned after this many instructions.
+    pub step_limit: u64,
+}
+
+impl Default for MinimiseOptions {
+    fn default() -> MinimiseOptions {
+        MinimiseOptions {
+            truncate_program: false,
+            step_limit: 1_000_000,
+        }
+    }
+}
+
+impl TestCase {
+    /// Run the program on the input, discarding its output, and
+    /// return the fault it stops with (if it faults within
+    /// `step_limit` instructions).
+    pub fn run(&self, step_limit: u64) -> Option<CpuFault> {
+        let mut cpu = Processor::new(Word(0));
+        if let Err(e) = cpu.load(Word(0), &self.program) {
+            return Some(e);
+        }
+        for w in self.input.iter() {
+            cpu.provide_input(*w);
+        }
+        let mut discard_output = |_| -> Result<(), InputOutputError> { Ok(()) };
+        for _ in 0..step_limit {
+            match cpu.step(&mut discard_output) {
+                Ok(CpuStatus::Run) => (),
+                Ok(CpuStatus::BreakpointHit(_) | CpuStatus::WatchpointHit(_)) => (),
+                Ok(CpuStatus::Halt) => {
+                    return None;
+                }
+                Ok(CpuStatus::AwaitingInput) => {
+                    return Some(CpuFault::IOError(InputOutputError::NoInput));
+                }
+                Err(fault) => {
+                    return Some(fault);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Find the smallest subsequence of `items` (removing runs of
+/// adjacent items at a time, then single items) for which `fails`
+/// still returns true.  This is the "ddmin" delta debugging
+/// algorithm.
+fn shrink<F>(mut items: Vec<Word>, fails: F) -> Vec<Word>
+where
+    F: Fn(&[Word]) -> bool,
+{
+    let mut pieces: usize = 2;
+    while !items.is_empty() {
+        let piece_len = items.len().div_ceil(pieces);
+        let smaller: Option<Vec<Word>> = (0..items.len()).step_by(piece_len).find_map(|start| {
+            let end = (start + piece_len).min(items.len());
+            let candidate: Vec<Word> = items[..start]
+                .iter()
+                .chain(items[end..].iter())
+                .copied()
+                .collect();
+            if fails(&candidate) {
+                Some(candidate)
+            } else {
+                None
+            }
+        });
+        match smaller {
+            Some(candidate) => {
+                items = candidate;
+                pieces = (pieces - 1).max(2);
+            }
+            None if pieces >= items.len() => break,
+            None => {
+                pieces = (pieces * 2).min(items.len());
+            }
+        }
+    }
+    items
+}
+
+/// Shrink `case`, which faults, to a smaller case which faults in
+/// the same way (that is, with the same kind of `CpuFault`).  Returns
+/// `None` if `case` does not fault.
+pub fn minimise(case: &TestCase, options: MinimiseOptions) -> Option<TestCase> {
+    let original = case.run(options.step_limit)?;
+    let same_fault = |candidate: &TestCase| -> bool {
+        candidate
+            .run(options.step_limit)
+            .is_some_and(|fault| discriminant(fault.root()) == discriminant(original.root()))
+    };
+    let input = shrink(case.input.clone(), |input| {
+        same_fault(&TestCase {
+            program: case.program.clone(),
+            input: input.to_vec(),
+        })
+    });
+    let mut result = TestCase {
+        program: case.program.clone(),
+        input,
+    };
+    if options.truncate_program {
+        if let Some(len) = (0..result.program.len()).find(|len| {
+            same_fault(&TestCase {
+                program: result.program[..*len].to_vec(),
+                input: result.input.clone(),
+            })
+        }) {
+            result.program.truncate(len);
+        }
+    }
+    Some(result)
+}
+
+#[test]
+fn test_minimise() {
+    use super::WordInt;
+
+    fn words(input: &[WordInt]) -> Vec<Word> {
+        input.iter().map(|n| Word(*n)).collect()
+    }
+    // Repeatedly read an address and output the word stored there;
+    // a negative address causes a memory fault.
+    let case = TestCase {
+        program: words(&[3, 3, 4, 0, 1105, 1, 0]),
+        input: words(&[5, 1, 0, 6, -3, 2, 8]),
+    };
+    assert!(matches!(
+        case.run(1000).map(CpuFault::into_root),
+        Some(CpuFault::MemoryFault)
+    ));
+    let input_only = minimise(&case, MinimiseOptions::default()).expect("case should fault");
+    assert_eq!(input_only.program, case.program);
+    assert_eq!(input_only.input, words(&[-3]));
+
+    let options = MinimiseOptions {
+        truncate_program: true,
+        ..MinimiseOptions::default()
+    };
+    let both = minimise(&case, options).expect("case should fault");
+    assert_eq!(both.program, words(&[3, 3, 4]));
+    assert_eq!(both.input, words(&[-3]));
+
+    // A case which does not fault cannot be minimised.
+    let working = TestCase {
+        program: words(&[3, 3, 4, 0, 99]),
+        input: words(&[0]),
+    };
+    assert_eq!(minimise(&working, MinimiseOptions::default()), None);
+}
@@ -1,21 +1,104 @@
-use std::cmp::max;
-use std::collections::BTreeMap;
+use std::cmp::{max, min};
+use std::collections::VecDeque;
 use std::fmt::{Debug, Display};
-use std::fs::{File, OpenOptions};
+use std::fs::File;
 use std::hash::{Hash, Hasher};
-use std::io::Write;
-use std::io::{self, BufRead, BufReader};
+use std::io::{BufRead, Write};
 use std::num::{ParseIntError, TryFromIntError};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::error::Fail;
+use crate::input::{context_snippet, open_decompressed, Location};
+
+mod annotate;
+mod ascii_io;
+mod asm;
+mod backend;
+mod breakpoints;
+mod builder;
+mod bundle;
+mod chunker;
+mod coredump;
+mod coverage;
+mod diff;
+mod disasm;
+mod dump;
+mod factory;
+mod fault;
+mod generate;
+mod instr;
+pub mod io;
+mod journal;
+mod loops;
+mod machine;
+mod metrics;
+mod minimise;
+mod profile;
+mod replay;
+mod shared;
+mod state;
+mod timing;
+mod trace_header;
+mod watch;
+
+pub use annotate::{Annotation, Annotations, BadAnnotation};
+pub use ascii_io::{AsciiIo, AsciiOutput, NotAscii};
+pub use asm::{assemble, assemble_with_labels, BadAssembly};
+pub use backend::{DenseMemory, MemoryBackend, PagedMemory, SparseMemory};
+pub use builder::ProcessorBuilder;
+pub use bundle::{BadBundle, ProgramBundle};
+pub use chunker::{IncompleteChunk, OutputChunker};
+pub use coverage::{Coverage, Usage};
+pub use diff::{format_memory_diff, StateDiff};
+pub use dump::{DumpFormat, Radix, RowAlignment};
+pub use factory::{
+    annotate_all_processors, count_cycles_of_all_processors, detect_loops_of_all_processors,
+    dump_core_of_all_processors, new_processor, profile_all_processors,
+    report_progress_of_all_processors, run_patched, strict_reads_of_all_processors,
+    trace_all_processors,
+};
+pub use fault::FaultContext;
+pub use generate::{random_program, GeneratorOptions};
+pub use instr::{encode_program, Instr, Param};
+pub use io::{from_iter, once, InputDevice, IterSource, Once, OutputDevice, Outputs, Then, Wire};
+pub use machine::{Machine, MachineStatus};
+pub use metrics::Metrics;
+pub use minimise::{minimise, MinimiseOptions, TestCase};
+pub use profile::Profile;
+pub use replay::{replay_trace, ReplayError};
+pub use shared::{Mailbox, SharedMemory};
+pub use state::{BadMachineState, MachineState};
+pub use timing::{BadCostModel, CostModel};
+pub use trace_header::{BadTraceHeader, TraceHeader};
+pub use watch::{AccessKind, MemoryAccess, WatchFor};
 
 pub const NUM_PARAMS: usize = 4;
 
-#[derive(Clone, Copy)]
-pub struct Word(pub i64);
+/// The integer type held in a `Word`.  The puzzles only need `i64`,
+/// but with the `wide-words` feature it is `i128`, for programs
+/// (such as experiments with the modular arithmetic of day 22) whose
+/// values would overflow.  An arbitrary-precision type is not
+/// supported, since a `Word` must be `Copy`.
+#[cfg(not(feature = "wide-words"))]
+pub type WordInt = i64;
+/// The integer type held in a `Word`; see above.
+#[cfg(feature = "wide-words")]
+pub type WordInt = i128;
+
+#[derive(Clone, Copy, Default)]
+pub struct Word(pub WordInt);
 
 impl Word {
+    /// The value as an `i64`, for code (such as `Screen`) which does
+    /// not use `WordInt`, or `None` if it does not fit (which can
+    /// only happen with the `wide-words` feature).
+    #[allow(clippy::useless_conversion)] // WordInt is usually i64.
+    pub fn as_i64(self) -> Option<i64> {
+        i64::try_from(self.0).ok()
+    }
+
     fn checked_add(&self, other: &Word) -> Result<Word, CpuFault> {
         match self.0.checked_add(other.0) {
             Some(total) => Ok(Word(total)),
@@ -24,7 +107,7 @@ impl Word {
     }
 
     fn checked_add_usize(&self, other: &usize) -> Result<Word, CpuFault> {
-        let n: i64 = match i64::try_from(*other) {
+        let n: WordInt = match WordInt::try_from(*other) {
             Ok(x) => x,
             Err(_) => {
                 return Err(CpuFault::Overflow);
@@ -44,12 +127,35 @@ impl Word {
     }
 }
 
-fn add(a: Word, b: Word) -> Result<Word, CpuFault> {
-    a.checked_add(&b)
+/// What the arithmetic instructions do when their result does not
+/// fit in a `Word`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowMode {
+    /// Stop the program with `CpuFault::Overflow`.
+    #[default]
+    Fault,
+    /// Wrap around, as `WordInt::wrapping_add` and `WordInt::wrapping_mul`
+    /// do.  Some Intcode programs written by others rely on this.
+    Wrap,
+    /// Give the largest or smallest `Word` instead, as
+    /// `WordInt::saturating_add` and `WordInt::saturating_mul` do.
+    Saturate,
 }
 
-fn mul(a: Word, b: Word) -> Result<Word, CpuFault> {
-    a.checked_mul(&b)
+fn add(a: Word, b: Word, mode: OverflowMode) -> Result<Word, CpuFault> {
+    match mode {
+        OverflowMode::Fault => a.checked_add(&b),
+        OverflowMode::Wrap => Ok(Word(a.0.wrapping_add(b.0))),
+        OverflowMode::Saturate => Ok(Word(a.0.saturating_add(b.0))),
+    }
+}
+
+fn mul(a: Word, b: Word, mode: OverflowMode) -> Result<Word, CpuFault> {
+    match mode {
+        OverflowMode::Fault => a.checked_mul(&b),
+        OverflowMode::Wrap => Ok(Word(a.0.wrapping_mul(b.0))),
+        OverflowMode::Saturate => Ok(Word(a.0.saturating_mul(b.0))),
+    }
 }
 
 impl Display for Word {
@@ -66,7 +172,7 @@ impl Debug for Word {
 
 #[derive(Debug, Copy, Clone)]
 pub struct BadAddressingMode {
-    mode: i64,
+    mode: WordInt,
 }
 
 impl Display for BadAddressingMode {
@@ -107,16 +213,27 @@ impl Display for BadInstruction {
 
 impl std::error::Error for BadInstruction {}
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InputOutputError {
     Unprintable(Word),
     NoInput,
+    Rejected(Word),
+    /// The program drew at a position which has no place on the
+    /// screen, such as one with a negative coordinate.
+    OffScreen {
+        x: Word,
+        y: Word,
+    },
 }
 
 impl Display for InputOutputError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             InputOutputError::NoInput => f.write_str("ran out of input"),
+            InputOutputError::Rejected(w) => write!(f, "output device rejected word {}", w),
+            InputOutputError::OffScreen { x, y } => {
+                write!(f, "cannot draw at {},{} as it is off the screen", x, y)
+            }
             InputOutputError::Unprintable(w) => write!(
                 f,
                 "cannot print word {} as this cannot be converted to a char",
@@ -136,6 +253,25 @@ pub enum CpuFault {
     AddressingModeNotValidInContext,
     IOError(InputOutputError),
     TraceError(String),
+    /// A setting for every processor, such as a core dump file, was
+    /// invalid or was given more than once.
+    BadOption(String),
+    /// The program tried to store into a read-only address.
+    WriteProtected(Word),
+    /// The program read an address which was never written, and
+    /// strict reads were requested.
+    UninitializedRead(Word),
+    /// The program returned to an earlier state without doing any
+    /// I/O in between, so it would never stop.  This is only
+    /// detected if loop detection was requested.  The word is the
+    /// program counter at the time.
+    LoopDetected(Word),
+    /// The program executed this many instructions without halting
+    /// or being paused (see `Processor::run_with_limit`).
+    StepLimitExceeded(u64),
+    /// A fault caused by an instruction, with where in the program
+    /// it happened.  Use `root` to find out what the fault was.
+    At(Box<FaultContext>),
 }
 
 impl From<BadInstruction> for CpuFault {
@@ -169,6 +305,20 @@ impl Display for CpuFault {
                 write!(f, "I/O error: {}", e)
             }
             CpuFault::TraceError(e) => f.write_str(e.as_str()),
+            CpuFault::BadOption(e) => f.write_str(e.as_str()),
+            CpuFault::WriteProtected(addr) => {
+                write!(f, "write to read-only address {}", addr)
+            }
+            CpuFault::UninitializedRead(addr) => {
+                write!(f, "read of uninitialised address {}", addr)
+            }
+            CpuFault::LoopDetected(pc) => {
+                write!(f, "infinite loop without I/O detected at address {}", pc)
+            }
+            CpuFault::StepLimitExceeded(limit) => {
+                write!(f, "program did not stop within {} instructions", limit)
+            }
+            CpuFault::At(context) => write!(f, "{}", context),
         }
     }
 }
@@ -207,7 +357,7 @@ impl Hash for Word {
 
 impl PartialOrd for Word {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
@@ -217,10 +367,120 @@ impl Ord for Word {
     }
 }
 
+/// Controls how much of a program's execution is traced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceConfig {
+    sample_interval: u64,
+}
+
+impl Default for TraceConfig {
+    /// Trace every event.
+    fn default() -> TraceConfig {
+        TraceConfig { sample_interval: 1 }
+    }
+}
+
+impl TraceConfig {
+    /// Trace only every `n`th instruction executed, together with its
+    /// memory loads and stores.  Input and output are always traced.
+    /// Events are numbered as if they had all been traced, so the
+    /// gaps show where events were skipped.
+    pub fn sample_every(n: u64) -> TraceConfig {
+        assert!(n > 0, "the trace sampling interval must be positive");
+        TraceConfig { sample_interval: n }
+    }
+}
+
+/// Receives a trace one line at a time, without the newline, as an
+/// alternative to writing it out.  The first line is the header.
+/// Closures taking a `&str` are trace sinks.
+pub trait TraceSink: Send + Sync {
+    fn trace_line(&mut self, line: &str);
+}
+
+impl<F> TraceSink for F
+where
+    F: FnMut(&str) + Send + Sync,
+{
+    fn trace_line(&mut self, line: &str) {
+        self(line)
+    }
+}
+
+/// Where a `Tracer` writes its trace.
+enum TraceOutput {
+    File(File),
+    Stderr,
+    Writer(Box<dyn Write + Send + Sync>),
+    /// The sink, and the part of the current line not yet given to it.
+    Sink(Box<dyn TraceSink>, Vec<u8>),
+}
+
+impl Debug for TraceOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceOutput::File(file) => f.debug_tuple("File").field(file).finish(),
+            TraceOutput::Stderr => f.write_str("Stderr"),
+            TraceOutput::Writer(_) => f.write_str("Writer"),
+            TraceOutput::Sink(_, pending) => f.debug_tuple("Sink").field(pending).finish(),
+        }
+    }
+}
+
+impl TraceOutput {
+    fn close(&mut self) -> Result<(), std::io::Error> {
+        match self {
+            TraceOutput::File(file) => file.sync_all(),
+            TraceOutput::Stderr => std::io::stderr().flush(),
+            TraceOutput::Writer(writer) => writer.flush(),
+            TraceOutput::Sink(sink, pending) => {
+                if !pending.is_empty() {
+                    sink.trace_line(&String::from_utf8_lossy(pending));
+                    pending.clear();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Write for TraceOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            TraceOutput::File(file) => file.write(buf),
+            TraceOutput::Stderr => std::io::stderr().write(buf),
+            TraceOutput::Writer(writer) => writer.write(buf),
+            TraceOutput::Sink(sink, pending) => {
+                pending.extend_from_slice(buf);
+                while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    sink.trace_line(&String::from_utf8_lossy(&line[..end]));
+                }
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            TraceOutput::File(file) => file.flush(),
+            TraceOutput::Stderr => std::io::stderr().flush(),
+            TraceOutput::Writer(writer) => writer.flush(),
+            TraceOutput::Sink(_, _) => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Tracer {
     event_seqno: u64,
-    output: Option<File>,
+    output: Option<TraceOutput>,
+    config: TraceConfig,
+    executions: u64,
+    /// Whether the events of the current instruction are traced.
+    sampled: bool,
+    annotations: Option<Arc<Annotations>>,
+    header_written: bool,
 }
 
 impl Tracer {
@@ -228,6 +488,19 @@ impl Tracer {
         Tracer {
             event_seqno: 0,
             output: None,
+            config: TraceConfig::default(),
+            executions: 0,
+            sampled: true,
+            annotations: None,
+            header_written: false,
+        }
+    }
+
+    /// How `addr` is shown in the trace.
+    fn place(annotations: Option<&Annotations>, addr: Word) -> String {
+        match annotations {
+            Some(annotations) => annotations.describe(addr),
+            None => addr.to_string(),
         }
     }
 
@@ -237,50 +510,72 @@ impl Tracer {
         result
     }
 
-    fn enable(&mut self, file: File) {
-        self.output = Some(file);
+    fn enable(&mut self, output: TraceOutput, config: TraceConfig) {
+        self.output = Some(output);
+        self.config = config;
     }
 
     fn close(&mut self) -> Result<(), std::io::Error> {
-        let result = if let Some(file) = self.output.as_ref() {
-            file.sync_all()
+        let result = if let Some(output) = self.output.as_mut() {
+            output.close()
         } else {
             Ok(())
         };
         self.output = None;
         result
     }
+    /// Whether the trace still needs its header.
+    fn needs_header(&self) -> bool {
+        self.output.is_some() && !self.header_written
+    }
+
+    fn write_header(&mut self, header: &TraceHeader) -> Result<(), std::io::Error> {
+        self.header_written = true;
+        match self.output.as_mut() {
+            Some(out) => writeln!(out, "{}", header),
+            None => Ok(()),
+        }
+    }
+
     fn trace_execution(&mut self, pc: Word, instruction: Word) -> Result<(), std::io::Error> {
         let seq = self.next_seq();
-        if let Some(mut file) = self.output.as_ref() {
-            writeln!(file, "{} @{}: execute {}", seq, pc, instruction)
-        } else {
-            Ok(())
+        self.sampled = self.executions.is_multiple_of(self.config.sample_interval);
+        self.executions += 1;
+        match self.output.as_mut() {
+            Some(out) if self.sampled => {
+                let place = Tracer::place(self.annotations.as_deref(), pc);
+                writeln!(out, "{} @{}: execute {}", seq, place, instruction)
+            }
+            _ => Ok(()),
         }
     }
 
     fn trace_mem_load(&mut self, addr: Word, value: Word) -> Result<(), std::io::Error> {
         let seq = self.next_seq();
-        if let Some(mut file) = self.output.as_ref() {
-            writeln!(file, "{} @{}: load {}", seq, addr, value)
-        } else {
-            Ok(())
+        match self.output.as_mut() {
+            Some(out) if self.sampled => {
+                let place = Tracer::place(self.annotations.as_deref(), addr);
+                writeln!(out, "{} @{}: load {}", seq, place, value)
+            }
+            _ => Ok(()),
         }
     }
 
     fn trace_mem_store(&mut self, addr: Word, value: Word) -> Result<(), std::io::Error> {
         let seq = self.next_seq();
-        if let Some(mut file) = self.output.as_ref() {
-            writeln!(file, "{} @{}: store {}", seq, addr, value)
-        } else {
-            Ok(())
+        match self.output.as_mut() {
+            Some(out) if self.sampled => {
+                let place = Tracer::place(self.annotations.as_deref(), addr);
+                writeln!(out, "{} @{}: store {}", seq, place, value)
+            }
+            _ => Ok(()),
         }
     }
 
     fn trace_io_read(&mut self, value: Word) -> Result<(), std::io::Error> {
         let seq = self.next_seq();
-        if let Some(mut file) = self.output.as_ref() {
-            writeln!(file, "{} io-read:{}", seq, value)
+        if let Some(out) = self.output.as_mut() {
+            writeln!(out, "{} io-read:{}", seq, value)
         } else {
             Ok(())
         }
@@ -288,8 +583,8 @@ impl Tracer {
 
     fn trace_io_write(&mut self, value: Word) -> Result<(), std::io::Error> {
         let seq = self.next_seq();
-        if let Some(mut file) = self.output.as_ref() {
-            writeln!(file, "{} io-write:{}", seq, value)
+        if let Some(out) = self.output.as_mut() {
+            writeln!(out, "{} io-write:{}", seq, value)
         } else {
             Ok(())
         }
@@ -303,7 +598,7 @@ pub enum AddressingMode {
     RELATIVE,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Opcode {
     Add = 1,       // day 2
     Multiply = 2,  // day 2
@@ -319,7 +614,7 @@ enum Opcode {
 
 #[derive(Debug, Copy, Clone)]
 pub struct BadOpcode {
-    code: i64,
+    code: WordInt,
 }
 
 impl Display for BadOpcode {
@@ -357,10 +652,10 @@ struct DecodedInstruction {
     addressing_modes: [AddressingMode; NUM_PARAMS],
 }
 
-impl TryFrom<&i64> for AddressingMode {
+impl TryFrom<&WordInt> for AddressingMode {
     type Error = BadAddressingMode;
 
-    fn try_from(instruction: &i64) -> Result<Self, Self::Error> {
+    fn try_from(instruction: &WordInt) -> Result<Self, Self::Error> {
         let mode = instruction % 10;
         match mode {
             0 => Ok(AddressingMode::POSITIONAL),
@@ -371,7 +666,7 @@ impl TryFrom<&i64> for AddressingMode {
     }
 }
 
-fn getmodes(m: &i64) -> Result<[AddressingMode; NUM_PARAMS], BadAddressingMode> {
+fn getmodes(m: &WordInt) -> Result<[AddressingMode; NUM_PARAMS], BadAddressingMode> {
     // The units and tens digits of the instruction are the opcode.
     // The 3 modes are (index 1) the hundreds, (index 2) thousands and
     // (index 3) the ten-thousands digit.
@@ -417,16 +712,55 @@ fn decode(insruction: Word, pc: Word) -> Result<DecodedInstruction, BadInstructi
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CpuStatus {
     Halt,
     Run,
+    /// The next instruction is a read, but no input has been given
+    /// to `provide_input`.  Only `Processor::step` and
+    /// `Processor::run_ascii` return this; the other methods ask
+    /// their input device instead.
+    AwaitingInput,
+    /// The program was paused at a breakpoint (see
+    /// `Processor::add_breakpoint`), before executing the
+    /// instruction at this address.
+    BreakpointHit(Word),
+    /// The program was paused after an instruction accessed a
+    /// watched address (see `Processor::add_watchpoint`).
+    WatchpointHit(MemoryAccess),
 }
 
-#[derive(Debug)]
+/// The reason why `Processor::run_until_blocked` (or
+/// `run_until_event`) stopped running the program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineEvent {
+    /// The program wrote this word.
+    ProducedOutput(Word),
+    /// The program tried to read input but none was available.  The
+    /// read instruction has not been executed, so the program can be
+    /// resumed once there is some input for it.
+    AwaitingInput,
+    /// The program executed the Stop instruction.
+    Halted,
+    /// The program was paused at the breakpoint at this address.
+    BreakpointHit(Word),
+    /// The program was paused after it made this access to a watched
+    /// address.
+    WatchpointHit(MemoryAccess),
+}
+
+/// The memory of a `Processor`.  Cloning a `Memory` is cheap: the
+/// clones share their contents until one of them is modified.  Any
+/// `SharedMemory` remains shared by the clones.
+#[derive(Debug, Clone)]
 pub struct Memory {
-    content: BTreeMap<Word, Word>,
-    top: i64,
+    content: Arc<Box<dyn MemoryBackend>>,
+    top: WordInt,
+    read_only: Vec<Range<Word>>,
+    shared: Option<(Range<Word>, SharedMemory)>,
+    strict: bool,
+    /// The smallest range containing everything passed to `load`.
+    loaded: Option<Range<Word>>,
 }
 
 impl Default for Memory {
@@ -437,9 +771,18 @@ impl Default for Memory {
 
 impl Memory {
     pub fn new() -> Memory {
+        Memory::with_backend(Box::new(DenseMemory::new()))
+    }
+
+    /// An empty memory which stores its words in `backend`.
+    pub fn with_backend(backend: Box<dyn MemoryBackend>) -> Memory {
         Memory {
-            content: BTreeMap::new(),
+            content: Arc::new(backend),
             top: 0,
+            read_only: Vec::new(),
+            shared: None,
+            strict: false,
+            loaded: None,
         }
     }
 
@@ -451,39 +794,106 @@ impl Memory {
         }
     }
 
+    /// The shared memory holding `addr`, if it is not held in this
+    /// memory itself.
+    fn shared_at(&self, addr: Word) -> Option<&SharedMemory> {
+        match self.shared.as_ref() {
+            Some((range, shared)) if range.contains(&addr) => Some(shared),
+            _ => None,
+        }
+    }
+
     pub fn fetch(&self, addr: Word) -> Result<Word, CpuFault> {
         let addr = Memory::pos(addr)?;
-        Ok(*self.content.get(&addr).unwrap_or(&Word(0)))
+        let value = match self.shared_at(addr) {
+            Some(shared) => shared.get(addr),
+            None => self.content.fetch(addr),
+        };
+        match value {
+            Some(w) => Ok(w),
+            None if self.strict => Err(CpuFault::UninitializedRead(addr)),
+            None => Ok(Word(0)),
+        }
+    }
+
+    /// Make `fetch` fail for addresses which have never been loaded
+    /// or stored into, instead of returning zero.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
     }
 
     pub fn store(&mut self, addr: Word, value: Word) -> Result<(), CpuFault> {
         let addr = Memory::pos(addr)?;
-        self.content.insert(addr, value);
-        self.top = max(self.top, addr.0);
+        if self.is_read_only(addr) {
+            return Err(CpuFault::WriteProtected(addr));
+        }
+        self.write(addr, value);
         Ok(())
     }
 
+    /// Store `value` at `addr`, which is not negative, even if it is
+    /// read-only.
+    fn write(&mut self, addr: Word, value: Word) {
+        if let Some(shared) = self.shared_at(addr) {
+            shared.store(addr, value);
+            return;
+        }
+        Arc::make_mut(&mut self.content).store(addr, value);
+        self.top = max(self.top, addr.0);
+    }
+
     pub fn load(&mut self, base: Word, program: &[Word]) -> Result<(), CpuFault> {
         let base: Word = Memory::pos(base)?;
-        for (offset, w) in program.iter().enumerate() {
-            let offset: Word = match offset.try_into() {
-                Ok(n) if n >= 0 => Word(n),
-                _ => {
-                    return Err(CpuFault::MemoryFault);
-                }
-            };
-            let addr = Word(base.0 + offset.0);
-            self.content.insert(addr, *w);
-            self.top = max(self.top, addr.0);
+        let end = match WordInt::try_from(program.len()).map(|len| base.0.checked_add(len)) {
+            Ok(Some(end)) => Word(end),
+            _ => {
+                return Err(CpuFault::MemoryFault);
+            }
+        };
+        Arc::make_mut(&mut self.content).load(base, program);
+        if !program.is_empty() {
+            self.top = max(self.top, end.0 - 1);
+            self.loaded = Some(match self.loaded.take() {
+                Some(r) => min(r.start, base)..max(r.end, end),
+                None => base..end,
+            });
         }
         Ok(())
     }
 
+    /// The addresses which have been given values by `load`, or
+    /// rather the smallest range which contains them all.
+    pub fn loaded(&self) -> Option<Range<Word>> {
+        self.loaded.clone()
+    }
+
+    /// Make `store` fail for the addresses in `range`.  `load` can
+    /// still change them.
+    pub fn protect(&mut self, range: Range<Word>) {
+        self.read_only.push(range);
+    }
+
+    /// Keep the addresses in `range` in `shared` instead.  This
+    /// replaces any range shared previously.  `load` still writes
+    /// to this memory.
+    pub fn share(&mut self, range: Range<Word>, shared: SharedMemory) {
+        self.shared = Some((range, shared));
+    }
+
+    pub fn is_read_only(&self, addr: Word) -> bool {
+        self.read_only.iter().any(|range| range.contains(&addr))
+    }
+
+    /// The number of locations which have been written.
+    pub fn size(&self) -> usize {
+        self.content.len()
+    }
+
     pub fn dump(&self, dest: &mut Vec<Word>) {
         dest.clear();
         let zero: Word = Word(0);
         if !self.content.is_empty() {
-            dest.extend((0..=self.top).map(|addr| self.content.get(&Word(addr)).unwrap_or(&zero)));
+            dest.extend((0..=self.top).map(|addr| self.content.fetch(Word(addr)).unwrap_or(zero)));
         }
     }
 }
@@ -491,23 +901,135 @@ impl Memory {
 #[derive(Debug)]
 pub struct Processor {
     ram: Memory,
-    relative_base: i64,
+    relative_base: WordInt,
     pc: Word,
     tracer: Tracer,
+    metrics: Metrics,
+    metrics_sink: Option<metrics::MetricsSink>,
+    core_dumper: Option<coredump::CoreDumper>,
+    pending_input: VecDeque<Word>,
+    overflow: OverflowMode,
+    cycle_counter: Option<timing::CycleCounter>,
+    loop_detector: Option<loops::LoopDetector>,
+    breakpoints: breakpoints::Breakpoints,
+    watchpoints: watch::Watchpoints,
+    profiler: Option<profile::Profiler>,
+    coverage: Option<Coverage>,
+    journal: Option<journal::Journal>,
+    recent_pcs: fault::RecentPcs,
+}
+
+/// Cloning a `Processor` forks the program: the clone starts with
+/// the same memory, program counter, relative base, metrics, unread
+/// input, overflow mode, cycle count, profile, coverage, journal and
+/// loop detection, and the two then run independently.  Tracing, metrics
+/// reporting, cycle and profile reporting, core dumps and watchpoint
+/// callbacks are not enabled in the clone.
+impl Clone for Processor {
+    fn clone(&self) -> Processor {
+        Processor {
+            ram: self.ram.clone(),
+            relative_base: self.relative_base,
+            pc: self.pc,
+            tracer: Tracer::new(),
+            metrics: self.metrics,
+            metrics_sink: None,
+            core_dumper: None,
+            pending_input: self.pending_input.clone(),
+            overflow: self.overflow,
+            cycle_counter: self.clone_cycle_counter(),
+            loop_detector: self.loop_detector.clone(),
+            breakpoints: self.breakpoints.clone(),
+            watchpoints: self.watchpoints.clone(),
+            profiler: self.clone_profiler(),
+            coverage: self.coverage.clone(),
+            journal: self.journal.clone(),
+            recent_pcs: self.recent_pcs,
+        }
+    }
 }
 
 impl Processor {
     pub fn new(initial_pc: Word) -> Processor {
+        Processor::with_backend(initial_pc, Box::new(DenseMemory::new()))
+    }
+
+    /// A processor whose memory stores its words in `backend`.
+    pub fn with_backend(initial_pc: Word, backend: Box<dyn MemoryBackend>) -> Processor {
         Processor {
-            ram: Memory::new(),
+            ram: Memory::with_backend(backend),
             relative_base: 0,
             pc: initial_pc,
             tracer: Tracer::new(),
+            metrics: Metrics::default(),
+            metrics_sink: None,
+            core_dumper: None,
+            pending_input: VecDeque::new(),
+            overflow: OverflowMode::default(),
+            cycle_counter: None,
+            loop_detector: None,
+            breakpoints: breakpoints::Breakpoints::default(),
+            watchpoints: watch::Watchpoints::default(),
+            profiler: None,
+            coverage: None,
+            journal: None,
+            recent_pcs: fault::RecentPcs::default(),
         }
     }
 
+    /// Choose what the Add and Multiply instructions do when their
+    /// result overflows.  By default, the program faults.
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow = mode;
+    }
+
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow
+    }
+
     pub fn enable_tracing(&mut self, file: File) {
-        self.tracer.enable(file)
+        self.tracer
+            .enable(TraceOutput::File(file), TraceConfig::default())
+    }
+
+    /// Trace to `file` as specified by `config`.
+    pub fn enable_tracing_with(&mut self, file: File, config: TraceConfig) {
+        self.tracer.enable(TraceOutput::File(file), config)
+    }
+
+    /// Trace to `writer` as specified by `config`.  This allows the
+    /// trace to go to an in-memory buffer or a compressing writer,
+    /// for example.  The writer is flushed (but not otherwise closed)
+    /// when the processor is dropped.
+    pub fn enable_tracing_to<W>(&mut self, writer: W, config: TraceConfig)
+    where
+        W: Write + Send + Sync + 'static,
+    {
+        self.tracer
+            .enable(TraceOutput::Writer(Box::new(writer)), config)
+    }
+
+    /// Give the trace, as specified by `config`, to `sink` one line
+    /// at a time instead of writing it out.
+    pub fn enable_tracing_to_sink<S>(&mut self, sink: S, config: TraceConfig)
+    where
+        S: TraceSink + 'static,
+    {
+        self.tracer
+            .enable(TraceOutput::Sink(Box::new(sink), Vec::new()), config)
+    }
+
+    /// Show the names of addresses given in `annotations` in the
+    /// trace.
+    pub fn annotate(&mut self, annotations: Arc<Annotations>) {
+        self.tracer.annotations = Some(annotations);
+    }
+
+    /// Trace to standard error.  This is convenient for quick
+    /// interactive debugging, since no trace file is needed.
+    pub fn enable_tracing_stderr(&mut self) {
+        self.tracer
+            .enable(TraceOutput::Stderr, TraceConfig::default())
     }
 
     fn update_relative_base(&mut self, delta: Word) -> Result<(), CpuFault> {
@@ -523,6 +1045,31 @@ impl Processor {
         self.pc = addr;
     }
 
+    /// The address of the next instruction to be executed.
+    pub fn pc(&self) -> Word {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> WordInt {
+        self.relative_base
+    }
+
+    /// The word at `addr`.  Unlike a load by the program, this is
+    /// not traced and does not trigger watchpoints.
+    pub fn peek(&self, addr: Word) -> Result<Word, CpuFault> {
+        self.ram.fetch(addr)
+    }
+
+    /// Change the word at `addr` to `value`, for example to patch the
+    /// program.  Unlike a store by the program, this is not traced,
+    /// does not trigger watchpoints and is allowed even if `addr` is
+    /// read-only.  Unlike `load`, it does not change which addresses
+    /// are considered part of the program.
+    pub fn poke(&mut self, addr: Word, value: Word) -> Result<(), CpuFault> {
+        self.ram.write(Memory::pos(addr)?, value);
+        Ok(())
+    }
+
     fn execute_arithmetic_instruction<F: Fn(Word, Word) -> Result<Word, CpuFault>>(
         &mut self,
         modes: &[AddressingMode; NUM_PARAMS],
@@ -537,44 +1084,109 @@ impl Processor {
         }
     }
 
-    pub fn execute_instruction<FI, FO>(
+    pub fn execute_instruction<I, O>(
         &mut self,
-        get_input: &mut FI,
-        do_output: &mut FO,
+        input: &mut I,
+        output: &mut O,
     ) -> Result<CpuStatus, CpuFault>
     where
-        FI: FnMut() -> Result<Word, InputOutputError>,
-        FO: FnMut(Word) -> Result<(), InputOutputError>,
+        I: InputDevice + ?Sized,
+        O: OutputDevice + ?Sized,
     {
+        if let Some(status) = self.check_pause() {
+            return Ok(status);
+        }
+        self.leave_breakpoint();
+        match self.execute_instruction_unchecked(input, output) {
+            Ok((status, op)) => {
+                // The instruction has completed (and stays in the
+                // journal) even if the program turns out to be stuck.
+                if let Err(fault) = self.check_for_loop(op) {
+                    self.take_watchpoint_hit();
+                    self.handle_fault(&fault);
+                    return Err(self.add_context_between_instructions(fault));
+                }
+                match status {
+                    CpuStatus::Run => match self.take_watchpoint_hit() {
+                        Some(access) => Ok(CpuStatus::WatchpointHit(access)),
+                        None => Ok(CpuStatus::Run),
+                    },
+                    status => Ok(status),
+                }
+            }
+            Err(fault) => {
+                self.take_watchpoint_hit();
+                self.abandon_journal_entry();
+                self.handle_fault(&fault);
+                Err(self.add_fault_context(fault))
+            }
+        }
+    }
+
+    /// Why the program should pause before executing another
+    /// instruction, if it should.
+    fn check_pause(&mut self) -> Option<CpuStatus> {
+        match self.take_watchpoint_hit() {
+            Some(access) => Some(CpuStatus::WatchpointHit(access)),
+            None => self.check_breakpoint().map(CpuStatus::BreakpointHit),
+        }
+    }
+
+    fn execute_instruction_unchecked<I, O>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<(CpuStatus, Opcode), CpuFault>
+    where
+        I: InputDevice + ?Sized,
+        O: OutputDevice + ?Sized,
+    {
+        self.journal_instruction();
+        self.recent_pcs.record(self.pc);
         let instruction = self.ram.fetch(self.pc)?;
+        if let Some(dumper) = self.core_dumper.as_mut() {
+            dumper.record(self.pc, instruction);
+        }
+        if self.tracer.needs_header() {
+            let header = TraceHeader::new(&self.ram, self.pc, &self.tracer.config);
+            self.tracer.write_header(&header)?;
+        }
         self.tracer.trace_execution(self.pc, instruction)?;
         let decoded = decode(instruction, self.pc)?;
         //println!("executing at {}: {:?}", &self.pc, &decoded);
         let (state, next_pc) = match decoded.op {
             Opcode::Add => {
-                self.execute_arithmetic_instruction(&decoded.addressing_modes, add)?;
-
-                (CpuStatus::Run, self.pc.checked_add(&Word(4_i64))?)
+                let mode = self.overflow;
+                self.execute_arithmetic_instruction(&decoded.addressing_modes, |a, b| {
+                    add(a, b, mode)
+                })?;
+                (CpuStatus::Run, self.pc.checked_add(&Word(4))?)
             }
             Opcode::Multiply => {
-                self.execute_arithmetic_instruction(&decoded.addressing_modes, mul)?;
-                (CpuStatus::Run, self.pc.checked_add(&Word(4_i64))?)
-            }
-            Opcode::Read => match get_input() {
-                Ok(input) => {
-                    self.tracer.trace_io_read(input)?;
-                    self.put(&decoded.addressing_modes, 1, input)?;
-                    (CpuStatus::Run, self.pc.checked_add(&Word(2_i64))?)
+                let mode = self.overflow;
+                self.execute_arithmetic_instruction(&decoded.addressing_modes, |a, b| {
+                    mul(a, b, mode)
+                })?;
+                (CpuStatus::Run, self.pc.checked_add(&Word(4))?)
+            }
+            Opcode::Read => match input.next_input() {
+                Ok(w) => {
+                    self.metrics.inputs += 1;
+                    self.journal_input(w);
+                    self.tracer.trace_io_read(w)?;
+                    self.put(&decoded.addressing_modes, 1, w)?;
+                    (CpuStatus::Run, self.pc.checked_add(&Word(2))?)
                 }
                 Err(e) => {
                     return Err(CpuFault::IOError(e));
                 }
             },
             Opcode::Write => {
-                let output = self.get(&decoded.addressing_modes, 1)?;
-                self.tracer.trace_io_write(output)?;
-                match do_output(output) {
-                    Ok(()) => (CpuStatus::Run, self.pc.checked_add(&Word(2_i64))?),
+                let w = self.get(&decoded.addressing_modes, 1)?;
+                self.metrics.outputs += 1;
+                self.tracer.trace_io_write(w)?;
+                match output.put_output(w) {
+                    Ok(()) => (CpuStatus::Run, self.pc.checked_add(&Word(2))?),
                     Err(e) => {
                         return Err(CpuFault::IOError(e));
                     }
@@ -585,7 +1197,7 @@ impl Processor {
                 let next_pc = if val.0 != 0 {
                     self.get(&decoded.addressing_modes, 2)?
                 } else {
-                    self.pc.checked_add(&Word(3_i64))?
+                    self.pc.checked_add(&Word(3))?
                 };
                 (CpuStatus::Run, next_pc)
             }
@@ -594,7 +1206,7 @@ impl Processor {
                 let next_pc = if val.0 == 0 {
                     self.get(&decoded.addressing_modes, 2)?
                 } else {
-                    self.pc.checked_add(&Word(3_i64))?
+                    self.pc.checked_add(&Word(3))?
                 };
                 (CpuStatus::Run, next_pc)
             }
@@ -602,7 +1214,7 @@ impl Processor {
                 let less: bool = self.get(&decoded.addressing_modes, 1)?
                     < self.get(&decoded.addressing_modes, 2)?;
                 self.put(&decoded.addressing_modes, 3, Word(if less { 1 } else { 0 }))?;
-                (CpuStatus::Run, self.pc.checked_add(&Word(4_i64))?)
+                (CpuStatus::Run, self.pc.checked_add(&Word(4))?)
             }
             Opcode::CmpEq => {
                 let left: Word = self.get(&decoded.addressing_modes, 1)?;
@@ -614,17 +1226,21 @@ impl Processor {
                     3,
                     Word(if equal { 1 } else { 0 }),
                 )?;
-                (CpuStatus::Run, self.pc.checked_add(&Word(4_i64))?)
+                (CpuStatus::Run, self.pc.checked_add(&Word(4))?)
             }
             Opcode::DeltaRelBase => {
                 let base = self.get(&decoded.addressing_modes, 1)?;
                 self.update_relative_base(base)?;
-                (CpuStatus::Run, self.pc.checked_add(&Word(2_i64))?)
+                (CpuStatus::Run, self.pc.checked_add(&Word(2))?)
             }
             Opcode::Stop => (CpuStatus::Halt, self.pc),
         };
+        self.profile_instruction(self.pc, decoded.op);
+        self.cover_instruction(self.pc, decoded.op);
         self.pc = next_pc;
-        Ok(state)
+        self.count_instruction();
+        self.count_cycles(decoded.op);
+        Ok((state, decoded.op))
     }
 
     fn get(
@@ -632,7 +1248,7 @@ impl Processor {
         modes: &[AddressingMode; NUM_PARAMS],
         index: usize,
     ) -> Result<Word, CpuFault> {
-        assert!(matches!(index, 1 | 2 | 3));
+        assert!(matches!(index, 1..=3));
         let fetch_loc: Word = self.pc.checked_add_usize(&index)?;
         let fetch_loc = match modes[index] {
             AddressingMode::POSITIONAL => self.ram.fetch(fetch_loc)?,
@@ -646,6 +1262,8 @@ impl Processor {
         };
         let result = self.ram.fetch(fetch_loc)?;
         self.tracer.trace_mem_load(fetch_loc, result)?;
+        self.note_access(AccessKind::Load, fetch_loc, result);
+        self.cover_access(AccessKind::Load, fetch_loc);
         Ok(result)
     }
 
@@ -655,7 +1273,7 @@ impl Processor {
         index: usize,
         value: Word,
     ) -> Result<(), CpuFault> {
-        assert!(matches!(index, 1 | 2 | 3));
+        assert!(matches!(index, 1..=3));
         let fetch_loc = self.pc.checked_add_usize(&index)?;
         let store_loc = match modes[index] {
             AddressingMode::POSITIONAL => self.ram.fetch(fetch_loc)?,
@@ -668,7 +1286,10 @@ impl Processor {
             }
         };
         self.tracer.trace_mem_store(store_loc, value)?;
+        self.journal_store(store_loc);
         self.ram.store(store_loc, value)?;
+        self.note_access(AccessKind::Store, store_loc, value);
+        self.cover_access(AccessKind::Store, store_loc);
         Ok(())
     }
 
@@ -682,28 +1303,223 @@ impl Processor {
         self.ram.load(base, content)
     }
 
-    pub fn run_with_io<FI, FO>(
+    /// Make the program fault with `CpuFault::UninitializedRead` if
+    /// it reads an address which was neither part of the loaded
+    /// program nor stored into, instead of reading zero.  This helps
+    /// to find bugs in hand-written Intcode.
+    pub fn set_strict_reads(&mut self, strict: bool) {
+        self.ram.set_strict(strict)
+    }
+
+    /// Make the addresses in `range` read-only, so that a program
+    /// which stores into them faults with `CpuFault::WriteProtected`.
+    /// This is useful for programs which are not supposed to modify
+    /// themselves.  `load` is not affected.
+    pub fn protect(&mut self, range: Range<Word>) {
+        self.ram.protect(range)
+    }
+
+    /// Run the program until it halts (returning `CpuStatus::Halt`)
+    /// or reaches a breakpoint (returning `CpuStatus::BreakpointHit`).
+    pub fn run_with_io<I, O>(
         &mut self,
-        get_input: &mut FI,
-        do_output: &mut FO,
-    ) -> Result<(), CpuFault>
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<CpuStatus, CpuFault>
     where
-        FI: FnMut() -> Result<Word, InputOutputError>,
-        FO: FnMut(Word) -> Result<(), InputOutputError>,
+        I: InputDevice + ?Sized,
+        O: OutputDevice + ?Sized,
     {
-        while self.execute_instruction(get_input, do_output)? == CpuStatus::Run {
-            // No need to do anything in the body.
+        loop {
+            match self.execute_instruction(input, output)? {
+                CpuStatus::Run => (),
+                status => {
+                    return Ok(status);
+                }
+            }
         }
-        Ok(())
     }
 
-    pub fn run_with_fixed_input<FO>(
+    /// Like `run_with_io`, but fail with `CpuFault::StepLimitExceeded`
+    /// if the program executes `max_instructions` instructions
+    /// without halting or being paused.  This protects against
+    /// programs which loop forever (for example when given bad
+    /// input).
+    pub fn run_with_limit<I, O>(
+        &mut self,
+        max_instructions: u64,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<CpuStatus, CpuFault>
+    where
+        I: InputDevice + ?Sized,
+        O: OutputDevice + ?Sized,
+    {
+        for _ in 0..max_instructions {
+            match self.execute_instruction(input, output)? {
+                CpuStatus::Run => (),
+                status => {
+                    return Ok(status);
+                }
+            }
+        }
+        let fault = CpuFault::StepLimitExceeded(max_instructions);
+        self.handle_fault(&fault);
+        Err(self.add_context_between_instructions(fault))
+    }
+
+    /// Run the program until it produces an output, needs input
+    /// which `input` cannot supply (indicated by
+    /// `InputOutputError::NoInput`), reaches a breakpoint, or halts.
+    pub fn run_until_event<I>(&mut self, input: &mut I) -> Result<MachineEvent, CpuFault>
+    where
+        I: InputDevice + ?Sized,
+    {
+        loop {
+            let mut output: Option<Word> = None;
+            let mut do_output = |w: Word| -> Result<(), InputOutputError> {
+                output = Some(w);
+                Ok(())
+            };
+            // As in `step`, a read for which there is no input is not
+            // executed (or traced, journalled and so on) at all.
+            let status = match self.check_pause() {
+                Some(status) => Ok(status),
+                None if self.wants_input() => match input.next_input() {
+                    Err(InputOutputError::NoInput) => Ok(CpuStatus::AwaitingInput),
+                    read => {
+                        let mut read = Some(read);
+                        let mut get_input =
+                            || read.take().unwrap_or(Err(InputOutputError::NoInput));
+                        self.execute_instruction(&mut get_input, &mut do_output)
+                    }
+                },
+                None => self.execute_instruction(input, &mut do_output),
+            };
+            match status {
+                Ok(CpuStatus::Halt) => {
+                    return Ok(MachineEvent::Halted);
+                }
+                Ok(CpuStatus::BreakpointHit(addr)) => {
+                    return Ok(MachineEvent::BreakpointHit(addr));
+                }
+                Ok(CpuStatus::WatchpointHit(access)) => {
+                    return Ok(self.watchpoint_event(access, output));
+                }
+                Ok(CpuStatus::Run) => {
+                    if let Some(w) = output {
+                        return Ok(MachineEvent::ProducedOutput(w));
+                    }
+                }
+                Ok(CpuStatus::AwaitingInput) => {
+                    return Ok(MachineEvent::AwaitingInput);
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Queue `input` for the program to read once it is resumed.
+    pub fn provide_input(&mut self, input: Word) {
+        self.pending_input.push_back(input);
+    }
+
+    /// Whether the next instruction is a read.  If the instruction
+    /// cannot be fetched or decoded, executing it will report why.
+    fn wants_input(&self) -> bool {
+        match self.ram.fetch(self.pc) {
+            Ok(instruction) => {
+                matches!(decode(instruction, self.pc), Ok(d) if d.op == Opcode::Read)
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Execute one instruction, which reads any input it needs from
+    /// the words given to `provide_input`.  If the instruction is a
+    /// read and there is no such input, it is not executed and
+    /// `CpuStatus::AwaitingInput` is returned instead.
+    pub fn step<O>(&mut self, output: &mut O) -> Result<CpuStatus, CpuFault>
+    where
+        O: OutputDevice + ?Sized,
+    {
+        if let Some(status) = self.check_pause() {
+            return Ok(status);
+        }
+        if self.pending_input.is_empty() && self.wants_input() {
+            return Ok(CpuStatus::AwaitingInput);
+        }
+        let mut pending = std::mem::take(&mut self.pending_input);
+        let mut get_input = || pending.pop_front().ok_or(InputOutputError::NoInput);
+        let result = self.execute_instruction(&mut get_input, output);
+        self.pending_input = pending;
+        result
+    }
+
+    /// Run the program until it produces an output, needs more input
+    /// than has been given to `provide_input`, reaches a breakpoint,
+    /// or halts.  This allows callers to drive several programs in
+    /// turn, providing the input of each as it becomes available.
+    pub fn run_until_blocked(&mut self) -> Result<MachineEvent, CpuFault> {
+        loop {
+            let mut output: Option<Word> = None;
+            let mut do_output = |w: Word| -> Result<(), InputOutputError> {
+                output = Some(w);
+                Ok(())
+            };
+            match self.step(&mut do_output)? {
+                CpuStatus::Run => {
+                    if let Some(w) = output {
+                        return Ok(MachineEvent::ProducedOutput(w));
+                    }
+                }
+                CpuStatus::AwaitingInput => {
+                    return Ok(MachineEvent::AwaitingInput);
+                }
+                CpuStatus::Halt => {
+                    return Ok(MachineEvent::Halted);
+                }
+                CpuStatus::BreakpointHit(addr) => {
+                    return Ok(MachineEvent::BreakpointHit(addr));
+                }
+                CpuStatus::WatchpointHit(access) => {
+                    return Ok(self.watchpoint_event(access, output));
+                }
+            }
+        }
+    }
+
+    /// The same as `run_until_blocked`.
+    pub fn resume(&mut self) -> Result<MachineEvent, CpuFault> {
+        self.run_until_blocked()
+    }
+
+    /// The event to report when the program is paused by `access`,
+    /// having written `output`.  The output is reported first, and
+    /// the access when the program is next run.
+    fn watchpoint_event(&mut self, access: MemoryAccess, output: Option<Word>) -> MachineEvent {
+        match output {
+            Some(w) => {
+                self.defer_watchpoint_hit(access);
+                MachineEvent::ProducedOutput(w)
+            }
+            None => MachineEvent::WatchpointHit(access),
+        }
+    }
+
+    /// Run the program to completion, giving it the words of
+    /// `fixed_input` as its input.  It is a fault for the program to
+    /// want more input than that.  Breakpoints and watchpoints are
+    /// ignored.
+    pub fn run_with_fixed_input<O>(
         &mut self,
         fixed_input: &[Word],
-        do_output: &mut FO,
+        output: &mut O,
     ) -> Result<(), CpuFault>
     where
-        FO: FnMut(Word) -> Result<(), InputOutputError>,
+        O: OutputDevice + ?Sized,
     {
         let mut it = fixed_input.iter();
         let mut get_input = || -> Result<Word, InputOutputError> {
@@ -714,11 +1530,17 @@ impl Processor {
             }
         };
         loop {
-            match self.execute_instruction(&mut get_input, do_output) {
+            match self.execute_instruction(&mut get_input, output) {
                 Ok(CpuStatus::Run) => (),
+                // Breakpoints and watchpoints do not stop a program
+                // run to completion.
+                Ok(CpuStatus::BreakpointHit(_) | CpuStatus::WatchpointHit(_)) => (),
                 Ok(CpuStatus::Halt) => {
                     return Ok(());
                 }
+                Ok(CpuStatus::AwaitingInput) => {
+                    return Err(CpuFault::IOError(InputOutputError::NoInput));
+                }
                 Err(e) => {
                     return Err(e);
                 }
@@ -729,6 +1551,8 @@ impl Processor {
 
 impl Drop for Processor {
     fn drop(&mut self) {
+        self.report_cycles();
+        self.report_profile();
         let possible_failure = self.tracer.close();
         drop(possible_failure)
     }
@@ -749,8 +1573,13 @@ fn assert_same(label: &str, expected: &[Word], got: &[Word]) {
 }
 
 #[cfg(test)]
-fn check_program(program: &[i64], input: &[i64], expected_ram: &[i64], expected_output: &[i64]) {
-    fn w(n: &i64) -> Word {
+fn check_program(
+    program: &[WordInt],
+    input: &[WordInt],
+    expected_ram: &[WordInt],
+    expected_output: &[WordInt],
+) {
+    fn w(n: &WordInt) -> Word {
         Word(*n)
     }
     let w_program: Vec<Word> = program.iter().map(w).collect();
@@ -790,7 +1619,7 @@ fn check_program(program: &[i64], input: &[i64], expected_ram: &[i64], expected_
 
 #[test]
 fn test_cpu() {
-    fn check(program: &[i64], expected_memory: &[i64]) {
+    fn check(program: &[WordInt], expected_memory: &[WordInt]) {
         check_program(program, &[], expected_memory, &[]);
     }
 
@@ -812,13 +1641,264 @@ fn test_quine() {
     check_program(quine, &[], quine, quine);
 }
 
+#[test]
+fn test_run_until_event() {
+    // Read a word, output it twice, then halt.
+    let program: Vec<Word> = [3, 9, 4, 9, 4, 9, 99, 0, 0, 0]
+        .iter()
+        .map(|n| Word(*n))
+        .collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program)
+        .expect("0 should be a valid load address");
+    let mut no_input = || -> Result<Word, InputOutputError> { Err(InputOutputError::NoInput) };
+    assert_eq!(
+        cpu.run_until_event(&mut no_input).expect("no fault"),
+        MachineEvent::AwaitingInput
+    );
+    let mut one_input = || -> Result<Word, InputOutputError> { Ok(Word(42)) };
+    assert_eq!(
+        cpu.run_until_event(&mut one_input).expect("no fault"),
+        MachineEvent::ProducedOutput(Word(42))
+    );
+    assert_eq!(
+        cpu.run_until_event(&mut no_input).expect("no fault"),
+        MachineEvent::ProducedOutput(Word(42))
+    );
+    assert_eq!(
+        cpu.run_until_event(&mut no_input).expect("no fault"),
+        MachineEvent::Halted
+    );
+
+    // Waiting for input does not execute the read, so it is only
+    // recorded once the input is there.
+    let program = encode_program(&[Instr::read(20), Instr::write(Param::Pos(-1))]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.enable_journal(10);
+    for _ in 0..2 {
+        assert_eq!(
+            cpu.run_until_event(&mut no_input).expect("no fault"),
+            MachineEvent::AwaitingInput
+        );
+    }
+    assert_eq!(cpu.undoable_steps(), 0);
+    let fault = cpu
+        .run_until_event(&mut one_input)
+        .expect_err("program should fault");
+    assert_eq!(
+        fault.context().map(|context| context.recent.clone()),
+        Some(vec![Word(0)])
+    );
+    assert_eq!(cpu.undoable_steps(), 1);
+}
+
+#[test]
+fn test_run_until_blocked() {
+    // Read two words and output their sum, then halt.
+    let program: Vec<Word> = [3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0]
+        .iter()
+        .map(|n| Word(*n))
+        .collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program)
+        .expect("0 should be a valid load address");
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::AwaitingInput
+    );
+    let mut no_output = |_| -> Result<(), InputOutputError> { panic!("unexpected output") };
+    assert_eq!(
+        cpu.step(&mut no_output).expect("no fault"),
+        CpuStatus::AwaitingInput
+    );
+    cpu.provide_input(Word(3));
+    assert_eq!(cpu.step(&mut no_output).expect("no fault"), CpuStatus::Run);
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::AwaitingInput
+    );
+    cpu.provide_input(Word(4));
+    let mut fork = cpu.clone();
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::ProducedOutput(Word(7))
+    );
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::Halted
+    );
+    // The fork has its own copy of the unread input.
+    assert_eq!(
+        fork.resume().expect("no fault"),
+        MachineEvent::ProducedOutput(Word(7))
+    );
+}
+
+#[test]
+fn test_overflow_mode() {
+    // Add then multiply memory[9] and memory[10], storing the sums in
+    // memory[11] and memory[12].
+    let program: Vec<Word> = [1, 9, 10, 11, 2, 9, 10, 12, 99, WordInt::MAX, 2, 0, 0]
+        .iter()
+        .map(|n| Word(*n))
+        .collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("load should succeed");
+    assert_eq!(cpu.overflow_mode(), OverflowMode::Fault);
+    assert!(matches!(
+        cpu.clone()
+            .run_with_fixed_input(&[], &mut |_| Ok(()))
+            .map_err(CpuFault::into_root),
+        Err(CpuFault::Overflow)
+    ));
+    let mut saturating = cpu.clone();
+    cpu.set_overflow_mode(OverflowMode::Wrap);
+    cpu.run_with_fixed_input(&[], &mut |_| Ok(()))
+        .expect("wrapping arithmetic should not fault");
+    let ram = cpu.ram();
+    assert_eq!(ram[11], Word(WordInt::MIN + 1));
+    assert_eq!(ram[12], Word(-2));
+    saturating.set_overflow_mode(OverflowMode::Saturate);
+    saturating
+        .run_with_fixed_input(&[], &mut |_| Ok(()))
+        .expect("saturating arithmetic should not fault");
+    let ram = saturating.ram();
+    assert_eq!(ram[11], Word(WordInt::MAX));
+    assert_eq!(ram[12], Word(WordInt::MAX));
+}
+
+#[cfg(feature = "wide-words")]
+#[test]
+fn test_wide_words() {
+    // Multiply memory[5] by itself, storing the product in memory[6].
+    let program = parse_program("2,5,5,6,99,9223372036854775807,0").expect("valid program");
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("load should succeed");
+    cpu.run_with_fixed_input(&[], &mut |_| Ok(()))
+        .expect("the product fits in a word");
+    assert_eq!(cpu.ram()[6], Word(85070591730234615847396907784232501249));
+}
+
+#[test]
+fn test_protect() {
+    // Store the input into memory[6] then into memory[2], which is
+    // part of the program.
+    let program: Vec<Word> = [3, 6, 3, 2, 99, 0, 0].iter().map(|n| Word(*n)).collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("load should succeed");
+    cpu.protect(Word(0)..Word(5));
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    match cpu
+        .run_with_fixed_input(&[Word(7), Word(8)], &mut discard)
+        .map_err(CpuFault::into_root)
+    {
+        Err(CpuFault::WriteProtected(Word(2))) => (),
+        other => panic!("expected a write to address 2 to fault, got {:?}", other),
+    }
+    assert_eq!(cpu.ram()[6], Word(7));
+    assert_eq!(cpu.ram()[2], Word(3));
+    // The host can still change protected memory.
+    cpu.load(Word(2), &[Word(4)]).expect("load should succeed");
+    assert_eq!(cpu.ram()[2], Word(4));
+}
+
+#[test]
+fn test_strict_reads() {
+    // Print memory[10], which is never written.
+    let program: Vec<Word> = [4, 10, 99].iter().map(|n| Word(*n)).collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("load should succeed");
+    let mut output = Vec::new();
+    let mut fork = cpu.clone();
+    cpu.run_with_fixed_input(&[], &mut |w| {
+        output.push(w);
+        Ok(())
+    })
+    .expect("a lenient read should not fault");
+    assert_eq!(output, vec![Word(0)]);
+    fork.set_strict_reads(true);
+    match fork
+        .run_with_fixed_input(&[], &mut |_| Ok(()))
+        .map_err(CpuFault::into_root)
+    {
+        Err(CpuFault::UninitializedRead(Word(10))) => (),
+        other => panic!("expected a read of address 10 to fault, got {:?}", other),
+    }
+    fork.load(Word(10), &[Word(3)])
+        .expect("load should succeed");
+    let mut output = Vec::new();
+    fork.run_with_fixed_input(&[], &mut |w| {
+        output.push(w);
+        Ok(())
+    })
+    .expect("address 10 has been loaded");
+    assert_eq!(output, vec![Word(3)]);
+}
+
+#[test]
+fn test_run_with_limit() {
+    // Output 1, then jump to the jump forever.  With its condition
+    // patched to 0, the jump is not taken and the program halts.
+    let program: Vec<Word> = [104, 1, 1105, 1, 2, 99].iter().map(|n| Word(*n)).collect();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("load should succeed");
+    let mut halting = cpu.clone();
+    halting
+        .load(Word(3), &[Word(0)])
+        .expect("load should succeed");
+    let mut no_input = || Err(InputOutputError::NoInput);
+    let mut output: Vec<Word> = Vec::new();
+    let fault = cpu
+        .run_with_limit(5, &mut no_input, &mut output)
+        .expect_err("the step limit should be exceeded");
+    assert!(matches!(fault.root(), CpuFault::StepLimitExceeded(5)));
+    // The program was stopped before the jump at 2, which was not
+    // executed, so it is not blamed for the fault.
+    let context = fault.context().expect("the fault has context");
+    assert_eq!(context.pc, Word(2));
+    assert_eq!(context.instruction, None);
+    assert_eq!(
+        context.recent,
+        vec![Word(0), Word(2), Word(2), Word(2), Word(2)]
+    );
+    assert_eq!(
+        fault.to_string(),
+        concat!(
+            "program did not stop within 5 instructions, after executing ",
+            "the instructions at 0, 2, 2, 2, 2"
+        )
+    );
+    assert_eq!(output, vec![Word(1)]);
+    // Three instructions are just enough.
+    assert_eq!(
+        halting
+            .run_with_limit(3, &mut no_input, &mut output)
+            .expect("the program should halt"),
+        CpuStatus::Halt
+    );
+    assert_eq!(
+        CpuFault::StepLimitExceeded(10).to_string(),
+        "program did not stop within 10 instructions"
+    );
+}
+
 #[derive(Debug)]
 pub enum ProgramLoadError {
     ReadFailed {
         filename: Option<PathBuf>,
         err: std::io::Error,
     },
-    BadWord(String, ParseIntError),
+    BadWord {
+        word: String,
+        location: Location,
+        context: String,
+        err: ParseIntError,
+    },
+    EmptyField {
+        location: Location,
+        context: String,
+    },
 }
 
 impl Display for ProgramLoadError {
@@ -836,8 +1916,24 @@ impl Display for ProgramLoadError {
             } => {
                 write!(f, "failed to read program from '{}': {}", name.display(), e)
             }
-            ProgramLoadError::BadWord(s, e) => {
-                write!(f, "program contained invalid word '{}': {}", s, e)
+            ProgramLoadError::BadWord {
+                word,
+                location,
+                context,
+                err,
+            } => {
+                write!(
+                    f,
+                    "program contained invalid word '{}' at {} (in \"{}\"): {}",
+                    word, location, context, err
+                )
+            }
+            ProgramLoadError::EmptyField { location, context } => {
+                write!(
+                    f,
+                    "program contained an empty field at {} (in \"{}\")",
+                    location, context
+                )
             }
         }
     }
@@ -851,15 +1947,58 @@ impl From<ProgramLoadError> for Fail {
     }
 }
 
-pub fn read_program_from_reader<T>(
+/// Append the words of one line of a program to `words`.  The words
+/// of a program may be spread over several lines, blank lines are
+/// ignored, and a line may end with a comma.  Any other empty field
+/// is an error.
+fn parse_program_line(
+    line: &str,
+    line_number: usize,
+    words: &mut Vec<Word>,
+) -> Result<(), ProgramLoadError> {
+    let content = line.trim_end();
+    let content = content.strip_suffix(',').unwrap_or(content);
+    if content.trim().is_empty() {
+        return Ok(());
+    }
+    let mut offset: usize = 0;
+    for raw_field in content.split(',') {
+        let field = raw_field.trim();
+        let field_offset = offset + (raw_field.len() - raw_field.trim_start().len());
+        offset += raw_field.len() + 1;
+        let location = Location::at_offset(line_number, line, field_offset);
+        if field.is_empty() {
+            return Err(ProgramLoadError::EmptyField {
+                location,
+                context: context_snippet(line, location.column),
+            });
+        }
+        match field.parse::<WordInt>() {
+            Ok(n) => {
+                words.push(Word(n));
+            }
+            Err(e) => {
+                return Err(ProgramLoadError::BadWord {
+                    word: field.to_string(),
+                    location,
+                    context: context_snippet(line, location.column),
+                    err: e,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn read_program_from_reader<R>(
     input_name: Option<PathBuf>,
-    r: BufReader<T>,
+    r: R,
 ) -> Result<Vec<Word>, ProgramLoadError>
 where
-    T: std::io::Read,
+    R: BufRead,
 {
     let mut words: Vec<Word> = Vec::new();
-    for input_element in r.lines() {
+    for (line_number, input_element) in r.lines().enumerate() {
         match input_element {
             Err(e) => {
                 return Err(ProgramLoadError::ReadFailed {
@@ -868,37 +2007,203 @@ where
                 });
             }
             Ok(line) => {
-                for field in line.trim().split(',') {
-                    match field.parse::<i64>() {
-                        Ok(n) => {
-                            words.push(Word(n));
-                        }
-                        Err(e) => {
-                            return Err(ProgramLoadError::BadWord(field.to_string(), e));
-                        }
-                    }
-                }
+                parse_program_line(&line, line_number + 1, &mut words)?;
             }
         }
     }
     Ok(words)
 }
 
+/// Parse the text of a program, in the same format as
+/// `read_program_from_reader` accepts.
+pub fn parse_program(text: &str) -> Result<Vec<Word>, ProgramLoadError> {
+    let mut words: Vec<Word> = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        parse_program_line(line, line_number + 1, &mut words)?;
+    }
+    Ok(words)
+}
+
 pub fn read_program_from_stdin() -> Result<Vec<Word>, ProgramLoadError> {
-    read_program_from_reader(None, io::BufReader::new(io::stdin()))
+    read_program_from_reader(None, std::io::BufReader::new(std::io::stdin()))
 }
 
 pub fn read_program_from_file(input_file_name: &Path) -> Result<Vec<Word>, ProgramLoadError> {
-    match OpenOptions::new()
-        .read(true)
-        .open(input_file_name.as_os_str())
-    {
-        Ok(file) => {
-            read_program_from_reader(Some(input_file_name.to_path_buf()), BufReader::new(file))
-        }
+    match open_decompressed(input_file_name) {
+        Ok(reader) => read_program_from_reader(Some(input_file_name.to_path_buf()), reader),
         Err(e) => Err(ProgramLoadError::ReadFailed {
             filename: Some(input_file_name.to_path_buf()),
             err: e,
         }),
     }
 }
+
+#[test]
+fn test_read_program_messy_input() {
+    let parse = |text: &'static str| {
+        read_program_from_reader(None, std::io::BufReader::new(text.as_bytes()))
+    };
+    let expected: Vec<Word> = [1, 2, -3, 4].iter().map(|n| Word(*n)).collect();
+    assert_eq!(parse("1,2,-3,4\n").expect("valid"), expected);
+    assert_eq!(parse("1,2,-3,4,\r\n").expect("valid"), expected);
+    assert_eq!(parse("1, 2,\r\n\r\n-3,4").expect("valid"), expected);
+    match parse("1,2\n3,,4\n") {
+        Err(ProgramLoadError::EmptyField { location, context }) => {
+            assert_eq!(location, Location { line: 2, column: 3 });
+            assert_eq!(context, "3,,4");
+        }
+        other => panic!("expected an empty field error, got {:?}", other),
+    }
+    match parse(",1") {
+        Err(ProgramLoadError::EmptyField { location, .. }) => {
+            assert_eq!(location, Location { line: 1, column: 1 });
+        }
+        other => panic!("expected an empty field error, got {:?}", other),
+    }
+    match parse("1,2,\n3, 4x\n") {
+        Err(ProgramLoadError::BadWord { word, location, .. }) => {
+            assert_eq!(word, "4x");
+            assert_eq!(location, Location { line: 2, column: 4 });
+        }
+        other => panic!("expected a bad word error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_trace_sampling() {
+    // Read a word, add 1 to it three times, then output it.
+    let program: Vec<Word> = encode_program(&[
+        Instr::read(13),
+        Instr::add(Param::Pos(13), Param::Imm(1), 13),
+        Instr::add(Param::Pos(13), Param::Imm(1), 13),
+        Instr::write(Param::Pos(13)),
+        Instr::stop(),
+    ]);
+    let path = std::env::temp_dir().join(format!("test_trace_sampling.{}", std::process::id()));
+    let file = File::create(&path).expect("trace file should be writable");
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.enable_tracing_with(file, TraceConfig::sample_every(2));
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[Word(5)], &mut discard)
+        .expect("program should run");
+    drop(cpu);
+    let trace = std::fs::read_to_string(&path).expect("trace should exist");
+    std::fs::remove_file(&path).expect("trace should be removable");
+    let (header, trace) = trace.split_once('\n').expect("trace should have a header");
+    let header: TraceHeader = header.parse().expect("header should parse");
+    assert_eq!(header.length, program.len());
+    assert_eq!(header.sample_interval, 2);
+    assert_eq!(
+        trace,
+        concat!(
+            "0 @0: execute 3\n",
+            "1 io-read:5\n",
+            "2 @13: store 5\n",
+            "7 @6: execute 1001\n",
+            "8 @13: load 6\n",
+            "9 @8: load 1\n",
+            "10 @13: store 7\n",
+            "13 io-write:7\n",
+            "14 @12: execute 99\n",
+        )
+    );
+}
+
+#[test]
+fn test_peek_poke() {
+    // Add 1 to the word at 5; then halt.
+    let program: Vec<Word> =
+        encode_program(&[Instr::add(Param::Pos(5), Param::Imm(1), 5), Instr::stop()]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.protect(Word(0)..Word(5));
+    cpu.poke(Word(5), Word(41)).expect("poke should succeed");
+    // Read-only memory can be patched.
+    cpu.poke(Word(2), Word(-1)).expect("poke should succeed");
+    cpu.poke(Word(2), Word(1)).expect("poke should succeed");
+    assert!(matches!(
+        cpu.poke(Word(-1), Word(0)),
+        Err(CpuFault::MemoryFault)
+    ));
+    assert_eq!(cpu.memory().loaded(), Some(Word(0)..Word(5)));
+    assert_eq!(cpu.pc(), Word(0));
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[], &mut discard)
+        .expect("program should run");
+    assert_eq!(cpu.peek(Word(5)).ok(), Some(Word(42)));
+    assert_eq!(cpu.pc(), Word(4));
+    assert_eq!(cpu.relative_base(), 0);
+}
+
+#[test]
+fn test_trace_to_writer_and_sink() {
+    use std::sync::Mutex;
+
+    /// A writer whose output can be inspected after it is handed over.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().expect("not poisoned").extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Write the input doubled; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::read(9),
+        Instr::mul(Param::Pos(9), Param::Imm(2), 9),
+        Instr::write(Param::Pos(9)),
+        Instr::stop(),
+    ]);
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    let buffer = SharedBuffer::default();
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.enable_tracing_to(buffer.clone(), TraceConfig::default());
+    cpu.run_with_fixed_input(&[Word(4)], &mut discard)
+        .expect("program should run");
+    drop(cpu);
+    let written = String::from_utf8(buffer.0.lock().expect("not poisoned").clone())
+        .expect("trace should be UTF-8");
+
+    let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&lines);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.enable_tracing_to_sink(
+        move |line: &str| log.lock().expect("not poisoned").push(line.to_string()),
+        TraceConfig::default(),
+    );
+    cpu.run_with_fixed_input(&[Word(4)], &mut discard)
+        .expect("program should run");
+    drop(cpu);
+    let lines = lines.lock().expect("not poisoned");
+
+    // Both receive the same trace, the sink without newlines.
+    assert_eq!(written.lines().collect::<Vec<_>>(), *lines);
+    let header: TraceHeader = lines[0].parse().expect("header should parse");
+    assert_eq!(header.length, program.len());
+    assert_eq!(
+        lines[1..],
+        [
+            "0 @0: execute 3",
+            "1 io-read:4",
+            "2 @9: store 4",
+            "3 @2: execute 1002",
+            "4 @9: load 4",
+            "5 @4: load 2",
+            "6 @9: store 8",
+            "7 @6: execute 4",
+            "8 @9: load 8",
+            "9 io-write:8",
+            "10 @8: execute 99",
+        ]
+    );
+}
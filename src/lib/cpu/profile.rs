@@ -0,0 +1,187 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use super::instr::OPCODES;
+use super::{Opcode, Processor, Word};
+
+/// The number of addresses shown by a profile's `Display`
+/// implementation.
+const HOTTEST_SHOWN: usize = 10;
+
+/// Where a program has spent its time: how many instructions with
+/// each opcode it has executed, and how many times it has executed
+/// the instruction at each address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    by_opcode: BTreeMap<i64, u64>,
+    by_address: HashMap<Word, u64>,
+    instructions: u64,
+}
+
+impl Profile {
+    /// The number of instructions executed.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// The number of instructions executed, by mnemonic, in opcode
+    /// order.  Opcodes which were never executed are left out.
+    pub fn opcode_counts(&self) -> Vec<(&'static str, u64)> {
+        self.by_opcode
+            .iter()
+            .filter_map(|(opcode, count)| {
+                let info = OPCODES.iter().find(|info| info.op as i64 == *opcode)?;
+                Some((info.mnemonic, *count))
+            })
+            .collect()
+    }
+
+    /// The number of times the instruction at `pc` was executed.
+    pub fn count_at(&self, pc: Word) -> u64 {
+        self.by_address.get(&pc).copied().unwrap_or(0)
+    }
+
+    /// The `n` addresses whose instructions were executed most
+    /// often, busiest first, with their counts.
+    pub fn hottest(&self, n: usize) -> Vec<(Word, u64)> {
+        let mut counts: Vec<(Word, u64)> = self
+            .by_address
+            .iter()
+            .map(|(pc, count)| (*pc, *count))
+            .collect();
+        counts.sort_by_key(|(pc, count)| (Reverse(*count), *pc));
+        counts.truncate(n);
+        counts
+    }
+
+    fn record(&mut self, pc: Word, op: Opcode) {
+        self.instructions += 1;
+        *self.by_opcode.entry(op as i64).or_insert(0) += 1;
+        *self.by_address.entry(pc).or_insert(0) += 1;
+    }
+}
+
+fn percentage(count: u64, total: u64) -> f64 {
+    100.0 * count as f64 / total.max(1) as f64
+}
+
+/// Shows the counts for each opcode and the busiest addresses, one
+/// per line.
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} instructions", self.instructions)?;
+        for (mnemonic, count) in self.opcode_counts() {
+            writeln!(
+                f,
+                "{:>6} {:>12} {:>5.1}%",
+                mnemonic,
+                count,
+                percentage(count, self.instructions)
+            )?;
+        }
+        writeln!(f, "busiest addresses:")?;
+        for (pc, count) in self.hottest(HOTTEST_SHOWN) {
+            writeln!(
+                f,
+                "{:>6} {:>12} {:>5.1}%",
+                pc,
+                count,
+                percentage(count, self.instructions)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The profile of a `Processor`, and whether to report it.
+#[derive(Debug, Clone)]
+pub(super) struct Profiler {
+    profile: Profile,
+    report: bool,
+}
+
+impl Processor {
+    /// Start profiling the program, from scratch.  The profile can be
+    /// retrieved with `profile`.
+    pub fn enable_profiling(&mut self) {
+        self.profiler = Some(Profiler {
+            profile: Profile::default(),
+            report: false,
+        });
+    }
+
+    /// Show the profile on the standard error when the processor is
+    /// dropped.  This has no effect unless profiling is enabled.
+    pub fn report_profile_when_done(&mut self) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.report = true;
+        }
+    }
+
+    /// The profile of the program so far, if profiling is enabled.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiler.as_ref().map(|profiler| &profiler.profile)
+    }
+
+    /// Account for the execution of the instruction at `pc`, with
+    /// opcode `op`.
+    pub(super) fn profile_instruction(&mut self, pc: Word, op: Opcode) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.profile.record(pc, op);
+        }
+    }
+
+    /// Called when the processor is dropped.
+    pub(super) fn report_profile(&self) {
+        if let Some(profiler) = self.profiler.as_ref() {
+            if profiler.report {
+                eprint!("profile: {}", profiler.profile);
+            }
+        }
+    }
+
+    /// The profiler for a clone of this processor.  The clone carries
+    /// on from the same profile, but does not report it.
+    pub(super) fn clone_profiler(&self) -> Option<Profiler> {
+        self.profiler.as_ref().map(|profiler| Profiler {
+            report: false,
+            ..profiler.clone()
+        })
+    }
+}
+
+#[test]
+fn test_profile() {
+    use super::{encode_program, InputOutputError, Instr, Param};
+
+    // Count down from 3 to 0; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::add(Param::Imm(3), Param::Imm(0), 20),
+        Instr::add(Param::Pos(20), Param::Imm(-1), 20),
+        Instr::jump_if_true(Param::Pos(20), Param::Imm(4)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    assert!(cpu.profile().is_none());
+    cpu.enable_profiling();
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[], &mut discard)
+        .expect("program should run");
+    let profile = cpu.profile().expect("profiling is enabled");
+    assert_eq!(profile.instructions(), 8);
+    assert_eq!(
+        profile.opcode_counts(),
+        vec![("add", 4), ("jt", 3), ("halt", 1)]
+    );
+    assert_eq!(profile.count_at(Word(4)), 3);
+    assert_eq!(profile.count_at(Word(5)), 0);
+    assert_eq!(
+        profile.hottest(3),
+        vec![(Word(4), 3), (Word(8), 3), (Word(0), 1)]
+    );
+    let text = profile.to_string();
+    assert!(text.starts_with("8 instructions\n   add            4  50.0%\n"));
+    assert!(text.contains("busiest addresses:\n     4            3  37.5%\n"));
+}
@@ -0,0 +1,242 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::BufRead;
+use std::sync::{Arc, Mutex};
+
+use super::{BadTraceHeader, InputOutputError, Processor, TraceConfig, TraceHeader, Word};
+use crate::error::Fail;
+
+/// A trace could not be replayed, or the replay did not do what the
+/// trace says the program did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    BadHeader(BadTraceHeader),
+    /// The trace could not be read or understood at `line`
+    /// (counting from 1, the header being line 1).
+    BadTrace {
+        line: usize,
+        problem: String,
+    },
+    /// The trace is of a different program, or the program was
+    /// started elsewhere.
+    DifferentProgram,
+    /// The replay did something other than what is recorded at
+    /// `line`.  When `expected` is `None`, the trace ended there; when
+    /// `actual` is `None`, the replay stopped (for the reason given
+    /// by `stopped`).
+    Diverged {
+        line: usize,
+        expected: Option<String>,
+        actual: Option<String>,
+        stopped: Option<String>,
+    },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::BadHeader(e) => write!(f, "{}", e),
+            ReplayError::BadTrace { line, problem } => {
+                write!(f, "bad trace at line {}: {}", line, problem)
+            }
+            ReplayError::DifferentProgram => {
+                f.write_str("the trace is not of this program, started at this address")
+            }
+            ReplayError::Diverged {
+                line,
+                expected,
+                actual,
+                stopped,
+            } => {
+                write!(f, "replay diverged at trace line {}: ", line)?;
+                match (expected, actual) {
+                    (Some(expected), Some(actual)) => {
+                        write!(f, "expected '{}' but got '{}'", expected, actual)
+                    }
+                    (Some(expected), None) => write!(
+                        f,
+                        "expected '{}' but the program stopped ({})",
+                        expected,
+                        stopped.as_deref().unwrap_or("halted")
+                    ),
+                    (None, Some(actual)) => write!(f, "the trace ends but got '{}'", actual),
+                    (None, None) => f.write_str("nothing happened"),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<ReplayError> for Fail {
+    fn from(e: ReplayError) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+impl From<BadTraceHeader> for ReplayError {
+    fn from(e: BadTraceHeader) -> ReplayError {
+        ReplayError::BadHeader(e)
+    }
+}
+
+/// The input which the traced program read, in order.
+fn recorded_input(events: &[String]) -> Result<Vec<Word>, ReplayError> {
+    let mut input: Vec<Word> = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        let bad = |problem: String| ReplayError::BadTrace {
+            line: i + 2,
+            problem,
+        };
+        let (seq, what) = event
+            .split_once(' ')
+            .ok_or_else(|| bad("expected a sequence number and an event".to_string()))?;
+        seq.parse::<u64>()
+            .map_err(|e| bad(format!("bad sequence number '{}': {}", seq, e)))?;
+        if let Some(value) = what.strip_prefix("io-read:") {
+            let value = value
+                .parse()
+                .map_err(|e| bad(format!("bad input '{}': {}", value, e)))?;
+            input.push(Word(value));
+        }
+    }
+    Ok(input)
+}
+
+/// Re-execute the program loaded into `cpu`, checking that every
+/// event (instruction, load, store, input and output) is the same as
+/// in `trace`, which was written by an earlier run of the same
+/// program.  The program is given the input recorded in the trace.
+/// This catches changes in the behaviour of the interpreter.
+///
+/// If the traced run used annotations, `cpu` must be annotated in the
+/// same way, since they change how addresses appear.  A trace which
+/// ends because the program faulted or was waiting for more input
+/// is checked as far as it goes.  Returns the number of events
+/// checked.
+pub fn replay_trace<R: BufRead>(mut cpu: Processor, trace: R) -> Result<usize, ReplayError> {
+    let mut lines = trace.lines();
+    let read_error = |line: usize, e: std::io::Error| ReplayError::BadTrace {
+        line,
+        problem: e.to_string(),
+    };
+    let header: TraceHeader = match lines.next() {
+        Some(line) => line.map_err(|e| read_error(1, e))?.parse()?,
+        None => return Err(BadTraceHeader("the trace is empty".to_string()).into()),
+    };
+    let events: Vec<String> = lines
+        .enumerate()
+        .map(|(i, line)| line.map_err(|e| read_error(i + 2, e)))
+        .collect::<Result<_, _>>()?;
+    let input = recorded_input(&events)?;
+    let config = TraceConfig::sample_every(header.sample_interval.max(1));
+    if !TraceHeader::new(&cpu.ram, cpu.pc, &config).same_program(&header) {
+        return Err(ReplayError::DifferentProgram);
+    }
+
+    let replayed: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&replayed);
+    cpu.enable_tracing_to_sink(
+        move |line: &str| log.lock().expect("not poisoned").push(line.to_string()),
+        config,
+    );
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    let stopped: Option<String> = cpu
+        .run_with_fixed_input(&input, &mut discard)
+        .err()
+        .map(|fault| fault.to_string());
+    drop(cpu);
+    let replayed = replayed.lock().expect("not poisoned");
+    // The first line the replay produced is its own header.
+    let mut actual = replayed.iter().skip(1);
+    let mut expected = events.iter();
+    let mut checked: usize = 0;
+    loop {
+        match (expected.next(), actual.next()) {
+            (None, None) => return Ok(checked),
+            (Some(e), Some(a)) if e == a => checked += 1,
+            (e, a) => {
+                return Err(ReplayError::Diverged {
+                    line: checked + 2,
+                    expected: e.cloned(),
+                    actual: a.cloned(),
+                    stopped,
+                })
+            }
+        }
+    }
+}
+
+#[test]
+fn test_replay_trace() {
+    use super::{encode_program, Instr, Param};
+
+    // Read two words and write their sum; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::read(13),
+        Instr::read(14),
+        Instr::add(Param::Pos(13), Param::Pos(14), 13),
+        Instr::write(Param::Pos(13)),
+        Instr::stop(),
+    ]);
+    let loaded = || {
+        let mut cpu = Processor::new(Word(0));
+        cpu.load(Word(0), &program).expect("program should load");
+        cpu
+    };
+    let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&lines);
+    let mut cpu = loaded();
+    cpu.enable_tracing_to_sink(
+        move |line: &str| log.lock().expect("not poisoned").push(line.to_string()),
+        TraceConfig::default(),
+    );
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[Word(3), Word(4)], &mut discard)
+        .expect("program should run");
+    drop(cpu);
+    let trace: String = lines
+        .lock()
+        .expect("not poisoned")
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_eq!(replay_trace(loaded(), trace.as_bytes()), Ok(14));
+
+    // A trace which records something the program does not do.
+    let altered = trace.replace("io-write:7", "io-write:8");
+    assert_eq!(
+        replay_trace(loaded(), altered.as_bytes()),
+        Err(ReplayError::Diverged {
+            line: 14,
+            expected: Some("12 io-write:8".to_string()),
+            actual: Some("12 io-write:7".to_string()),
+            stopped: None,
+        })
+    );
+    // A trace of a program which was waiting for more input is
+    // checked as far as it goes.
+    let (start, _) = trace.split_at(trace.find("4 io-read").expect("trace reads twice"));
+    assert_eq!(replay_trace(loaded(), start.as_bytes()), Ok(4));
+    // Some of the input is missing.
+    let missing = trace.replace("io-read:4", "io-write:4");
+    assert!(matches!(
+        replay_trace(loaded(), missing.as_bytes()),
+        Err(ReplayError::Diverged {
+            line: 6,
+            stopped: Some(_),
+            ..
+        })
+    ));
+
+    let mut other = loaded();
+    other.load(Word(1), &[Word(14)]).expect("patch should load");
+    assert_eq!(
+        replay_trace(other, trace.as_bytes()),
+        Err(ReplayError::DifferentProgram)
+    );
+    assert!(matches!(
+        replay_trace(loaded(), "0 @0: execute 3\n".as_bytes()),
+        Err(ReplayError::BadHeader(_))
+    ));
+}
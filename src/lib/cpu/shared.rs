@@ -0,0 +1,205 @@
+//! Experimental support for several processors, running on their
+//! own threads, which work on the same data.  The processors share
+//! a region of memory and use mailboxes to tell each other when the
+//! data is ready.
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Range;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use super::{InputDevice, InputOutputError, OutputDevice, Processor, Word};
+
+/// Memory which several processors can read and write.  Clones of a
+/// `SharedMemory` refer to the same memory.  Each load and store is
+/// atomic, but a sequence of them is not, so programs sharing memory
+/// need some other way to take turns, such as a `Mailbox`.
+#[derive(Debug, Clone, Default)]
+pub struct SharedMemory(Arc<Mutex<BTreeMap<Word, Word>>>);
+
+impl SharedMemory {
+    pub fn new() -> SharedMemory {
+        SharedMemory::default()
+    }
+
+    fn content(&self) -> MutexGuard<'_, BTreeMap<Word, Word>> {
+        // A panic while the lock was held cannot leave the map
+        // inconsistent, so a poisoned lock is still usable.
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn fetch(&self, addr: Word) -> Word {
+        self.get(addr).unwrap_or(Word(0))
+    }
+
+    /// The word at `addr`, if anything has been stored there.
+    pub fn get(&self, addr: Word) -> Option<Word> {
+        self.content().get(&addr).copied()
+    }
+
+    pub fn store(&self, addr: Word, value: Word) {
+        self.content().insert(addr, value);
+    }
+
+    /// The addresses in `range` which have been stored into, with
+    /// their content, in address order.
+    pub(super) fn dump(&self, range: Range<Word>) -> Vec<(Word, Word)> {
+        self.content()
+            .range(range)
+            .map(|(addr, value)| (*addr, *value))
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MailboxState {
+    queue: VecDeque<Word>,
+    closed: bool,
+}
+
+#[derive(Debug, Default)]
+struct MailboxInner {
+    state: Mutex<MailboxState>,
+    /// Notified when a word is sent or the mailbox is closed.
+    changed: Condvar,
+}
+
+/// A queue of words which processors on different threads can use
+/// to send each other messages.  Reading from an empty mailbox waits
+/// until another thread sends a word, or closes the mailbox.  Clones
+/// of a `Mailbox` refer to the same queue.
+#[derive(Debug, Clone, Default)]
+pub struct Mailbox(Arc<MailboxInner>);
+
+impl Mailbox {
+    pub fn new() -> Mailbox {
+        Mailbox::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MailboxState> {
+        self.0
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn send(&self, w: Word) {
+        self.state().queue.push_back(w);
+        self.0.changed.notify_one();
+    }
+
+    /// Stop waiting for more words.  Words already sent can still be
+    /// received, but after that receiving fails instead of waiting.
+    pub fn close(&self) {
+        self.state().closed = true;
+        self.0.changed.notify_all();
+    }
+
+    /// The next word, waiting for one to be sent if necessary.
+    /// Returns `None` once the mailbox is closed and empty.
+    pub fn receive(&self) -> Option<Word> {
+        let mut state = self.state();
+        loop {
+            if let Some(w) = state.queue.pop_front() {
+                return Some(w);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .0
+                .changed
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// The next word, if one has already been sent.
+    pub fn try_receive(&self) -> Option<Word> {
+        self.state().queue.pop_front()
+    }
+}
+
+/// A program reading from a mailbox waits for input, rather than
+/// stopping with `MachineEvent::AwaitingInput`.
+impl InputDevice for Mailbox {
+    fn next_input(&mut self) -> Result<Word, InputOutputError> {
+        self.receive().ok_or(InputOutputError::NoInput)
+    }
+}
+
+impl OutputDevice for Mailbox {
+    fn put_output(&mut self, w: Word) -> Result<(), InputOutputError> {
+        self.send(w);
+        Ok(())
+    }
+}
+
+impl Processor {
+    /// Make the addresses in `range` refer to `shared` instead of
+    /// this processor's own memory.  Other processors given a clone
+    /// of `shared` see what this one stores there, and vice versa.
+    /// The shared addresses are not included in `ram` or in memory
+    /// dumps.
+    pub fn share(&mut self, range: Range<Word>, shared: &SharedMemory) {
+        self.ram.share(range, shared.clone());
+    }
+}
+
+#[test]
+fn test_mailbox() {
+    let mailbox = Mailbox::new();
+    assert_eq!(mailbox.try_receive(), None);
+    let sender = mailbox.clone();
+    let thread = std::thread::spawn(move || {
+        sender.send(Word(1));
+        sender.send(Word(2));
+        sender.close();
+    });
+    assert_eq!(mailbox.receive(), Some(Word(1)));
+    assert_eq!(mailbox.receive(), Some(Word(2)));
+    assert_eq!(mailbox.receive(), None);
+    thread.join().expect("sender should not panic");
+}
+
+#[test]
+fn test_shared_memory() {
+    use super::{encode_program, new_processor, Instr, Param};
+
+    // The producer stores 42 at address 1000, then says so.
+    let producer_program = encode_program(&[
+        Instr::add(Param::Imm(20), Param::Imm(22), 1000),
+        Instr::write(Param::Imm(1)),
+        Instr::stop(),
+    ]);
+    // The consumer waits to be told, then prints address 1000.
+    let consumer_program = encode_program(&[
+        Instr::read(100),
+        Instr::write(Param::Pos(1000)),
+        Instr::stop(),
+    ]);
+    let shared = SharedMemory::new();
+    let ready = Mailbox::new();
+    let mut producer = new_processor(&producer_program).expect("program should load");
+    let mut consumer = new_processor(&consumer_program).expect("program should load");
+    producer.share(Word(1000)..Word(1010), &shared);
+    consumer.share(Word(1000)..Word(1010), &shared);
+    let mut consumer_input = ready.clone();
+    let consumer_thread = std::thread::spawn(move || {
+        let mut output: Vec<Word> = Vec::new();
+        consumer
+            .run_with_io(&mut consumer_input, &mut output)
+            .map(|_| output)
+    });
+    producer
+        .run_with_io(&mut || Err(InputOutputError::NoInput), &mut ready.clone())
+        .expect("producer should run");
+    let output = consumer_thread
+        .join()
+        .expect("consumer should not panic")
+        .expect("consumer should run");
+    assert_eq!(output, vec![Word(42)]);
+    assert_eq!(shared.fetch(Word(1000)), Word(42));
+    // The producer's own memory was not changed.
+    assert_eq!(producer.ram().len(), producer_program.len());
+}
@@ -0,0 +1,352 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::{CpuFault, Memory, Processor, Word, WordInt};
+use crate::error::Fail;
+
+/// Everything needed to resume a program at the point a `Processor`
+/// had reached: its memory, program counter and relative base.
+///
+/// A `MachineState` can be saved as text (using `Display`) and read
+/// back (using `FromStr`).  When reading, lines starting with '#' are
+/// ignored.  In the text, each word of memory is written as
+/// `address:value`, but the address is left out when it follows on
+/// from the previous one (or is 0, for the first word).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineState {
+    pub pc: Word,
+    pub relative_base: WordInt,
+    /// The locations which have been written, in address order, as
+    /// (address, value).
+    pub ram: Vec<(Word, Word)>,
+}
+
+impl MachineState {
+    /// The word at `addr`; locations never written hold 0.
+    pub fn fetch(&self, addr: Word) -> Word {
+        match self.ram.binary_search_by_key(&addr, |(a, _)| *a) {
+            Ok(i) => self.ram[i].1,
+            Err(_) => Word(0),
+        }
+    }
+}
+
+impl Processor {
+    /// Capture the state of the program, so that it can be resumed
+    /// later with `restore`.
+    pub fn snapshot(&self) -> MachineState {
+        MachineState {
+            pc: self.pc,
+            relative_base: self.relative_base,
+            ram: self.ram.cells(),
+        }
+    }
+
+    /// Replace the state of the program with `state`.  Any input
+    /// given to `provide_input` but not yet read is discarded, and
+    /// loop detection starts afresh.  The way memory is set up (its
+    /// backend, protected ranges, strictness and any shared range)
+    /// and tracing are not affected, but locations in a shared range
+    /// are given the values in `state`.
+    pub fn restore(&mut self, state: &MachineState) -> Result<(), CpuFault> {
+        self.ram.clear();
+        for (addr, value) in state.ram.iter() {
+            self.ram.restore(*addr, *value)?;
+        }
+        self.pc = state.pc;
+        self.relative_base = state.relative_base;
+        self.pending_input.clear();
+        self.leave_breakpoint();
+        if let Some(journal) = self.journal.as_mut() {
+            journal.clear();
+        }
+        if let Some(detector) = self.loop_detector.as_mut() {
+            detector.reset();
+        }
+        Ok(())
+    }
+}
+
+impl Memory {
+    /// Forget everything stored in this memory (but not in any
+    /// `SharedMemory`), keeping its backend and settings.
+    fn clear(&mut self) {
+        self.content = Arc::new(self.content.empty());
+        self.top = 0;
+        self.loaded = None;
+    }
+
+    /// The locations which have been written, including those in
+    /// any shared range, in address order, as (address, value).
+    fn cells(&self) -> Vec<(Word, Word)> {
+        let mut cells: Vec<(Word, Word)> = self
+            .content
+            .dump()
+            .filter(|(addr, _)| self.shared_at(*addr).is_none())
+            .collect();
+        if let Some((range, shared)) = self.shared.as_ref() {
+            cells.extend(shared.dump(range.clone()));
+            cells.sort_unstable_by_key(|(addr, _)| *addr);
+        }
+        cells
+    }
+
+    /// Put back a location recorded by `cells`.
+    fn restore(&mut self, addr: Word, value: Word) -> Result<(), CpuFault> {
+        let addr = Memory::pos(addr)?;
+        if self.shared_at(addr).is_some() {
+            self.write(addr, value);
+            Ok(())
+        } else {
+            self.load(addr, &[value])
+        }
+    }
+}
+
+impl Display for MachineState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pc {}", self.pc)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        write!(f, "ram ")?;
+        let mut next = Word(0);
+        for (i, (addr, w)) in self.ram.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if *addr == next {
+                write!(f, "{}", w)?;
+            } else {
+                write!(f, "{}:{}", addr, w)?;
+            }
+            next = Word(addr.0.saturating_add(1));
+        }
+        writeln!(f)
+    }
+}
+
+/// Saved machine state could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadMachineState(pub String);
+
+impl Display for BadMachineState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad saved machine state: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadMachineState {}
+
+impl From<BadMachineState> for Fail {
+    fn from(e: BadMachineState) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+fn parse_number(field: &str, value: &str) -> Result<WordInt, BadMachineState> {
+    value
+        .trim()
+        .parse()
+        .map_err(|e| BadMachineState(format!("bad value for {}: '{}': {}", field, value, e)))
+}
+
+/// Parse the memory of a saved `MachineState`, in the form written
+/// by `Display`.
+fn parse_cells(value: &str) -> Result<Vec<(Word, Word)>, BadMachineState> {
+    let mut cells: Vec<(Word, Word)> = Vec::new();
+    for item in value.split(',').filter(|item| !item.trim().is_empty()) {
+        let (addr, w) = match item.split_once(':') {
+            Some((addr, w)) => (Word(parse_number("ram", addr)?), w),
+            None => match cells.last() {
+                Some((prev, _)) => match prev.0.checked_add(1) {
+                    Some(addr) => (Word(addr), item),
+                    None => {
+                        return Err(BadMachineState("ram is too large".to_string()));
+                    }
+                },
+                None => (Word(0), item),
+            },
+        };
+        if addr.0 < 0 {
+            return Err(BadMachineState(format!("negative address {} in ram", addr)));
+        }
+        if matches!(cells.last(), Some((prev, _)) if addr <= *prev) {
+            return Err(BadMachineState(format!(
+                "address {} in ram is out of order",
+                addr
+            )));
+        }
+        cells.push((addr, Word(parse_number("ram", w)?)));
+    }
+    Ok(cells)
+}
+
+impl FromStr for MachineState {
+    type Err = BadMachineState;
+
+    fn from_str(s: &str) -> Result<MachineState, BadMachineState> {
+        let mut pc: Option<Word> = None;
+        let mut relative_base: Option<WordInt> = None;
+        let mut ram: Option<Vec<(Word, Word)>> = None;
+        for line in s
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        {
+            let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+            match field {
+                "pc" => {
+                    pc = Some(Word(parse_number(field, value)?));
+                }
+                "relative_base" => {
+                    relative_base = Some(parse_number(field, value)?);
+                }
+                "ram" => {
+                    ram = Some(parse_cells(value)?);
+                }
+                _ => {
+                    return Err(BadMachineState(format!("unknown field '{}'", field)));
+                }
+            }
+        }
+        match (pc, relative_base, ram) {
+            (Some(pc), Some(relative_base), Some(ram)) => Ok(MachineState {
+                pc,
+                relative_base,
+                ram,
+            }),
+            (None, _, _) => Err(BadMachineState("pc is missing".to_string())),
+            (_, None, _) => Err(BadMachineState("relative_base is missing".to_string())),
+            (_, _, None) => Err(BadMachineState("ram is missing".to_string())),
+        }
+    }
+}
+
+#[test]
+fn test_snapshot_restore() {
+    use super::{encode_program, Instr, Param};
+
+    // Read a word, add 10 to it and print it; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::read(9),
+        Instr::add(Param::Pos(9), Param::Imm(10), 9),
+        Instr::write(Param::Pos(9)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let mut no_input = || Err(super::InputOutputError::NoInput);
+    assert_eq!(
+        cpu.run_until_event(&mut no_input).expect("no fault"),
+        super::MachineEvent::AwaitingInput
+    );
+    let saved: MachineState = cpu.snapshot();
+    let text = saved.to_string();
+    let loaded: MachineState = text.parse().expect("saved state should parse");
+    assert_eq!(saved, loaded);
+
+    let mut resumed = Processor::new(Word(0));
+    resumed.restore(&loaded).expect("state should load");
+    let mut input = || Ok(Word(5));
+    assert_eq!(
+        resumed.run_until_event(&mut input).expect("no fault"),
+        super::MachineEvent::ProducedOutput(Word(15))
+    );
+
+    // Restoring keeps the way memory is set up, but discards input
+    // which was not read.
+    let mut strict = Processor::with_backend(Word(0), Box::new(super::SparseMemory::new()));
+    strict.set_strict_reads(true);
+    strict.provide_input(Word(7));
+    strict.restore(&loaded).expect("state should load");
+    assert_eq!(strict.ram().len(), program.len());
+    assert_eq!(
+        strict.run_until_event(&mut input).expect("no fault"),
+        super::MachineEvent::ProducedOutput(Word(15))
+    );
+    strict.restore(&loaded).expect("state should load");
+    strict.set_pc(Word(2));
+    assert!(matches!(
+        strict
+            .run_until_event(&mut input)
+            .map_err(CpuFault::into_root),
+        Err(CpuFault::UninitializedRead(Word(9)))
+    ));
+    assert_eq!(
+        "pc 0\nram 1,2\n".parse::<MachineState>(),
+        Err(BadMachineState("relative_base is missing".to_string()))
+    );
+}
+
+#[test]
+fn test_snapshot_is_sparse() {
+    let far = Word(1 << 40);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &[Word(99), Word(3)])
+        .expect("program should load");
+    cpu.poke(far, Word(8)).expect("poke should work");
+    let saved = cpu.snapshot();
+    assert_eq!(
+        saved.ram,
+        vec![(Word(0), Word(99)), (Word(1), Word(3)), (far, Word(8))]
+    );
+    let text = saved.to_string();
+    assert_eq!(text, "pc 0\nrelative_base 0\nram 99,3,1099511627776:8\n");
+    assert_eq!(text.parse::<MachineState>(), Ok(saved.clone()));
+
+    let mut resumed = Processor::new(Word(0));
+    resumed.restore(&saved).expect("state should load");
+    assert_eq!(resumed.memory().fetch(far).ok(), Some(Word(8)));
+    assert_eq!(resumed.memory().size(), 3);
+    assert_eq!(
+        "pc 0\nrelative_base 0\nram 5:1,2:1\n".parse::<MachineState>(),
+        Err(BadMachineState(
+            "address 2 in ram is out of order".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_restore_strict_with_loop_detection() {
+    use super::{encode_program, InputOutputError, Instr, Param};
+
+    // Count location 20 up to 5, print it and halt.  The last
+    // instruction, at 14, is not reached; it reads location 18,
+    // which is never written.
+    let program = encode_program(&[
+        Instr::add(Param::Pos(20), Param::Imm(1), 20),
+        Instr::less_than(Param::Pos(20), Param::Imm(5), 21),
+        Instr::jump_if_true(Param::Pos(21), Param::Imm(0)),
+        Instr::write(Param::Pos(20)),
+        Instr::stop(),
+        Instr::write(Param::Pos(18)),
+    ]);
+    let mut cpu = Processor::with_backend(Word(0), Box::new(super::SparseMemory::new()));
+    cpu.set_strict_reads(true);
+    cpu.detect_loops(1);
+    cpu.load(Word(0), &program).expect("program should load");
+    cpu.load(Word(20), &[Word(0)]).expect("data should load");
+    let saved = cpu.snapshot();
+
+    // Record some states, then go back to the start.  Passing
+    // through the same states again is not a loop.
+    let mut no_input = || Err(InputOutputError::NoInput);
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    for _ in 0..3 {
+        cpu.execute_instruction(&mut no_input, &mut discard)
+            .expect("no fault");
+    }
+    cpu.restore(&saved).expect("state should load");
+    let mut output: Vec<Word> = Vec::new();
+    cpu.run_with_fixed_input(&[], &mut output)
+        .expect("program should run");
+    assert_eq!(output, vec![Word(5)]);
+
+    // The gap between the program and its data is still unwritten.
+    cpu.restore(&saved).expect("state should load");
+    cpu.set_pc(Word(14));
+    assert!(matches!(
+        cpu.run_until_event(&mut no_input)
+            .map_err(CpuFault::into_root),
+        Err(CpuFault::UninitializedRead(Word(18)))
+    ));
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use super::{Opcode, Processor, Word, WordInt};
+
+/// The number of cycles taken by each kind of instruction, for
+/// comparing the speed of different Intcode programs.  Opcodes not
+/// given a cost explicitly take one cycle.
+///
+/// A cost model is written as a comma-separated list of
+/// `opcode=cycles` pairs, for example `1=1,2=3,99=0`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CostModel {
+    costs: BTreeMap<i64, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadCostModel(String);
+
+impl Display for BadCostModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad cost model: {}", self.0)
+    }
+}
+
+impl Error for BadCostModel {}
+
+impl CostModel {
+    /// Set the cost of instructions with opcode `opcode`.
+    pub fn set(&mut self, opcode: i64, cycles: u64) -> Result<(), BadCostModel> {
+        match Opcode::try_from(&Word(opcode as WordInt)) {
+            Ok(op) if op as i64 == opcode => {
+                self.costs.insert(opcode, cycles);
+                Ok(())
+            }
+            _ => Err(BadCostModel(format!("{} is not an opcode", opcode))),
+        }
+    }
+
+    pub fn cost(&self, opcode: i64) -> u64 {
+        self.costs.get(&opcode).copied().unwrap_or(1)
+    }
+}
+
+impl FromStr for CostModel {
+    type Err = BadCostModel;
+
+    fn from_str(s: &str) -> Result<CostModel, BadCostModel> {
+        let mut model = CostModel::default();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let parsed = item.split_once('=').and_then(|(op, cycles)| {
+                Some((op.trim().parse().ok()?, cycles.trim().parse().ok()?))
+            });
+            match parsed {
+                Some((opcode, cycles)) => model.set(opcode, cycles)?,
+                None => {
+                    return Err(BadCostModel(format!(
+                        "expected opcode=cycles but got '{}'",
+                        item
+                    )));
+                }
+            }
+        }
+        Ok(model)
+    }
+}
+
+impl Display for CostModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (opcode, cycles)) in self.costs.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", opcode, cycles)?;
+        }
+        Ok(())
+    }
+}
+
+/// The cycles a `Processor` has used so far, according to its cost
+/// model.
+#[derive(Debug, Clone)]
+pub(super) struct CycleCounter {
+    model: CostModel,
+    cycles: u64,
+    report: bool,
+}
+
+impl Processor {
+    /// Count the cycles used by the program according to `model`,
+    /// starting from zero.
+    pub fn set_cost_model(&mut self, model: CostModel) {
+        self.cycle_counter = Some(CycleCounter {
+            model,
+            cycles: 0,
+            report: false,
+        });
+    }
+
+    /// Report the number of cycles used on the standard error when
+    /// the processor is dropped.  This has no effect unless a cost
+    /// model has been set.
+    pub fn report_cycles_when_done(&mut self) {
+        if let Some(counter) = self.cycle_counter.as_mut() {
+            counter.report = true;
+        }
+    }
+
+    /// The number of cycles used so far, if a cost model has been
+    /// set.
+    pub fn cycles(&self) -> Option<u64> {
+        self.cycle_counter.as_ref().map(|counter| counter.cycles)
+    }
+
+    /// Account for the execution of an instruction with opcode `op`.
+    pub(super) fn count_cycles(&mut self, op: Opcode) {
+        if let Some(counter) = self.cycle_counter.as_mut() {
+            counter.cycles += counter.model.cost(op as i64);
+        }
+    }
+
+    /// Called when the processor is dropped.
+    pub(super) fn report_cycles(&self) {
+        if let Some(counter) = self.cycle_counter.as_ref() {
+            if counter.report {
+                eprintln!("cycles: {}", counter.cycles);
+            }
+        }
+    }
+
+    /// The cycle counter for a clone of this processor.  The clone
+    /// carries on counting from the same total, but does not report
+    /// it.
+    pub(super) fn clone_cycle_counter(&self) -> Option<CycleCounter> {
+        self.cycle_counter.as_ref().map(|counter| CycleCounter {
+            report: false,
+            ..counter.clone()
+        })
+    }
+}
+
+#[test]
+fn test_parse_cost_model() {
+    let model: CostModel = "2=3, 99=0,1=2".parse().expect("the model is valid");
+    assert_eq!(model.cost(1), 2);
+    assert_eq!(model.cost(2), 3);
+    assert_eq!(model.cost(3), 1);
+    assert_eq!(model.cost(99), 0);
+    assert_eq!(model.to_string(), "1=2,2=3,99=0");
+    assert_eq!("".parse::<CostModel>(), Ok(CostModel::default()));
+    assert!("1=x".parse::<CostModel>().is_err());
+    assert!("12=1".parse::<CostModel>().is_err());
+    assert!("101=1".parse::<CostModel>().is_err());
+}
+
+#[test]
+fn test_count_cycles() {
+    use super::{encode_program, InputOutputError, Instr, Param};
+
+    // Read a word, multiply it by 10 and print it; then halt.
+    let program: Vec<Word> = encode_program(&[
+        Instr::read(9),
+        Instr::mul(Param::Pos(9), Param::Imm(10), 9),
+        Instr::write(Param::Pos(9)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    assert_eq!(cpu.cycles(), None);
+    cpu.set_cost_model("2=10,99=0".parse().expect("the model is valid"));
+    let mut discard = |_| -> Result<(), InputOutputError> { Ok(()) };
+    cpu.run_with_fixed_input(&[Word(5)], &mut discard)
+        .expect("program should run");
+    assert_eq!(cpu.cycles(), Some(12));
+}
@@ -0,0 +1,182 @@
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Memory, TraceConfig, Word};
+use crate::error::Fail;
+
+/// The first line of a trace, identifying the program which was
+/// traced and how.  It looks like
+///
+/// ```text
+/// # intcode-trace program=fnv1a64:a4c2f1e0d3b29687 base=0 length=13 pc=0 time=1760000000 sample-every=1
+/// ```
+///
+/// The program is identified by a hash of the loaded memory (after
+/// any patches), so that traces of different programs, or of the
+/// same program given different patches, can be told apart.  The
+/// time is in seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceHeader {
+    pub fingerprint: u64,
+    pub base: Word,
+    pub length: usize,
+    pub pc: Word,
+    pub time: u64,
+    pub sample_interval: u64,
+}
+
+/// A trace header could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadTraceHeader(pub String);
+
+impl Display for BadTraceHeader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad trace header: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadTraceHeader {}
+
+impl From<BadTraceHeader> for Fail {
+    fn from(e: BadTraceHeader) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+const MAGIC: &str = "# intcode-trace";
+
+/// The 64-bit FNV-1a hash of `words`.  Unlike the hashers of the
+/// standard library, this is the same in every build.
+pub(super) fn fnv1a64(words: impl Iterator<Item = Word>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash = OFFSET_BASIS;
+    for w in words {
+        for byte in w.0.to_le_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+impl Memory {
+    /// A hash of the content of `range`, identifying a program.
+    pub fn fingerprint(&self, range: Range<Word>) -> u64 {
+        let words = (range.start.0..range.end.0).map(|addr| self.fetch(Word(addr)));
+        fnv1a64(words.map(|w| w.unwrap_or(Word(0))))
+    }
+}
+
+impl TraceHeader {
+    /// The header for tracing the program loaded into `memory`,
+    /// starting at `pc`.
+    pub(super) fn new(memory: &Memory, pc: Word, config: &TraceConfig) -> TraceHeader {
+        let loaded = memory.loaded().unwrap_or(Word(0)..Word(0));
+        TraceHeader {
+            fingerprint: memory.fingerprint(loaded.clone()),
+            base: loaded.start,
+            length: (loaded.end.0 - loaded.start.0) as usize,
+            pc,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            sample_interval: config.sample_interval,
+        }
+    }
+
+    /// Whether the traces with headers `self` and `other` are of the
+    /// same program, started at the same place.
+    pub fn same_program(&self, other: &TraceHeader) -> bool {
+        (self.fingerprint, self.base, self.length, self.pc)
+            == (other.fingerprint, other.base, other.length, other.pc)
+    }
+}
+
+impl Display for TraceHeader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} program=fnv1a64:{:016x} base={} length={} pc={} time={} sample-every={}",
+            MAGIC,
+            self.fingerprint,
+            self.base,
+            self.length,
+            self.pc,
+            self.time,
+            self.sample_interval
+        )
+    }
+}
+
+impl FromStr for TraceHeader {
+    type Err = BadTraceHeader;
+
+    fn from_str(s: &str) -> Result<TraceHeader, BadTraceHeader> {
+        let fields = s
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| BadTraceHeader(format!("it should start with '{}'", MAGIC)))?;
+        let mut header = TraceHeader {
+            fingerprint: 0,
+            base: Word(0),
+            length: 0,
+            pc: Word(0),
+            time: 0,
+            sample_interval: 1,
+        };
+        let mut fingerprint_seen = false;
+        for field in fields.split_whitespace() {
+            let bad = |e: &dyn Display| BadTraceHeader(format!("bad field '{}': {}", field, e));
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| bad(&"expected key=value"))?;
+            match key {
+                "program" => {
+                    let hex = value
+                        .strip_prefix("fnv1a64:")
+                        .ok_or_else(|| bad(&"unknown hash"))?;
+                    header.fingerprint = u64::from_str_radix(hex, 16).map_err(|e| bad(&e))?;
+                    fingerprint_seen = true;
+                }
+                "base" => header.base = Word(value.parse().map_err(|e| bad(&e))?),
+                "length" => header.length = value.parse().map_err(|e| bad(&e))?,
+                "pc" => header.pc = Word(value.parse().map_err(|e| bad(&e))?),
+                "time" => header.time = value.parse().map_err(|e| bad(&e))?,
+                "sample-every" => header.sample_interval = value.parse().map_err(|e| bad(&e))?,
+                // Later versions may add fields.
+                _ => (),
+            }
+        }
+        if fingerprint_seen {
+            Ok(header)
+        } else {
+            Err(BadTraceHeader(
+                "it does not identify the program".to_string(),
+            ))
+        }
+    }
+}
+
+#[test]
+fn test_trace_header() {
+    let mut memory = Memory::new();
+    memory
+        .load(Word(0), &[Word(1), Word(2), Word(99)])
+        .expect("load should succeed");
+    let header = TraceHeader::new(&memory, Word(0), &TraceConfig::sample_every(3));
+    assert_eq!(header.base, Word(0));
+    assert_eq!(header.length, 3);
+    assert_eq!(header.sample_interval, 3);
+    let parsed: TraceHeader = header.to_string().parse().expect("header should parse");
+    assert_eq!(parsed, header);
+    // A patched program has a different fingerprint.
+    memory
+        .load(Word(1), &[Word(3)])
+        .expect("load should succeed");
+    let patched = TraceHeader::new(&memory, Word(0), &TraceConfig::default());
+    assert!(!patched.same_program(&header));
+    assert!("0 @0: execute 3".parse::<TraceHeader>().is_err());
+    assert_eq!(fnv1a64(std::iter::empty()), 0xcbf29ce484222325);
+}
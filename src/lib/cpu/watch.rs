@@ -0,0 +1,228 @@
+use std::fmt::{self, Debug, Display, Formatter};
+use std::ops::Range;
+
+use super::{Processor, Word};
+
+/// Whether a memory access read or wrote the word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Load,
+    Store,
+}
+
+/// Which accesses to a watched range are of interest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchFor {
+    Loads,
+    Stores,
+    LoadsAndStores,
+}
+
+impl WatchFor {
+    fn includes(&self, kind: AccessKind) -> bool {
+        !matches!(
+            (self, kind),
+            (WatchFor::Loads, AccessKind::Store) | (WatchFor::Stores, AccessKind::Load)
+        )
+    }
+}
+
+/// An access by the program to a watched address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    /// The address of the instruction which made the access.
+    pub pc: Word,
+    pub addr: Word,
+    /// The word which was loaded or stored.
+    pub value: Word,
+    pub kind: AccessKind,
+}
+
+impl Display for MemoryAccess {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            AccessKind::Load => write!(
+                f,
+                "instruction at {} loaded {} from {}",
+                self.pc, self.value, self.addr
+            ),
+            AccessKind::Store => write!(
+                f,
+                "instruction at {} stored {} at {}",
+                self.pc, self.value, self.addr
+            ),
+        }
+    }
+}
+
+type WatchCallback = Box<dyn FnMut(&MemoryAccess) + Send + Sync>;
+
+/// The address ranges whose loads or stores pause the program (or
+/// are reported to a callback).
+#[derive(Default)]
+pub(super) struct Watchpoints {
+    watches: Vec<(Range<Word>, WatchFor)>,
+    callback: Option<WatchCallback>,
+    /// The access which is to pause the program, if any.
+    hit: Option<MemoryAccess>,
+}
+
+impl Debug for Watchpoints {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchpoints")
+            .field("watches", &self.watches)
+            .field("callback", &self.callback.is_some())
+            .field("hit", &self.hit)
+            .finish()
+    }
+}
+
+/// The callback is not cloned.
+impl Clone for Watchpoints {
+    fn clone(&self) -> Watchpoints {
+        Watchpoints {
+            watches: self.watches.clone(),
+            callback: None,
+            hit: self.hit,
+        }
+    }
+}
+
+impl Processor {
+    /// Pause the program after any instruction which loads from (or
+    /// stores into, as chosen by `watch_for`) the addresses in
+    /// `range`.  The methods which run the program then return
+    /// `CpuStatus::WatchpointHit` (or `MachineEvent::WatchpointHit`)
+    /// describing the first such access.  Loads include those of
+    /// immediate operands, as in traces.
+    pub fn add_watchpoint(&mut self, range: Range<Word>, watch_for: WatchFor) {
+        self.watchpoints.watches.push((range, watch_for));
+    }
+
+    /// Remove the watchpoints for `range`, returning whether there
+    /// were any.
+    pub fn remove_watchpoint(&mut self, range: Range<Word>) -> bool {
+        let before = self.watchpoints.watches.len();
+        self.watchpoints.watches.retain(|(r, _)| *r != range);
+        self.watchpoints.watches.len() != before
+    }
+
+    /// Call `report` with each access to a watched address, instead
+    /// of pausing the program.  This replaces any previous callback.
+    /// Clones of the processor do not call it.
+    pub fn on_watched_access<F>(&mut self, report: F)
+    where
+        F: FnMut(&MemoryAccess) + Send + Sync + 'static,
+    {
+        self.watchpoints.callback = Some(Box::new(report));
+    }
+
+    /// Account for an access by the current instruction.
+    pub(super) fn note_access(&mut self, kind: AccessKind, addr: Word, value: Word) {
+        let watchpoints = &mut self.watchpoints;
+        if watchpoints.watches.is_empty()
+            || !watchpoints
+                .watches
+                .iter()
+                .any(|(range, watch_for)| range.contains(&addr) && watch_for.includes(kind))
+        {
+            return;
+        }
+        let access = MemoryAccess {
+            pc: self.pc,
+            addr,
+            value,
+            kind,
+        };
+        match watchpoints.callback.as_mut() {
+            Some(report) => report(&access),
+            None => {
+                watchpoints.hit.get_or_insert(access);
+            }
+        }
+    }
+
+    /// The access which is to pause the program, if any.  It is
+    /// forgotten.
+    pub(super) fn take_watchpoint_hit(&mut self) -> Option<MemoryAccess> {
+        self.watchpoints.hit.take()
+    }
+
+    /// Make the next attempt to run the program report `access`
+    /// instead of executing an instruction.
+    pub(super) fn defer_watchpoint_hit(&mut self, access: MemoryAccess) {
+        self.watchpoints.hit = Some(access);
+    }
+}
+
+#[test]
+fn test_watchpoints() {
+    use super::{encode_program, CpuStatus, Instr, MachineEvent, Param, WordInt};
+    use std::sync::{Arc, Mutex};
+
+    // Read a word into 20, copy it to 21 and write it; then halt.
+    let program = encode_program(&[
+        Instr::read(20),
+        Instr::add(Param::Pos(20), Param::Imm(0), 21),
+        Instr::write(Param::Pos(21)),
+        Instr::stop(),
+    ]);
+    let mut cpu = Processor::new(Word(0));
+    cpu.load(Word(0), &program).expect("program should load");
+    let mut fork = cpu.clone();
+    cpu.add_watchpoint(Word(21)..Word(22), WatchFor::LoadsAndStores);
+    cpu.add_watchpoint(Word(20)..Word(21), WatchFor::Loads);
+    cpu.provide_input(Word(7));
+    let access = |pc: WordInt, addr: WordInt, kind: AccessKind| MemoryAccess {
+        pc: Word(pc),
+        addr: Word(addr),
+        value: Word(7),
+        kind,
+    };
+    // The store into 20 is not watched, but the load from it is.
+    let hit = access(2, 20, AccessKind::Load);
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::WatchpointHit(hit)
+    );
+    assert_eq!(hit.to_string(), "instruction at 2 loaded 7 from 20");
+    // An output made by the same instruction is reported first.
+    let mut deferred = cpu.clone();
+    for event in [
+        MachineEvent::ProducedOutput(Word(7)),
+        MachineEvent::WatchpointHit(access(6, 21, AccessKind::Load)),
+        MachineEvent::Halted,
+    ] {
+        assert_eq!(deferred.run_until_blocked().expect("no fault"), event);
+    }
+    // The instruction which made the access has been executed.
+    let mut output: Vec<Word> = Vec::new();
+    assert_eq!(
+        cpu.step(&mut output).expect("no fault"),
+        CpuStatus::WatchpointHit(access(6, 21, AccessKind::Load))
+    );
+    assert_eq!(output, vec![Word(7)]);
+    assert!(cpu.remove_watchpoint(Word(21)..Word(22)));
+    assert!(!cpu.remove_watchpoint(Word(21)..Word(22)));
+    assert_eq!(
+        cpu.run_until_blocked().expect("no fault"),
+        MachineEvent::Halted
+    );
+
+    // With a callback, the program is not paused.
+    let seen: Arc<Mutex<Vec<MemoryAccess>>> = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&seen);
+    fork.add_watchpoint(Word(20)..Word(22), WatchFor::Stores);
+    fork.on_watched_access(move |access| log.lock().expect("not poisoned").push(*access));
+    assert_eq!(
+        fork.run_with_fixed_input(&[Word(7)], &mut Vec::new()).ok(),
+        Some(())
+    );
+    assert_eq!(
+        *seen.lock().expect("not poisoned"),
+        vec![
+            access(0, 20, AccessKind::Store),
+            access(2, 21, AccessKind::Store)
+        ]
+    );
+}
@@ -0,0 +1,41 @@
+//! Day 1: The Tyranny of the Rocket Equation.
+use crate::error::Fail;
+
+pub fn fuel(mass: i64) -> i64 {
+    mass / 3 - 2
+}
+
+/// The fuel needed for `mass`, including the fuel needed to carry
+/// that fuel, and so on.
+pub fn cumulative_fuel(mass: i64) -> i64 {
+    let mut tot: i64 = 0;
+    let mut f = fuel(mass);
+    while f > 0 {
+        tot += f;
+        f = fuel(f);
+    }
+    tot
+}
+
+#[test]
+fn test_fuel() {
+    assert!(fuel(12) == 2);
+    assert!(fuel(14) == 2);
+    assert!(fuel(1969) == 654);
+    assert!(fuel(100756) == 33583);
+}
+
+/// Solve both parts: the fuel needed for the modules alone, and the
+/// fuel needed including the mass of the fuel.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let (mut fuel1, mut fuel2): (i64, i64) = (0, 0);
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        let mass: i64 = line
+            .trim()
+            .parse()
+            .map_err(|e| Fail(format!("bad module mass '{}': {}", line, e)))?;
+        fuel1 += fuel(mass);
+        fuel2 += cumulative_fuel(mass);
+    }
+    Ok((fuel1.to_string(), Some(fuel2.to_string())))
+}
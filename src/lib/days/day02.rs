@@ -0,0 +1,37 @@
+//! Day 2: 1202 Program Alarm.
+use crate::cpu::{parse_program, run_patched, CpuFault, Word, WordInt};
+use crate::error::Fail;
+
+/// Run `program` with the given noun and verb in locations 1 and 2,
+/// and return the content of location 0 when it halts.
+pub fn run_program(program: &[Word], noun: Word, verb: Word) -> Result<Word, CpuFault> {
+    let ram = run_patched(program, &[(Word(1), noun), (Word(2), verb)])?;
+    Ok(ram[0])
+}
+
+pub fn part1(program: &[Word]) -> Result<Word, Fail> {
+    Ok(run_program(program, Word(12), Word(2))?)
+}
+
+/// Find the noun and verb which give 19690720, and return 100 *
+/// noun + verb.
+pub fn part2(program: &[Word]) -> Result<WordInt, Fail> {
+    const WANTED: Word = Word(19690720);
+    for noun in 1..100 {
+        for verb in 1..100 {
+            let result: Word = run_program(program, Word(noun), Word(verb))?;
+            if result == WANTED {
+                return Ok(100 * noun + verb);
+            }
+        }
+    }
+    Err(Fail("Day 2 part 2: no solution found".to_string()))
+}
+
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    Ok((
+        part1(&program)?.to_string(),
+        Some(part2(&program)?.to_string()),
+    ))
+}
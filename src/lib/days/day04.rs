@@ -0,0 +1,20 @@
+//! Day 4: Secure Container.  The password rules themselves are in
+//! `crate::password`.
+use crate::error::Fail;
+use crate::password::{count_valid_passwords, RepeatRule};
+
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    match input.trim().split_once('-') {
+        Some((begin, end)) => match (begin.parse(), end.parse()) {
+            (Ok(b), Ok(e)) => Ok((
+                count_valid_passwords(b..=e, RepeatRule::AtLeastTwo).to_string(),
+                Some(count_valid_passwords(b..=e, RepeatRule::ExactlyTwo).to_string()),
+            )),
+            (Err(e), _) | (_, Err(e)) => Err(Fail(format!(
+                "Day 4: failed to parse input '{}': {}",
+                input, e
+            ))),
+        },
+        None => Err(Fail(format!("input has unexpected format: {}", input))),
+    }
+}
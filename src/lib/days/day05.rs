@@ -0,0 +1,39 @@
+//! Day 5: Sunny with a Chance of Asteroids.
+use crate::cpu::{new_processor, parse_program, InputOutputError, Word};
+use crate::error::Fail;
+
+/// Run `program` with the single input `input_word`, returning its
+/// output.
+pub fn run_program(program: &[Word], input_word: Word) -> Result<Vec<Word>, Fail> {
+    let mut cpu = new_processor(program).map_err(|e| Fail(e.to_string()))?;
+    let mut output_words = Vec::new();
+    let mut output = |w: Word| -> Result<(), InputOutputError> {
+        output_words.push(w);
+        Ok(())
+    };
+    let input: Vec<Word> = vec![input_word];
+    if let Err(e) = cpu.run_with_fixed_input(&input, &mut output) {
+        Err(Fail(format!("program should be valid: {:?}", e)))
+    } else {
+        Ok(output_words)
+    }
+}
+
+fn format_output(words: &[Word]) -> String {
+    words
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Part 1 runs the air conditioner diagnostic (system ID 1), and
+/// part 2 the thermal radiator diagnostic (system ID 5).  The answer
+/// is all the output of the program.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    Ok((
+        format_output(&run_program(&program, Word(1))?),
+        Some(format_output(&run_program(&program, Word(5))?)),
+    ))
+}
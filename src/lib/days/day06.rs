@@ -0,0 +1,240 @@
+//! Day 6: Universal Orbit Map.
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use crate::error::Fail;
+use crate::parse::{split_once_parsed, BadFragment};
+
+/// Returns the parent of each body, and the set of all bodies.
+pub fn build_tree(orbits: &[(String, String)]) -> (HashMap<String, String>, HashSet<String>) {
+    let mut all_bodies: HashSet<String> = HashSet::new();
+    let mut parent_of: HashMap<String, String> = HashMap::new();
+    for (parent_name, child_name) in orbits {
+        parent_of.insert(child_name.to_string(), parent_name.to_string());
+    }
+    for (parent, child) in orbits {
+        all_bodies.insert(parent.to_string());
+        all_bodies.insert(child.to_string());
+    }
+    (parent_of, all_bodies)
+}
+
+pub fn count_orbits(parent_of: &HashMap<String, String>, all_bodies: &HashSet<String>) -> usize {
+    fn count_parents(name: &str, parent_of: &HashMap<String, String>) -> usize {
+        match parent_of.get(&name.to_string()) {
+            None => 0,
+            Some(parent_name) => 1 + count_parents(parent_name, parent_of),
+        }
+    }
+    all_bodies
+        .iter()
+        .map(|name| count_parents(name, parent_of))
+        .sum()
+}
+
+#[test]
+fn test_count_orbits() {
+    let test_input: Vec<&str> = vec![
+        "COM)B", "B)C", "C)D", "D)E", "E)F", "B)G", "G)H", "D)I", "E)J", "J)K", "K)L",
+    ];
+    let orbits: Vec<(String, String)> = test_input
+        .iter()
+        .cloned()
+        .map(parse_orbit)
+        .map(|x| x.expect("test data should be valid"))
+        .collect::<Vec<(String, String)>>();
+    let (parent_of, all_bodies) = build_tree(&orbits);
+    assert_eq!(count_orbits(&parent_of, &all_bodies), 42);
+}
+
+fn compute_transfer_counts(
+    mut who: String,
+    parent_of: &HashMap<String, String>,
+) -> HashMap<String, usize> {
+    let mut result: HashMap<String, usize> = HashMap::new();
+    let mut count: usize = 0;
+    loop {
+        match parent_of.get(&who) {
+            Some(p) => {
+                result.insert(p.to_string(), count);
+                count += 1;
+                who = p.to_string();
+            }
+            None => {
+                return result;
+            }
+        }
+    }
+}
+
+pub fn count_transfers(
+    from: String,
+    to: String,
+    parent_of: &HashMap<String, String>,
+) -> Option<usize> {
+    let transfers_to = compute_transfer_counts(from, parent_of);
+    let mut body = to;
+    let mut transfers: usize = 0;
+    loop {
+        match parent_of.get(&body) {
+            None => {
+                return None;
+            }
+            Some(p) => match transfers_to.get(p) {
+                None => {
+                    transfers += 1;
+                    body = p.to_string();
+                }
+                Some(n) => {
+                    return Some(n + transfers);
+                }
+            },
+        }
+    }
+}
+
+#[test]
+fn test_count_transfers() {
+    let test_input: Vec<&str> = vec![
+        "COM)B", "B)C", "C)D", "D)E", "E)F", "B)G", "G)H", "D)I", "E)J", "J)K", "K)L", "K)YOU",
+        "I)SAN",
+    ];
+    let orbits: Vec<(String, String)> = test_input
+        .iter()
+        .cloned()
+        .map(parse_orbit)
+        .map(|x| x.expect("test data should be valid"))
+        .collect();
+
+    let (parent_of, _all_bodies) = build_tree(&orbits);
+    assert_eq!(
+        count_transfers("YOU".to_string(), "SAN".to_string(), &parent_of),
+        Some(4)
+    );
+}
+
+/// The bodies which orbit a body on the path of the orbital
+/// transfers from `from` to `to`.  That is, the orbits (identified by
+/// the body on the inside of each) which the path follows.  Returns
+/// `None` if there is no such path.
+pub fn transfer_route<'a>(
+    from: &'a str,
+    to: &'a str,
+    parent_of: &'a HashMap<String, String>,
+) -> Option<HashSet<&'a str>> {
+    let ancestors = |mut body: &'a str| -> Vec<&'a str> {
+        let mut result = vec![body];
+        while let Some(parent) = parent_of.get(body) {
+            result.push(parent.as_str());
+            body = parent.as_str();
+        }
+        result
+    };
+    let from_ancestors = ancestors(from);
+    let to_ancestors = ancestors(to);
+    let common: HashSet<&str> = from_ancestors
+        .iter()
+        .filter(|body| to_ancestors.contains(body))
+        .copied()
+        .collect();
+    if common.is_empty() {
+        return None;
+    }
+    Some(
+        from_ancestors
+            .into_iter()
+            .take_while(|body| !common.contains(body))
+            .chain(
+                to_ancestors
+                    .into_iter()
+                    .take_while(|body| !common.contains(body)),
+            )
+            .collect(),
+    )
+}
+
+/// Write the orbit map as a Graphviz graph, with the orbits on the
+/// path from YOU to SAN highlighted.
+pub fn write_dot<W: Write>(
+    out: &mut W,
+    orbits: &[(String, String)],
+    parent_of: &HashMap<String, String>,
+) -> io::Result<()> {
+    let route: HashSet<&str> = transfer_route("YOU", "SAN", parent_of).unwrap_or_default();
+    writeln!(out, "digraph orbits {{")?;
+    writeln!(out, "  rankdir=LR;")?;
+    for endpoint in ["YOU", "SAN"] {
+        if parent_of.contains_key(endpoint) {
+            writeln!(out, "  \"{}\" [style=filled, fillcolor=red];", endpoint)?;
+        }
+    }
+    for (parent, child) in orbits {
+        if route.contains(child.as_str()) {
+            writeln!(
+                out,
+                "  \"{}\" -> \"{}\" [color=red, penwidth=3];",
+                parent, child
+            )?;
+        } else {
+            writeln!(out, "  \"{}\" -> \"{}\";", parent, child)?;
+        }
+    }
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+#[test]
+fn test_write_dot() {
+    let orbits: Vec<(String, String)> = ["COM)B", "B)YOU", "B)C", "C)SAN", "COM)D"]
+        .iter()
+        .map(|s| parse_orbit(s).expect("test data should be valid"))
+        .collect();
+    let (parent_of, _all_bodies) = build_tree(&orbits);
+    let route = transfer_route("YOU", "SAN", &parent_of).expect("there should be a route");
+    assert_eq!(route, HashSet::from(["YOU", "C", "SAN"]));
+    let mut out: Vec<u8> = Vec::new();
+    write_dot(&mut out, &orbits, &parent_of).expect("write to Vec should succeed");
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "digraph orbits {\n",
+            "  rankdir=LR;\n",
+            "  \"YOU\" [style=filled, fillcolor=red];\n",
+            "  \"SAN\" [style=filled, fillcolor=red];\n",
+            "  \"COM\" -> \"B\";\n",
+            "  \"B\" -> \"YOU\" [color=red, penwidth=3];\n",
+            "  \"B\" -> \"C\" [color=red, penwidth=3];\n",
+            "  \"C\" -> \"SAN\" [color=red, penwidth=3];\n",
+            "  \"COM\" -> \"D\";\n",
+            "}\n"
+        )
+    );
+}
+
+/// Parse an orbit such as "COM)B".
+fn parse_orbit(s: &str) -> Result<(String, String), BadFragment> {
+    split_once_parsed(s, ")")
+}
+
+/// Parse the orbits in `input`, one per line.  Each orbit is a
+/// (parent, child) pair.
+pub fn parse_orbits(input: &str) -> Result<Vec<(String, String)>, Fail> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_orbit(line).map_err(|e| e.in_line(i + 1, line)))
+        .collect()
+}
+
+/// Part 1 is the total number of direct and indirect orbits, and part
+/// 2 the number of orbital transfers from YOU to SAN (or `None` if
+/// there is no such route).
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let orbits = parse_orbits(input)?;
+    let (parent_of, all_bodies) = build_tree(&orbits);
+    Ok((
+        count_orbits(&parent_of, &all_bodies).to_string(),
+        count_transfers("YOU".to_string(), "SAN".to_string(), &parent_of).map(|n| n.to_string()),
+    ))
+}
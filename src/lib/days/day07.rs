@@ -0,0 +1,352 @@
+//! Day 7: Amplification Circuit.
+use std::fmt::{self, Display, Formatter};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::cpu::{new_processor, parse_program, MachineEvent, Processor, Word, WordInt};
+use crate::error::Fail;
+use crate::optimize::best_selection_parallel;
+
+/// One end of a wire between amplifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Node {
+    /// An amplifier, numbered from 0 (named A, B, C, ... in the
+    /// puzzle).
+    Amplifier(usize),
+    /// The ship's thrusters, which receive the result.
+    Thrusters,
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Amplifier(n) => match u8::try_from(*n) {
+                Ok(n) if n < 26 => write!(f, "{}", char::from(b'A' + n)),
+                _ => write!(f, "amplifier {}", n),
+            },
+            Node::Thrusters => f.write_str("out"),
+        }
+    }
+}
+
+/// Which amplifier feeds which.  Every output of an amplifier is sent
+/// to each of the nodes it is wired to; the result is the last value
+/// to reach the thrusters.  The first amplifier also receives the
+/// initial input signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology {
+    amplifiers: usize,
+    wires: Vec<(usize, Node)>,
+}
+
+impl Topology {
+    /// Amplifiers connected in series, the last feeding the thrusters.
+    pub fn chain(amplifiers: usize) -> Topology {
+        let mut wires: Vec<(usize, Node)> = (1..amplifiers)
+            .map(|n| (n - 1, Node::Amplifier(n)))
+            .collect();
+        wires.push((amplifiers - 1, Node::Thrusters));
+        Topology { amplifiers, wires }
+    }
+
+    /// Amplifiers connected in series, the last feeding both the
+    /// thrusters and the first amplifier.
+    pub fn feedback_loop(amplifiers: usize) -> Topology {
+        let mut topology = Topology::chain(amplifiers);
+        topology.wires.push((amplifiers - 1, Node::Amplifier(0)));
+        topology
+    }
+
+    fn destinations(&self, source: usize) -> impl Iterator<Item = Node> + '_ {
+        self.wires
+            .iter()
+            .filter(move |(from, _)| *from == source)
+            .map(|(_, to)| *to)
+    }
+}
+
+impl Display for Topology {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (from, to)) in self.wires.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}->{}", Node::Amplifier(*from), to)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_node(s: &str) -> Result<Node, Fail> {
+    let s = s.trim();
+    match s.as_bytes() {
+        b"out" => Ok(Node::Thrusters),
+        [letter] if letter.is_ascii_uppercase() => Ok(Node::Amplifier((letter - b'A') as usize)),
+        _ => Err(Fail(format!(
+            "'{}' is not a node name (expected A-Z or 'out')",
+            s
+        ))),
+    }
+}
+
+impl FromStr for Topology {
+    type Err = Fail;
+
+    /// Parse a description such as "A->B->C,C->A,C->out": a
+    /// comma-separated list of paths, each of which wires every node
+    /// to the next.
+    fn from_str(s: &str) -> Result<Topology, Fail> {
+        let mut wires: Vec<(usize, Node)> = Vec::new();
+        for path in s.split(',') {
+            let nodes: Vec<Node> = path
+                .split("->")
+                .map(parse_node)
+                .collect::<Result<Vec<Node>, Fail>>()?;
+            if nodes.len() < 2 {
+                return Err(Fail(format!("path '{}' has no wires", path.trim())));
+            }
+            for pair in nodes.windows(2) {
+                match pair {
+                    [Node::Amplifier(from), to] => wires.push((*from, *to)),
+                    _ => {
+                        return Err(Fail(format!(
+                            "in '{}': nothing can be wired from the thrusters",
+                            path.trim()
+                        )));
+                    }
+                }
+            }
+        }
+        let amplifiers = wires
+            .iter()
+            .flat_map(|(from, to)| match to {
+                Node::Amplifier(to) => [*from, *to],
+                Node::Thrusters => [*from, *from],
+            })
+            .max()
+            .map_or(0, |n| n + 1);
+        if !wires.iter().any(|(_, to)| *to == Node::Thrusters) {
+            return Err(Fail(format!("'{}' has no wire to 'out'", s)));
+        }
+        Ok(Topology { amplifiers, wires })
+    }
+}
+
+#[test]
+fn test_parse_topology() {
+    let t: Topology = "A->B->C->out,C->A".parse().expect("topology should parse");
+    assert_eq!(t.amplifiers, 3);
+    assert_eq!(t, Topology::feedback_loop(3));
+    assert_eq!(t.to_string(), "A->B,B->C,C->out,C->A");
+    assert!("A->B".parse::<Topology>().is_err());
+    assert!("A->out->B".parse::<Topology>().is_err());
+    assert!("A->b,b->out".parse::<Topology>().is_err());
+}
+
+/// Run one copy of `program` per amplifier, wired as described by
+/// `topology`.  Each amplifier's first input is its phase setting.
+pub fn run_amplifiers(
+    program: &[Word],
+    topology: &Topology,
+    phases: &[Word],
+    first_input: Word,
+) -> Result<Word, Fail> {
+    assert_eq!(phases.len(), topology.amplifiers);
+    // The program is loaded only once; the other amplifiers are
+    // clones of the first, sharing its memory until they write to it.
+    // Only the first amplifier is traced.
+    let mut amplifiers: Vec<Processor> = vec![new_processor(program)?];
+    while amplifiers.len() < phases.len() {
+        amplifiers.push(amplifiers[0].clone());
+    }
+    for (amp, phase) in amplifiers.iter_mut().zip(phases) {
+        amp.provide_input(*phase);
+    }
+    amplifiers[0].provide_input(first_input);
+    let mut halted: Vec<bool> = vec![false; amplifiers.len()];
+    let mut thruster_input: Option<Word> = None;
+    loop {
+        let mut progress = false;
+        for i in 0..amplifiers.len() {
+            if halted[i] {
+                continue;
+            }
+            match amplifiers[i].run_until_blocked()? {
+                MachineEvent::ProducedOutput(output) => {
+                    progress = true;
+                    for dest in topology.destinations(i) {
+                        match dest {
+                            Node::Amplifier(n) => amplifiers[n].provide_input(output),
+                            Node::Thrusters => thruster_input = Some(output),
+                        }
+                    }
+                }
+                MachineEvent::AwaitingInput => (),
+                MachineEvent::Halted => {
+                    progress = true;
+                    halted[i] = true;
+                }
+                MachineEvent::BreakpointHit(_) | MachineEvent::WatchpointHit(_) => {
+                    unreachable!("there are no breakpoints or watchpoints")
+                }
+            }
+        }
+        if !progress {
+            break;
+        }
+    }
+    thruster_input.ok_or_else(|| {
+        Fail(format!(
+            "with topology {}, no signal reached the thrusters",
+            topology
+        ))
+    })
+}
+
+/// Find the permutation of `phase_settings` (or of as many of them
+/// as there are amplifiers) which gives the highest output.  The
+/// permutations are tried in parallel if the `rayon` feature is
+/// enabled.
+pub fn best_phases(
+    program: &[Word],
+    topology: &Topology,
+    phase_settings: RangeInclusive<WordInt>,
+    input: Word,
+) -> Result<(Word, Vec<Word>), Fail> {
+    let settings: Vec<Word> = phase_settings.map(Word).collect();
+    let best = best_selection_parallel(&settings, topology.amplifiers, |phases| {
+        run_amplifiers(program, topology, phases, input)
+    })?;
+    best.ok_or_else(|| {
+        Fail(format!(
+            "there are too few phase settings for {} amplifiers",
+            topology.amplifiers
+        ))
+    })
+}
+
+pub fn solve1(program: &[Word], input: Word) -> Result<(Word, Vec<Word>), Fail> {
+    best_phases(program, &Topology::chain(5), 0..=4, input)
+}
+
+#[cfg(test)]
+type Solver = fn(&[Word], Word) -> Result<(Word, Vec<Word>), Fail>;
+
+#[cfg(test)]
+fn check_amplifier_program(
+    program: &[WordInt],
+    solver: Solver,
+    expected_best_output: WordInt,
+    expected_best_phases: &[WordInt],
+) {
+    fn words(input: &[WordInt]) -> Vec<Word> {
+        input.iter().map(|n| Word(*n)).collect()
+    }
+    let program = words(program);
+    let expected_best_output = Word(expected_best_output);
+    let expected_best_phases = words(expected_best_phases);
+    match solver(&program, Word(0)) {
+        Ok((got_best_output, got_best_phases)) => {
+            assert_eq!(
+                expected_best_output, got_best_output,
+                "incorrect best output"
+            );
+            assert_eq!(
+                expected_best_phases, got_best_phases,
+                "incorrect best phases"
+            );
+        }
+        Err(e) => {
+            panic!("check_amplifier_program: failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+fn check_amplifier_chain_program(
+    program: &[WordInt],
+    expected_best_output: WordInt,
+    expected_best_phases: &[WordInt],
+) {
+    check_amplifier_program(program, solve1, expected_best_output, expected_best_phases)
+}
+
+#[test]
+fn test_amplifier_chain_program() {
+    check_amplifier_chain_program(
+        &[
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ],
+        43210,
+        &[4, 3, 2, 1, 0],
+    );
+    check_amplifier_chain_program(
+        &[
+            3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4, 23,
+            99, 0, 0,
+        ],
+        54321,
+        &[0, 1, 2, 3, 4],
+    );
+    check_amplifier_chain_program(
+        &[
+            3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7, 33, 1,
+            33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
+        ],
+        65210,
+        &[1, 0, 4, 3, 2],
+    );
+}
+
+pub fn solve2(program: &[Word], input: Word) -> Result<(Word, Vec<Word>), Fail> {
+    best_phases(program, &Topology::feedback_loop(5), 5..=9, input)
+}
+
+#[cfg(test)]
+fn check_amplifier_loop_program(
+    program: &[WordInt],
+    expected_best_output: WordInt,
+    expected_best_phases: &[WordInt],
+) {
+    check_amplifier_program(program, solve2, expected_best_output, expected_best_phases)
+}
+
+#[test]
+fn test_solve2() {
+    check_amplifier_loop_program(
+        &[
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ],
+        139629729,
+        &[9, 8, 7, 6, 5],
+    );
+    check_amplifier_loop_program(
+        &[
+            3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55, 26, 1001, 54,
+            -5, 54, 1105, 1, 12, 1, 53, 54, 53, 1008, 54, 0, 55, 1001, 55, 1, 55, 2, 53, 55, 53, 4,
+            53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0, 0, 0, 10,
+        ],
+        18216,
+        &[9, 7, 8, 5, 6],
+    );
+}
+
+/// Part 1 is the highest signal which five amplifiers in series can
+/// send to the thrusters, and part 2 the highest signal when the
+/// part 2 amplifiers are wired as `topology`.
+pub fn solve_with_topology(
+    input: &str,
+    topology: &Topology,
+) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    let (part1, _) = solve1(&program, Word(0))?;
+    let (part2, _) = best_phases(&program, topology, 5..=9, Word(0))?;
+    Ok((part1.to_string(), Some(part2.to_string())))
+}
+
+/// Solve both parts, with the amplifiers of part 2 in a feedback
+/// loop as the puzzle describes.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    solve_with_topology(input, &Topology::feedback_loop(5))
+}
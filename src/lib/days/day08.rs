@@ -0,0 +1,134 @@
+//! Day 8: Space Image Format.
+use crate::error::Fail;
+use std::io::BufRead;
+
+#[derive(Debug)]
+pub enum BadInput {
+    Incomplete(String),
+    BadPixel(char),
+    ReadFailed(std::io::Error),
+}
+
+impl From<BadInput> for Fail {
+    fn from(e: BadInput) -> Fail {
+        match e {
+            BadInput::Incomplete(msg) => Fail(format!("bad input: input is incomplete: {}", msg)),
+            BadInput::BadPixel(ch) => Fail(format!("bad input: {:?} is not a pixel colour", ch)),
+            BadInput::ReadFailed(e) => Fail(format!("failed to read input: {}", e)),
+        }
+    }
+}
+
+const BLACK: u8 = 0;
+const WHITE: u8 = 1;
+const TRANSPARENT: u8 = 2;
+
+/// What we need to know about a layered image: how many pixels of
+/// each colour there are in each layer, and what the image looks
+/// like when the layers are stacked.
+pub struct Image {
+    pub width: usize,
+    pub layer_counts: Vec<[usize; 3]>,
+    pub front: Vec<u8>,
+}
+
+/// Read an image of size `w`x`h` from `reader`, one pixel at a time.
+/// The layers themselves are not kept.
+pub fn parse_image<R: BufRead>(w: usize, h: usize, reader: R) -> Result<Image, BadInput> {
+    let pixels_per_layer = w * h;
+    let mut image = Image {
+        width: w,
+        layer_counts: Vec::new(),
+        front: vec![TRANSPARENT; pixels_per_layer],
+    };
+    let mut total_pixels: usize = 0;
+    for byte in reader.bytes() {
+        let ch = char::from(byte.map_err(BadInput::ReadFailed)?);
+        if ch.is_ascii_whitespace() {
+            continue;
+        }
+        let colour: u8 = match ch.to_digit(10) {
+            Some(d) if d <= u32::from(TRANSPARENT) => d as u8,
+            _ => {
+                return Err(BadInput::BadPixel(ch));
+            }
+        };
+        let pos = total_pixels % pixels_per_layer;
+        if pos == 0 {
+            image.layer_counts.push([0; 3]);
+        }
+        if let Some(counts) = image.layer_counts.last_mut() {
+            counts[colour as usize] += 1;
+        }
+        if image.front[pos] == TRANSPARENT {
+            image.front[pos] = colour;
+        }
+        total_pixels += 1;
+    }
+    if !total_pixels.is_multiple_of(pixels_per_layer) {
+        return Err(BadInput::Incomplete(format!(
+            "{} pixels is not enough to fill a whole number of {}x{} layers",
+            total_pixels, w, h
+        )));
+    }
+    Ok(image)
+}
+
+#[test]
+fn test_parse_image() {
+    let image = parse_image(2, 2, "0222112222120000\n".as_bytes()).expect("input is valid");
+    assert_eq!(
+        image.layer_counts,
+        vec![[1, 0, 3], [0, 2, 2], [0, 1, 3], [4, 0, 0]]
+    );
+    assert_eq!(image.front, vec![BLACK, WHITE, WHITE, BLACK]);
+    assert!(matches!(
+        parse_image(2, 2, "02221".as_bytes()),
+        Err(BadInput::Incomplete(_))
+    ));
+    assert!(matches!(
+        parse_image(2, 2, "0123".as_bytes()),
+        Err(BadInput::BadPixel('3'))
+    ));
+}
+
+fn part1(image: &Image) -> Result<usize, Fail> {
+    match image
+        .layer_counts
+        .iter()
+        .min_by_key(|counts| counts[BLACK as usize])
+    {
+        Some(counts) => Ok(counts[WHITE as usize] * counts[TRANSPARENT as usize]),
+        None => Err(Fail("Day 8 part 1: the image has no layers".to_string())),
+    }
+}
+
+/// The visible image, one line per row, with white pixels shown as
+/// '#' and black ones as spaces.
+fn part2(image: &Image) -> String {
+    let mut result = String::new();
+    for row in image.front.chunks(image.width) {
+        for colour in row {
+            result.push(match *colour {
+                WHITE => '#',
+                BLACK => ' ',
+                _ => '.',
+            });
+        }
+        result.push('\n');
+    }
+    result
+}
+
+pub const WIDTH: usize = 25;
+pub const HEIGHT: usize = 6;
+
+/// Part 1 checks the image for corruption, and part 2 is the
+/// decoded image itself (the answer is the text it shows).
+pub fn solve_image(image: &Image) -> Result<(String, Option<String>), Fail> {
+    Ok((part1(image)?.to_string(), Some(part2(image))))
+}
+
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    solve_image(&parse_image(WIDTH, HEIGHT, input.as_bytes())?)
+}
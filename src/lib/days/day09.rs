@@ -0,0 +1,35 @@
+//! Day 9: Sensor Boost.
+use crate::cpu::{new_processor, parse_program, Word};
+use crate::error::Fail;
+
+pub fn run_program(program: &[Word], input_word: Word) -> Result<Vec<Word>, Fail> {
+    let mut cpu = new_processor(program)?;
+    cpu.provide_input(input_word);
+    let output: Result<Vec<Word>, _> = cpu.outputs().collect();
+    output.map_err(|e| Fail(format!("program should be valid: {}", e)))
+}
+
+/// Part 1 is the BOOST keycode (produced in test mode), and part 2
+/// the coordinates of the distress signal (in sensor boost mode).
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    let mut output = run_program(&program, Word(1))?; // 1 is test mode.
+    let boost_keycode = output
+        .pop()
+        .ok_or_else(|| Fail("BOOST produced no keycode".to_string()))?;
+    if !output.is_empty() {
+        let opcodes: Vec<String> = output.iter().map(|w| w.to_string()).collect();
+        return Err(Fail(format!(
+            "BOOST self-check thinks these opcodes are not working: {}",
+            opcodes.join(", ")
+        )));
+    }
+    let output = run_program(&program, Word(2))?; // 2 is sensor boost mode.
+    match output.as_slice() {
+        [coordinates] => Ok((boost_keycode.to_string(), Some(coordinates.to_string()))),
+        _ => Err(Fail(format!(
+            "expected sensor boost mode to output one word, but it output {}",
+            output.len()
+        ))),
+    }
+}
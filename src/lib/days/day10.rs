@@ -0,0 +1,520 @@
+//! Day 10: Monitoring Station.
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt::Display;
+use std::io::Write;
+
+use crate::error::Fail;
+use crate::grid::{self, Position, Step};
+
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Clone)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    fn manhattan(&self, other: &Point) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    pub fn bearing(&self, to: &Point) -> f64 {
+        let dx: f64 = (to.x - self.x).into();
+        let dy: f64 = (to.y - self.y).into();
+        let mut rad = -(-dy).atan2(dx) + (PI / 2.0);
+        if rad < 0.0 {
+            rad += 2.0 * PI;
+        }
+        radians_to_degrees(rad)
+    }
+}
+
+impl Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+impl From<&Point> for Position {
+    fn from(p: &Point) -> Position {
+        Position {
+            x: p.x.into(),
+            y: p.y.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AsteroidField {
+    pub asteroids: HashSet<Point>,
+}
+
+impl From<&str> for AsteroidField {
+    fn from(input: &str) -> AsteroidField {
+        let mut asteroids: HashSet<Point> = HashSet::new();
+        let mut x = 0;
+        let mut y = 0;
+        for ch in input.chars() {
+            match ch {
+                '.' => (), // empty
+                '\n' => {
+                    y += 1;
+                    x = 0;
+                    continue;
+                }
+                _ => {
+                    asteroids.insert(Point { x, y });
+                }
+            }
+            x += 1;
+        }
+        AsteroidField { asteroids }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Candidate {
+    pub p: Point,
+    pub visible_count: usize,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        match self.visible_count.cmp(&other.visible_count) {
+            Ordering::Equal => self.p.cmp(&other.p),
+            unequal => unequal,
+        }
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The asteroid from which the most others are visible.
+pub fn solve1(field: &AsteroidField) -> Option<Candidate> {
+    let occupied: HashSet<Position> = field.asteroids.iter().map(Position::from).collect();
+    let is_asteroid = |pos: &Position| occupied.contains(pos);
+    field
+        .asteroids
+        .iter()
+        .map(|p| {
+            let here = Position::from(p);
+            let visible_count = occupied
+                .iter()
+                .filter(|q| **q != here && grid::visible(&here, q, is_asteroid))
+                .count();
+            Candidate {
+                p: p.clone(),
+                visible_count,
+            }
+        })
+        .max()
+}
+
+#[cfg(test)]
+fn check_solution1(input: &str, expected_solution: &Candidate) {
+    let asteroids: AsteroidField = input.into();
+    let best = solve1(&asteroids);
+    assert_eq!(Some(expected_solution), best.as_ref());
+}
+
+#[test]
+fn test_solve1() {
+    check_solution1(
+        r#".#..#
+.....
+#####
+....#
+...##"#,
+        &Candidate {
+            p: Point { x: 3, y: 4 },
+            visible_count: 8,
+        },
+    );
+
+    check_solution1(
+        concat!(
+            "......#.#.\n",
+            "#..#.#....\n",
+            "..#######.\n",
+            ".#.#.###..\n",
+            ".#..#.....\n",
+            "..#....#.#\n",
+            "#..#....#.\n",
+            ".##.#..###\n",
+            "##...#..#.\n",
+            ".#....####\n"
+        ), // input
+        &Candidate {
+            p: Point { x: 5, y: 8 },
+            visible_count: 33,
+        },
+    );
+
+    check_solution1(
+        concat!(
+            "#.#...#.#.\n",
+            ".###....#.\n",
+            ".#....#...\n",
+            "##.#.#.#.#\n",
+            "....#.#.#.\n",
+            ".##..###.#\n",
+            "..#...##..\n",
+            "..##....##\n",
+            "......#...\n",
+            ".####.###.\n"
+        ), // input
+        &Candidate {
+            p: Point { x: 1, y: 2 },
+            visible_count: 35,
+        },
+    );
+
+    check_solution1(
+        concat!(
+            ".#..#..###\n",
+            "####.###.#\n",
+            "....###.#.\n",
+            "..###.##.#\n",
+            "##.##.#.#.\n",
+            "....###..#\n",
+            "..#.#..#.#\n",
+            "#..#.#.###\n",
+            ".##...##.#\n",
+            ".....#.#..\n"
+        ), // input
+        &Candidate {
+            p: Point { x: 6, y: 3 },
+            visible_count: 41,
+        },
+    );
+
+    check_solution1(
+        concat!(
+            ".#..##.###...#######\n",
+            "##.############..##.\n",
+            ".#.######.########.#\n",
+            ".###.#######.####.#.\n",
+            "#####.##.#.##.###.##\n",
+            "..#####..#.#########\n",
+            "####################\n",
+            "#.####....###.#.#.##\n",
+            "##.#################\n",
+            "#####.##.###..####..\n",
+            "..######..##.#######\n",
+            "####.##.####...##..#\n",
+            ".#####..#.######.###\n",
+            "##...#.##########...\n",
+            "#.##########.#######\n",
+            ".####.#.###.###.#.##\n",
+            "....##.##.###..#####\n",
+            ".#.#.###########.###\n",
+            "#.#.#.#####.####.###\n",
+            "###.##.####.##.#..##\n"
+        ),
+        &Candidate {
+            p: Point { x: 11, y: 13 },
+            visible_count: 210,
+        },
+    );
+}
+
+fn radians_to_degrees(rad: f64) -> f64 {
+    180.0 * rad / PI
+}
+
+#[cfg(test)]
+fn is_close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1.0e-5
+}
+
+#[cfg(test)]
+fn check_radians_to_degrees(radians: f64, expected: f64) {
+    let got = radians_to_degrees(radians);
+    assert!(is_close(expected, got), "{} vs {}", expected, got);
+}
+
+#[cfg(test)]
+fn check_bearing_from(from: &Point, to: &Point, expected: f64) {
+    let got = from.bearing(to);
+    assert!(
+        is_close(got, expected),
+        "bearing of {} from {}: expected {}, got {}",
+        to,
+        from,
+        expected,
+        got
+    );
+}
+
+#[test]
+fn test_bearing() {
+    let base = Point { x: 5, y: 5 };
+    let examples = &[
+        Point { x: 5, y: 4 },
+        Point { x: 6, y: 4 },
+        Point { x: 6, y: 5 },
+        Point { x: 6, y: 6 },
+        Point { x: 5, y: 6 },
+        Point { x: 4, y: 6 },
+        Point { x: 4, y: 5 },
+    ];
+    for p in examples {
+        let b = base.bearing(p);
+        println!("Bearing from {} to {} is {}", base, p, b);
+    }
+
+    check_radians_to_degrees(0.0, 0.0);
+    check_radians_to_degrees(4.0 * PI / 9.0, 80.0);
+
+    check_bearing_from(&Point { x: 5, y: 5 }, &Point { x: 5, y: 4 }, 0.0);
+    check_bearing_from(&Point { x: 5, y: 5 }, &Point { x: 10, y: 5 }, 90.0);
+    check_bearing_from(&Point { x: 5, y: 5 }, &Point { x: 5, y: 10 }, 180.0);
+    check_bearing_from(&Point { x: 5, y: 5 }, &Point { x: 0, y: 5 }, 270.0);
+}
+
+/// The direction from one point to another.  The vector is reduced
+/// to lowest terms, so points on the same line of sight from some
+/// base have equal directions.  Directions are ordered clockwise,
+/// starting from "up" (which is negative y).  Unlike a bearing
+/// computed in floating point, this ordering is exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Direction(pub Step);
+
+impl Direction {
+    pub fn between(from: &Point, to: &Point) -> Direction {
+        assert!(from != to, "there is no direction from {} to itself", from);
+        Direction(Step::between(&Position::from(from), &Position::from(to)).reduced())
+    }
+
+    /// 0 for directions from "up" (inclusive) to "down" (exclusive),
+    /// going clockwise; 1 for the others.
+    fn half(&self) -> u8 {
+        let Step { dx, dy } = self.0;
+        if dx > 0 || (dx == 0 && dy < 0) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({},{})", self.0.dx, self.0.dy)
+    }
+}
+
+impl Ord for Direction {
+    fn cmp(&self, other: &Direction) -> Ordering {
+        self.half().cmp(&other.half()).then_with(|| {
+            // Within a half, self comes first if turning clockwise
+            // takes us from self to other; that is, if the cross
+            // product is positive (remembering that y increases
+            // downwards).
+            let cross = self.0.dx * other.0.dy - self.0.dy * other.0.dx;
+            0.cmp(&cross)
+        })
+    }
+}
+
+impl PartialOrd for Direction {
+    fn partial_cmp(&self, other: &Direction) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[test]
+fn test_direction_ordering() {
+    let base = Point { x: 0, y: 0 };
+    let clockwise: Vec<Direction> = [
+        (0, -1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+    ]
+    .iter()
+    .map(|(x, y)| Direction::between(&base, &Point { x: *x, y: *y }))
+    .collect();
+    for pair in clockwise.windows(2) {
+        assert!(
+            pair[0] < pair[1],
+            "{} should come before {}",
+            pair[0],
+            pair[1]
+        );
+    }
+    // Points on the same line of sight have the same direction.
+    assert_eq!(
+        Direction::between(&base, &Point { x: 2, y: -4 }),
+        Direction::between(&base, &Point { x: 1, y: -2 })
+    );
+    // These directions differ by far less than a floating-point
+    // bearing rounded to a millionth of a degree can distinguish.
+    let a = Direction::between(
+        &base,
+        &Point {
+            x: 100000,
+            y: -99999,
+        },
+    );
+    let b = Direction::between(
+        &base,
+        &Point {
+            x: 99999,
+            y: -99998,
+        },
+    );
+    assert!(a != b);
+    assert!(a < b);
+}
+
+fn order_by_reverse_distance(base: &Point, points: &mut [Point]) {
+    // We already know tha the slopes of the line betwen base and a is the
+    // same as the slope of the line between base and b.  Hence to find the
+    // closer of a and b we can simply use the manhattan distance.
+    points
+        .sort_by(|a: &Point, b: &Point| -> Ordering { base.manhattan(b).cmp(&base.manhattan(a)) });
+}
+
+/// One asteroid destroyed by the laser.
+pub struct Zap {
+    pub asteroid: Point,
+    pub direction: Direction,
+}
+
+/// The order in which the laser at `base` destroys the other
+/// asteroids.
+pub fn zap_order(base: &Point, asteroids: &AsteroidField) -> Vec<Zap> {
+    let mut by_direction: BTreeMap<Direction, Vec<Point>> = BTreeMap::new();
+    for asteroid in asteroids.asteroids.iter() {
+        if asteroid != base {
+            by_direction
+                .entry(Direction::between(base, asteroid))
+                .or_default()
+                .push(asteroid.clone());
+        }
+    }
+    for points in by_direction.values_mut() {
+        order_by_reverse_distance(base, points);
+    }
+
+    let total: usize = by_direction.values().map(|v| v.len()).sum();
+    let mut result: Vec<Zap> = Vec::with_capacity(total);
+    while result.len() < total {
+        // The laser starts by pointing up.  So, iterate in order (so
+        // that we start at 0 ("up") and move clockwise).
+        for (direction, asteroid_locations) in by_direction.iter_mut() {
+            if let Some(goner) = asteroid_locations.pop() {
+                result.push(Zap {
+                    asteroid: goner,
+                    direction: *direction,
+                });
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+fn solve2(index: usize, base: &Point, asteroids: &AsteroidField) -> Option<Point> {
+    match index.checked_sub(1) {
+        Some(i) => zap_order(base, asteroids)
+            .into_iter()
+            .nth(i)
+            .map(|zap| zap.asteroid),
+        None => None,
+    }
+}
+
+/// Write the zap order as CSV, one line per asteroid.
+pub fn write_zap_csv<W: Write>(out: &mut W, base: &Point, zaps: &[Zap]) -> std::io::Result<()> {
+    writeln!(out, "index,x,y,bearing")?;
+    for (i, zap) in zaps.iter().enumerate() {
+        writeln!(
+            out,
+            "{},{},{},{:.6}",
+            i + 1,
+            zap.asteroid.x,
+            zap.asteroid.y,
+            base.bearing(&zap.asteroid)
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_solve2() {
+    let asteroids: AsteroidField = concat!(
+        ".#..##.###...#######\n",
+        "##.############..##.\n",
+        ".#.######.########.#\n",
+        ".###.#######.####.#.\n",
+        "#####.##.#.##.###.##\n",
+        "..#####..#.#########\n",
+        "####################\n",
+        "#.####....###.#.#.##\n",
+        "##.#################\n",
+        "#####.##.###..####..\n",
+        "..######..##.#######\n",
+        "####.##.####...##..#\n",
+        ".#####..#.######.###\n",
+        "##...#.##########...\n",
+        "#.##########.#######\n",
+        ".####.#.###.###.#.##\n",
+        "....##.##.###..#####\n",
+        ".#.#.###########.###\n",
+        "#.#.#.#####.####.###\n",
+        "###.##.####.##.#..##\n"
+    )
+    .into();
+    let base = Point { x: 11, y: 13 };
+    assert_eq!(Some(Point { x: 11, y: 12 }), solve2(1, &base, &asteroids));
+    assert_eq!(Some(Point { x: 8, y: 2 }), solve2(200, &base, &asteroids));
+    assert_eq!(Some(Point { x: 11, y: 1 }), solve2(299, &base, &asteroids));
+    assert_eq!(None, solve2(300, &base, &asteroids));
+
+    let zaps = zap_order(&base, &asteroids);
+    let mut csv: Vec<u8> = Vec::new();
+    write_zap_csv(&mut csv, &base, &zaps[0..2]).expect("writing to a Vec should succeed");
+    assert_eq!(
+        String::from_utf8(csv).expect("CSV should be UTF-8"),
+        "index,x,y,bearing\n1,11,12,0.000000\n2,12,1,4.763642\n"
+    );
+}
+
+/// The answer to part 2: 100 times the X coordinate of the 200th
+/// asteroid to be destroyed, plus its Y coordinate.
+pub fn part2(zaps: &[Zap]) -> Result<i32, Fail> {
+    match zaps.get(199) {
+        Some(zap) => Ok(zap.asteroid.x * 100 + zap.asteroid.y),
+        None => Err(Fail("Day 10 part 2: no solution found".to_string())),
+    }
+}
+
+/// Part 1 is the number of asteroids visible from the best place for
+/// a monitoring station, and part 2 identifies the 200th asteroid
+/// destroyed by the laser at that station.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let field: AsteroidField = input.into();
+    match solve1(&field) {
+        Some(best) => {
+            let zaps = zap_order(&best.p, &field);
+            Ok((
+                best.visible_count.to_string(),
+                Some(part2(&zaps)?.to_string()),
+            ))
+        }
+        None => Err(Fail(
+            "Day 10 part 1: no solution found (so can't solve part 2 either)".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,284 @@
+//! Day 11: Space Police.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::cpu::{new_processor, parse_program, CpuFault, InputOutputError, Word};
+use crate::error::Fail;
+use crate::png;
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone)]
+pub struct Panel {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Panel {
+    fn up(&self) -> Panel {
+        Panel {
+            y: self.y - 1,
+            ..*self
+        }
+    }
+    fn down(&self) -> Panel {
+        Panel {
+            y: self.y + 1,
+            ..*self
+        }
+    }
+    fn right(&self) -> Panel {
+        Panel {
+            x: self.x + 1,
+            ..*self
+        }
+    }
+    fn left(&self) -> Panel {
+        Panel {
+            x: self.x - 1,
+            ..*self
+        }
+    }
+}
+
+impl Display for Panel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum PaintColour {
+    White,
+    Black,
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
+enum PaintStatus {
+    PaintedWhite,
+    PaintedBlack,
+}
+
+#[derive(Debug, Default)]
+pub struct ShipSurface {
+    panels: HashMap<Panel, PaintStatus>,
+    total_painted_panels: usize,
+}
+
+impl ShipSurface {
+    pub fn new() -> ShipSurface {
+        ShipSurface {
+            panels: HashMap::new(),
+            total_painted_panels: 0,
+        }
+    }
+
+    pub fn get_painted_panel_count(&self) -> usize {
+        self.total_painted_panels
+    }
+
+    fn paint_panel(&mut self, location: Panel, colour: PaintColour) {
+        let new_state: PaintStatus = match colour {
+            PaintColour::White => PaintStatus::PaintedWhite,
+            PaintColour::Black => PaintStatus::PaintedBlack,
+        };
+        if self.panels.insert(location, new_state).is_none() {
+            self.total_painted_panels += 1;
+        }
+    }
+
+    pub fn get_panel_colour(&self, location: &Panel) -> PaintColour {
+        match self.panels.get(location) {
+            Some(PaintStatus::PaintedWhite) => PaintColour::White,
+            _ => PaintColour::Black,
+        }
+    }
+}
+
+impl ShipSurface {
+    /// The top-left and bottom-right corners of the painted area.
+    fn bounds(&self) -> Option<(Panel, Panel)> {
+        let xs = self.panels.keys().map(|p| p.x);
+        let ys = self.panels.keys().map(|p| p.y);
+        match (xs.clone().min(), xs.max(), ys.clone().min(), ys.max()) {
+            (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) => {
+                Some((Panel { x: min_x, y: min_y }, Panel { x: max_x, y: max_y }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes the painted area as a PNG image, each panel being a
+    /// square of `scale` by `scale` pixels.
+    pub fn write_png<W: Write>(&self, out: &mut W, scale: u32) -> io::Result<()> {
+        let (min, max) = match self.bounds() {
+            Some(corners) => corners,
+            None => {
+                return png::write_greyscale(out, 0, 0, &[]);
+            }
+        };
+        let width = (max.x - min.x + 1) as u32 * scale;
+        let height = (max.y - min.y + 1) as u32 * scale;
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for py in 0..height {
+            for px in 0..width {
+                let panel = Panel {
+                    x: min.x + (px / scale) as i32,
+                    y: min.y + (py / scale) as i32,
+                };
+                pixels.push(match self.get_panel_colour(&panel) {
+                    PaintColour::Black => 0,
+                    PaintColour::White => 255,
+                });
+            }
+        }
+        png::write_greyscale(out, width, height, &pixels)
+    }
+}
+
+impl Display for ShipSurface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (min, max) = match self.bounds() {
+            Some(corners) => corners,
+            None => {
+                // Nothing to display: empty!
+                return Ok(());
+            }
+        };
+
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let colour = self.get_panel_colour(&Panel { x, y });
+                write!(
+                    f,
+                    "{}",
+                    match colour {
+                        PaintColour::Black => ' ',
+                        PaintColour::White => '#',
+                    }
+                )?;
+            }
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+enum Heading {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+fn perform_turn_and_move(
+    w: Word,
+    heading: &mut Heading,
+    location: &mut Panel,
+) -> Result<(), InputOutputError> {
+    let right: bool = w.0 != 0;
+    use Heading::*;
+    match heading {
+        Heading::Up => {
+            *heading = if right { Right } else { Left };
+            *location = if right {
+                location.right()
+            } else {
+                location.left()
+            };
+        }
+        Heading::Right => {
+            *heading = if right { Down } else { Up };
+            *location = if right {
+                location.down()
+            } else {
+                location.up()
+            };
+        }
+        Heading::Down => {
+            *heading = if right { Left } else { Right };
+            *location = if right {
+                location.left()
+            } else {
+                location.right()
+            };
+        }
+        Heading::Left => {
+            *heading = if right { Up } else { Down };
+            *location = if right {
+                location.up()
+            } else {
+                location.down()
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Run the painting robot's `program`, starting at `start` on a
+/// panel of colour `start_colour`.  Returns the robot's final
+/// location.
+pub fn run_robot(
+    start: Panel,
+    start_colour: PaintColour,
+    surface: &mut ShipSurface,
+    program: &[Word],
+) -> Result<Panel, CpuFault> {
+    let panel_colour = Arc::new(Mutex::new(start_colour));
+
+    let mut get_input = || -> Result<Word, InputOutputError> {
+        match *panel_colour.lock().unwrap() {
+            PaintColour::Black => Ok(Word(0)),
+            PaintColour::White => Ok(Word(1)),
+        }
+    };
+
+    let mut moving: bool = false;
+    let mut location: Panel = start;
+    let mut heading = Heading::Up;
+
+    let mut do_output = |w: Word| -> Result<(), InputOutputError> {
+        let new_colour = if moving {
+            perform_turn_and_move(w, &mut heading, &mut location)?;
+            surface.get_panel_colour(&location)
+        } else {
+            let new_colour: PaintColour = match w {
+                Word(0) => PaintColour::Black,
+                Word(1) => PaintColour::White,
+                _ => {
+                    // invalid; ignore it.
+                    return Ok(());
+                }
+            };
+            surface.paint_panel(location.clone(), new_colour);
+            new_colour
+        };
+        moving = !moving;
+        *panel_colour.lock().unwrap() = new_colour;
+        Ok(())
+    };
+
+    let mut cpu = new_processor(program)?;
+    cpu.run_with_io(&mut get_input, &mut do_output)?;
+    Ok(location)
+}
+
+/// Run the robot from the origin, starting on a panel of colour
+/// `start_colour`, and return what it painted.
+pub fn paint(program: &[Word], start_colour: PaintColour) -> Result<ShipSurface, Fail> {
+    let start = Panel { x: 0, y: 0 };
+    let mut surface = ShipSurface::new();
+    run_robot(start, start_colour, &mut surface, program)?;
+    Ok(surface)
+}
+
+/// Part 1 is the number of panels painted when starting on a black
+/// panel, and part 2 the picture painted when starting on a white
+/// one.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    let part1 = paint(&program, PaintColour::Black)?.get_painted_panel_count();
+    let part2 = paint(&program, PaintColour::White)?.to_string();
+    Ok((part1.to_string(), Some(part2)))
+}
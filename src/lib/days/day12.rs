@@ -0,0 +1,483 @@
+//! Day 12: The N-Body Problem.
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{self, Write};
+use std::ops::{Add, Div, Mul, Range, Rem};
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::error::Fail;
+use crate::input::{context_snippet, Location};
+use crate::{verbose, warning};
+
+const DIMENSIONS: usize = 3;
+const AXIS_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+
+fn axis_name(axis: usize) -> &'static str {
+    AXIS_NAMES.get(axis).copied().unwrap_or("?")
+}
+
+/// Controls the output of a simulation: after each step for which
+/// `verbose` returns true, the state of the system is printed.
+#[derive(Debug)]
+pub struct SimulationFlags<FV>
+where
+    FV: Fn(u64) -> bool,
+{
+    pub verbose: FV,
+}
+
+struct IntegerExtractor {
+    re: Regex,
+}
+
+impl IntegerExtractor {
+    pub fn new() -> IntegerExtractor {
+        IntegerExtractor {
+            re: Regex::new(r"[+-]?\d+").unwrap(),
+        }
+    }
+
+    /// Extract the integers in `s`.  On failure, returns the byte
+    /// offset of the integer which could not be converted.
+    pub fn get_integers<T, S>(&self, s: S) -> Result<Vec<T>, (usize, <T as FromStr>::Err)>
+    where
+        S: AsRef<str>,
+        T: FromStr + Debug,
+    {
+        self.re
+            .find_iter(s.as_ref())
+            .map(|m| m.as_str().parse::<T>().map_err(|e| (m.start(), e)))
+            .collect()
+    }
+}
+
+/// A system of bodies moving in `D` dimensions.
+///
+/// Motion along each axis is independent of the other axes, so the
+/// positions and velocities are stored axis by axis: the position of
+/// body `b` on axis `a` is `position[a * bodies + b]`.
+#[derive(Clone)]
+pub struct System<const D: usize> {
+    bodies: usize,
+    position: Vec<i64>,
+    velocity: Vec<i64>,
+}
+
+impl<const D: usize> System<D> {
+    /// A system of bodies at the given positions, all stationary.
+    pub fn new(positions: &[[i64; D]]) -> System<D> {
+        let bodies = positions.len();
+        let mut position = vec![0; D * bodies];
+        for (body, pos) in positions.iter().enumerate() {
+            for (axis, value) in pos.iter().enumerate() {
+                position[axis * bodies + body] = *value;
+            }
+        }
+        System {
+            bodies,
+            position,
+            velocity: vec![0; D * bodies],
+        }
+    }
+
+    fn axis_range(&self, axis: usize) -> Range<usize> {
+        axis * self.bodies..(axis + 1) * self.bodies
+    }
+
+    fn step_axis(&mut self, axis: usize) {
+        let range = self.axis_range(axis);
+        let position = &mut self.position[range.clone()];
+        let velocity = &mut self.velocity[range];
+        // Apply gravity
+        for first in 0..position.len() {
+            for second in 0..first {
+                let pull = (position[second] - position[first]).signum();
+                velocity[first] += pull;
+                velocity[second] -= pull;
+            }
+        }
+        // Apply velocity
+        for (p, v) in position.iter_mut().zip(velocity.iter()) {
+            *p += *v;
+        }
+    }
+
+    fn step<FV>(&mut self, step_number: u64, flags: &SimulationFlags<FV>)
+    where
+        FV: Fn(u64) -> bool,
+    {
+        for axis in 0..D {
+            self.step_axis(axis);
+        }
+        if (flags.verbose)(step_number) {
+            println!(
+                "After {} {}:\n{}",
+                step_number,
+                if step_number == 1 { "step" } else { "steps" },
+                self
+            );
+        }
+    }
+
+    fn potential_energy(&self, i: usize) -> i64 {
+        (0..D)
+            .map(|axis| self.position[axis * self.bodies + i].abs())
+            .sum()
+    }
+
+    fn kinetic_energy(&self, i: usize) -> i64 {
+        (0..D)
+            .map(|axis| self.velocity[axis * self.bodies + i].abs())
+            .sum()
+    }
+
+    fn energy(&self) -> i64 {
+        (0..self.bodies)
+            .map(|i| self.potential_energy(i) * self.kinetic_energy(i))
+            .sum()
+    }
+
+    fn total_energy(&self) -> i64 {
+        (0..self.bodies)
+            .map(|i| {
+                let pot = self.potential_energy(i);
+                let kin = self.kinetic_energy(i);
+                verbose!(
+                    "Body {} has potential energy {}, kinetic energy {}",
+                    i,
+                    &pot,
+                    &kin
+                );
+                pot * kin
+            })
+            .sum()
+    }
+
+    fn axis_match(&self, axis: usize, initial: &System<D>) -> bool {
+        let range = self.axis_range(axis);
+        self.position[range.clone()] == initial.position[range.clone()]
+            && self.velocity[range.clone()] == initial.velocity[range]
+    }
+}
+
+impl<const D: usize> Display for System<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let vector = |values: &[i64], body: usize| -> String {
+            let items: Vec<String> = (0..D)
+                .map(|axis| {
+                    format!(
+                        "{}={:>3}",
+                        axis_name(axis),
+                        values[axis * self.bodies + body]
+                    )
+                })
+                .collect();
+            items.join(", ")
+        };
+        for body in 0..self.bodies {
+            // pos=<x= -8, y=-10, z=  0>, vel=<x=  0, y=  0, z=  0>
+            writeln!(
+                f,
+                "pos=<{}>, vel=<{}>",
+                vector(&self.position, body),
+                vector(&self.velocity, body)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub type System3 = System<DIMENSIONS>;
+
+pub fn parse_initial_state<S>(lines: &[S]) -> Result<System3, Fail>
+where
+    S: AsRef<str>,
+{
+    let mut initial_positions: Vec<[i64; DIMENSIONS]> = Vec::new();
+    let extractor = IntegerExtractor::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+        let values: Vec<i64> = extractor
+            .get_integers::<i64, _>(&line)
+            .map_err(|(offset, e)| {
+                let location = Location::at_offset(i + 1, line, offset);
+                Fail(format!(
+                    "{} (in \"{}\"): {}",
+                    location,
+                    context_snippet(line, location.column),
+                    e
+                ))
+            })?;
+        match <[i64; DIMENSIONS]>::try_from(values.as_slice()) {
+            Ok(position) => initial_positions.push(position),
+            Err(_) => {
+                return Err(Fail(format!(
+                    "line {}: expected {} fields, got {}: {}",
+                    (i + 1),
+                    DIMENSIONS,
+                    values.len(),
+                    &line
+                )));
+            }
+        }
+    }
+    Ok(System::new(&initial_positions))
+}
+
+/// Simulate `steps` steps, returning the total energy of the system
+/// at the end.
+pub fn solve1<FV, const D: usize>(
+    system: &mut System<D>,
+    steps: u64,
+    flags: &SimulationFlags<FV>,
+) -> i64
+where
+    FV: Fn(u64) -> bool,
+{
+    if (flags.verbose)(0) {
+        println!("After 0 steps:\n{}", system);
+    }
+    for step_number in 1..=steps {
+        system.step(step_number, flags);
+    }
+    system.total_energy()
+}
+
+#[test]
+fn test_solve1_first_example() {
+    let input: Vec<String> = vec![
+        "<x=-1, y=0, z=2>\n",
+        "<x=2, y=-10, z=-7>\n",
+        "<x=4, y=-8, z=8>\n",
+        "<x=3, y=5, z=-1>\n",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    let mut system = parse_initial_state(&input).expect("test input should be valid");
+    let flags = SimulationFlags { verbose: |_| true };
+    let energy = solve1(&mut system, 10, &flags);
+    assert_eq!(energy, 179);
+}
+
+#[test]
+fn test_two_dimensional_system() {
+    // The bodies oscillate with period 6 on x and 8 on y.
+    let mut system: System<2> = System::new(&[[0, 0], [2, 4]]);
+    let flags = SimulationFlags { verbose: |_| false };
+    assert_eq!(solve1(&mut system.clone(), 1, &flags), 2 * 2 + 4 * 2);
+    assert_eq!(solve2(&mut system, 100, &flags), Some(24));
+}
+
+#[test]
+fn test_solve1_second_example() {
+    let input: Vec<String> = vec![
+        "<x=-8, y=-10, z=0>\n",
+        "<x=5, y=5, z=10>\n",
+        "<x=2, y=-7, z=3>\n",
+        "<x=9, y=-8, z=-3>\n",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    let mut system = parse_initial_state(&input).expect("test input should be valid");
+    let flags = SimulationFlags { verbose: |_| false };
+    let energy = solve1(&mut system, 100, &flags);
+    assert_eq!(energy, 1940);
+}
+
+fn write_history_header<W: Write, const D: usize>(
+    out: &mut W,
+    system: &System<D>,
+    bodies: bool,
+) -> io::Result<()> {
+    write!(out, "step,energy")?;
+    if bodies {
+        for body in 0..system.bodies {
+            for prefix in ["", "v"] {
+                for axis in 0..D {
+                    write!(out, ",body{}_{}{}", body, prefix, axis_name(axis))?;
+                }
+            }
+        }
+    }
+    writeln!(out)
+}
+
+fn write_history_row<W: Write, const D: usize>(
+    out: &mut W,
+    step_number: u64,
+    system: &System<D>,
+    bodies: bool,
+) -> io::Result<()> {
+    write!(out, "{},{}", step_number, system.energy())?;
+    if bodies {
+        for body in 0..system.bodies {
+            for values in [&system.position, &system.velocity] {
+                for axis in 0..D {
+                    write!(out, ",{}", values[axis * system.bodies + body])?;
+                }
+            }
+        }
+    }
+    writeln!(out)
+}
+
+/// Simulate `steps` steps of `system`, writing the total energy (and
+/// if `bodies` is set, the position and velocity of each body) after
+/// each step to `out` as CSV.  The first row is the initial state.
+pub fn write_history<W: Write, const D: usize>(
+    out: &mut W,
+    system: &mut System<D>,
+    steps: u64,
+    bodies: bool,
+) -> io::Result<()> {
+    let flags = SimulationFlags { verbose: |_| false };
+    write_history_header(out, system, bodies)?;
+    write_history_row(out, 0, system, bodies)?;
+    for step_number in 1..=steps {
+        system.step(step_number, &flags);
+        write_history_row(out, step_number, system, bodies)?;
+    }
+    out.flush()
+}
+
+#[test]
+fn test_write_history() {
+    let mut system: System<2> = System::new(&[[0, 0], [2, 4]]);
+    let mut out: Vec<u8> = Vec::new();
+    write_history(&mut out, &mut system.clone(), 1, false).expect("write to Vec should succeed");
+    assert_eq!(String::from_utf8(out).unwrap(), "step,energy\n0,0\n1,12\n");
+    let mut out: Vec<u8> = Vec::new();
+    write_history(&mut out, &mut system, 1, true).expect("write to Vec should succeed");
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "step,energy,body0_x,body0_y,body0_vx,body0_vy,body1_x,body1_y,body1_vx,body1_vy\n",
+            "0,0,0,0,0,0,2,4,0,0\n",
+            "1,12,1,1,1,1,1,3,-1,-1\n"
+        )
+    );
+}
+
+fn gcd<T>(a: T, b: T) -> T
+where
+    T: Add + Rem<Output = T> + PartialEq + From<u8> + Copy,
+{
+    if a == 0_u8.into() {
+        b
+    } else {
+        gcd(b % a, a)
+    }
+}
+
+#[test]
+fn test_gcd() {
+    assert_eq!(gcd(12_u8, 9_u8), 3_u8);
+    assert_eq!(gcd(12_u8, 8_u8), 4_u8);
+    assert_eq!(gcd(12_u8, 11_u8), 1_u8);
+}
+
+fn lcm<T>(a: T, b: T) -> T
+where
+    T: Add + Rem<Output = T> + Mul<Output = T> + Div<Output = T> + PartialEq + From<u8> + Copy,
+{
+    (a * b) / gcd(a, b)
+}
+
+#[test]
+fn test_lcm() {
+    assert_eq!(lcm(3_u8, 4_u8), 12_u8);
+    assert_eq!(lcm(12_u8, 8_u8), 24_u8);
+}
+
+/// Find the number of steps after which the system first returns to
+/// its initial state, giving up after `step_limit` steps.
+pub fn solve2<FV, const D: usize>(
+    system: &mut System<D>,
+    step_limit: u64,
+    flags: &SimulationFlags<FV>,
+) -> Option<u64>
+where
+    FV: Fn(u64) -> bool,
+{
+    let initial = system.clone();
+    let mut cycles_to_find: usize = D;
+    let mut cycle: [Option<u64>; D] = [None; D];
+    for step_number in 1..=step_limit {
+        if cycles_to_find == 0 {
+            break;
+        }
+        system.step(step_number, flags);
+        for (axis, cyc) in cycle
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, cyc)| cyc.is_none())
+        {
+            if system.axis_match(axis, &initial) {
+                *cyc = Some(step_number);
+                cycles_to_find -= 1;
+                verbose!(
+                    "solve2: at iteration {} found cycle in dimension {}",
+                    step_number,
+                    axis
+                );
+            }
+        }
+    }
+    match cycle
+        .iter()
+        .try_fold(1_u64, |acc, cyc| cyc.map(|n| lcm(acc, n)))
+    {
+        Some(full_cycle) => {
+            verbose!("Cycle length on all dimensions is {}", full_cycle);
+            Some(full_cycle)
+        }
+        None => {
+            warning!(
+                "Did not find a cycle on at least one dimension: {:?}",
+                cycle
+            );
+            None
+        }
+    }
+}
+
+#[test]
+fn test_solve2_first_example() {
+    let input: Vec<String> = vec![
+        "<x=-1, y=0, z=2>\n",
+        "<x=2, y=-10, z=-7>\n",
+        "<x=4, y=-8, z=8>\n",
+        "<x=3, y=5, z=-1>\n",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    let mut system = parse_initial_state(&input).expect("test input should be valid");
+    let flags = SimulationFlags {
+        verbose: |n| matches!(n, 0 | 2770 | 2771 | 2772),
+    };
+    assert_eq!(solve2(&mut system, 3000, &flags), Some(2772));
+}
+
+/// The number of steps simulated in part 1.
+pub const PART1_STEPS: u64 = 1000;
+
+/// Part 1 is the total energy of the system after `PART1_STEPS`
+/// steps, and part 2 the number of steps before the system first
+/// repeats a previous state.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let lines: Vec<&str> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let system = parse_initial_state(&lines)?;
+    let flags = SimulationFlags { verbose: |_| false };
+    let energy = solve1(&mut system.clone(), PART1_STEPS, &flags);
+    match solve2(&mut system.clone(), 1000000, &flags) {
+        Some(n) => Ok((energy.to_string(), Some(n.to_string()))),
+        None => Err(Fail("Day 12 part 2: no solution".to_string())),
+    }
+}
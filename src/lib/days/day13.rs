@@ -0,0 +1,484 @@
+//! Day 13: Care Package.
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use crate::cpu::{
+    new_processor, parse_program, CpuFault, InputOutputError, MachineEvent, MachineState,
+    OutputChunker, Word, WordInt,
+};
+use crate::error::Fail;
+use crate::grid::{Position, Step};
+use crate::input::read_file_as_string;
+use crate::screen::{DrawCommand, OffScreen, Screen};
+
+pub const EMPTY: i64 = 0;
+pub const WALL: i64 = 1;
+pub const BLOCK: i64 = 2;
+pub const PADDLE: i64 = 3;
+pub const BALL: i64 = 4;
+
+pub fn tile_symbol(tile: i64) -> char {
+    match tile {
+        EMPTY => ' ',
+        WALL => '|',
+        BLOCK => '#',
+        PADDLE => '=',
+        BALL => 'o',
+        _ => '?',
+    }
+}
+
+/// Returns a callback suitable for `OutputChunker` which applies draw
+/// commands to `screen` and then passes them to `observe`.
+fn draw_on<'a, F>(
+    screen: &'a RefCell<Screen>,
+    mut observe: F,
+) -> impl FnMut([Word; 3]) -> Result<(), InputOutputError> + 'a
+where
+    F: FnMut(DrawCommand) + 'a,
+{
+    move |chunk: [Word; 3]| -> Result<(), InputOutputError> {
+        let command = DrawCommand::try_from(chunk)?;
+        match screen.borrow_mut().apply(command) {
+            Ok(()) => {
+                observe(command);
+                Ok(())
+            }
+            Err(OffScreen(_)) => Err(InputOutputError::OffScreen {
+                x: chunk[0],
+                y: chunk[1],
+            }),
+        }
+    }
+}
+
+/// Run the game without a coin, and count the blocks it draws.
+pub fn part1(program: &[Word]) -> Result<usize, Fail> {
+    let screen = RefCell::new(Screen::new());
+    let mut chunker = OutputChunker::new(draw_on(&screen, |_| ()));
+    let mut get_input = || Ok(Word(0));
+    let mut do_output = |w: Word| chunker.put(w);
+    let mut cpu = new_processor(program)?;
+    cpu.run_with_io(&mut get_input, &mut do_output)?;
+    chunker.finish()?;
+    let blocks = screen.borrow().count(BLOCK);
+    Ok(blocks)
+}
+
+/// What the player has seen of the game: where the paddle and ball
+/// are, and how the ball is moving.
+pub struct Player {
+    pub bat: Position,
+    pub ball: Position,
+    /// How far the ball moved when it was last drawn, if it has been
+    /// seen to move.
+    pub ball_velocity: Option<Step>,
+    /// The x coordinate of the right-hand wall.
+    pub right_wall: i64,
+}
+
+impl Player {
+    /// A player who has seen the game so far, as shown on `screen`.
+    fn watching(screen: &Screen) -> Player {
+        let origin = Position { x: 0, y: 0 };
+        Player {
+            bat: screen.find(PADDLE).unwrap_or(origin),
+            ball: screen.find(BALL).unwrap_or(origin),
+            ball_velocity: None,
+            right_wall: screen.width() as i64 - 1,
+        }
+    }
+
+    fn observe(&mut self, update: DrawCommand) {
+        match update {
+            DrawCommand::DrawTile { pos, tile: PADDLE } => {
+                self.bat = pos;
+            }
+            DrawCommand::DrawTile { pos, tile: BALL } => {
+                self.ball_velocity = Some(Step::between(&self.ball, &pos));
+                self.ball = pos;
+            }
+            DrawCommand::DrawTile { pos, tile: WALL } => {
+                self.right_wall = self.right_wall.max(pos.x);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Decides which way to push the joystick.
+pub trait Strategy {
+    /// Returns -1 for left, 0 for neutral or 1 for right.
+    fn joystick(&self, player: &Player) -> Word;
+}
+
+/// The joystick position which moves the paddle towards `x`.
+fn towards(player: &Player, x: i64) -> Word {
+    Word((x - player.bat.x).signum() as WordInt)
+}
+
+/// Keeps the paddle under the ball.
+pub struct Reactive;
+
+impl Strategy for Reactive {
+    fn joystick(&self, player: &Player) -> Word {
+        towards(player, player.ball.x)
+    }
+}
+
+/// Moves the paddle to where the falling ball will land, allowing
+/// for bounces off the side walls but not off blocks.  While the
+/// ball is rising, it follows the ball like `Reactive`.
+pub struct Predictive;
+
+/// Folds `x` into `lo..=hi` as if it were reflected at each end.
+fn reflect(x: i64, lo: i64, hi: i64) -> i64 {
+    if hi <= lo {
+        return x;
+    }
+    let span = hi - lo;
+    let offset = (x - lo).rem_euclid(2 * span);
+    if offset > span {
+        hi - (offset - span)
+    } else {
+        lo + offset
+    }
+}
+
+impl Strategy for Predictive {
+    fn joystick(&self, player: &Player) -> Word {
+        let target = match player.ball_velocity {
+            Some(v) if v.dy > 0 && player.ball.y < player.bat.y => {
+                let steps = (player.bat.y - 1 - player.ball.y) / v.dy;
+                reflect(player.ball.x + v.dx * steps, 1, player.right_wall - 1)
+            }
+            _ => player.ball.x,
+        };
+        towards(player, target)
+    }
+}
+
+/// The names accepted by `strategy_named`.
+pub const STRATEGY_NAMES: [&str; 2] = ["reactive", "predictive"];
+
+pub fn strategy_named(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "reactive" => Some(Box::new(Reactive)),
+        "predictive" => Some(Box::new(Predictive)),
+        _ => None,
+    }
+}
+
+/// Shows a joystick position.
+pub fn indicator(joystick: Word) -> &'static str {
+    match joystick.0.cmp(&0) {
+        Ordering::Less => "<",
+        Ordering::Equal => "^",
+        Ordering::Greater => ">",
+    }
+}
+
+#[test]
+fn test_strategies() {
+    let mut player = Player {
+        bat: Position { x: 5, y: 20 },
+        ball: Position { x: 8, y: 10 },
+        ball_velocity: Some(Step { dx: 1, dy: 1 }),
+        right_wall: 12,
+    };
+    assert_eq!(Reactive.joystick(&player), Word(1));
+    // The ball reaches row 19 at x = 17, which is reflected from the
+    // right wall back to x = 5.
+    assert_eq!(reflect(17, 1, 11), 5);
+    assert_eq!(Predictive.joystick(&player), Word(0));
+    player.ball_velocity = Some(Step { dx: -1, dy: 1 });
+    assert_eq!(Predictive.joystick(&player), Word(-1));
+    // A rising ball is simply followed.
+    player.ball_velocity = Some(Step { dx: -1, dy: -1 });
+    assert_eq!(Predictive.joystick(&player), Word(1));
+    assert_eq!(indicator(Word(-1)), "<");
+}
+
+/// The choice made each time the game needs input.
+pub struct Move {
+    pub joystick: Word,
+    /// Shows the joystick position.
+    pub indicator: &'static str,
+    /// Stop and save the game.  This can only happen between draw
+    /// commands; at other times the request is ignored.
+    pub save: bool,
+}
+
+/// Decides how to move the joystick, and shows the game.
+pub trait Controller {
+    /// Choose the next move.  `screen` shows the game so far, and
+    /// `player` what is known about the paddle and ball.
+    fn next_move(&mut self, screen: &mut Screen, player: &Player) -> Move;
+
+    /// Called when the game is over.
+    fn finished(&mut self, _screen: &mut Screen) {}
+}
+
+/// Plays according to a `Strategy` without showing the game.
+pub struct Autopilot {
+    pub strategy: Box<dyn Strategy>,
+}
+
+impl Controller for Autopilot {
+    fn next_move(&mut self, _screen: &mut Screen, player: &Player) -> Move {
+        let joystick = self.strategy.joystick(player);
+        Move {
+            joystick,
+            indicator: indicator(joystick),
+            save: false,
+        }
+    }
+}
+
+/// A part 2 game in progress, which can be saved with `--save-state`
+/// and resumed with `--load-state`.
+pub struct SavedGame {
+    machine: MachineState,
+    screen: Screen,
+}
+
+const MACHINE_SECTION: &str = "[machine]\n";
+const SCREEN_SECTION: &str = "[screen]\n";
+
+impl SavedGame {
+    pub fn save(&self, path: &Path) -> Result<(), Fail> {
+        let text = format!(
+            "{}{}{}{}",
+            MACHINE_SECTION, self.machine, SCREEN_SECTION, self.screen
+        );
+        fs::write(path, text)
+            .map_err(|e| Fail(format!("failed to save game to {}: {}", path.display(), e)))
+    }
+
+    pub fn load(path: &Path) -> Result<SavedGame, Fail> {
+        let text = read_file_as_string(path)?;
+        match text
+            .strip_prefix(MACHINE_SECTION)
+            .and_then(|rest| rest.split_once(SCREEN_SECTION))
+        {
+            Some((machine, screen)) => Ok(SavedGame {
+                machine: machine.parse()?,
+                screen: screen.parse()?,
+            }),
+            None => Err(Fail(format!(
+                "{} does not contain a saved day 13 game",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Why a game was lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Loss {
+    /// The ball got past the paddle.
+    BallMissed,
+    /// No block was broken in this many moves, so the ball is
+    /// presumably stuck in a loop which never reaches the remaining
+    /// blocks.
+    NoProgress(u64),
+}
+
+impl Display for Loss {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Loss::BallMissed => f.write_str("the ball got past the paddle"),
+            Loss::NoProgress(moves) => write!(f, "no block was broken in {} moves", moves),
+        }
+    }
+}
+
+/// After this many moves without breaking a block, the game is
+/// considered lost.
+const IDLE_MOVE_LIMIT: u64 = 10_000;
+
+/// Notices when a game can no longer be won.
+struct Referee {
+    blocks: usize,
+    idle_moves: u64,
+}
+
+impl Referee {
+    fn new(screen: &Screen) -> Referee {
+        Referee {
+            blocks: screen.count(BLOCK),
+            idle_moves: 0,
+        }
+    }
+
+    /// Called each time the player is about to move.
+    fn check(&mut self, screen: &Screen, player: &Player) -> Option<Loss> {
+        if player.ball.y > player.bat.y {
+            return Some(Loss::BallMissed);
+        }
+        let blocks = screen.count(BLOCK);
+        if blocks == self.blocks {
+            self.idle_moves += 1;
+        } else {
+            self.blocks = blocks;
+            self.idle_moves = 0;
+        }
+        if self.idle_moves >= IDLE_MOVE_LIMIT {
+            Some(Loss::NoProgress(self.idle_moves))
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_draw_off_screen() {
+    let screen = RefCell::new(Screen::new());
+    let mut draw = draw_on(&screen, |_| ());
+    assert_eq!(draw([Word(2), Word(3), Word(BLOCK as WordInt)]), Ok(()));
+    let rejected = draw([Word(2), Word(-3), Word(BLOCK as WordInt)]);
+    assert_eq!(
+        rejected,
+        Err(InputOutputError::OffScreen {
+            x: Word(2),
+            y: Word(-3)
+        })
+    );
+    assert_eq!(
+        rejected.unwrap_err().to_string(),
+        "cannot draw at 2,-3 as it is off the screen"
+    );
+}
+
+#[test]
+fn test_referee() {
+    let mut screen = Screen::new();
+    for x in 0..3 {
+        screen
+            .apply(DrawCommand::DrawTile {
+                pos: Position { x, y: 1 },
+                tile: BLOCK,
+            })
+            .expect("the block should fit on the screen");
+    }
+    let mut player = Player {
+        bat: Position { x: 5, y: 20 },
+        ball: Position { x: 8, y: 10 },
+        ball_velocity: None,
+        right_wall: 12,
+    };
+    let mut referee = Referee::new(&screen);
+    for _ in 1..IDLE_MOVE_LIMIT {
+        assert_eq!(referee.check(&screen, &player), None);
+    }
+    assert_eq!(
+        referee.check(&screen, &player),
+        Some(Loss::NoProgress(IDLE_MOVE_LIMIT))
+    );
+    // Breaking a block restarts the count.
+    screen
+        .apply(DrawCommand::DrawTile {
+            pos: Position { x: 0, y: 1 },
+            tile: EMPTY,
+        })
+        .expect("the position is on the screen");
+    assert_eq!(referee.check(&screen, &player), None);
+    player.ball.y = 21;
+    assert_eq!(referee.check(&screen, &player), Some(Loss::BallMissed));
+}
+
+pub enum Outcome {
+    /// The game ended with this score.
+    Finished(i64),
+    /// The controller asked for the game to be saved.
+    Saved(SavedGame),
+    /// The game was lost (or could not be won) with this score.
+    Lost(i64, Loss),
+}
+
+/// Play the game with a coin inserted, or resume the `saved` game.
+pub fn play<C: Controller>(
+    program: &[Word],
+    saved: Option<SavedGame>,
+    controller: &mut C,
+) -> Result<Outcome, Fail> {
+    let mut cpu = new_processor(program)?;
+    let screen = match saved {
+        Some(game) => {
+            cpu.restore(&game.machine)?;
+            game.screen
+        }
+        None => {
+            cpu.poke(Word(0), Word(2))?; // insert coin.
+            Screen::new()
+        }
+    };
+    let player = RefCell::new(Player::watching(&screen));
+    let screen = RefCell::new(screen);
+
+    let mut referee = Referee::new(&screen.borrow());
+    let mut chunker = OutputChunker::new(draw_on(&screen, |cmd| player.borrow_mut().observe(cmd)));
+    let mut loss: Option<Loss> = None;
+    loop {
+        match cpu.run_until_blocked()? {
+            MachineEvent::ProducedOutput(w) => {
+                chunker.put(w).map_err(CpuFault::IOError)?;
+            }
+            MachineEvent::AwaitingInput => {
+                loss = referee.check(&screen.borrow(), &player.borrow());
+                if loss.is_some() {
+                    break;
+                }
+                let choice = controller.next_move(&mut screen.borrow_mut(), &player.borrow());
+                // We can only save the game between draw commands.
+                if choice.save && chunker.pending().is_empty() {
+                    return Ok(Outcome::Saved(SavedGame {
+                        machine: cpu.snapshot(),
+                        screen: screen.borrow().clone(),
+                    }));
+                }
+                cpu.provide_input(choice.joystick);
+            }
+            MachineEvent::Halted => {
+                // The program stops when the ball is missed.
+                if screen.borrow().count(BLOCK) > 0 {
+                    loss = Some(Loss::BallMissed);
+                }
+                break;
+            }
+            MachineEvent::BreakpointHit(_) | MachineEvent::WatchpointHit(_) => {
+                unreachable!("there are no breakpoints or watchpoints")
+            }
+        }
+    }
+    if loss.is_none() {
+        chunker.finish()?;
+    }
+    controller.finished(&mut screen.borrow_mut());
+    let score = screen.borrow().score().unwrap_or(0);
+    match loss {
+        Some(loss) => Ok(Outcome::Lost(score, loss)),
+        None => Ok(Outcome::Finished(score)),
+    }
+}
+
+/// Part 1 is the number of blocks on the screen when the game
+/// starts, and part 2 the score when the computer has broken all of
+/// them.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    let blocks = part1(&program)?;
+    let mut autopilot = Autopilot {
+        strategy: Box::new(Reactive),
+    };
+    match play(&program, None, &mut autopilot)? {
+        Outcome::Finished(score) => Ok((blocks.to_string(), Some(score.to_string()))),
+        Outcome::Saved(_) => Err(Fail("the autopilot should not save the game".to_string())),
+        Outcome::Lost(score, loss) => {
+            Err(Fail(format!("game lost with score {}: {}", score, loss)))
+        }
+    }
+}
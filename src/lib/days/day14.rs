@@ -0,0 +1,634 @@
+//! Day 14: Space Stoichiometry.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::{Fail, ResultExt};
+use crate::parse::{
+    parse_fragment, parse_separated_list, split_arrow, split_once_parsed, BadFragment,
+};
+use crate::verbose;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Chemical(String);
+
+impl Chemical {
+    fn new(s: &str) -> Chemical {
+        Chemical(s.to_string())
+    }
+
+    fn is_ore(&self) -> bool {
+        self.0.as_str() == "ORE"
+    }
+}
+
+impl Display for Chemical {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(self.0.as_str())
+    }
+}
+
+pub type Quantity = i64;
+
+#[derive(Debug)]
+struct Reagent {
+    quantity: Quantity,
+    chemical: Chemical,
+}
+
+impl Display for Reagent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.quantity, self.chemical.0.as_str())
+    }
+}
+
+impl FromStr for Reagent {
+    type Err = BadFragment;
+
+    /// Parse a reagent such as "7 A".
+    fn from_str(s: &str) -> Result<Reagent, BadFragment> {
+        let (quantity, chemical) = split_once_parsed(s.trim(), " ")?;
+        Ok(Reagent {
+            quantity,
+            chemical: Chemical(chemical),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Recipe {
+    inputs: Vec<Reagent>,
+    output: Reagent,
+}
+
+impl Recipe {
+    fn multiplier_to_produce(&self, quantity: &Quantity) -> i64 {
+        let d = self.output.quantity;
+        (quantity + d - 1) / d
+    }
+}
+
+#[test]
+fn test_multiplier_to_produce() {
+    let r1 = Recipe {
+        inputs: vec![Reagent {
+            quantity: 9,
+            chemical: Chemical("ORE".to_string()),
+        }],
+        output: Reagent {
+            quantity: 2,
+            chemical: Chemical("A".to_string()),
+        },
+    };
+    assert_eq!(5, r1.multiplier_to_produce(&10));
+    assert_eq!(6, r1.multiplier_to_produce(&11));
+}
+
+impl FromStr for Recipe {
+    type Err = BadFragment;
+
+    /// Parse a recipe such as "7 A, 1 B => 1 C".
+    fn from_str(s: &str) -> Result<Recipe, BadFragment> {
+        let (inputs, output) = split_arrow(s)?;
+        Ok(Recipe {
+            inputs: parse_separated_list(inputs, ",")?,
+            output: parse_fragment(output)?,
+        })
+    }
+}
+
+#[cfg(test)]
+fn parse_recipes<S: AsRef<str>>(input: &[S]) -> Result<Vec<Recipe>, BadFragment> {
+    input.iter().map(|s| s.as_ref().parse()).collect()
+}
+
+fn make_recipe_map(recipes: Vec<Recipe>) -> HashMap<Chemical, Recipe> {
+    let mut result = HashMap::new();
+    for recipe in recipes.into_iter() {
+        result.insert(recipe.output.chemical.to_owned(), recipe);
+    }
+    result.insert(
+        Chemical::new("ORE"),
+        Recipe {
+            // You "make" ORE from nothing.
+            inputs: Vec::with_capacity(0),
+            output: Reagent {
+                quantity: 1,
+                chemical: Chemical::new("ORE"),
+            },
+        },
+    );
+    result
+}
+
+/// Works out how much ore is needed to make a given amount of fuel.
+///
+/// The chemicals are ordered by their distance from ORE (that is,
+/// the length of the longest chain of recipes which leads from ORE to
+/// them).  Every chemical which consumes some chemical X is further
+/// from ORE than X is, so by working through the chemicals from the
+/// furthest to the nearest, we know the total demand for each
+/// chemical before we decide how many times to run its recipe.
+struct Factory<'a> {
+    mapping: &'a HashMap<Chemical, Recipe>,
+    /// Every chemical, furthest from ORE first.
+    order: Vec<Chemical>,
+}
+
+impl<'a> Factory<'a> {
+    /// Fails if the recipes are cyclic or if some chemical has no
+    /// recipe.
+    fn new(mapping: &'a HashMap<Chemical, Recipe>) -> Result<Factory<'a>, String> {
+        let mut distance: HashMap<Chemical, usize> = HashMap::new();
+        let mut in_progress: Vec<Chemical> = Vec::new();
+        for chemical in mapping.keys() {
+            distance_from_ore(chemical, mapping, &mut distance, &mut in_progress)?;
+        }
+        let mut order: Vec<Chemical> = distance.keys().cloned().collect();
+        order.sort_by(|a, b| distance[b].cmp(&distance[a]).then_with(|| b.0.cmp(&a.0)));
+        Ok(Factory { mapping, order })
+    }
+
+    fn plan(&self, fuel_demand: Quantity) -> ProductionPlan {
+        let fuel = Chemical::new("FUEL");
+        let mut demand: HashMap<&Chemical, Quantity> = HashMap::new();
+        demand.insert(&fuel, fuel_demand);
+        let mut steps: Vec<PlanStep> = Vec::new();
+        for chemical in self.order.iter().filter(|c| !c.is_ore()) {
+            let needed: Quantity = demand.get(chemical).copied().unwrap_or(0);
+            if needed == 0 {
+                continue;
+            }
+            let recipe = &self.mapping[chemical];
+            let multiplier = recipe.multiplier_to_produce(&needed);
+            for input in recipe.inputs.iter() {
+                *demand.entry(&input.chemical).or_insert(0) += input.quantity * multiplier;
+            }
+            let produced = recipe.output.quantity * multiplier;
+            steps.push(PlanStep {
+                chemical: chemical.clone(),
+                runs: multiplier,
+                produced,
+                consumed: needed,
+                left_over: produced - needed,
+            });
+        }
+        ProductionPlan {
+            fuel: fuel_demand,
+            ore: demand.get(&Chemical::new("ORE")).copied().unwrap_or(0),
+            steps,
+        }
+    }
+
+    fn ore_cost_of_fuel(&self, fuel_demand: Quantity) -> Quantity {
+        self.plan(fuel_demand).ore
+    }
+}
+
+/// How many times one recipe is run in a `ProductionPlan`.
+struct PlanStep {
+    chemical: Chemical,
+    runs: Quantity,
+    produced: Quantity,
+    /// The amount of the chemical used by later steps (or, for FUEL,
+    /// the amount we wanted).
+    consumed: Quantity,
+    left_over: Quantity,
+}
+
+/// The reactions needed to produce some fuel, in the order they must
+/// happen (FUEL last).
+pub struct ProductionPlan {
+    fuel: Quantity,
+    ore: Quantity,
+    steps: Vec<PlanStep>,
+}
+
+impl ProductionPlan {
+    pub fn to_json(&self) -> String {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .rev()
+            .map(|step| {
+                format!(
+                    concat!(
+                        "{{\"chemical\":\"{}\",\"runs\":{},\"produced\":{},",
+                        "\"consumed\":{},\"left_over\":{}}}"
+                    ),
+                    step.chemical.0.escape_default(),
+                    step.runs,
+                    step.produced,
+                    step.consumed,
+                    step.left_over
+                )
+            })
+            .collect();
+        format!(
+            "{{\"fuel\":{},\"ore\":{},\"steps\":[{}]}}",
+            self.fuel,
+            self.ore,
+            steps.join(",")
+        )
+    }
+}
+
+/// Shows the plan as a table.
+impl Display for ProductionPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Producing {} FUEL uses {} ORE in these reactions:",
+            self.fuel, self.ore
+        )?;
+        writeln!(
+            f,
+            "{:<10} {:>12} {:>14} {:>14} {:>10}",
+            "chemical", "runs", "produced", "consumed", "left over"
+        )?;
+        for step in self.steps.iter().rev() {
+            writeln!(
+                f,
+                "{:<10} {:>12} {:>14} {:>14} {:>10}",
+                step.chemical, step.runs, step.produced, step.consumed, step.left_over
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compute the distance of `chemical` from ORE, recording it (and
+/// the distances of the chemicals it is made from) in `distance`.
+/// `in_progress` holds the chain of chemicals whose distance we are
+/// currently computing; if `chemical` is already in it, the recipes
+/// are cyclic.
+fn distance_from_ore(
+    chemical: &Chemical,
+    mapping: &HashMap<Chemical, Recipe>,
+    distance: &mut HashMap<Chemical, usize>,
+    in_progress: &mut Vec<Chemical>,
+) -> Result<usize, String> {
+    if let Some(d) = distance.get(chemical) {
+        return Ok(*d);
+    }
+    if let Some(pos) = in_progress.iter().position(|c| c == chemical) {
+        let cycle: Vec<String> = in_progress[pos..]
+            .iter()
+            .chain(std::iter::once(chemical))
+            .map(|c| c.to_string())
+            .collect();
+        return Err(format!("recipes are cyclic: {}", cycle.join(" <- ")));
+    }
+    let recipe = match mapping.get(chemical) {
+        Some(recipe) => recipe,
+        None => {
+            return Err(format!("Need {} but there is no way to make it", chemical));
+        }
+    };
+    in_progress.push(chemical.clone());
+    let mut result: usize = 0;
+    for input in recipe.inputs.iter() {
+        result =
+            result.max(1 + distance_from_ore(&input.chemical, mapping, distance, in_progress)?);
+    }
+    in_progress.pop();
+    distance.insert(chemical.clone(), result);
+    Ok(result)
+}
+
+fn ore_cost_of_fuel(
+    fuel_demand: Quantity,
+    mapping: &HashMap<Chemical, Recipe>,
+) -> Result<Quantity, String> {
+    Ok(Factory::new(mapping)?.ore_cost_of_fuel(fuel_demand))
+}
+
+fn solve1(mapping: &HashMap<Chemical, Recipe>) -> Result<Quantity, String> {
+    ore_cost_of_fuel(1, mapping)
+}
+
+#[test]
+fn test_solve1_example1() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "9 ORE => 2 A",
+        "8 ORE => 3 B",
+        "7 ORE => 5 C",
+        "3 A, 4 B => 1 AB",
+        "5 B, 7 C => 1 BC",
+        "4 C, 1 A => 1 CA",
+        "2 AB, 3 BC, 4 CA => 1 FUEL",
+    ])
+    .expect("example 1 should be valid");
+    let mapping = make_recipe_map(recipes);
+    assert_eq!(solve1(&mapping), Ok(165));
+}
+
+#[test]
+fn test_solve1_example2() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "157 ORE => 5 NZVS",
+        "165 ORE => 6 DCFZ",
+        "44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL",
+        "12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ",
+        "179 ORE => 7 PSHF",
+        "177 ORE => 5 HKGWZ",
+        "7 DCFZ, 7 PSHF => 2 XJWVT",
+        "165 ORE => 2 GPVTF",
+        "3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT",
+    ])
+    .expect("part 1 example 2 should be valid");
+    let mapping = make_recipe_map(recipes);
+    assert_eq!(solve1(&mapping), Ok(13312));
+}
+
+#[test]
+fn test_solve1_example3() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG",
+        "17 NVRVD, 3 JNWZP => 8 VPVL",
+        "53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL",
+        "22 VJHF, 37 MNCFX => 5 FWMGM",
+        "139 ORE => 4 NVRVD",
+        "144 ORE => 7 JNWZP",
+        "5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC",
+        "5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV",
+        "145 ORE => 6 MNCFX",
+        "1 NVRVD => 8 CXFTF",
+        "1 VJHF, 6 MNCFX => 4 RFSQX",
+        "176 ORE => 6 VJHF",
+    ])
+    .expect("part 1 example 3 should be valid");
+    let mapping = make_recipe_map(recipes);
+    assert_eq!(solve1(&mapping), Ok(180697));
+}
+
+#[test]
+fn test_solve1_example4() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "171 ORE => 8 CNZTR",
+        "7 ZLQW, 3 BMBT, 9 XCVML, 26 XMNCP, 1 WPTQ, 2 MZWV, 1 RJRHP => 4 PLWSL",
+        "114 ORE => 4 BHXH",
+        "14 VRPVC => 6 BMBT",
+        "6 BHXH, 18 KTJDG, 12 WPTQ, 7 PLWSL, 31 FHTLT, 37 ZDVW => 1 FUEL",
+        "6 WPTQ, 2 BMBT, 8 ZLQW, 18 KTJDG, 1 XMNCP, 6 MZWV, 1 RJRHP => 6 FHTLT",
+        "15 XDBXC, 2 LTCX, 1 VRPVC => 6 ZLQW",
+        "13 WPTQ, 10 LTCX, 3 RJRHP, 14 XMNCP, 2 MZWV, 1 ZLQW => 1 ZDVW",
+        "5 BMBT => 4 WPTQ",
+        "189 ORE => 9 KTJDG",
+        "1 MZWV, 17 XDBXC, 3 XCVML => 2 XMNCP",
+        "12 VRPVC, 27 CNZTR => 2 XDBXC",
+        "15 KTJDG, 12 BHXH => 5 XCVML",
+        "3 BHXH, 2 VRPVC => 7 MZWV",
+        "121 ORE => 7 VRPVC",
+        "7 XCVML => 6 RJRHP",
+        "5 BHXH, 4 VRPVC => 5 LTCX",
+    ])
+    .expect("part 1 example 4 should be valid");
+    let mapping = make_recipe_map(recipes);
+    assert_eq!(solve1(&mapping), Ok(2210736));
+}
+
+#[test]
+fn test_cyclic_recipes() {
+    let recipes: Vec<Recipe> = parse_recipes(&["1 ORE, 1 B => 1 A", "1 A => 1 B", "1 A => 1 FUEL"])
+        .expect("recipes should be syntactically valid");
+    let mapping = make_recipe_map(recipes);
+    match solve1(&mapping) {
+        Err(msg) => assert!(msg.starts_with("recipes are cyclic"), "{}", msg),
+        Ok(n) => panic!("expected cyclic recipes to be rejected, got {}", n),
+    }
+}
+
+#[test]
+fn test_plan() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "10 ORE => 10 A",
+        "1 ORE => 1 B",
+        "7 A, 1 B => 1 C",
+        "7 A, 1 C => 1 D",
+        "7 A, 1 D => 1 E",
+        "7 A, 1 E => 1 FUEL",
+    ])
+    .expect("example should be valid");
+    let mapping = make_recipe_map(recipes);
+    let factory = Factory::new(&mapping).expect("recipes should be solvable");
+    let plan = factory.plan(1);
+    assert_eq!(plan.ore, 31);
+    let a = plan
+        .steps
+        .iter()
+        .find(|step| step.chemical.0 == "A")
+        .expect("A should be made");
+    assert_eq!(
+        (a.runs, a.produced, a.consumed, a.left_over),
+        (3, 30, 28, 2)
+    );
+    assert!(plan
+        .to_json()
+        .starts_with(r#"{"fuel":1,"ore":31,"steps":[{"chemical":"A","runs":3,"#));
+}
+
+fn midpoint(lower: i64, upper: i64) -> i64 {
+    let width = upper.checked_sub(lower).unwrap_or(i64::MAX);
+    lower + width / 2
+}
+
+fn open_ended_binary_search<P>(
+    mut lower: i64,
+    mut upper: Option<i64>,
+    test: P,
+) -> Result<i64, String>
+where
+    P: Fn(i64) -> Ordering,
+{
+    let mut guess = lower;
+    loop {
+        let previous_guess = guess;
+        let comparison_result = test(guess);
+        match comparison_result {
+            Ordering::Less => {
+                // needle is less than guess; i.e. in the range [lower, guess)
+                upper = Some(guess);
+                guess = midpoint(lower, guess);
+                if guess == previous_guess {
+                    match lower.checked_sub(1) {
+                        Some(n) => {
+                            return Ok(n);
+                        }
+                        None => {
+                            return Err(format!(
+				"predicate had returned Less for guess {} but there are no lower representable numbers",
+				lower
+			    ));
+                        }
+                    }
+                }
+            }
+            Ordering::Equal => {
+                return Ok(guess);
+            }
+            Ordering::Greater => {
+                if let Some(u) = upper {
+                    // needle is greater than guess; i.e. in the range [guess+1, u)
+                    lower = match guess.checked_add(1) {
+                        Some(n) => n,
+                        None => {
+                            return Err(format!("predicate had returned Greater for guess {} but there are no higher representable numbers",
+					       guess));
+                        }
+                    };
+                    guess = midpoint(lower, u);
+                    if guess == previous_guess {
+                        return Ok(u);
+                    }
+                } else {
+                    // needle is greater than guess
+                    lower = guess;
+                    guess = guess.checked_mul(2).unwrap_or(i64::MAX);
+                }
+            }
+        }
+        assert!(guess != previous_guess, "got stuck at {}", guess);
+    }
+}
+
+#[cfg(test)]
+fn check_can_guess_number(goal: i64) {
+    let check = |guess: i64| -> Ordering { goal.cmp(&guess) };
+    let solution = open_ended_binary_search(i64::MIN, None, check);
+    assert_eq!(solution, Ok(goal), "failed to guess {}", goal);
+}
+
+#[test]
+fn test_open_ended_binary_search_exact() {
+    check_can_guess_number(1);
+    check_can_guess_number(2);
+    check_can_guess_number(3);
+    check_can_guess_number(15);
+    check_can_guess_number(16);
+    check_can_guess_number(17);
+    check_can_guess_number(100);
+    check_can_guess_number(1000000);
+    check_can_guess_number(i64::MAX - 1);
+    check_can_guess_number(i64::MAX);
+}
+
+#[cfg(test)]
+fn check_can_guess_number_and_a_half(goal: i64) {
+    let check = |guess: i64| -> Ordering {
+        match goal.cmp(&guess) {
+            Ordering::Equal => Ordering::Greater,
+            other => other,
+        }
+    };
+    let solution = open_ended_binary_search(1, None, check);
+    assert_eq!(solution, Ok(goal), "failed to guess {}½", goal);
+}
+
+#[test]
+fn test_open_ended_binary_search_inexact() {
+    check_can_guess_number_and_a_half(1);
+    check_can_guess_number_and_a_half(2);
+    check_can_guess_number_and_a_half(3);
+    check_can_guess_number_and_a_half(15);
+    check_can_guess_number_and_a_half(16);
+    check_can_guess_number_and_a_half(17);
+    check_can_guess_number_and_a_half(100);
+    check_can_guess_number_and_a_half(1000000);
+    check_can_guess_number_and_a_half(i64::MAX - 1);
+}
+
+fn solve2(mapping: &HashMap<Chemical, Recipe>) -> Result<Quantity, String> {
+    const ONE_TRILLION: Quantity = 1_000_000_000_000;
+    let factory = Factory::new(mapping)?;
+    let check = |fuel: Quantity| -> Ordering {
+        let required_ore = factory.ore_cost_of_fuel(fuel);
+        verbose!(
+            "Producing {} units of fuel requires {} ore",
+            fuel,
+            required_ore
+        );
+        match required_ore.cmp(&ONE_TRILLION) {
+            Ordering::Greater => Ordering::Less,
+            Ordering::Equal => Ordering::Equal,
+            Ordering::Less => Ordering::Greater,
+        }
+    };
+    open_ended_binary_search(1, None, check)
+}
+
+#[test]
+fn test_solve2_example2() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "157 ORE => 5 NZVS",
+        "165 ORE => 6 DCFZ",
+        "44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL",
+        "12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ",
+        "179 ORE => 7 PSHF",
+        "177 ORE => 5 HKGWZ",
+        "7 DCFZ, 7 PSHF => 2 XJWVT",
+        "165 ORE => 2 GPVTF",
+        "3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT",
+    ])
+    .expect("part 2 example 2 should be valid");
+    let mapping = make_recipe_map(recipes);
+    assert_eq!(solve2(&mapping), Ok(82892753));
+}
+
+#[test]
+fn test_solve2_example3() {
+    let recipes: Vec<Recipe> = parse_recipes(&[
+        "2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG",
+        "17 NVRVD, 3 JNWZP => 8 VPVL",
+        "53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL",
+        "22 VJHF, 37 MNCFX => 5 FWMGM",
+        "139 ORE => 4 NVRVD",
+        "144 ORE => 7 JNWZP",
+        "5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC",
+        "5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV",
+        "145 ORE => 6 MNCFX",
+        "1 NVRVD => 8 CXFTF",
+        "1 VJHF, 6 MNCFX => 4 RFSQX",
+        "176 ORE => 6 VJHF",
+    ])
+    .expect("part 1 example 3 should be valid");
+    let mapping = make_recipe_map(recipes);
+    assert_eq!(solve2(&mapping), Ok(5586022));
+}
+
+/// Parse the recipes in `input`, one per line.
+fn parse_recipe_map(input: &str) -> Result<HashMap<Chemical, Recipe>, Fail> {
+    let mut recipes: Vec<Recipe> = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        recipes.push(
+            line.parse()
+                .map_err(|e: BadFragment| e.in_line(i + 1, line))?,
+        );
+    }
+    Ok(make_recipe_map(recipes))
+}
+
+fn unsolvable(msg: String) -> Fail {
+    Fail(format!("cannot make fuel: {}", msg))
+}
+
+/// The reactions needed to make `fuel` units of fuel using the
+/// recipes in `input`.
+pub fn production_plan(input: &str, fuel: Quantity) -> Result<ProductionPlan, Fail> {
+    let mapping = parse_recipe_map(input).context("failed to load the recipes")?;
+    let factory = Factory::new(&mapping).map_err(unsolvable)?;
+    Ok(factory.plan(fuel))
+}
+
+/// Part 1 is the ore needed to make one unit of fuel, and part 2 the
+/// most fuel which can be made from a trillion units of ore.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let mapping = parse_recipe_map(input).context("failed to load the recipes")?;
+    let part1 = solve1(&mapping).map_err(unsolvable)?;
+    let part2 = solve2(&mapping).map_err(unsolvable)?;
+    Ok((part1.to_string(), Some(part2.to_string())))
+}
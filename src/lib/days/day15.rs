@@ -0,0 +1,564 @@
+//! Day 15: Oxygen System.
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::thread;
+
+use crate::cpu::Word;
+use crate::cpu::{parse_program, CpuFault, Machine};
+use crate::error::Fail;
+use crate::grid;
+use crate::threads;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum RoomType {
+    Wall,
+    Open(bool),
+    Goal,
+    Start,
+}
+
+impl From<RoomType> for char {
+    fn from(rt: RoomType) -> char {
+        match rt {
+            RoomType::Start => 'S',
+            RoomType::Wall => '#',
+            RoomType::Open(filled) => {
+                if filled {
+                    'O'
+                } else {
+                    '.'
+                }
+            }
+            RoomType::Goal => 'X',
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BadMap(String);
+
+impl Display for BadMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad map: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadMap {}
+
+impl TryFrom<char> for RoomType {
+    type Error = BadMap;
+    fn try_from(ch: char) -> Result<RoomType, BadMap> {
+        match ch {
+            'S' => Ok(RoomType::Start),
+            '#' => Ok(RoomType::Wall),
+            'O' => Ok(RoomType::Open(true)),
+            '.' => Ok(RoomType::Open(false)),
+            ' ' => Ok(RoomType::Wall),
+            'X' => Ok(RoomType::Goal),
+            _ => Err(BadMap(format!("unexpected character '{}'", ch))),
+        }
+    }
+}
+
+/// A path through the ship, as a sequence of steps.
+#[derive(Clone, Debug)]
+pub struct Movements {
+    steps: Vec<CompassDirection>,
+}
+
+impl Movements {
+    pub fn empty() -> Movements {
+        Movements { steps: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    fn push_step(&mut self, step: &CompassDirection) {
+        self.steps.push(*step);
+    }
+
+    /// The positions visited by following the path from `origin`,
+    /// including `origin` itself.
+    pub fn compute_path_locations(&self, origin: &Position) -> Vec<Position> {
+        self.steps
+            .iter()
+            .fold((*origin, vec![*origin]), |(here, mut path), direction| {
+                let next = here.move_direction(direction);
+                path.push(next);
+                (next, path)
+            })
+            .1
+    }
+}
+
+/// Shows the path as a string of compass directions, such as "NNWS".
+impl Display for Movements {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.steps
+            .iter()
+            .try_for_each(|step| write!(f, "{}", char::from(*step)))
+    }
+}
+
+/// Reads a path written by `Display`.  Whitespace is ignored.
+impl FromStr for Movements {
+    type Err = BadDirection;
+
+    fn from_str(s: &str) -> Result<Movements, BadDirection> {
+        let steps = s
+            .chars()
+            .filter(|ch| !ch.is_whitespace())
+            .map(CompassDirection::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Movements { steps })
+    }
+}
+
+#[test]
+fn test_movements_text() {
+    let path: Movements = "NNE W".parse().expect("valid path");
+    assert_eq!(path.len(), 4);
+    assert_eq!(path.to_string(), "NNEW");
+    assert_eq!(
+        path.compute_path_locations(&Position { x: 0, y: 0 }).last(),
+        Some(&Position { x: 0, y: -2 })
+    );
+    assert_eq!("NX".parse::<Movements>().err(), Some(BadDirection('X')));
+}
+
+use grid::{BadDirection, CompassDirection, Position, ALL_MOVE_OPTIONS};
+
+pub struct ShipMap {
+    tiles: HashMap<grid::Position, RoomType>,
+    goal: Option<Position>,
+}
+
+impl ShipMap {
+    pub fn new(start: Position) -> ShipMap {
+        let mut tiles = HashMap::new();
+        tiles.insert(start, RoomType::Start);
+        ShipMap { tiles, goal: None }
+    }
+
+    fn add_location(&mut self, pos: Position, t: RoomType) {
+        if t == RoomType::Goal {
+            self.goal = Some(pos);
+        }
+        self.tiles.insert(pos, t);
+    }
+
+    fn oxygen_fill(&mut self, pos: Position) {
+        if let Some(RoomType::Open(filled)) = self.tiles.get_mut(&pos) {
+            *filled = true;
+        }
+    }
+
+    /// The location of the oxygen system, if it has been found.
+    pub fn goal(&self) -> Option<Position> {
+        self.goal
+    }
+
+    pub fn get_location_type(&self, pos: &Position) -> Option<&RoomType> {
+        match self.goal.as_ref() {
+            Some(g) if g == pos => Some(&RoomType::Goal),
+            _ => self.tiles.get(pos),
+        }
+    }
+
+    fn get_open_rooms(&self) -> HashSet<Position> {
+        self.tiles
+            .iter()
+            .filter_map(|(pos, room_type)| match room_type {
+                RoomType::Start | RoomType::Open(_) | RoomType::Goal => Some(*pos),
+                RoomType::Wall => None,
+            })
+            .collect()
+    }
+
+    fn options_from(&self, pos: &Position) -> Vec<CompassDirection> {
+        ALL_MOVE_OPTIONS
+            .iter()
+            .filter(|direction| !self.tiles.contains_key(&pos.move_direction(direction)))
+            .copied()
+            .collect()
+    }
+}
+
+impl Display for ShipMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match grid::bounds(self.tiles.keys().chain(self.goal.iter())) {
+            Some((min, max)) => {
+                for y in min.y..=max.y {
+                    let row: String = (min.x..=max.x)
+                        .map(|x: i64| -> char {
+                            let here = Position { x, y };
+                            if x == 0 && y == 0 {
+                                '@' // the droid
+                            } else {
+                                self.get_location_type(&here)
+                                    .map(|t| (*t).into())
+                                    .unwrap_or(' ')
+                            }
+                        })
+                        .collect();
+                    writeln!(f, "{}", row)?;
+                }
+                Ok(())
+            }
+            None => {
+                // Empty; nothing to display.
+                Ok(())
+            }
+        }
+    }
+}
+
+impl TryFrom<&str> for ShipMap {
+    type Error = BadMap;
+    fn try_from(s: &str) -> Result<ShipMap, BadMap> {
+        let mut result = ShipMap::new(Position { x: 0, y: 0 });
+        for (y, line) in s.split('\n').enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                let t: RoomType = RoomType::try_from(ch)?;
+                result.add_location(
+                    Position {
+                        x: x as i64,
+                        y: y as i64,
+                    },
+                    t,
+                );
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Clone)]
+pub struct RepairDroid {
+    machine: Machine,
+}
+
+impl RepairDroid {
+    pub fn new(program: &[Word]) -> Result<RepairDroid, CpuFault> {
+        Ok(RepairDroid {
+            machine: Machine::new(program)?,
+        })
+    }
+
+    /// Try to move the droid one step.  Returns the type of the room
+    /// in that direction (the droid only moves if it is not a wall),
+    /// or None if the droid's program halted.
+    fn move_droid(&mut self, which_way: &CompassDirection) -> Result<Option<RoomType>, CpuFault> {
+        let input_word: Word = match which_way {
+            CompassDirection::North => Word(1),
+            CompassDirection::South => Word(2),
+            CompassDirection::West => Word(3),
+            CompassDirection::East => Word(4),
+        };
+        match self.machine.run_until_output(input_word)? {
+            None if self.machine.is_halted() => Ok(None),
+            None => {
+                panic!("RepairDroid::move: program wanted more input without replying");
+            }
+            Some(Word(0)) => Ok(Some(RoomType::Wall)),
+            Some(Word(1)) => Ok(Some(RoomType::Open(false))),
+            Some(Word(2)) => Ok(Some(RoomType::Goal)),
+            Some(other) => {
+                panic!("program generated unexpected output {}", other);
+            }
+        }
+    }
+}
+
+/// A droid which has reached `position` by following `path` from
+/// the start.
+struct Explorer {
+    position: Position,
+    path: Movements,
+    droid: RepairDroid,
+}
+
+/// What an explorer found when it tried to move one step.  If the
+/// step was possible, `explorer` is a fork of the original droid
+/// which has taken it.
+struct Discovery {
+    target: Position,
+    room: RoomType,
+    explorer: Option<Explorer>,
+}
+
+fn explore_from(
+    explorer: &Explorer,
+    directions: &[CompassDirection],
+) -> Result<Vec<Discovery>, CpuFault> {
+    let mut result = Vec::with_capacity(directions.len());
+    for direction in directions {
+        let target = explorer.position.move_direction(direction);
+        let mut droid = explorer.droid.clone();
+        match droid.move_droid(direction)? {
+            None => {
+                panic!("droid CPU halted during move");
+            }
+            Some(RoomType::Wall) => {
+                result.push(Discovery {
+                    target,
+                    room: RoomType::Wall,
+                    explorer: None,
+                });
+            }
+            Some(room) => {
+                let mut path = explorer.path.clone();
+                path.push_step(direction);
+                result.push(Discovery {
+                    target,
+                    room,
+                    explorer: Some(Explorer {
+                        position: target,
+                        path,
+                        droid,
+                    }),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Try every unexplored direction from each cell of the frontier.
+/// The explorers are independent of each other, so they run in
+/// parallel.
+fn explore_frontier(frontier: &[Explorer], ship_map: &ShipMap) -> Result<Vec<Discovery>, CpuFault> {
+    let jobs: Vec<(&Explorer, Vec<CompassDirection>)> = frontier
+        .iter()
+        .map(|explorer| (explorer, ship_map.options_from(&explorer.position)))
+        .collect();
+    let chunk_size: usize = jobs.len().div_ceil(threads::thread_count()).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || -> Result<Vec<Discovery>, CpuFault> {
+                    let mut found = Vec::new();
+                    for (explorer, directions) in chunk {
+                        found.extend(explore_from(explorer, directions)?);
+                    }
+                    Ok(found)
+                })
+            })
+            .collect();
+        let mut result = Vec::new();
+        for handle in handles {
+            result.extend(handle.join().expect("explorer thread panicked")?);
+        }
+        Ok(result)
+    })
+}
+
+/// Map the whole ship by breadth-first search, forking the droid at
+/// each open cell instead of walking a single droid back and forth.
+/// Returns the map and the shortest path to the goal (if there is
+/// one).  `show` is called with the map so far, and the path to the
+/// goal if it has been found, after each step of the search.
+pub fn explore<F>(
+    start: &Position,
+    droid: RepairDroid,
+    mut show: F,
+) -> Result<(ShipMap, Option<Movements>), CpuFault>
+where
+    F: FnMut(&ShipMap, Option<&Movements>),
+{
+    let mut ship_map = ShipMap::new(*start);
+    let mut path_to_goal: Option<Movements> = None;
+    let mut frontier: Vec<Explorer> = vec![Explorer {
+        position: *start,
+        path: Movements::empty(),
+        droid,
+    }];
+    while !frontier.is_empty() {
+        let discoveries = explore_frontier(&frontier, &ship_map)?;
+        frontier.clear();
+        for Discovery {
+            target,
+            room,
+            explorer,
+        } in discoveries
+        {
+            if ship_map.tiles.contains_key(&target) {
+                // Another explorer got here in the same number of steps.
+                continue;
+            }
+            ship_map.add_location(target, room);
+            if let Some(explorer) = explorer {
+                if room == RoomType::Goal && path_to_goal.is_none() {
+                    path_to_goal = Some(explorer.path.clone());
+                }
+                frontier.push(explorer);
+            }
+        }
+        show(&ship_map, path_to_goal.as_ref());
+    }
+    Ok((ship_map, path_to_goal))
+}
+
+/// Drive a single droid from `start` along `path`, mapping the rooms
+/// it passes through.  `show` is called with the map and the part of
+/// the path followed so far after each step.  Returns the map and
+/// where the droid ended up.  It is an error for the path to run
+/// into a wall.
+pub fn replay<F>(
+    start: &Position,
+    mut droid: RepairDroid,
+    path: &Movements,
+    mut show: F,
+) -> Result<(ShipMap, Position), Fail>
+where
+    F: FnMut(&ShipMap, &Movements),
+{
+    let mut ship_map = ShipMap::new(*start);
+    let mut position = *start;
+    let mut followed = Movements::empty();
+    for (i, direction) in path.steps.iter().enumerate() {
+        let target = position.move_direction(direction);
+        match droid.move_droid(direction)? {
+            None => {
+                return Err(Fail(format!(
+                    "the droid's program halted at step {} of the path",
+                    i + 1
+                )));
+            }
+            Some(RoomType::Wall) => {
+                ship_map.add_location(target, RoomType::Wall);
+                show(&ship_map, &followed);
+                return Err(Fail(format!(
+                    "step {} of the path ({} from {}) runs into a wall",
+                    i + 1,
+                    char::from(*direction),
+                    position
+                )));
+            }
+            Some(room) => {
+                if ship_map.get_location_type(&target).is_none() {
+                    ship_map.add_location(target, room);
+                }
+                position = target;
+                followed.push_step(direction);
+            }
+        }
+        show(&ship_map, &followed);
+    }
+    Ok((ship_map, position))
+}
+
+#[test]
+fn test_replay() {
+    // A droid which reports that every move succeeds.
+    let open = parse_program("3,100,104,1,1105,1,0").expect("valid program");
+    let start = Position { x: 0, y: 0 };
+    let path: Movements = "NNES".parse().expect("valid path");
+    let mut steps_shown = 0;
+    let (_, end) = replay(
+        &start,
+        RepairDroid::new(&open).expect("program should load"),
+        &path,
+        |_, followed| {
+            steps_shown += 1;
+            assert_eq!(followed.len(), steps_shown);
+        },
+    )
+    .expect("the path is clear");
+    assert_eq!(end, Position { x: 1, y: -1 });
+    // A droid which is surrounded by walls.
+    let walled = parse_program("3,100,104,0,1105,1,0").expect("valid program");
+    let blocked = replay(
+        &start,
+        RepairDroid::new(&walled).expect("program should load"),
+        &path,
+        |_, _| (),
+    );
+    assert!(blocked.is_err());
+}
+
+fn advance(boundary: &HashSet<Position>, open_rooms: &HashSet<Position>) -> HashSet<Position> {
+    boundary
+        .iter()
+        .flat_map(|pos| {
+            ALL_MOVE_OPTIONS.iter().filter_map(|direction| {
+                let next_pos = pos.move_direction(direction);
+                if open_rooms.contains(&next_pos) {
+                    Some(next_pos)
+                } else {
+                    None
+                }
+            })
+        })
+        .collect()
+}
+
+/// Fill the ship with oxygen from `start`, returning the number of
+/// steps needed to fill it.  `display_state` is called with the step
+/// number, the number of cells filled so far, and the map, before
+/// each step.
+pub fn part2<F>(start: &Position, ship_map: &mut ShipMap, mut display_state: F) -> usize
+where
+    F: FnMut(usize, usize, &ShipMap),
+{
+    let mut boundary: HashSet<Position> = HashSet::new();
+    let mut to_fill: HashSet<Position> = ship_map.get_open_rooms();
+    boundary.insert(*start);
+    to_fill.remove(start);
+    let mut fill_count: usize = 1;
+    for step_number in 0.. {
+        display_state(step_number, fill_count, ship_map);
+        if to_fill.is_empty() {
+            return step_number;
+        }
+        let new_boundary: HashSet<Position> = advance(&boundary, &to_fill);
+        for filled_pos in new_boundary.iter() {
+            ship_map.oxygen_fill(*filled_pos);
+            to_fill.remove(filled_pos);
+            fill_count += 1;
+        }
+        boundary = new_boundary;
+    }
+    unreachable!()
+}
+
+#[test]
+fn test_part2() {
+    let mut sm = ShipMap::try_from(concat!(
+        " ##   \n", "#..## \n", "#.#..#\n", "#.X.# \n", " ###  \n",
+    ))
+    .expect("test input should be valid");
+    let oxy = Position { x: 2, y: 3 };
+    let display_map = |step: usize, occupied: usize, sm: &ShipMap| {
+        println!("Step {}: {} cells occupied:\n{}", step, occupied, sm);
+    };
+    assert_eq!(part2(&oxy, &mut sm, display_map), 4);
+}
+
+/// Part 1 is the number of steps from the start to the oxygen
+/// system, and part 2 the number of minutes it takes for oxygen to
+/// fill the ship.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    let start = Position { x: 0, y: 0 };
+    let droid = RepairDroid::new(&program)?;
+    match explore(&start, droid, |_, _| ())? {
+        (mut ship_map, Some(path)) => match ship_map.goal() {
+            Some(goal) => {
+                let minutes = part2(&goal, &mut ship_map, |_, _, _| ());
+                Ok((path.len().to_string(), Some(minutes.to_string())))
+            }
+            None => Err(Fail("no oxygen system".to_string())),
+        },
+        (_, None) => Err(Fail("Day 15: no solution found to part 1".to_string())),
+    }
+}
@@ -0,0 +1,175 @@
+//! Day 16: Flawed Frequency Transmission.
+use crate::error::Fail;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(test)]
+const BASE_PATTERN: [i32; 4] = [0, 1, 0, -1];
+
+#[cfg(test)]
+fn get_pattern(input_len: usize, out_pos: usize) -> Vec<i32> {
+    assert!(out_pos > 0); // counted from 1.
+    let mut result: Vec<i32> = Vec::with_capacity(input_len + 1);
+    let mut pat_pos: usize = 0;
+    loop {
+        for _repeat in 1..(out_pos + 1) {
+            if result.len() > input_len {
+                return result.into_iter().skip(1).collect();
+            }
+            result.push(BASE_PATTERN[pat_pos]);
+        }
+        pat_pos = (pat_pos + 1) % BASE_PATTERN.len();
+    }
+}
+
+#[test]
+fn test_pattern() {
+    fn v(input_len: usize, out_pos: usize) -> Vec<i32> {
+        get_pattern(input_len, out_pos)
+    }
+
+    assert_eq!(v(10, 1), vec![1, 0, -1, 0, 1, 0, -1, 0, 1, 0]);
+    assert_eq!(
+        v(15, 2),
+        vec![0, 1, 1, 0, 0, -1, -1, 0, 0, 1, 1, 0, 0, -1, -1]
+    );
+    assert_eq!(v(10, 3), vec![0, 0, 1, 1, 1, 0, 0, 0, -1, -1]);
+}
+
+/// Compute output digit `out_pos` (counted from 0) of an FFT phase
+/// whose input has the prefix sums `prefix` (that is, `prefix[i]` is
+/// the sum of the first `i` input digits).  The pattern for this
+/// digit is made of runs of `out_pos + 1` equal values, so the sum of
+/// the products is a sum of differences of prefix sums.
+fn fft_digit(prefix: &[i32], out_pos: usize) -> i32 {
+    let run = out_pos + 1;
+    let len = prefix.len() - 1;
+    let run_total = |start: usize| -> i32 {
+        if start >= len {
+            0
+        } else {
+            prefix[(start + run).min(len)] - prefix[start]
+        }
+    };
+    // The first run of 1s starts at `out_pos`, since the pattern is
+    // shifted left by one place.  Runs of 1s and -1s alternate,
+    // separated by runs of 0s.
+    let total: i32 = (out_pos..len)
+        .step_by(4 * run)
+        .map(|start| run_total(start) - run_total(start + 2 * run))
+        .sum();
+    total.abs() % 10
+}
+
+fn prefix_sums(input: &[i32]) -> Vec<i32> {
+    let mut prefix: Vec<i32> = Vec::with_capacity(input.len() + 1);
+    prefix.push(0);
+    let mut total = 0;
+    for digit in input {
+        total += digit;
+        prefix.push(total);
+    }
+    prefix
+}
+
+#[cfg(not(feature = "rayon"))]
+fn fft(input: &[i32]) -> Vec<i32> {
+    let prefix = prefix_sums(input);
+    (0..(input.len()))
+        .map(|pos| fft_digit(&prefix, pos))
+        .collect()
+}
+
+/// Each output digit depends only on the input, so the digits of a
+/// phase can be computed in parallel.
+#[cfg(feature = "rayon")]
+fn fft(input: &[i32]) -> Vec<i32> {
+    // Digits near the start of the output take the longest to
+    // compute, so hand out fairly small pieces of work.
+    const MIN_CHUNK: usize = 64;
+    let prefix = prefix_sums(input);
+    (0..(input.len()))
+        .into_par_iter()
+        .with_min_len(MIN_CHUNK)
+        .map(|pos| fft_digit(&prefix, pos))
+        .collect()
+}
+
+#[test]
+fn test_fft_digit_matches_pattern() {
+    let input: Vec<i32> = (0..37).map(|n| (n * 7 + 3) % 10).collect();
+    let prefix = prefix_sums(&input);
+    for pos in 0..input.len() {
+        let expected: i32 = input
+            .iter()
+            .zip(get_pattern(input.len(), pos + 1))
+            .map(|(digit, factor)| digit * factor)
+            .sum();
+        assert_eq!(fft_digit(&prefix, pos), expected.abs() % 10, "pos {}", pos);
+    }
+}
+
+pub fn fft_rounds(input: &[i32], rounds: usize) -> Vec<i32> {
+    let mut output = input.to_owned();
+    for _round in 0..rounds {
+        output = fft(&output);
+    }
+    output
+}
+
+#[test]
+fn test_fft() {
+    assert_eq!(fft(&[1, 2, 3, 4, 5, 6, 7, 8]), vec![4, 8, 2, 2, 6, 1, 5, 8]);
+}
+
+#[test]
+fn test_fft_rounds() {
+    assert_eq!(
+        fft_rounds(&[1, 2, 3, 4, 5, 6, 7, 8], 1),
+        vec![4, 8, 2, 2, 6, 1, 5, 8]
+    );
+    assert_eq!(
+        fft_rounds(&[1, 2, 3, 4, 5, 6, 7, 8], 2),
+        vec![3, 4, 0, 4, 0, 4, 3, 8]
+    );
+    assert_eq!(
+        fft_rounds(&[1, 2, 3, 4, 5, 6, 7, 8], 3),
+        vec![0, 3, 4, 1, 5, 5, 1, 8]
+    );
+    assert_eq!(
+        fft_rounds(&[1, 2, 3, 4, 5, 6, 7, 8], 4),
+        vec![0, 1, 0, 2, 9, 4, 9, 8]
+    );
+}
+
+/// The first eight digits after 100 phases of FFT.
+pub fn solve1(digits: &[i32]) -> String {
+    let result: Vec<String> = fft_rounds(digits, 100)
+        .into_iter()
+        .take(8)
+        .map(|d| d.to_string())
+        .collect();
+    result.join("")
+}
+
+pub fn parse_digits(input: &str) -> Result<Vec<i32>, Fail> {
+    const DECIMAL: u32 = 10;
+    input
+        .trim()
+        .chars()
+        .map(|ch: char| -> Result<i32, Fail> {
+            match ch.to_digit(DECIMAL) {
+                Some(d) => match i32::try_from(d) {
+                    Ok(d) => Ok(d),
+                    Err(e) => Err(Fail(format!("failed to convert {} to i32: {}", d, e))),
+                },
+                None => Err(Fail(format!("{} is not a decimal digit", ch))),
+            }
+        })
+        .collect()
+}
+
+/// Only part 1 is solved so far.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    Ok((solve1(&parse_digits(input)?), None))
+}
@@ -0,0 +1,107 @@
+//! Day 17: Set and Forget.
+use crate::ascii::{Frame, FrameParser, MISSING};
+use crate::cpu::{new_processor, parse_program, InputOutputError, Word};
+use crate::error::Fail;
+use crate::grid::Position;
+
+use ndarray::prelude::*;
+
+fn is_scaffold(arr: &Array2<char>, pos: &(usize, usize)) -> bool {
+    matches!(arr[*pos], '#' | '^' | 'v' | '>' | '<')
+}
+
+fn is_scaffold_intersection(arr: &Array2<char>, pos: &(usize, usize)) -> bool {
+    let (h, w) = arr.dim();
+    let (r, c) = *pos;
+    // An intersection has scaffold on all four sides, so it cannot
+    // be on the edge of the image (note, y axis points down the page).
+    r > 0
+        && c > 0
+        && r + 1 < h
+        && c + 1 < w
+        && [(r, c), (r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)]
+            .iter()
+            .all(|p| is_scaffold(arr, p))
+}
+
+fn find_matches<F>(array: &Array2<char>, pred: F) -> Vec<Position>
+where
+    F: Fn(&Array2<char>, &(usize, usize)) -> bool,
+{
+    array
+        .indexed_iter()
+        .filter(|(pos, _)| pred(array, &(pos.0, pos.1)))
+        .map(|(pos, _)| Position {
+            y: pos.0 as i64,
+            x: pos.1 as i64,
+        })
+        .collect()
+}
+
+pub fn alignment_parameter(pos: &Position) -> i64 {
+    pos.x * pos.y
+}
+
+/// Run the camera program, passing each character it outputs to
+/// `observe`, and return the image it shows.
+pub fn capture<F>(program: &[Word], mut observe: F) -> Result<Array2<char>, Fail>
+where
+    F: FnMut(char) -> Result<(), InputOutputError>,
+{
+    let mut cpu = new_processor(program)?;
+    let mut parser = FrameParser::new();
+    let mut last_frame: Option<Frame> = None;
+    let mut get_input = || -> Result<Word, InputOutputError> { Err(InputOutputError::NoInput) };
+    let mut do_output = |w: Word| -> Result<(), InputOutputError> {
+        let ch = u32::try_from(w.0)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(InputOutputError::Unprintable(w))?;
+        if let Some(frame) = parser.push(ch) {
+            last_frame = Some(frame);
+        }
+        observe(ch)
+    };
+    cpu.run_with_io(&mut get_input, &mut do_output)?;
+    Ok(parser
+        .finish()
+        .or(last_frame)
+        .unwrap_or_else(|| Array2::from_elem((0, 0), MISSING)))
+}
+
+/// The scaffold intersections in `image`.
+pub fn intersections(image: &Array2<char>) -> Vec<Position> {
+    find_matches(image, is_scaffold_intersection)
+}
+
+#[test]
+fn test_intersections() {
+    // The example from the puzzle.
+    let mut parser = FrameParser::new();
+    let example = concat!(
+        "..#..........\n",
+        "..#..........\n",
+        "#######...###\n",
+        "#.#...#...#.#\n",
+        "#############\n",
+        "..#...#...#..\n",
+        "..#####...^..\n",
+        "\n"
+    );
+    let image = example
+        .chars()
+        .find_map(|ch| parser.push(ch))
+        .expect("the example is a complete frame");
+    assert_eq!(image.dim(), (7, 13));
+    let total: i64 = intersections(&image).iter().map(alignment_parameter).sum();
+    assert_eq!(total, 76);
+}
+
+/// Part 1 is the sum of the alignment parameters of the scaffold
+/// intersections.  Only part 1 is solved so far.
+pub fn solve(input: &str) -> Result<(String, Option<String>), Fail> {
+    let program = parse_program(input)?;
+    let image = capture(&program, |_| Ok(()))?;
+    let total: i64 = intersections(&image).iter().map(alignment_parameter).sum();
+    Ok((total.to_string(), None))
+}
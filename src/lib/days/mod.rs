@@ -0,0 +1,196 @@
+//! The solutions of each day's puzzle.  Each module has a function
+//! `solve` which takes the puzzle input and returns the answers to
+//! both parts (part 2 is `None` if it has no automatic solution), so
+//! that solutions can be called from other programs.  The day
+//! binaries are thin wrappers around these modules, adding their
+//! command-line options and visualisations.  `DAYS` lists every
+//! implemented day with its `solve` function.
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Fail;
+
+/// The answers to both parts of a puzzle.
+pub type Answers = (String, Option<String>);
+
+/// The `solve` function of a day.
+pub type Solver = fn(&str) -> Result<Answers, Fail>;
+
+/// An implemented day.
+#[derive(Debug, Clone, Copy)]
+pub struct Day {
+    pub number: u8,
+    pub title: &'static str,
+    /// How many parts `solve` answers: 1 if part 2 has no automatic
+    /// solution yet, and 0 for a day which `aoc new` has only just
+    /// created.
+    pub parts_solved: u8,
+    pub solve: Solver,
+}
+
+impl Day {
+    /// Whether the input of this day is present in the directory
+    /// `dir` (see `input_path`).
+    pub fn has_input(&self, dir: &Path) -> bool {
+        input_path(dir, self.number).exists()
+    }
+}
+
+/// The number of days in the Advent calendar.
+pub const LAST_DAY: u8 = 25;
+
+/// Every implemented day, in order.
+pub const DAYS: [Day; 17] = [
+    Day {
+        number: 1,
+        title: "The Tyranny of the Rocket Equation",
+        parts_solved: 2,
+        solve: day01::solve,
+    },
+    Day {
+        number: 2,
+        title: "1202 Program Alarm",
+        parts_solved: 2,
+        solve: day02::solve,
+    },
+    Day {
+        number: 3,
+        title: "Crossed Wires",
+        parts_solved: 2,
+        solve: day03::solve,
+    },
+    Day {
+        number: 4,
+        title: "Secure Container",
+        parts_solved: 2,
+        solve: day04::solve,
+    },
+    Day {
+        number: 5,
+        title: "Sunny with a Chance of Asteroids",
+        parts_solved: 2,
+        solve: day05::solve,
+    },
+    Day {
+        number: 6,
+        title: "Universal Orbit Map",
+        parts_solved: 2,
+        solve: day06::solve,
+    },
+    Day {
+        number: 7,
+        title: "Amplification Circuit",
+        parts_solved: 2,
+        solve: day07::solve,
+    },
+    Day {
+        number: 8,
+        title: "Space Image Format",
+        parts_solved: 2,
+        solve: day08::solve,
+    },
+    Day {
+        number: 9,
+        title: "Sensor Boost",
+        parts_solved: 2,
+        solve: day09::solve,
+    },
+    Day {
+        number: 10,
+        title: "Monitoring Station",
+        parts_solved: 2,
+        solve: day10::solve,
+    },
+    Day {
+        number: 11,
+        title: "Space Police",
+        parts_solved: 2,
+        solve: day11::solve,
+    },
+    Day {
+        number: 12,
+        title: "The N-Body Problem",
+        parts_solved: 2,
+        solve: day12::solve,
+    },
+    Day {
+        number: 13,
+        title: "Care Package",
+        parts_solved: 2,
+        solve: day13::solve,
+    },
+    Day {
+        number: 14,
+        title: "Space Stoichiometry",
+        parts_solved: 2,
+        solve: day14::solve,
+    },
+    Day {
+        number: 15,
+        title: "Oxygen System",
+        parts_solved: 2,
+        solve: day15::solve,
+    },
+    Day {
+        number: 16,
+        title: "Flawed Frequency Transmission",
+        parts_solved: 1,
+        solve: day16::solve,
+    },
+    Day {
+        number: 17,
+        title: "Set and Forget",
+        parts_solved: 1,
+        solve: day17::solve,
+    },
+];
+
+/// The implemented day `number`, if there is one.
+pub fn day(number: u8) -> Option<&'static Day> {
+    DAYS.iter().find(|d| d.number == number)
+}
+
+/// The solution of `day`, if it has one.
+pub fn solver(day: u8) -> Option<Solver> {
+    self::day(day).map(|d| d.solve)
+}
+
+/// Where the input for `day` is kept in the directory `dir`, for
+/// programs which run several days' solutions.
+pub fn input_path(dir: &Path, day: u8) -> PathBuf {
+    dir.join(format!("day{:02}.txt", day))
+}
+
+#[test]
+fn test_days() {
+    for (i, d) in DAYS.iter().enumerate() {
+        if i > 0 {
+            assert!(DAYS[i - 1].number < d.number);
+        }
+        assert!((1..=LAST_DAY).contains(&d.number));
+        assert!(d.parts_solved <= 2);
+    }
+    assert_eq!(
+        day(5).map(|d| d.title),
+        Some("Sunny with a Chance of Asteroids")
+    );
+    assert!(day(LAST_DAY).is_none());
+    assert!(solver(1).is_some());
+}
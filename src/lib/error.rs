@@ -11,3 +11,79 @@ impl Display for Fail {
 }
 
 impl std::error::Error for Fail {}
+
+/// An error together with a description of what was being done when
+/// it happened, for example "failed to load the recipes".  The
+/// original error remains available as the `source`.
+#[derive(Debug)]
+pub struct Context {
+    what: String,
+    source: Box<dyn std::error::Error + Send + Sync + 'static>,
+}
+
+impl Display for Context {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.what, self.source)
+    }
+}
+
+impl std::error::Error for Context {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+impl From<Context> for Fail {
+    fn from(e: Context) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// Adds a `Context` to the error of a `Result`.
+pub trait ResultExt<T> {
+    fn context<C: Display>(self, what: C) -> Result<T, Context>;
+
+    /// Like `context`, but the description is only built if there
+    /// is an error.
+    fn with_context<C: Display, F: FnOnce() -> C>(self, what: F) -> Result<T, Context>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn context<C: Display>(self, what: C) -> Result<T, Context> {
+        self.with_context(|| what)
+    }
+
+    fn with_context<C: Display, F: FnOnce() -> C>(self, what: F) -> Result<T, Context> {
+        self.map_err(|e| Context {
+            what: what().to_string(),
+            source: Box::new(e),
+        })
+    }
+}
+
+#[test]
+fn test_context() {
+    use std::error::Error;
+    let inner: Result<(), Fail> = Err(Fail("bad quantity 'x'".to_string()));
+    let outer = inner
+        .context("failed to load the recipes")
+        .context("failed to solve day 14");
+    let e = outer.expect_err("the error should be kept");
+    assert_eq!(
+        e.to_string(),
+        "failed to solve day 14: failed to load the recipes: bad quantity 'x'"
+    );
+    let chain: Vec<String> = std::iter::successors(e.source(), |e| (*e).source())
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(
+        chain,
+        vec![
+            "failed to load the recipes: bad quantity 'x'",
+            "bad quantity 'x'"
+        ]
+    );
+}
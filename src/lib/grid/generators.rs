@@ -0,0 +1,293 @@
+//! Random maps for testing and benchmarking path-finding code on
+//! inputs larger than the puzzle examples.  The same seed always
+//! produces the same map.
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display, Formatter};
+
+use super::{Position, ALL_MOVE_OPTIONS};
+use crate::automaton::{Automaton, Cell, Topology};
+
+/// The SplitMix64 generator; it is small and good enough for
+/// making test maps.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.  Panics if `n` is zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns true with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < p
+    }
+}
+
+/// A rectangular map of walls and open squares.  It is displayed
+/// with `#` for walls and `.` for open squares, which is a format
+/// `ShipMap` (day 15) can read.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GeneratedMap {
+    width: i64,
+    height: i64,
+    open: Vec<bool>,
+}
+
+impl GeneratedMap {
+    fn all_walls(width: i64, height: i64) -> GeneratedMap {
+        GeneratedMap {
+            width,
+            height,
+            open: vec![false; (width.max(0) * height.max(0)) as usize],
+        }
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    fn index(&self, pos: &Position) -> Option<usize> {
+        if (0..self.width).contains(&pos.x) && (0..self.height).contains(&pos.y) {
+            Some((pos.y * self.width + pos.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Squares outside the map are walls.
+    pub fn is_open(&self, pos: &Position) -> bool {
+        self.index(pos).map(|i| self.open[i]).unwrap_or(false)
+    }
+
+    fn set_open(&mut self, pos: &Position, open: bool) {
+        if let Some(i) = self.index(pos) {
+            self.open[i] = open;
+        }
+    }
+
+    /// The open squares, row by row.
+    pub fn open_positions(&self) -> impl Iterator<Item = Position> + '_ {
+        let width = self.width;
+        self.open
+            .iter()
+            .enumerate()
+            .filter(|(_, open)| **open)
+            .map(move |(i, _)| Position {
+                x: i as i64 % width,
+                y: i as i64 / width,
+            })
+    }
+
+    /// The length of the shortest path from `start` to each open
+    /// square reachable from it, found by breadth-first search.
+    /// This is the reference answer to check other solvers against.
+    pub fn distances_from(&self, start: &Position) -> HashMap<Position, usize> {
+        let mut result = HashMap::new();
+        if !self.is_open(start) {
+            return result;
+        }
+        let mut queue = VecDeque::new();
+        result.insert(*start, 0);
+        queue.push_back(*start);
+        while let Some(pos) = queue.pop_front() {
+            let distance = result[&pos] + 1;
+            for direction in ALL_MOVE_OPTIONS.iter() {
+                let next = pos.move_direction(direction);
+                if self.is_open(&next) && !result.contains_key(&next) {
+                    result.insert(next, distance);
+                    queue.push_back(next);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Display for GeneratedMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            let row: String = (0..self.width)
+                .map(|x| {
+                    if self.is_open(&Position { x, y }) {
+                        '.'
+                    } else {
+                        '#'
+                    }
+                })
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// A perfect maze (one with exactly one path between any two open
+/// squares) of `cells_wide` by `cells_high` rooms, made by a
+/// randomised depth-first search.  Rooms are at odd coordinates and
+/// the map is surrounded by wall, so it is `2 * cells_wide + 1`
+/// squares wide.
+pub fn perfect_maze(cells_wide: usize, cells_high: usize, seed: u64) -> GeneratedMap {
+    let mut rng = Rng::new(seed);
+    let (w, h) = (cells_wide as i64, cells_high as i64);
+    let mut map = GeneratedMap::all_walls(2 * w + 1, 2 * h + 1);
+    if w == 0 || h == 0 {
+        return map;
+    }
+    let room = |cell: &Position| Position {
+        x: 2 * cell.x + 1,
+        y: 2 * cell.y + 1,
+    };
+    let start = Position {
+        x: rng.below(cells_wide) as i64,
+        y: rng.below(cells_high) as i64,
+    };
+    map.set_open(&room(&start), true);
+    let mut stack = vec![start];
+    while let Some(current) = stack.last().copied() {
+        let unvisited: Vec<Position> = ALL_MOVE_OPTIONS
+            .iter()
+            .map(|d| current.move_direction(d))
+            .filter(|next| {
+                (0..w).contains(&next.x) && (0..h).contains(&next.y) && !map.is_open(&room(next))
+            })
+            .collect();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let next = unvisited[rng.below(unvisited.len())];
+        let (a, b) = (room(&current), room(&next));
+        map.set_open(
+            &Position {
+                x: (a.x + b.x) / 2,
+                y: (a.y + b.y) / 2,
+            },
+            true,
+        );
+        map.set_open(&b, true);
+        stack.push(next);
+    }
+    map
+}
+
+/// An irregular cave-like map of `width` by `height` squares.
+/// Squares start as wall with probability `wall_density` and the
+/// result is smoothed with a cellular automaton.  Only the largest
+/// connected open area is kept, so every open square can reach
+/// every other.  The edge of the map is always wall.
+pub fn cavern(width: i64, height: i64, wall_density: f64, seed: u64) -> GeneratedMap {
+    let mut map = GeneratedMap::all_walls(width, height);
+    if width < 3 || height < 3 {
+        return map;
+    }
+    let mut rng = Rng::new(seed);
+    let mut walls =
+        Automaton::new(width, height, Topology::Bounded).expect("the size should be valid");
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Position { x, y };
+            let edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            walls.set(Cell { level: 0, pos }, edge || rng.chance(wall_density));
+        }
+    }
+    // A square becomes wall when most of its neighbours are.
+    let smooth =
+        |wall: bool, wall_neighbours: usize| wall_neighbours >= 3 || (wall && wall_neighbours == 2);
+    let walls = walls.run(&smooth, 4);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let pos = Position { x, y };
+            map.set_open(&pos, !walls.is_alive(&Cell { level: 0, pos }));
+        }
+    }
+    // Keep only the largest connected area.
+    let mut best: HashMap<Position, usize> = HashMap::new();
+    let mut seen: HashSet<Position> = HashSet::new();
+    for pos in map.open_positions().collect::<Vec<_>>() {
+        if seen.contains(&pos) {
+            continue;
+        }
+        let area = map.distances_from(&pos);
+        seen.extend(area.keys().copied());
+        if area.len() > best.len() {
+            best = area;
+        }
+    }
+    for pos in map.open_positions().collect::<Vec<_>>() {
+        if !best.contains_key(&pos) {
+            map.set_open(&pos, false);
+        }
+    }
+    map
+}
+
+#[test]
+fn test_perfect_maze_is_a_tree() {
+    let maze = perfect_maze(15, 10, 2019);
+    assert_eq!(maze.width(), 31);
+    assert_eq!(maze.height(), 21);
+    let open: Vec<Position> = maze.open_positions().collect();
+    // Every room is reachable.
+    let start = Position { x: 1, y: 1 };
+    assert_eq!(maze.distances_from(&start).len(), open.len());
+    // A connected graph with one fewer edge than it has nodes has no
+    // cycles.
+    let edges: usize = open
+        .iter()
+        .map(|pos| {
+            [
+                Position {
+                    x: pos.x + 1,
+                    ..*pos
+                },
+                Position {
+                    y: pos.y + 1,
+                    ..*pos
+                },
+            ]
+            .iter()
+            .filter(|next| maze.is_open(next))
+            .count()
+        })
+        .sum();
+    assert_eq!(edges + 1, open.len());
+}
+
+#[test]
+fn test_same_seed_same_map() {
+    assert_eq!(perfect_maze(8, 8, 7), perfect_maze(8, 8, 7));
+    assert_ne!(perfect_maze(8, 8, 7), perfect_maze(8, 8, 8));
+    assert_eq!(cavern(40, 20, 0.45, 3), cavern(40, 20, 0.45, 3));
+}
+
+#[test]
+fn test_cavern_is_connected() {
+    let cave = cavern(60, 30, 0.4, 11);
+    let open: Vec<Position> = cave.open_positions().collect();
+    assert!(!open.is_empty());
+    assert_eq!(cave.distances_from(&open[0]).len(), open.len());
+    assert!(open
+        .iter()
+        .all(|pos| pos.x > 0 && pos.y > 0 && pos.x < 59 && pos.y < 29));
+}
@@ -0,0 +1,246 @@
+//! Positions and directions on a square grid.  With the `serde`
+//! feature, these can be serialised, so that results such as
+//! explored maps can be saved between runs.
+use std::fmt::{self, Display, Formatter};
+
+pub mod generators;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompassDirection {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl CompassDirection {
+    pub fn reversed(&self) -> CompassDirection {
+        use CompassDirection::*;
+        match self {
+            North => South,
+            South => North,
+            East => West,
+            West => East,
+        }
+    }
+}
+
+impl From<CompassDirection> for char {
+    fn from(d: CompassDirection) -> char {
+        use CompassDirection::*;
+        match d {
+            North => 'N',
+            East => 'E',
+            South => 'S',
+            West => 'W',
+        }
+    }
+}
+
+/// A character which is not one of N, E, S or W.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadDirection(pub char);
+
+impl Display for BadDirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a direction (expected N, E, S or W)", self.0)
+    }
+}
+
+impl std::error::Error for BadDirection {}
+
+impl TryFrom<char> for CompassDirection {
+    type Error = BadDirection;
+    fn try_from(ch: char) -> Result<CompassDirection, BadDirection> {
+        use CompassDirection::*;
+        match ch {
+            'N' => Ok(North),
+            'E' => Ok(East),
+            'S' => Ok(South),
+            'W' => Ok(West),
+            _ => Err(BadDirection(ch)),
+        }
+    }
+}
+
+pub const ALL_MOVE_OPTIONS: [CompassDirection; 4] = [
+    CompassDirection::North,
+    CompassDirection::East,
+    CompassDirection::South,
+    CompassDirection::West,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+impl Position {
+    pub fn move_direction(&self, d: &CompassDirection) -> Position {
+        match d {
+            CompassDirection::North => Position {
+                y: self.y - 1,
+                ..*self
+            },
+            CompassDirection::South => Position {
+                y: self.y + 1,
+                ..*self
+            },
+            CompassDirection::East => Position {
+                x: self.x + 1,
+                ..*self
+            },
+            CompassDirection::West => Position {
+                x: self.x - 1,
+                ..*self
+            },
+        }
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// A movement of a whole number of squares in each axis.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Step {
+    pub dx: i64,
+    pub dy: i64,
+}
+
+impl Step {
+    /// The smallest step in the same direction as this one which
+    /// lands exactly on a square.
+    pub fn reduced(&self) -> Step {
+        match gcd(self.dx, self.dy) {
+            0 => *self,
+            divisor => Step {
+                dx: self.dx / divisor,
+                dy: self.dy / divisor,
+            },
+        }
+    }
+
+    /// The step from `from` to `to`.
+    pub fn between(from: &Position, to: &Position) -> Step {
+        Step {
+            dx: to.x - from.x,
+            dy: to.y - from.y,
+        }
+    }
+}
+
+/// The squares exactly on the line from `from` in `direction`, in
+/// order of increasing distance and not including `from` itself.  The
+/// sequence is endless, unless `direction` is zero in which case it
+/// is empty.
+pub fn ray(from: Position, direction: Step) -> impl Iterator<Item = Position> {
+    let step = direction.reduced();
+    let moving = step.dx != 0 || step.dy != 0;
+    (1..).map_while(move |k: i64| {
+        if moving {
+            Some(Position {
+                x: from.x + k * step.dx,
+                y: from.y + k * step.dy,
+            })
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns true if none of the squares exactly on the line between
+/// `from` and `to` (exclusive at both ends) is `blocked`.
+pub fn visible<F>(from: &Position, to: &Position, blocked: F) -> bool
+where
+    F: Fn(&Position) -> bool,
+{
+    from == to
+        || !ray(*from, Step::between(from, to))
+            .take_while(|pos| pos != to)
+            .any(|pos| blocked(&pos))
+}
+
+#[test]
+fn test_ray() {
+    let origin = Position { x: 1, y: 1 };
+    let points: Vec<Position> = ray(origin, Step { dx: 4, dy: -2 }).take(3).collect();
+    assert_eq!(
+        points,
+        vec![
+            Position { x: 3, y: 0 },
+            Position { x: 5, y: -1 },
+            Position { x: 7, y: -2 },
+        ]
+    );
+    assert_eq!(ray(origin, Step { dx: 0, dy: 0 }).count(), 0);
+}
+
+#[test]
+fn test_visible() {
+    let wall = Position { x: 2, y: 1 };
+    let blocked = |pos: &Position| *pos == wall;
+    let origin = Position { x: 0, y: 0 };
+    assert!(!visible(&origin, &Position { x: 4, y: 2 }, blocked));
+    // The wall itself is visible, as is anything not exactly behind it.
+    assert!(visible(&origin, &wall, blocked));
+    assert!(visible(&origin, &Position { x: 4, y: 3 }, blocked));
+    assert!(visible(&origin, &origin, blocked));
+}
+
+pub fn bounds<'a, I>(points: I) -> Option<(Position, Position)>
+where
+    I: IntoIterator<Item = &'a Position>,
+{
+    let mut min_x: Option<i64> = None;
+    let mut max_x: Option<i64> = None;
+    let mut min_y: Option<i64> = None;
+    let mut max_y: Option<i64> = None;
+    fn maybe_update_min(min: &mut Option<i64>, val: i64) {
+        match min {
+            None => {
+                *min = Some(val);
+            }
+            Some(v) if *v > val => *min = Some(val),
+            Some(_) => (),
+        }
+    }
+    fn maybe_update_max(max: &mut Option<i64>, val: i64) {
+        match max {
+            None => {
+                *max = Some(val);
+            }
+            Some(v) if *v < val => *max = Some(val),
+            Some(_) => (),
+        }
+    }
+    for p in points.into_iter() {
+        maybe_update_min(&mut min_x, p.x);
+        maybe_update_max(&mut max_x, p.x);
+        maybe_update_min(&mut min_y, p.y);
+        maybe_update_max(&mut max_y, p.y);
+    }
+    match (min_x, max_x, min_y, max_y) {
+        (Some(xlow), Some(xhigh), Some(ylow), Some(yhigh)) => {
+            let min: Position = Position { x: xlow, y: ylow };
+            let max: Position = Position { x: xhigh, y: yhigh };
+            Some((min, max))
+        }
+        _ => None,
+    }
+}
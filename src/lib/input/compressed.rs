@@ -0,0 +1,147 @@
+//! Input files may be stored compressed, which is useful for large
+//! generated inputs.  Files whose names end in `.gz` or `.xz` are
+//! decompressed as they are opened, provided that the `gzip` or `xz`
+//! feature (respectively) is enabled.
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Xz,
+}
+
+impl Compression {
+    fn of(path: &Path) -> Option<Compression> {
+        let ext = path.extension()?;
+        if ext.eq_ignore_ascii_case("gz") {
+            Some(Compression::Gzip)
+        } else if ext.eq_ignore_ascii_case("xz") {
+            Some(Compression::Xz)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(any(not(feature = "gzip"), not(feature = "xz")))]
+fn unsupported(feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "this file is compressed, but the program was built without the '{}' feature",
+            feature
+        ),
+    )
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(file: File) -> io::Result<Box<dyn BufRead>> {
+    Ok(Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(
+        file,
+    ))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_: File) -> io::Result<Box<dyn BufRead>> {
+    Err(unsupported("gzip"))
+}
+
+/// The xz decoder does not offer a reader, so the whole file is
+/// decompressed up front.
+#[cfg(feature = "xz")]
+fn unxz(file: File) -> io::Result<Box<dyn BufRead>> {
+    let mut data: Vec<u8> = Vec::new();
+    lzma_rs::xz_decompress(&mut BufReader::new(file), &mut data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(Box::new(io::Cursor::new(data)))
+}
+
+#[cfg(not(feature = "xz"))]
+fn unxz(_: File) -> io::Result<Box<dyn BufRead>> {
+    Err(unsupported("xz"))
+}
+
+/// Opens `path` for reading, decompressing it if its name shows that
+/// it is compressed.
+pub fn open_decompressed(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    match Compression::of(path) {
+        None => Ok(Box::new(BufReader::new(file))),
+        Some(Compression::Gzip) => gunzip(file),
+        Some(Compression::Xz) => unxz(file),
+    }
+}
+
+#[cfg(test)]
+fn read_back(name: &str, contents: &[u8]) -> io::Result<String> {
+    use std::io::Read;
+    let path = std::env::temp_dir().join(format!("{}.{}", std::process::id(), name));
+    std::fs::write(&path, contents)?;
+    let result = open_decompressed(&path).and_then(|mut r| {
+        let mut text = String::new();
+        r.read_to_string(&mut text).map(|_| text)
+    });
+    std::fs::remove_file(&path)?;
+    result
+}
+
+#[test]
+fn test_compression_of() {
+    assert_eq!(Compression::of(Path::new("day01.txt")), None);
+    assert_eq!(Compression::of(Path::new("day01")), None);
+    assert_eq!(
+        Compression::of(Path::new("big/day16.txt.gz")),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(
+        Compression::of(Path::new("day16.txt.XZ")),
+        Some(Compression::Xz)
+    );
+}
+
+#[test]
+fn test_uncompressed() {
+    assert_eq!(
+        read_back("plain.txt", b"1,2,3\n").expect("plain files are readable"),
+        "1,2,3\n"
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_gzip() {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(b"1,2,3\n")
+        .and_then(|()| encoder.flush())
+        .expect("compressing into a Vec should work");
+    let compressed = encoder.finish().expect("compressing should work");
+    assert_eq!(
+        read_back("test.gz", &compressed).expect("gzip files are readable"),
+        "1,2,3\n"
+    );
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn test_gzip_unsupported() {
+    let err = read_back("test.gz", b"not really gzip").expect_err("gzip is not supported");
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    assert!(err.to_string().contains("'gzip' feature"));
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn test_xz() {
+    let mut compressed: Vec<u8> = Vec::new();
+    lzma_rs::xz_compress(&mut &b"1,2,3\n"[..], &mut compressed)
+        .expect("compressing into a Vec should work");
+    assert_eq!(
+        read_back("test.xz", &compressed).expect("xz files are readable"),
+        "1,2,3\n"
+    );
+    assert!(read_back("corrupt.xz", b"not really xz").is_err());
+}
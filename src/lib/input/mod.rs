@@ -1,16 +1,24 @@
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Lines, Read};
 use std::path::{Path, PathBuf};
 
+use crate::cpu;
 use crate::error::Fail;
+use crate::threads;
+use crate::verbosity::{set_verbosity, Verbosity};
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
+
+mod compressed;
+
+pub use compressed::open_decompressed;
 
 #[derive(Debug)]
 pub enum InputError {
     NoInputFile,
+    TraceError(String),
+    BadOption(String),
     IoError {
         filename: Option<PathBuf>,
         err: std::io::Error,
@@ -21,6 +29,8 @@ impl Display for InputError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             InputError::NoInputFile => write!(f, "no input file was specified"),
+            InputError::TraceError(msg) => write!(f, "cannot trace as requested: {}", msg),
+            InputError::BadOption(msg) => write!(f, "{}", msg),
             InputError::IoError {
                 filename: Some(name),
                 err,
@@ -41,9 +51,65 @@ impl From<InputError> for Fail {
     }
 }
 
-fn open_input_file(input_file_name: &Path) -> Result<BufReader<File>, InputError> {
-    match OpenOptions::new().read(true).open(input_file_name) {
-        Ok(file) => Ok(BufReader::new(file)),
+/// A place in an input file.  Lines and columns are counted from 1,
+/// and columns count characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// The location of byte offset `offset` within `text`, which is
+    /// line number `line`.
+    pub fn at_offset(line: usize, text: &str, offset: usize) -> Location {
+        let offset = offset.min(text.len());
+        Location {
+            line,
+            column: text[..offset].chars().count() + 1,
+        }
+    }
+
+    /// The location of the first occurrence of `fragment` in `text`
+    /// (which is line number `line`), or of the start of the line if
+    /// `fragment` does not occur in it.
+    pub fn of_fragment(line: usize, text: &str, fragment: &str) -> Location {
+        Location::at_offset(line, text, text.find(fragment).unwrap_or(0))
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The text of `line` around column `column`, shortened with "..."
+/// if the line is long, for use in error messages.
+pub fn context_snippet(line: &str, column: usize) -> String {
+    const CONTEXT_CHARS: usize = 15;
+    let chars: Vec<char> = line.chars().collect();
+    let here = column.saturating_sub(1).min(chars.len());
+    let begin = here.saturating_sub(CONTEXT_CHARS);
+    let end = (here + CONTEXT_CHARS).min(chars.len());
+    let mut result = String::new();
+    if begin > 0 {
+        result.push_str("...");
+    }
+    result.extend(&chars[begin..end]);
+    if end < chars.len() {
+        result.push_str("...");
+    }
+    result
+}
+
+/// Open an input file for reading, decompressing it if necessary
+/// (see `open_decompressed`).  This has the signature needed for the
+/// input reader of `run_with_input`, for days which parse their input
+/// as it is read.
+pub fn open_input_file(input_file_name: &Path) -> Result<Box<dyn BufRead>, InputError> {
+    match open_decompressed(input_file_name) {
+        Ok(reader) => Ok(reader),
         Err(e) => Err(InputError::IoError {
             filename: Some(input_file_name.to_path_buf()),
             err: e,
@@ -65,25 +131,55 @@ pub fn read_file_as_string(input_file_name: &Path) -> Result<String, InputError>
     }
 }
 
-pub fn read_file_as_lines(input_file_name: &Path) -> Result<Vec<String>, InputError> {
-    match open_input_file(input_file_name) {
-        Ok(reader) => {
-            let result: Result<Vec<String>, InputError> = reader
-                .lines()
-                .map(|item| match item {
-                    Ok(line) => Ok(line),
-                    Err(e) => Err(InputError::IoError {
-                        filename: Some(input_file_name.to_path_buf()),
-                        err: e,
-                    }),
-                })
-                .collect();
-            result
+/// The lines of an input file (or of the standard input), read one
+/// at a time as the iterator is advanced.  Read errors are reported
+/// with the name of the file they occurred on.
+pub struct InputLines {
+    filename: Option<PathBuf>,
+    lines: Lines<Box<dyn BufRead>>,
+}
+
+impl InputLines {
+    pub fn from_reader(filename: Option<&Path>, reader: Box<dyn BufRead>) -> InputLines {
+        InputLines {
+            filename: filename.map(|name| name.to_path_buf()),
+            lines: reader.lines(),
         }
-        Err(e) => Err(e),
+    }
+
+    pub fn from_file(input_file_name: &Path) -> Result<InputLines, InputError> {
+        let reader = open_input_file(input_file_name)?;
+        Ok(InputLines::from_reader(Some(input_file_name), reader))
+    }
+
+    pub fn from_stdin() -> InputLines {
+        InputLines::from_reader(None, Box::new(BufReader::new(io::stdin())))
     }
 }
 
+impl Iterator for InputLines {
+    type Item = Result<String, InputError>;
+
+    fn next(&mut self) -> Option<Result<String, InputError>> {
+        self.lines.next().map(|item| {
+            item.map_err(|e| InputError::IoError {
+                filename: self.filename.clone(),
+                err: e,
+            })
+        })
+    }
+}
+
+/// Read the lines of a file lazily.  This has the signature needed
+/// for the input reader of `run_with_input`.
+pub fn read_file_lines_iter(input_file_name: &Path) -> Result<InputLines, InputError> {
+    InputLines::from_file(input_file_name)
+}
+
+pub fn read_file_as_lines(input_file_name: &Path) -> Result<Vec<String>, InputError> {
+    read_file_lines_iter(input_file_name)?.collect()
+}
+
 pub fn run_with_input<ErrorType, InputErrorType, InputReader, F, T, InputType>(
     day: i8,
     input_reader: InputReader,
@@ -93,22 +189,224 @@ where
     InputReader: Fn(&Path) -> Result<InputType, InputErrorType>,
     ErrorType: From<InputError> + From<InputErrorType> + Error,
     F: Fn(InputType) -> Result<T, ErrorType>,
+{
+    run_with_input_and_options(day, Vec::new(), input_reader, |input, _| runner(input))
+}
+
+/// Like `run_with_input`, but also accepts the day-specific command
+/// line options `extra_args`.  The runner is given the parsed command
+/// line so that it can examine them.
+pub fn run_with_input_and_options<ErrorType, InputErrorType, InputReader, F, T, InputType>(
+    day: i8,
+    extra_args: Vec<Arg<'static>>,
+    input_reader: InputReader,
+    runner: F,
+) -> Result<T, ErrorType>
+where
+    InputReader: Fn(&Path) -> Result<InputType, InputErrorType>,
+    ErrorType: From<InputError> + From<InputErrorType> + Error,
+    F: Fn(InputType, &ArgMatches) -> Result<T, ErrorType>,
 {
     let program_name: String = format!("Advent of code 2019 day {}", day);
-    let about = format!("Solves Advent of Code 2019 puzzle for day {}", day);
     let cmd = Command::new(program_name.as_str())
         .author("James Youngman, james@youngman.org")
-        .about(about.as_str())
-        .arg(Arg::new("input_file").allow_invalid_utf8(true).index(1));
+        .about("Solves an Advent of Code 2019 puzzle")
+        .arg(Arg::new("input_file").allow_invalid_utf8(true).index(1))
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .value_name("FILE")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("Trace the execution of Intcode programs in FILE"),
+        )
+        .arg(
+            Arg::new("trace-sample")
+                .long("trace-sample")
+                .value_name("N")
+                .takes_value(true)
+                .requires("trace")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Trace only every Nth instruction (input and output are always traced)"),
+        )
+        .arg(
+            Arg::new("annotations")
+                .long("annotations")
+                .value_name("FILE")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .requires("trace")
+                .help("Show the names of addresses given in FILE (lines such as '386 = ball_x') in the trace"),
+        )
+        .arg(
+            Arg::new("core-dump")
+                .long("core-dump")
+                .value_name("FILE")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("If an Intcode program faults, write its state to FILE"),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .value_name("N")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Report the progress of Intcode programs every N instructions"),
+        )
+        .arg(
+            Arg::new("cycle-costs")
+                .long("cycle-costs")
+                .value_name("TABLE")
+                .takes_value(true)
+                .help("Count the cycles used by Intcode programs, costing each opcode as given in TABLE (for example 1=1,2=3,99=0; other opcodes cost 1), and report the total when each program finishes"),
+        )
+        .arg(
+            Arg::new("strict-reads")
+                .long("strict-reads")
+                .help("Make Intcode programs fault if they read memory which was never written, instead of reading 0"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .help("Count the instructions executed by Intcode programs by opcode and by address, and show the counts when each program finishes"),
+        )
+        .arg(
+            Arg::new("detect-loops")
+                .long("detect-loops")
+                .value_name("N")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Make Intcode programs fault if they get stuck in a loop which does no I/O, checking every N instructions"),
+        )
+        .arg(
+            Arg::new("threads")
+                .long("threads")
+                .value_name("N")
+                .takes_value(true)
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Use at most N threads in solutions which work in parallel; 1 makes them run serially (defaults to $AOC_THREADS, or the number of CPUs)"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .conflicts_with("verbose")
+                .help("Show only the answers, without warnings"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Also show how the answers were reached"),
+        )
+        .args(extra_args);
     let m = cmd.get_matches();
+    if m.is_present("quiet") {
+        set_verbosity(Verbosity::Quiet);
+    } else if m.is_present("verbose") {
+        set_verbosity(Verbosity::Verbose);
+    }
+    if let Some(trace_file_name) = m.value_of_os("trace") {
+        let config = match m.get_one::<u64>("trace-sample") {
+            Some(n) => cpu::TraceConfig::sample_every(*n),
+            None => cpu::TraceConfig::default(),
+        };
+        match cpu::trace_all_processors(Path::new(trace_file_name), config) {
+            Ok(()) => (),
+            Err(cpu::CpuFault::BadOption(msg)) => {
+                return Err(ErrorType::from(InputError::BadOption(msg)));
+            }
+            Err(e) => {
+                return Err(ErrorType::from(InputError::TraceError(e.to_string())));
+            }
+        }
+    }
+    if let Some(annotations_file_name) = m.value_of_os("annotations") {
+        let annotated = cpu::Annotations::from_file(Path::new(annotations_file_name))
+            .map_err(|e| e.to_string())
+            .and_then(|a| cpu::annotate_all_processors(a).map_err(|e| e.to_string()));
+        if let Err(msg) = annotated {
+            return Err(ErrorType::from(InputError::BadOption(msg)));
+        }
+    }
+    if let Some(core_file_name) = m.value_of_os("core-dump") {
+        if let Err(e) = cpu::dump_core_of_all_processors(Path::new(core_file_name)) {
+            return Err(ErrorType::from(InputError::BadOption(e.to_string())));
+        }
+    }
+    if let Some(interval) = m.get_one::<u64>("progress") {
+        if let Err(e) = cpu::report_progress_of_all_processors(*interval) {
+            return Err(ErrorType::from(InputError::BadOption(e.to_string())));
+        }
+    }
+    if let Some(table) = m.get_one::<String>("cycle-costs") {
+        let counted = table
+            .parse::<cpu::CostModel>()
+            .map_err(|e| e.to_string())
+            .and_then(|model| {
+                cpu::count_cycles_of_all_processors(model).map_err(|e| e.to_string())
+            });
+        if let Err(msg) = counted {
+            return Err(ErrorType::from(InputError::BadOption(msg)));
+        }
+    }
+    if m.is_present("strict-reads") {
+        if let Err(e) = cpu::strict_reads_of_all_processors() {
+            return Err(ErrorType::from(InputError::BadOption(e.to_string())));
+        }
+    }
+    if m.is_present("profile") {
+        if let Err(e) = cpu::profile_all_processors() {
+            return Err(ErrorType::from(InputError::BadOption(e.to_string())));
+        }
+    }
+    if let Some(interval) = m.get_one::<u64>("detect-loops") {
+        if let Err(e) = cpu::detect_loops_of_all_processors(*interval) {
+            return Err(ErrorType::from(InputError::BadOption(e.to_string())));
+        }
+    }
+    let threads_set = match m.get_one::<u64>("threads") {
+        Some(n) => threads::set_thread_count(*n as usize),
+        None => threads::set_thread_count_from_env(),
+    };
+    if let Err(msg) = threads_set {
+        return Err(ErrorType::from(InputError::BadOption(msg)));
+    }
     match m.value_of_os("input_file") {
         Some(input_file_name) => {
             let path_name = PathBuf::from(input_file_name);
             match input_reader(&path_name) {
                 Err(e) => Err(ErrorType::from(e)),
-                Ok(the_input) => runner(the_input),
+                Ok(the_input) => runner(the_input, &m),
             }
         }
         None => Err(ErrorType::from(InputError::NoInputFile)),
     }
 }
+
+#[test]
+fn test_input_lines() {
+    let text: &'static [u8] = b"first\nsecond\n\nlast";
+    let lines: Result<Vec<String>, InputError> =
+        InputLines::from_reader(None, Box::new(text)).collect();
+    assert_eq!(
+        lines.expect("reading from a slice should not fail"),
+        vec!["first", "second", "", "last"]
+    );
+}
+
+#[test]
+fn test_location_and_context() {
+    let line = "1,2,xyz,4";
+    let loc = Location::of_fragment(3, line, "xyz");
+    assert_eq!(loc, Location { line: 3, column: 5 });
+    assert_eq!(loc.to_string(), "line 3, column 5");
+    assert_eq!(Location::of_fragment(1, line, "absent").column, 1);
+    assert_eq!(context_snippet(line, loc.column), line);
+    let long = "a".repeat(20) + "X" + &"b".repeat(20);
+    assert_eq!(
+        context_snippet(&long, 21),
+        format!("...{}X{}...", "a".repeat(15), "b".repeat(14))
+    );
+}
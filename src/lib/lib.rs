@@ -1,4 +1,17 @@
+pub mod ascii;
+pub mod automaton;
 pub mod cpu;
+pub mod days;
 pub mod error;
 pub mod grid;
 pub mod input;
+pub mod math;
+pub mod optimize;
+pub mod parse;
+pub mod password;
+pub mod png;
+pub mod scaffold;
+pub mod screen;
+pub mod threads;
+pub mod usage;
+pub mod verbosity;
@@ -0,0 +1,366 @@
+//! Modular arithmetic, and iterators over the permutations and
+//! combinations of a slice.
+use std::fmt::{self, Display, Formatter};
+
+/// Returns (g, x, y) such that a·x + b·y = g = gcd(a, b).
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+        (g, y, x - a.div_euclid(b) * y)
+    }
+}
+
+/// The inverse of `a` modulo `m`, if there is one.
+pub fn mod_inverse(a: i128, m: i128) -> Option<i128> {
+    match extended_gcd(a.rem_euclid(m), m) {
+        (1, x, _) => Some(x.rem_euclid(m)),
+        _ => None,
+    }
+}
+
+/// Computes a·b mod m without overflow, even when a·b does not fit
+/// in an i128 (as happens when m is around 2^64 or more).
+fn mul_mod(a: i128, b: i128, m: i128) -> i128 {
+    let (mut a, mut b) = (a.rem_euclid(m), b.rem_euclid(m));
+    match a.checked_mul(b) {
+        Some(product) => product.rem_euclid(m),
+        None => {
+            let mut result = 0;
+            while b > 0 {
+                if b & 1 == 1 {
+                    result = (result + a) % m;
+                }
+                a = (a + a) % m;
+                b >>= 1;
+            }
+            result
+        }
+    }
+}
+
+/// The function x ↦ a·x + b (mod m).
+///
+/// Each of the shuffling techniques of day 22 moves the card at
+/// position x to one of these functions of x, so a whole shuffle
+/// (or many repeats of one) is a single `LinearTransform`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LinearTransform {
+    pub a: i128,
+    pub b: i128,
+    pub m: i128,
+}
+
+impl LinearTransform {
+    /// Panics if `m` is not positive.
+    pub fn new(a: i128, b: i128, m: i128) -> LinearTransform {
+        assert!(m > 0, "modulus {} should be positive", m);
+        LinearTransform {
+            a: a.rem_euclid(m),
+            b: b.rem_euclid(m),
+            m,
+        }
+    }
+
+    pub fn identity(m: i128) -> LinearTransform {
+        LinearTransform::new(1, 0, m)
+    }
+
+    /// Reversing the order of the deck: "deal into new stack".
+    pub fn deal_into_new_stack(m: i128) -> LinearTransform {
+        LinearTransform::new(-1, -1, m)
+    }
+
+    /// Moving the top `n` cards to the bottom: "cut n".
+    pub fn cut(n: i128, m: i128) -> LinearTransform {
+        LinearTransform::new(1, -n, m)
+    }
+
+    /// "deal with increment n".
+    pub fn deal_with_increment(n: i128, m: i128) -> LinearTransform {
+        LinearTransform::new(n, 0, m)
+    }
+
+    pub fn apply(&self, x: i128) -> i128 {
+        (mul_mod(self.a, x, self.m) + self.b) % self.m
+    }
+
+    /// The transform which applies `self` and then `next`.  Panics
+    /// if the moduli differ.
+    pub fn then(&self, next: &LinearTransform) -> LinearTransform {
+        assert_eq!(
+            self.m, next.m,
+            "cannot compose transforms with different moduli"
+        );
+        LinearTransform::new(
+            mul_mod(next.a, self.a, self.m),
+            (mul_mod(next.a, self.b, self.m) + next.b) % self.m,
+            self.m,
+        )
+    }
+
+    /// The transform which applies `self` `n` times over.
+    pub fn pow(&self, mut n: u128) -> LinearTransform {
+        let mut result = LinearTransform::identity(self.m);
+        let mut square = *self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            n >>= 1;
+        }
+        result
+    }
+
+    /// The transform which undoes this one.  There is none when `a`
+    /// shares a factor with `m`.
+    pub fn inverse(&self) -> Option<LinearTransform> {
+        let a_inv = mod_inverse(self.a, self.m)?;
+        Some(LinearTransform::new(
+            a_inv,
+            -mul_mod(a_inv, self.b, self.m),
+            self.m,
+        ))
+    }
+}
+
+impl Display for LinearTransform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "x -> {}x + {} (mod {})", self.a, self.b, self.m)
+    }
+}
+
+#[cfg(test)]
+fn deal(shuffle: &LinearTransform) -> Vec<i128> {
+    // Returns the cards in order of position.
+    let mut deck = vec![0; shuffle.m as usize];
+    for card in 0..shuffle.m {
+        deck[shuffle.apply(card) as usize] = card;
+    }
+    deck
+}
+
+#[test]
+fn test_day22_examples() {
+    let shuffle = LinearTransform::deal_with_increment(7, 10)
+        .then(&LinearTransform::deal_into_new_stack(10))
+        .then(&LinearTransform::deal_into_new_stack(10));
+    assert_eq!(deal(&shuffle), vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]);
+
+    let shuffle = LinearTransform::cut(6, 10)
+        .then(&LinearTransform::deal_with_increment(7, 10))
+        .then(&LinearTransform::deal_into_new_stack(10));
+    assert_eq!(deal(&shuffle), vec![3, 0, 7, 4, 1, 8, 5, 2, 9, 6]);
+
+    assert_eq!(
+        deal(&LinearTransform::cut(-4, 10)),
+        vec![6, 7, 8, 9, 0, 1, 2, 3, 4, 5]
+    );
+}
+
+#[test]
+fn test_pow_and_inverse() {
+    let m = 119315717514047;
+    let t = LinearTransform::deal_with_increment(31, m)
+        .then(&LinearTransform::cut(-8427, m))
+        .then(&LinearTransform::deal_into_new_stack(m));
+    let repeats = 101741582076661;
+    let many = t.pow(repeats);
+    let x = 2020;
+    let mut slow = x;
+    for _ in 0..1000 {
+        slow = t.apply(slow);
+    }
+    assert_eq!(t.pow(1000).apply(x), slow);
+    let undo = many.inverse().expect("m is prime, so a is invertible");
+    assert_eq!(undo.apply(many.apply(x)), x);
+    assert_eq!(many.then(&undo), LinearTransform::identity(m));
+    assert_eq!(LinearTransform::deal_with_increment(2, 10).inverse(), None);
+}
+
+#[test]
+fn test_mul_mod_large() {
+    let m = (1i128 << 100) + 277;
+    let big = m - 1;
+    // (-1)·(-1) = 1
+    assert_eq!(mul_mod(big, big, m), 1);
+    assert_eq!(mod_inverse(big, m), Some(big));
+}
+
+/// Rearrange `a` into the next permutation in lexicographic order.
+/// Returns false (leaving `a` unchanged) if `a` is the last one.
+fn next_permutation(a: &mut [usize]) -> bool {
+    let i = match (1..a.len()).rev().find(|&i| a[i - 1] < a[i]) {
+        Some(i) => i - 1,
+        None => {
+            return false;
+        }
+    };
+    let j = (i + 1..a.len())
+        .rev()
+        .find(|&j| a[j] > a[i])
+        .expect("a[i + 1] is larger than a[i]");
+    a.swap(i, j);
+    a[i + 1..].reverse();
+    true
+}
+
+/// See `permutations` and `partial_permutations`.
+#[derive(Debug, Clone)]
+pub struct Permutations<'a, T> {
+    values: &'a [T],
+    /// A permutation of the positions of `values`; the first `k`
+    /// of them are the next item.  The rest are kept in ascending
+    /// order.
+    indices: Vec<usize>,
+    k: usize,
+    done: bool,
+}
+
+impl<T: Clone> Iterator for Permutations<'_, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.done {
+            return None;
+        }
+        let item: Vec<T> = self.indices[..self.k]
+            .iter()
+            .map(|&i| self.values[i].clone())
+            .collect();
+        // Reversing the unused positions makes them the last
+        // arrangement of themselves, so that the next permutation
+        // of the whole changes the first k.
+        self.indices[self.k..].reverse();
+        self.done = !next_permutation(&mut self.indices);
+        Some(item)
+    }
+}
+
+/// Every ordering of `values`, in lexicographic order of their
+/// positions in `values` (so if `values` is sorted, the orderings
+/// are too).  Values are not compared, so if some are equal the
+/// same ordering appears more than once.
+pub fn permutations<T: Clone>(values: &[T]) -> Permutations<'_, T> {
+    partial_permutations(values, values.len())
+}
+
+/// Every ordered selection of `k` of `values`, in lexicographic
+/// order of their positions in `values`.  There are none if `k` is
+/// larger than the number of values.
+pub fn partial_permutations<T: Clone>(values: &[T], k: usize) -> Permutations<'_, T> {
+    Permutations {
+        values,
+        indices: (0..values.len()).collect(),
+        k,
+        done: k > values.len(),
+    }
+}
+
+/// See `combinations`.
+#[derive(Debug, Clone)]
+pub struct Combinations<'a, T> {
+    values: &'a [T],
+    /// The positions of the next item, in ascending order.
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl<T: Clone> Iterator for Combinations<'_, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.done {
+            return None;
+        }
+        let item: Vec<T> = self
+            .indices
+            .iter()
+            .map(|&i| self.values[i].clone())
+            .collect();
+        // Advance the last position which is not already as far
+        // right as it can go, and put the later ones just after it.
+        let (n, k) = (self.values.len(), self.indices.len());
+        match (0..k).rev().find(|&i| self.indices[i] != i + n - k) {
+            Some(i) => {
+                self.indices[i] += 1;
+                for j in i + 1..k {
+                    self.indices[j] = self.indices[j - 1] + 1;
+                }
+            }
+            None => {
+                self.done = true;
+            }
+        }
+        Some(item)
+    }
+}
+
+/// Every selection of `k` of `values`, each in the order they
+/// appear in `values`.  The selections are in lexicographic order of
+/// their positions.  There are none if `k` is larger than the number
+/// of values.
+pub fn combinations<T: Clone>(values: &[T], k: usize) -> Combinations<'_, T> {
+    Combinations {
+        values,
+        indices: (0..k).collect(),
+        done: k > values.len(),
+    }
+}
+
+#[test]
+fn test_permutations() {
+    let all: Vec<Vec<char>> = permutations(&['a', 'b', 'c']).collect();
+    assert_eq!(
+        all,
+        vec![
+            vec!['a', 'b', 'c'],
+            vec!['a', 'c', 'b'],
+            vec!['b', 'a', 'c'],
+            vec!['b', 'c', 'a'],
+            vec!['c', 'a', 'b'],
+            vec!['c', 'b', 'a'],
+        ]
+    );
+    let digits: Vec<u32> = (0..6).collect();
+    assert_eq!(permutations(&digits[..5]).count(), 120);
+    assert_eq!(partial_permutations(&digits, 4).count(), 360);
+    let pairs: Vec<Vec<u32>> = partial_permutations(&digits[..3], 2).collect();
+    assert_eq!(
+        pairs,
+        vec![
+            vec![0, 1],
+            vec![0, 2],
+            vec![1, 0],
+            vec![1, 2],
+            vec![2, 0],
+            vec![2, 1]
+        ]
+    );
+    assert_eq!(partial_permutations(&digits, 0).count(), 1);
+    assert_eq!(partial_permutations(&digits, 7).count(), 0);
+    assert_eq!(permutations::<u32>(&[]).count(), 1);
+}
+
+#[test]
+fn test_combinations() {
+    let digits: Vec<u32> = (0..6).collect();
+    assert_eq!(combinations(&digits, 3).count(), 20);
+    assert_eq!(combinations(&digits, 6).count(), 1);
+    assert_eq!(combinations(&digits, 0).count(), 1);
+    assert_eq!(combinations(&digits, 7).count(), 0);
+    // Day 25 needs every subset of the items.
+    assert_eq!(
+        (0..=digits.len())
+            .map(|k| combinations(&digits, k).count())
+            .sum::<usize>(),
+        64
+    );
+    let triples: Vec<Vec<u32>> = combinations(&digits[..4], 3).collect();
+    assert_eq!(
+        triples,
+        vec![vec![0, 1, 2], vec![0, 1, 3], vec![0, 2, 3], vec![1, 2, 3]]
+    );
+}
@@ -0,0 +1,99 @@
+//! Brute-force searches for the best arrangement of some values,
+//! such as the phase settings of the day 7 amplifiers.
+use crate::math::partial_permutations;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// The first of `candidates` with the highest score.
+fn first_best<T, S: Ord>(candidates: impl Iterator<Item = (S, Vec<T>)>) -> Option<(S, Vec<T>)> {
+    candidates.fold(None, |best, (score, arrangement)| match best {
+        Some((ref best_score, _)) if *best_score >= score => best,
+        _ => Some((score, arrangement)),
+    })
+}
+
+/// Try every ordered selection of `k` of `values`, scoring each with
+/// `eval`, and return the highest score with the selection which
+/// achieved it.  If several selections share the highest score, the
+/// first (in the lexicographic order of positions in `values`) is
+/// chosen.  Returns `None` if there are fewer than `k` values, and
+/// the first error from `eval` if there is one.
+pub fn best_selection<T, S, E, F>(values: &[T], k: usize, eval: F) -> Result<Option<(S, Vec<T>)>, E>
+where
+    T: Clone,
+    S: Ord,
+    F: Fn(&[T]) -> Result<S, E>,
+{
+    let mut scored = Vec::new();
+    for arrangement in partial_permutations(values, k) {
+        scored.push((eval(&arrangement)?, arrangement));
+    }
+    Ok(first_best(scored.into_iter()))
+}
+
+/// Like `best_selection`, but the selections are scored in parallel
+/// when the `rayon` feature is enabled (using the number of threads
+/// configured in `crate::threads`).  The result is the same.
+pub fn best_selection_parallel<T, S, E, F>(
+    values: &[T],
+    k: usize,
+    eval: F,
+) -> Result<Option<(S, Vec<T>)>, E>
+where
+    T: Clone + Send + Sync,
+    S: Ord + Send,
+    E: Send,
+    F: Fn(&[T]) -> Result<S, E> + Sync,
+{
+    #[cfg(feature = "rayon")]
+    {
+        let arrangements: Vec<Vec<T>> = partial_permutations(values, k).collect();
+        let scores: Vec<S> = arrangements
+            .par_iter()
+            .map(|arrangement| eval(arrangement))
+            .collect::<Result<Vec<S>, E>>()?;
+        Ok(first_best(scores.into_iter().zip(arrangements)))
+    }
+    #[cfg(not(feature = "rayon"))]
+    best_selection(values, k, eval)
+}
+
+/// Try every permutation of `values`; see `best_selection`.
+pub fn best_permutation<T, S, E, F>(values: &[T], eval: F) -> Result<Option<(S, Vec<T>)>, E>
+where
+    T: Clone,
+    S: Ord,
+    F: Fn(&[T]) -> Result<S, E>,
+{
+    best_selection(values, values.len(), eval)
+}
+
+#[test]
+fn test_best_permutation() {
+    // The best ordering puts the largest weights first.
+    let score = |p: &[i64]| -> Result<i64, ()> {
+        Ok(p.iter().enumerate().map(|(i, v)| v * (10 - i as i64)).sum())
+    };
+    assert_eq!(
+        best_permutation(&[1, 3, 2], score),
+        Ok(Some((10 * 3 + 9 * 2 + 8, vec![3, 2, 1])))
+    );
+    assert_eq!(
+        best_selection_parallel(&[1, 3, 2], 3, score),
+        Ok(Some((10 * 3 + 9 * 2 + 8, vec![3, 2, 1])))
+    );
+    // Every arrangement scores the same, so the first wins.
+    assert_eq!(
+        best_selection(&[5, 6, 7], 2, |_| -> Result<i64, ()> { Ok(0) }),
+        Ok(Some((0, vec![5, 6])))
+    );
+    assert_eq!(
+        best_selection(&[5, 6], 3, |_| -> Result<i64, ()> { Ok(0) }),
+        Ok(None)
+    );
+    assert_eq!(
+        best_permutation(&[1, 2], |p| if p[0] == 2 { Err("no") } else { Ok(1) }),
+        Err("no")
+    );
+}
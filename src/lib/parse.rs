@@ -0,0 +1,189 @@
+//! Helpers for the split-and-parse patterns which recur in puzzle
+//! inputs, such as "7 A, 1 B => 1 C" (day 14) or "COM)B" (day 6).
+//! Errors carry the fragment of the input which could not be parsed,
+//! so that it can be located within the line.
+use std::any::Any;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::Fail;
+use crate::input::{context_snippet, Location};
+
+/// Part of the input could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadFragment {
+    /// The part of the input which is wrong.
+    pub fragment: String,
+    pub msg: String,
+}
+
+impl BadFragment {
+    pub fn new(fragment: &str, msg: String) -> BadFragment {
+        BadFragment {
+            fragment: fragment.to_string(),
+            msg,
+        }
+    }
+
+    /// Describe the error as happening in `line`, which is line
+    /// number `line_number` of the input, showing where in the line
+    /// the bad fragment is.
+    pub fn in_line(&self, line_number: usize, line: &str) -> Fail {
+        let location = Location::of_fragment(line_number, line, &self.fragment);
+        Fail(format!(
+            "bad input at {} (in \"{}\"): {}",
+            location,
+            context_snippet(line, location.column),
+            self
+        ))
+    }
+}
+
+impl Display for BadFragment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for BadFragment {}
+
+impl From<BadFragment> for Fail {
+    fn from(e: BadFragment) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// Parse `s`, ignoring surrounding whitespace.  If `T` is itself
+/// parsed with these helpers, its error (which identifies the bad
+/// part of `s` more precisely) is returned as it is.
+pub fn parse_fragment<T>(s: &str) -> Result<T, BadFragment>
+where
+    T: FromStr,
+    T::Err: Display + 'static,
+{
+    let s = s.trim();
+    s.parse().map_err(
+        |e: T::Err| match (&e as &dyn Any).downcast_ref::<BadFragment>() {
+            Some(inner) => inner.clone(),
+            None => BadFragment::new(s, format!("invalid value '{}': {}", s, e)),
+        },
+    )
+}
+
+/// Split `s` at the first `sep`, trimming whitespace from both parts.
+pub fn split_once_required<'a>(s: &'a str, sep: &str) -> Result<(&'a str, &'a str), BadFragment> {
+    match s.split_once(sep) {
+        Some((a, b)) => Ok((a.trim(), b.trim())),
+        None => Err(BadFragment::new(
+            s,
+            format!("expected '{}' in '{}'", sep.trim(), s.trim()),
+        )),
+    }
+}
+
+/// Split `s` at the first `sep` and parse both parts.  For example
+/// `split_once_parsed::<i64, String>("7 A", " ")` is `(7, "A")`.
+pub fn split_once_parsed<A, B>(s: &str, sep: &str) -> Result<(A, B), BadFragment>
+where
+    A: FromStr,
+    A::Err: Display + 'static,
+    B: FromStr,
+    B::Err: Display + 'static,
+{
+    let (a, b) = split_once_required(s, sep)?;
+    Ok((parse_fragment(a)?, parse_fragment(b)?))
+}
+
+/// Parse each of the items of `s` which are separated by `sep`.
+/// Whitespace around the items is ignored, so for example the items
+/// of "1, 2,3" are separated by ",".
+pub fn parse_separated_list<T>(s: &str, sep: &str) -> Result<Vec<T>, BadFragment>
+where
+    T: FromStr,
+    T::Err: Display + 'static,
+{
+    s.split(sep).map(parse_fragment).collect()
+}
+
+/// Split `s` at the first arrow, "=>" or "->", trimming whitespace
+/// from both sides.
+pub fn split_arrow(s: &str) -> Result<(&str, &str), BadFragment> {
+    let arrow = ["=>", "->"]
+        .into_iter()
+        .filter_map(|arrow| s.find(arrow))
+        .min()
+        .ok_or_else(|| BadFragment::new(s, format!("expected '=>' or '->' in '{}'", s.trim())))?;
+    Ok((s[..arrow].trim(), s[arrow + 2..].trim()))
+}
+
+/// Parse both sides of an arrow (see `split_arrow`).
+pub fn parse_arrow_pair<A, B>(s: &str) -> Result<(A, B), BadFragment>
+where
+    A: FromStr,
+    A::Err: Display + 'static,
+    B: FromStr,
+    B::Err: Display + 'static,
+{
+    let (a, b) = split_arrow(s)?;
+    Ok((parse_fragment(a)?, parse_fragment(b)?))
+}
+
+#[test]
+fn test_split_once_parsed() {
+    assert_eq!(
+        split_once_parsed::<i64, String>("7 A", " "),
+        Ok((7, "A".to_string()))
+    );
+    assert_eq!(
+        split_once_parsed::<i64, String>("x A", " "),
+        Err(BadFragment::new(
+            "x",
+            "invalid value 'x': invalid digit found in string".to_string()
+        ))
+    );
+    let missing = split_once_parsed::<String, String>("COMB", ")").unwrap_err();
+    assert_eq!(missing.to_string(), "expected ')' in 'COMB'");
+}
+
+#[test]
+fn test_parse_separated_list() {
+    assert_eq!(parse_separated_list::<u8>("1, 2,3", ","), Ok(vec![1, 2, 3]));
+    let err = parse_separated_list::<u8>("1, 2x, 3", ",").unwrap_err();
+    assert_eq!(err.fragment, "2x");
+    let fail = err.in_line(4, "1, 2x, 3");
+    assert!(
+        fail.0.starts_with("bad input at line 4, column 4"),
+        "{}",
+        fail
+    );
+}
+
+#[test]
+fn test_nested() {
+    #[derive(Debug, PartialEq, Eq)]
+    struct Pair(u8, u8);
+
+    impl FromStr for Pair {
+        type Err = BadFragment;
+
+        fn from_str(s: &str) -> Result<Pair, BadFragment> {
+            let (a, b) = split_once_parsed(s, "/")?;
+            Ok(Pair(a, b))
+        }
+    }
+
+    assert_eq!(
+        parse_separated_list::<Pair>("1/2, 3/4", ","),
+        Ok(vec![Pair(1, 2), Pair(3, 4)])
+    );
+    let err = parse_separated_list::<Pair>("1/2, 3/x", ",").unwrap_err();
+    assert_eq!(err.fragment, "x");
+}
+
+#[test]
+fn test_arrow_pair() {
+    assert_eq!(split_arrow("7 A, 1 B => 1 C"), Ok(("7 A, 1 B", "1 C")));
+    assert_eq!(split_arrow("A->B=>C"), Ok(("A", "B=>C")));
+    assert_eq!(parse_arrow_pair::<u8, u8>(" 1 -> 2 "), Ok((1, 2)));
+    assert!(split_arrow("A B").is_err());
+}
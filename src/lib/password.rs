@@ -0,0 +1,225 @@
+//! The password rules of day 4.  A valid password's digits never
+//! decrease from left to right, and it contains a run of repeated
+//! digits which satisfies a `RepeatRule`.
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Which runs of repeated adjacent digits qualify a password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatRule {
+    /// Some digit occurs at least twice in a row (part 1).
+    AtLeastTwo,
+    /// Some digit occurs exactly twice in a row, that is, as part of
+    /// no longer run (part 2).
+    ExactlyTwo,
+}
+
+impl RepeatRule {
+    fn run_qualifies(&self, run: u32) -> bool {
+        match self {
+            RepeatRule::AtLeastTwo => run >= 2,
+            RepeatRule::ExactlyTwo => run == 2,
+        }
+    }
+
+    /// Runs longer than this are all alike as far as the rule is
+    /// concerned.
+    fn longest_distinct_run(&self) -> u32 {
+        match self {
+            RepeatRule::AtLeastTwo => 2,
+            RepeatRule::ExactlyTwo => 3,
+        }
+    }
+}
+
+fn digits_of(mut n: u64) -> Vec<u8> {
+    let mut digits = Vec::new();
+    loop {
+        digits.push((n % 10) as u8);
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+/// Progress through the digits of a candidate password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Scan {
+    last: Option<u8>,
+    run: u32,
+    satisfied: bool,
+}
+
+impl Scan {
+    fn new() -> Scan {
+        Scan {
+            last: None,
+            run: 0,
+            satisfied: false,
+        }
+    }
+
+    /// The scan after appending `digit`, or `None` if that would make
+    /// the digits decrease.
+    fn push(&self, digit: u8, rule: RepeatRule) -> Option<Scan> {
+        match self.last {
+            Some(last) if digit < last => None,
+            Some(last) if digit == last => Some(Scan {
+                run: (self.run + 1).min(rule.longest_distinct_run()),
+                ..*self
+            }),
+            _ => Some(Scan {
+                last: Some(digit),
+                run: 1,
+                satisfied: self.satisfied || rule.run_qualifies(self.run),
+            }),
+        }
+    }
+
+    fn accepts(&self, rule: RepeatRule) -> bool {
+        self.satisfied || rule.run_qualifies(self.run)
+    }
+}
+
+/// Returns true if `password` obeys the rules.
+pub fn is_valid_password(password: u64, rule: RepeatRule) -> bool {
+    digits_of(password)
+        .into_iter()
+        .try_fold(Scan::new(), |scan, digit| scan.push(digit, rule))
+        .is_some_and(|scan| scan.accepts(rule))
+}
+
+struct Counter {
+    rule: RepeatRule,
+    memo: HashMap<(usize, Scan), u64>,
+}
+
+impl Counter {
+    /// The number of ways of appending `remaining` more digits to
+    /// `scan` which give a valid password.
+    fn completions(&mut self, remaining: usize, scan: Scan) -> u64 {
+        if remaining == 0 {
+            return u64::from(scan.accepts(self.rule));
+        }
+        if let Some(n) = self.memo.get(&(remaining, scan)) {
+            return *n;
+        }
+        let rule = self.rule;
+        let n = (0..=9)
+            .filter_map(|digit| scan.push(digit, rule))
+            .map(|next| self.completions(remaining - 1, next))
+            .sum();
+        self.memo.insert((remaining, scan), n);
+        n
+    }
+
+    /// The number of valid passwords in `0..=limit`.
+    fn count_up_to(&mut self, limit: u64) -> u64 {
+        let digits = digits_of(limit);
+        // Numbers with fewer digits than `limit`.  Since digits
+        // cannot decrease, none of these can start with 0.
+        let mut total: u64 = 0;
+        for len in 1..digits.len() {
+            for first in 1..=9 {
+                if let Some(scan) = Scan::new().push(first, self.rule) {
+                    total += self.completions(len - 1, scan);
+                }
+            }
+        }
+        // Numbers with as many digits as `limit`: those which match
+        // its first i digits and then have a smaller digit.
+        let mut scan = Scan::new();
+        for (i, digit) in digits.iter().enumerate() {
+            let lowest = if i == 0 { 1 } else { 0 };
+            for smaller in lowest..*digit {
+                if let Some(next) = scan.push(smaller, self.rule) {
+                    total += self.completions(digits.len() - i - 1, next);
+                }
+            }
+            match scan.push(*digit, self.rule) {
+                Some(next) => {
+                    scan = next;
+                }
+                None => {
+                    return total;
+                }
+            }
+        }
+        // Finally, `limit` itself.
+        total + u64::from(scan.accepts(self.rule))
+    }
+}
+
+/// Count the valid passwords in `range`.  This takes time
+/// proportional to the number of digits, not the size of the range.
+pub fn count_valid_passwords(range: RangeInclusive<u64>, rule: RepeatRule) -> u64 {
+    let (begin, end) = range.into_inner();
+    if begin > end {
+        return 0;
+    }
+    let mut counter = Counter {
+        rule,
+        memo: HashMap::new(),
+    };
+    let below = match begin.checked_sub(1) {
+        Some(n) => counter.count_up_to(n),
+        None => 0,
+    };
+    counter.count_up_to(end) - below
+}
+
+#[test]
+fn test_is_valid_password() {
+    use RepeatRule::*;
+    assert!(is_valid_password(111111, AtLeastTwo));
+    assert!(!is_valid_password(223450, AtLeastTwo));
+    assert!(!is_valid_password(123789, AtLeastTwo));
+    assert!(is_valid_password(112345, AtLeastTwo));
+    assert!(is_valid_password(122345, AtLeastTwo));
+    assert!(is_valid_password(1356799, AtLeastTwo));
+
+    assert!(is_valid_password(112233, ExactlyTwo));
+    assert!(!is_valid_password(123444, ExactlyTwo));
+    assert!(is_valid_password(111122, ExactlyTwo));
+    assert!(is_valid_password(11122, ExactlyTwo));
+}
+
+#[test]
+fn test_count_valid_passwords() {
+    for rule in [RepeatRule::AtLeastTwo, RepeatRule::ExactlyTwo] {
+        for range in [0..=0, 0..=11, 10..=99, 99..=1234, 123456..=178999] {
+            let expected = range
+                .clone()
+                .filter(|pw| is_valid_password(*pw, rule))
+                .count() as u64;
+            assert_eq!(
+                count_valid_passwords(range.clone(), rule),
+                expected,
+                "range {:?}, rule {:?}",
+                range,
+                rule
+            );
+        }
+    }
+    #[allow(clippy::reversed_empty_ranges)]
+    let empty = 7..=5;
+    assert_eq!(count_valid_passwords(empty, RepeatRule::AtLeastTwo), 0);
+    // Of the numbers below 10^19, C(28, 9) - 1 have non-decreasing
+    // digits (which cannot include 0).  All of those have a repeated
+    // digit except the C(9, k) of each length k whose digits strictly
+    // increase.
+    let non_decreasing: u64 = binomial(28, 9) - 1;
+    let distinct: u64 = (1..=9).map(|k| binomial(9, k)).sum();
+    assert_eq!(
+        count_valid_passwords(0..=9_999_999_999_999_999_999, RepeatRule::AtLeastTwo),
+        non_decreasing - distinct
+    );
+}
+
+#[cfg(test)]
+fn binomial(n: u64, k: u64) -> u64 {
+    (1..=k).fold(1, |acc, i| acc * (n + 1 - i) / i)
+}
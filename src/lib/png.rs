@@ -0,0 +1,128 @@
+//! A minimal PNG encoder for saving pictures such as the day 11
+//! hull painting.  Images are 8-bit greyscale and are stored without
+//! compression, which keeps the encoder small at the cost of larger
+//! files.
+use std::io::{self, Write};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// The largest amount of data a stored deflate block can hold.
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffff_ffff;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "PNG chunk is too large"))?;
+    out.write_all(&len.to_be_bytes())?;
+    let mut body = Vec::with_capacity(4 + data.len());
+    body.extend_from_slice(kind);
+    body.extend_from_slice(data);
+    out.write_all(&body)?;
+    out.write_all(&crc32(&body).to_be_bytes())
+}
+
+/// Wraps `data` in a zlib stream of uncompressed blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut result = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        result.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        result.push(u8::from(last));
+        result.extend_from_slice(&len.to_le_bytes());
+        result.extend_from_slice(&(!len).to_le_bytes());
+        result.extend_from_slice(block);
+    }
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+    result
+}
+
+/// Writes a `width` by `height` greyscale image whose pixels are
+/// given row by row in `pixels` (0 is black, 255 white).
+pub fn write_greyscale<W: Write>(
+    out: &mut W,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> io::Result<()> {
+    if pixels.len() as u64 != u64::from(width) * u64::from(height) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} pixels do not make a {}x{} image",
+                pixels.len(),
+                width,
+                height
+            ),
+        ));
+    }
+    out.write_all(&SIGNATURE)?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, colour type 0 (greyscale), then the default
+    // compression, filter and interlace methods.
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &header)?;
+    let mut raw = Vec::with_capacity(pixels.len() + height as usize);
+    if width > 0 {
+        for row in pixels.chunks(width as usize) {
+            raw.push(0); // no filter
+            raw.extend_from_slice(row);
+        }
+    }
+    write_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+#[test]
+fn test_checksums() {
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+}
+
+#[test]
+fn test_write_greyscale() {
+    let mut out: Vec<u8> = Vec::new();
+    write_greyscale(&mut out, 2, 1, &[0, 255]).expect("writing to a Vec should work");
+    assert_eq!(&out[0..8], &SIGNATURE);
+    // IHDR: length 13, then the width and height.
+    assert_eq!(&out[8..16], &[0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+    assert_eq!(&out[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+    // The data is one row: filter byte, then the two pixels.
+    let idat = out
+        .windows(4)
+        .position(|w| w == b"IDAT")
+        .expect("there should be an IDAT chunk");
+    assert_eq!(&out[idat + 4..idat + 6], &[0x78, 0x01]);
+    assert_eq!(&out[idat + 6..idat + 11], &[1, 3, 0, 0xfc, 0xff]);
+    assert_eq!(&out[idat + 11..idat + 14], &[0, 0, 255]);
+    assert!(out.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    assert!(write_greyscale(&mut out, 2, 2, &[0, 255]).is_err());
+}
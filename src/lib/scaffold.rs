@@ -0,0 +1,274 @@
+//! Generates the files for a new day, so that it does not have to
+//! start as a trimmed-down copy of an old one.  A new day has a
+//! module in `lib::days` with a placeholder `solve` function, a
+//! binary which calls it, an entry in `DAYS` and a `[[bin]]` entry in
+//! `Cargo.toml`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Fail;
+
+const MODULE_TEMPLATE: &str = r#"//! Day @DAY@: @TITLE@.
+use crate::error::Fail;
+
+/// Solve both parts.
+pub fn solve(_input: &str) -> Result<(String, Option<String>), Fail> {
+    Err(Fail("day @DAY@ is not solved yet".to_string()))
+}
+
+#[test]
+fn test_solve() {}
+"#;
+
+const BINARY_TEMPLATE: &str = r#"use clap::{Arg, ArgMatches};
+
+use lib::days::day@DD@::solve;
+use lib::error::{Fail, ResultExt};
+use lib::input::{read_file_as_string, run_with_input_and_options};
+
+/// The command-line options of this day, in addition to the usual
+/// ones.
+fn extra_args() -> Vec<Arg<'static>> {
+    Vec::new()
+}
+
+fn run(input: String, _matches: &ArgMatches) -> Result<(), Fail> {
+    let (part1, part2) = solve(&input).context("failed to solve day @DAY@")?;
+    println!("Day @DD@ part 1: {}", part1);
+    if let Some(part2) = part2 {
+        println!("Day @DD@ part 2: {}", part2);
+    }
+    Ok(())
+}
+
+/// Usage: day@DD@ inputs/day@DD@.txt
+fn main() -> Result<(), Fail> {
+    run_with_input_and_options(@DAY@, extra_args(), read_file_as_string, run)
+}
+"#;
+
+fn expand(template: &str, day: u8, title: &str) -> String {
+    template
+        .replace("@DD@", &format!("{:02}", day))
+        .replace("@DAY@", &day.to_string())
+        .replace("@TITLE@", title)
+}
+
+/// The source of the `lib::days` module of a new day.
+pub fn module_source(day: u8, title: &str) -> String {
+    expand(MODULE_TEMPLATE, day, title)
+}
+
+/// The source of the binary of a new day.
+pub fn binary_source(day: u8) -> String {
+    expand(BINARY_TEMPLATE, day, "")
+}
+
+/// The day numbered by `line` (which has the form `prefix` NN
+/// `suffix`, after indentation), if it is one.
+fn numbered(line: &str, prefix: &str, suffix: &str) -> Option<u8> {
+    line.trim_start()
+        .strip_prefix(prefix)?
+        .strip_suffix(suffix)?
+        .parse()
+        .ok()
+}
+
+/// Insert `block` into `lines`, which contains a sequence of blocks
+/// of the same length, each for one day.  `starts` gives the number
+/// of each day and the index of the first line of its block, in
+/// order.  The new block goes before the first block of a later day,
+/// or after the last block.  Fails if `day` already has a block, or
+/// there are no blocks.
+fn insert_in_order(
+    lines: &mut Vec<String>,
+    day: u8,
+    block: Vec<String>,
+    starts: &[(u8, usize)],
+    what: &str,
+) -> Result<(), Fail> {
+    if starts.iter().any(|(n, _)| *n == day) {
+        return Err(Fail(format!("day {} already has {}", day, what)));
+    }
+    let index = match starts.iter().find(|(n, _)| *n > day) {
+        Some((_, start)) => *start,
+        None => match starts.last() {
+            Some((_, start)) => start + block.len(),
+            None => return Err(Fail(format!("could not find where to add {}", what))),
+        },
+    };
+    lines.splice(index..index, block);
+    Ok(())
+}
+
+fn to_lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+fn from_lines(lines: &[String]) -> String {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Add day `day` to `src/lib/days/mod.rs` (whose content is
+/// `days_mod`), declaring its module and adding it to `DAYS`.
+pub fn register_module(days_mod: &str, day: u8, title: &str) -> Result<String, Fail> {
+    let mut lines = to_lines(days_mod);
+    let modules: Vec<(u8, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| Some((numbered(line, "pub mod day", ";")?, i)))
+        .collect();
+    insert_in_order(
+        &mut lines,
+        day,
+        vec![format!("pub mod day{:02};", day)],
+        &modules,
+        "a module",
+    )?;
+
+    let header = lines
+        .iter()
+        .position(|line| line.starts_with("pub const DAYS"))
+        .ok_or_else(|| Fail("could not find DAYS".to_string()))?;
+    // Each entry of DAYS starts with a "Day {" line, just before the
+    // one giving its number.
+    let entries: Vec<(u8, usize)> = lines
+        .iter()
+        .enumerate()
+        .skip(header)
+        .take_while(|(_, line)| *line != "];")
+        .filter_map(|(i, line)| Some((numbered(line, "number: ", ",")?, i - 1)))
+        .collect();
+    let entry = vec![
+        "    Day {".to_string(),
+        format!("        number: {},", day),
+        format!("        title: {:?},", title),
+        "        parts_solved: 0,".to_string(),
+        format!("        solve: day{:02}::solve,", day),
+        "    },".to_string(),
+    ];
+    insert_in_order(&mut lines, day, entry, &entries, "an entry in DAYS")?;
+    lines[header] = format!("pub const DAYS: [Day; {}] = [", entries.len() + 1);
+    Ok(from_lines(&lines))
+}
+
+/// Add the binary of day `day` to `Cargo.toml` (whose content is
+/// `manifest`).
+pub fn register_binary(manifest: &str, day: u8) -> Result<String, Fail> {
+    let mut lines = to_lines(manifest);
+    let binaries: Vec<(u8, usize)> = lines
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, _)| lines[i - 1] == "[[bin]]")
+        .filter_map(|(i, line)| Some((numbered(line, "name = \"day", "\"")?, i - 1)))
+        .collect();
+    insert_in_order(
+        &mut lines,
+        day,
+        vec!["[[bin]]".to_string(), format!("name = \"day{:02}\"", day)],
+        &binaries,
+        "a binary",
+    )?;
+    Ok(from_lines(&lines))
+}
+
+fn read(path: &Path) -> Result<String, Fail> {
+    fs::read_to_string(path).map_err(|e| Fail(format!("failed to read {}: {}", path.display(), e)))
+}
+
+fn write(path: &Path, content: &str) -> Result<(), Fail> {
+    fs::write(path, content).map_err(|e| Fail(format!("failed to write {}: {}", path.display(), e)))
+}
+
+/// Create the files for day `day` (whose puzzle is called `title`)
+/// in the crate whose top directory is `root`, and register it.
+/// Returns the paths of the files which were created or changed.
+/// Nothing is changed if any of the files cannot be updated.
+pub fn create_day(root: &Path, day: u8, title: &str) -> Result<Vec<PathBuf>, Fail> {
+    let manifest_path = root.join("Cargo.toml");
+    let days_mod_path = root.join("src/lib/days/mod.rs");
+    let module_path = root.join(format!("src/lib/days/day{:02}.rs", day));
+    let binary_path = root.join(format!("src/bin/day{:02}.rs", day));
+    for path in [&module_path, &binary_path] {
+        if path.exists() {
+            return Err(Fail(format!("{} already exists", path.display())));
+        }
+    }
+    let manifest = register_binary(&read(&manifest_path)?, day)?;
+    let days_mod = register_module(&read(&days_mod_path)?, day, title)?;
+    write(&module_path, &module_source(day, title))?;
+    write(&binary_path, &binary_source(day))?;
+    write(&days_mod_path, &days_mod)?;
+    write(&manifest_path, &manifest)?;
+    Ok(vec![module_path, binary_path, days_mod_path, manifest_path])
+}
+
+#[test]
+fn test_register_module() {
+    let days_mod = r#"pub mod day01;
+pub mod day03;
+
+pub const DAYS: [Day; 2] = [
+    Day {
+        number: 1,
+        title: "One",
+        parts_solved: 2,
+        solve: day01::solve,
+    },
+    Day {
+        number: 3,
+        title: "Three",
+        parts_solved: 1,
+        solve: day03::solve,
+    },
+];
+"#;
+    let updated = register_module(days_mod, 2, "Two \"2\"").expect("day 2 should be added");
+    assert_eq!(
+        updated,
+        r#"pub mod day01;
+pub mod day02;
+pub mod day03;
+
+pub const DAYS: [Day; 3] = [
+    Day {
+        number: 1,
+        title: "One",
+        parts_solved: 2,
+        solve: day01::solve,
+    },
+    Day {
+        number: 2,
+        title: "Two \"2\"",
+        parts_solved: 0,
+        solve: day02::solve,
+    },
+    Day {
+        number: 3,
+        title: "Three",
+        parts_solved: 1,
+        solve: day03::solve,
+    },
+];
+"#
+    );
+    let last = register_module(days_mod, 4, "Four").expect("day 4 should be added");
+    assert!(last.contains("pub mod day03;\npub mod day04;\n"));
+    assert!(last.contains("        solve: day04::solve,\n    },\n];\n"));
+    assert!(register_module(days_mod, 3, "Three").is_err());
+}
+
+#[test]
+fn test_register_binary() {
+    let manifest = "[lib]\nname = \"lib\"\n\n[[bin]]\nname = \"day01\"\n[[bin]]\nname = \"aoc\"\n";
+    assert_eq!(
+        register_binary(manifest, 2).expect("day 2 should be added"),
+        "[lib]\nname = \"lib\"\n\n[[bin]]\nname = \"day01\"\n[[bin]]\nname = \"day02\"\n[[bin]]\nname = \"aoc\"\n"
+    );
+    assert!(register_binary(manifest, 1).is_err());
+    assert!(binary_source(18).contains("use lib::days::day18::solve;"));
+    assert!(module_source(18, "Many-Worlds Interpretation").starts_with("//! Day 18: Many-Worlds"));
+}
@@ -0,0 +1,784 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::cpu::{InputOutputError, Word};
+use crate::error::Fail;
+use crate::grid::Position;
+
+/// A command emitted by a program which draws on a `Screen`, as
+/// the (x, y, tile) triples of day 13.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DrawCommand {
+    DrawTile { pos: Position, tile: i64 },
+    UpdateScore(i64),
+}
+
+/// Fails if a value does not fit in an `i64`, which can only happen
+/// with the `wide-words` feature.
+impl TryFrom<[Word; 3]> for DrawCommand {
+    type Error = InputOutputError;
+
+    fn try_from(chunk: [Word; 3]) -> Result<DrawCommand, InputOutputError> {
+        match chunk {
+            [Word(-1), Word(0), score] => score
+                .as_i64()
+                .map(DrawCommand::UpdateScore)
+                .ok_or(InputOutputError::Rejected(score)),
+            [x, y, tile] => {
+                let pos = match (x.as_i64(), y.as_i64()) {
+                    (Some(x), Some(y)) => Position { x, y },
+                    _ => {
+                        return Err(InputOutputError::OffScreen { x, y });
+                    }
+                };
+                match tile.as_i64() {
+                    Some(tile) => Ok(DrawCommand::DrawTile { pos, tile }),
+                    None => Err(InputOutputError::Rejected(tile)),
+                }
+            }
+        }
+    }
+}
+
+/// A tile was drawn at a position which has no place on the screen,
+/// either because it is negative or because it is too far away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffScreen(pub Position);
+
+impl Display for OffScreen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot draw at {} as it is off the screen", self.0)
+    }
+}
+
+impl std::error::Error for OffScreen {}
+
+impl From<OffScreen> for Fail {
+    fn from(e: OffScreen) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// An inclusive rectangle of screen positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub top_left: Position,
+    pub bottom_right: Position,
+}
+
+impl Rect {
+    fn single(pos: Position) -> Rect {
+        Rect {
+            top_left: pos,
+            bottom_right: pos,
+        }
+    }
+
+    fn including(&self, pos: Position) -> Rect {
+        Rect {
+            top_left: Position {
+                x: self.top_left.x.min(pos.x),
+                y: self.top_left.y.min(pos.y),
+            },
+            bottom_right: Position {
+                x: self.bottom_right.x.max(pos.x),
+                y: self.bottom_right.y.max(pos.y),
+            },
+        }
+    }
+
+    pub fn contains(&self, pos: &Position) -> bool {
+        (self.top_left.x..=self.bottom_right.x).contains(&pos.x)
+            && (self.top_left.y..=self.bottom_right.y).contains(&pos.y)
+    }
+
+    /// The positions within the rectangle, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (xmin, xmax) = (self.top_left.x, self.bottom_right.x);
+        (self.top_left.y..=self.bottom_right.y)
+            .flat_map(move |y| (xmin..=xmax).map(move |x| Position { x, y }))
+    }
+}
+
+/// The part of a picture which is shown on a terminal.  Pictures
+/// such as the day 15 map can be larger than the terminal and can
+/// extend in any direction, so the viewport can be panned, or can
+/// follow an interesting position (such as the droid) as it moves.
+///
+/// The size of a viewport is measured in characters of the display.
+/// When zoomed out, each character shows a square of `zoom` by
+/// `zoom` positions of the picture (see `quadrant_glyph`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    top_left: Position,
+    width: i64,
+    height: i64,
+    zoom: i64,
+}
+
+/// A character showing which quarters of a square are filled, for
+/// showing four positions of a picture in one character.
+pub fn quadrant_glyph(
+    top_left: bool,
+    top_right: bool,
+    bottom_left: bool,
+    bottom_right: bool,
+) -> char {
+    const GLYPHS: [char; 16] = [
+        ' ', '\u{2598}', '\u{259d}', '\u{2580}', '\u{2596}', '\u{258c}', '\u{259e}', '\u{259b}',
+        '\u{2597}', '\u{259a}', '\u{2590}', '\u{259c}', '\u{2584}', '\u{2599}', '\u{259f}',
+        '\u{2588}',
+    ];
+    let mask = usize::from(top_left)
+        | usize::from(top_right) << 1
+        | usize::from(bottom_left) << 2
+        | usize::from(bottom_right) << 3;
+    GLYPHS[mask]
+}
+
+/// The new start of a span of `size` cells beginning at `start`,
+/// scrolled as little as possible so that `at` is at least `margin`
+/// cells from either end.
+fn scroll(start: i64, size: i64, at: i64, margin: i64) -> i64 {
+    if size <= 0 {
+        return start;
+    }
+    let margin = margin.clamp(0, (size - 1) / 2);
+    if at < start + margin {
+        at - margin
+    } else if at > start + size - 1 - margin {
+        at - (size - 1 - margin)
+    } else {
+        start
+    }
+}
+
+impl Viewport {
+    /// A viewport `width` characters wide and `height` high whose
+    /// top-left corner shows `top_left`.
+    pub fn new(top_left: Position, width: usize, height: usize) -> Viewport {
+        Viewport {
+            top_left,
+            width: width as i64,
+            height: height as i64,
+            zoom: 1,
+        }
+    }
+
+    /// A viewport `width` characters wide and `height` high with
+    /// `centre` in the middle.
+    pub fn centred_on(centre: Position, width: usize, height: usize) -> Viewport {
+        let top_left = Position {
+            x: centre.x - width as i64 / 2,
+            y: centre.y - height as i64 / 2,
+        };
+        Viewport::new(top_left, width, height)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    pub fn top_left(&self) -> Position {
+        self.top_left
+    }
+
+    pub fn zoom(&self) -> usize {
+        self.zoom as usize
+    }
+
+    /// Show `zoom` by `zoom` positions in each character, keeping
+    /// the same position in the middle of the viewport.
+    pub fn set_zoom(&mut self, zoom: usize) {
+        let zoom = zoom.max(1) as i64;
+        let centre = Position {
+            x: self.top_left.x + self.width * self.zoom / 2,
+            y: self.top_left.y + self.height * self.zoom / 2,
+        };
+        self.zoom = zoom;
+        self.top_left = Position {
+            x: centre.x - self.width * zoom / 2,
+            y: centre.y - self.height * zoom / 2,
+        };
+    }
+
+    /// The positions shown, or `None` if the viewport is empty.
+    pub fn rect(&self) -> Option<Rect> {
+        if self.width > 0 && self.height > 0 {
+            Some(Rect {
+                top_left: self.top_left,
+                bottom_right: Position {
+                    x: self.top_left.x + self.width * self.zoom - 1,
+                    y: self.top_left.y + self.height * self.zoom - 1,
+                },
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Change the size of the viewport, for example to fit the
+    /// terminal after it has been resized.  Returns true if the size
+    /// changed.
+    pub fn resize(&mut self, width: usize, height: usize) -> bool {
+        let before = *self;
+        self.width = width as i64;
+        self.height = height as i64;
+        *self != before
+    }
+
+    /// Move the viewport `dx` cells right and `dy` cells down.
+    pub fn pan(&mut self, dx: i64, dy: i64) {
+        self.top_left.x += dx;
+        self.top_left.y += dy;
+    }
+
+    /// Scroll as little as possible to keep `pos` in view, at least
+    /// `margin` cells from the edge (or in the middle, if the
+    /// viewport is too small for that).  Returns true if the
+    /// viewport moved, in which case all of it needs to be redrawn.
+    pub fn follow(&mut self, pos: Position, margin: usize) -> bool {
+        let before = self.top_left;
+        let margin = margin as i64;
+        let (width, height) = (self.width * self.zoom, self.height * self.zoom);
+        self.top_left.x = scroll(self.top_left.x, width, pos.x, margin * self.zoom);
+        self.top_left.y = scroll(self.top_left.y, height, pos.y, margin * self.zoom);
+        self.top_left != before
+    }
+
+    /// Fit the viewport to a display `width` by `height` cells in
+    /// size, and follow `focus` (if given) as `follow` does.  Returns
+    /// true if the viewport changed, in which case all of it needs to
+    /// be redrawn.
+    pub fn update(
+        &mut self,
+        width: usize,
+        height: usize,
+        focus: Option<Position>,
+        margin: usize,
+    ) -> bool {
+        let resized = self.resize(width, height);
+        let moved = match focus {
+            Some(pos) => self.follow(pos, margin),
+            None => false,
+        };
+        resized || moved
+    }
+
+    /// The (row, column) at which `pos` is shown, counting from the
+    /// top-left corner of the viewport, or `None` if it is not shown.
+    pub fn to_screen(&self, pos: &Position) -> Option<(i32, i32)> {
+        if !self.rect()?.contains(pos) {
+            return None;
+        }
+        let row = i32::try_from((pos.y - self.top_left.y) / self.zoom).ok()?;
+        let column = i32::try_from((pos.x - self.top_left.x) / self.zoom).ok()?;
+        Some((row, column))
+    }
+
+    /// The positions shown by the character at (`row`, `column`),
+    /// row by row.  When the zoom is 2 these are the top-left,
+    /// top-right, bottom-left and bottom-right quarters of the
+    /// character, as `quadrant_glyph` expects.
+    pub fn cells_at(&self, row: usize, column: usize) -> Rect {
+        let top_left = Position {
+            x: self.top_left.x + column as i64 * self.zoom,
+            y: self.top_left.y + row as i64 * self.zoom,
+        };
+        Rect {
+            top_left,
+            bottom_right: Position {
+                x: top_left.x + self.zoom - 1,
+                y: top_left.y + self.zoom - 1,
+            },
+        }
+    }
+}
+
+/// The largest number of tiles a `Screen` may hold.
+const MAX_CELLS: usize = 1 << 20;
+
+/// A frame buffer of tiles, plus a score, which is updated by
+/// `DrawCommand`s.  Positions start at (0, 0) in the top-left
+/// corner and the buffer grows as needed, up to `MAX_CELLS` tiles.
+/// Tiles which have never been drawn have the value 0.
+///
+/// The screen keeps track of the region changed since the last
+/// call to `take_dirty`, so that a display only needs to redraw
+/// that region.
+#[derive(Debug, Clone, Default)]
+pub struct Screen {
+    width: usize,
+    height: usize,
+    cells: Vec<i64>,
+    score: Option<i64>,
+    dirty: Option<Rect>,
+    score_dirty: bool,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen::default()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn score(&self) -> Option<i64> {
+        self.score
+    }
+
+    fn index(&self, pos: &Position) -> Option<usize> {
+        match (usize::try_from(pos.x), usize::try_from(pos.y)) {
+            (Ok(x), Ok(y)) if x < self.width && y < self.height => Some(y * self.width + x),
+            _ => None,
+        }
+    }
+
+    /// Make the screen big enough to include (`x`, `y`).  Returns
+    /// false, leaving the screen as it was, if it would then hold
+    /// more than `MAX_CELLS` tiles.
+    fn grow_to_include(&mut self, x: usize, y: usize) -> bool {
+        let new_width = self.width.max(x.saturating_add(1));
+        let new_height = self.height.max(y.saturating_add(1));
+        match new_width.checked_mul(new_height) {
+            Some(size) if size <= MAX_CELLS => (),
+            _ => {
+                return false;
+            }
+        }
+        if new_width != self.width || new_height != self.height {
+            let mut cells = vec![0; new_width * new_height];
+            for row in 0..self.height {
+                let old = &self.cells[row * self.width..(row + 1) * self.width];
+                cells[row * new_width..row * new_width + self.width].copy_from_slice(old);
+            }
+            self.cells = cells;
+            self.width = new_width;
+            self.height = new_height;
+        }
+        true
+    }
+
+    /// The tile at `pos`; 0 if nothing has been drawn there.
+    pub fn get(&self, pos: &Position) -> i64 {
+        self.index(pos).map(|i| self.cells[i]).unwrap_or(0)
+    }
+
+    pub fn draw(&mut self, pos: Position, tile: i64) -> Result<(), OffScreen> {
+        let (x, y) = match (usize::try_from(pos.x), usize::try_from(pos.y)) {
+            (Ok(x), Ok(y)) => (x, y),
+            _ => {
+                return Err(OffScreen(pos));
+            }
+        };
+        if !self.grow_to_include(x, y) {
+            return Err(OffScreen(pos));
+        }
+        let i = y * self.width + x;
+        if self.cells[i] != tile {
+            self.cells[i] = tile;
+            self.dirty = Some(match self.dirty {
+                None => Rect::single(pos),
+                Some(r) => r.including(pos),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn set_score(&mut self, score: i64) {
+        if self.score != Some(score) {
+            self.score = Some(score);
+            self.score_dirty = true;
+        }
+    }
+
+    pub fn apply(&mut self, command: DrawCommand) -> Result<(), OffScreen> {
+        match command {
+            DrawCommand::DrawTile { pos, tile } => self.draw(pos, tile),
+            DrawCommand::UpdateScore(score) => {
+                self.set_score(score);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the region which has changed since the last call,
+    /// and marks the whole screen as clean.
+    pub fn take_dirty(&mut self) -> Option<Rect> {
+        self.dirty.take()
+    }
+
+    /// Returns true if the score has changed since the last call.
+    pub fn take_score_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.score_dirty, false)
+    }
+
+    /// The number of cells containing `tile`.
+    pub fn count(&self, tile: i64) -> usize {
+        self.cells.iter().filter(|t| **t == tile).count()
+    }
+
+    /// The position of the first cell (in row order) containing `tile`.
+    pub fn find(&self, tile: i64) -> Option<Position> {
+        self.cells
+            .iter()
+            .position(|t| *t == tile)
+            .map(|i| Position {
+                x: (i % self.width) as i64,
+                y: (i / self.width) as i64,
+            })
+    }
+
+    /// Render the screen as text, one line per row.
+    pub fn render<F>(&self, symbol: F) -> String
+    where
+        F: Fn(i64) -> char,
+    {
+        let mut result = String::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.chunks(self.width.max(1)).take(self.height) {
+            result.extend(row.iter().map(|t| symbol(*t)));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// Saves the screen contents and score as text, which `FromStr`
+/// can read back.
+impl Display for Screen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "size {} {}", self.width, self.height)?;
+        match self.score {
+            Some(score) => writeln!(f, "score {}", score)?,
+            None => writeln!(f, "score none")?,
+        }
+        for row in self.cells.chunks(self.width.max(1)).take(self.height) {
+            let tiles: Vec<String> = row.iter().map(|t| t.to_string()).collect();
+            writeln!(f, "row {}", tiles.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Saved screen contents could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadScreenState(pub String);
+
+impl Display for BadScreenState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bad saved screen: {}", self.0)
+    }
+}
+
+impl std::error::Error for BadScreenState {}
+
+impl From<BadScreenState> for Fail {
+    fn from(e: BadScreenState) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+fn parse_number<T: FromStr>(what: &str, s: &str) -> Result<T, BadScreenState>
+where
+    T::Err: Display,
+{
+    s.trim()
+        .parse()
+        .map_err(|e| BadScreenState(format!("bad {} '{}': {}", what, s, e)))
+}
+
+/// Reads a screen saved with `Display`.  The whole of the restored
+/// screen (and its score) is marked as needing to be redrawn.
+impl FromStr for Screen {
+    type Err = BadScreenState;
+
+    fn from_str(s: &str) -> Result<Screen, BadScreenState> {
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+        let (width, height): (usize, usize) = match lines
+            .next()
+            .and_then(|line| line.strip_prefix("size "))
+            .and_then(|size| size.split_once(' '))
+        {
+            Some((w, h)) => (parse_number("width", w)?, parse_number("height", h)?),
+            None => {
+                return Err(BadScreenState("size is missing".to_string()));
+            }
+        };
+        let score: Option<i64> = match lines.next().and_then(|line| line.strip_prefix("score ")) {
+            Some("none") => None,
+            Some(n) => Some(parse_number("score", n)?),
+            None => {
+                return Err(BadScreenState("score is missing".to_string()));
+            }
+        };
+        // An empty screen is saved as "size 0 0", but no other
+        // screen has a zero width or height.
+        let size = match width.checked_mul(height) {
+            Some(size) if size <= MAX_CELLS && (width == 0) == (height == 0) => size,
+            _ => {
+                return Err(BadScreenState(format!(
+                    "a screen cannot be {} by {}",
+                    width, height
+                )));
+            }
+        };
+        let mut cells: Vec<i64> = Vec::new();
+        for line in lines {
+            match line.strip_prefix("row ") {
+                Some(row) => {
+                    let before = cells.len();
+                    for tile in row.split(',') {
+                        cells.push(parse_number("tile", tile)?);
+                    }
+                    if cells.len() - before != width {
+                        return Err(BadScreenState(format!(
+                            "row {} should have {} tiles but has {}",
+                            before / width.max(1),
+                            width,
+                            cells.len() - before
+                        )));
+                    }
+                }
+                None => {
+                    return Err(BadScreenState(format!("unexpected line '{}'", line)));
+                }
+            }
+        }
+        if cells.len() != size {
+            return Err(BadScreenState(format!(
+                "expected {} rows, got {}",
+                height,
+                cells.len() / width.max(1)
+            )));
+        }
+        let dirty = if width > 0 && height > 0 {
+            Some(Rect {
+                top_left: Position { x: 0, y: 0 },
+                bottom_right: Position {
+                    x: width as i64 - 1,
+                    y: height as i64 - 1,
+                },
+            })
+        } else {
+            None
+        };
+        Ok(Screen {
+            width,
+            height,
+            cells,
+            score,
+            dirty,
+            score_dirty: true,
+        })
+    }
+}
+
+#[test]
+fn test_draw_command_from_words() {
+    assert_eq!(
+        DrawCommand::try_from([Word(-1), Word(0), Word(12)]),
+        Ok(DrawCommand::UpdateScore(12))
+    );
+    assert_eq!(
+        DrawCommand::try_from([Word(1), Word(2), Word(3)]),
+        Ok(DrawCommand::DrawTile {
+            pos: Position { x: 1, y: 2 },
+            tile: 3
+        })
+    );
+}
+
+#[cfg(feature = "wide-words")]
+#[test]
+fn test_draw_command_from_wide_words() {
+    let wide = Word(crate::cpu::WordInt::from(i64::MAX) + 1);
+    assert_eq!(wide.as_i64(), None);
+    assert_eq!(
+        DrawCommand::try_from([wide, Word(0), Word(1)]),
+        Err(InputOutputError::OffScreen {
+            x: wide,
+            y: Word(0)
+        })
+    );
+    assert_eq!(
+        DrawCommand::try_from([Word(1), Word(0), wide]),
+        Err(InputOutputError::Rejected(wide))
+    );
+    assert_eq!(
+        DrawCommand::try_from([Word(-1), Word(0), wide]),
+        Err(InputOutputError::Rejected(wide))
+    );
+}
+
+#[test]
+fn test_screen_draw_and_grow() {
+    let mut screen = Screen::new();
+    screen
+        .draw(Position { x: 2, y: 0 }, 1)
+        .expect("should be on screen");
+    screen
+        .draw(Position { x: 0, y: 1 }, 4)
+        .expect("should be on screen");
+    assert_eq!(screen.width(), 3);
+    assert_eq!(screen.height(), 2);
+    assert_eq!(screen.get(&Position { x: 2, y: 0 }), 1);
+    assert_eq!(screen.get(&Position { x: 0, y: 1 }), 4);
+    assert_eq!(screen.get(&Position { x: 9, y: 9 }), 0);
+    assert_eq!(screen.count(1), 1);
+    assert_eq!(screen.find(4), Some(Position { x: 0, y: 1 }));
+    let symbol = |t: i64| match t {
+        0 => '.',
+        1 => '#',
+        _ => 'o',
+    };
+    assert_eq!(screen.render(symbol), "..#\no..\n");
+    assert_eq!(
+        screen.draw(Position { x: -1, y: 0 }, 1),
+        Err(OffScreen(Position { x: -1, y: 0 }))
+    );
+    let far = Position {
+        x: 1_000_000_000_000,
+        y: 0,
+    };
+    assert_eq!(screen.draw(far, 1), Err(OffScreen(far)));
+    let far = Position { x: 2000, y: 2000 };
+    assert_eq!(screen.draw(far, 1), Err(OffScreen(far)));
+    assert_eq!(screen.width(), 3);
+    assert_eq!(screen.height(), 2);
+}
+
+#[test]
+fn test_screen_dirty_tracking() {
+    let mut screen = Screen::new();
+    assert_eq!(screen.take_dirty(), None);
+    screen.draw(Position { x: 3, y: 1 }, 2).expect("on screen");
+    screen.draw(Position { x: 1, y: 4 }, 2).expect("on screen");
+    let dirty = screen.take_dirty().expect("screen should be dirty");
+    assert_eq!(
+        dirty,
+        Rect {
+            top_left: Position { x: 1, y: 1 },
+            bottom_right: Position { x: 3, y: 4 },
+        }
+    );
+    assert_eq!(dirty.positions().count(), 12);
+    assert_eq!(screen.take_dirty(), None);
+    // Drawing the same tile again does not dirty the screen.
+    screen.draw(Position { x: 3, y: 1 }, 2).expect("on screen");
+    assert_eq!(screen.take_dirty(), None);
+
+    assert!(!screen.take_score_dirty());
+    screen
+        .apply(DrawCommand::UpdateScore(7))
+        .expect("on screen");
+    assert_eq!(screen.score(), Some(7));
+    assert!(screen.take_score_dirty());
+    assert!(!screen.take_score_dirty());
+}
+
+#[test]
+fn test_screen_save_and_restore() {
+    let mut screen = Screen::new();
+    screen.draw(Position { x: 2, y: 1 }, 3).expect("on screen");
+    screen.set_score(42);
+    let saved = screen.to_string();
+    assert_eq!(saved, "size 3 2\nscore 42\nrow 0,0,0\nrow 0,0,3\n");
+    let mut restored: Screen = saved.parse().expect("saved screen should parse");
+    assert_eq!(restored.get(&Position { x: 2, y: 1 }), 3);
+    assert_eq!(restored.score(), Some(42));
+    assert_eq!(
+        restored.take_dirty().map(|r| r.bottom_right),
+        Some(Position { x: 2, y: 1 })
+    );
+    assert!(restored.take_score_dirty());
+    assert!("size 3 2\nscore none\nrow 0,0\n".parse::<Screen>().is_err());
+    let empty: Screen = Screen::new()
+        .to_string()
+        .parse()
+        .expect("empty screen should parse");
+    assert_eq!(empty.width(), 0);
+}
+
+#[test]
+fn test_screen_restore_rejects_bad_sizes() {
+    for size in ["18446744073709551615 2", "100000 100000", "0 5", "3 0"] {
+        let saved = format!("size {}\nscore none\n", size);
+        assert!(
+            matches!(saved.parse::<Screen>(), Err(BadScreenState(_))),
+            "size {} should be rejected",
+            size
+        );
+    }
+}
+
+#[test]
+fn test_viewport_follow() {
+    let mut view = Viewport::centred_on(Position { x: 0, y: 0 }, 10, 5);
+    assert_eq!(view.top_left(), Position { x: -5, y: -2 });
+    assert_eq!(view.to_screen(&Position { x: 0, y: 0 }), Some((2, 5)));
+    assert_eq!(view.to_screen(&Position { x: 5, y: 0 }), None);
+    // Already far enough from the edge.
+    assert!(!view.follow(Position { x: 1, y: 0 }, 2));
+    // Moving right scrolls just enough to keep the margin.
+    assert!(view.follow(Position { x: 6, y: 0 }, 2));
+    assert_eq!(view.top_left(), Position { x: -1, y: -2 });
+    assert_eq!(view.to_screen(&Position { x: 6, y: 0 }), Some((2, 7)));
+    // A margin too big for the viewport centres the position.
+    assert!(view.follow(Position { x: 6, y: 10 }, 100));
+    assert_eq!(view.to_screen(&Position { x: 6, y: 10 }), Some((2, 5)));
+    view.pan(-3, 1);
+    assert_eq!(view.top_left(), Position { x: -2, y: 9 });
+    assert!(!view.update(10, 5, Some(Position { x: 0, y: 11 }), 2));
+    assert!(view.update(10, 5, Some(Position { x: 20, y: 11 }), 2));
+    assert!(view.update(12, 5, None, 2));
+}
+
+#[test]
+fn test_viewport_resize() {
+    let mut view = Viewport::new(Position { x: 0, y: 0 }, 0, 0);
+    assert_eq!(view.rect(), None);
+    assert_eq!(view.to_screen(&Position { x: 0, y: 0 }), None);
+    assert!(!view.follow(Position { x: 50, y: 50 }, 1));
+    assert!(view.resize(3, 2));
+    assert!(!view.resize(3, 2));
+    assert_eq!(view.rect().map(|r| r.positions().count()), Some(6));
+}
+
+#[test]
+fn test_viewport_zoom() {
+    let mut view = Viewport::centred_on(Position { x: 0, y: 0 }, 4, 2);
+    assert_eq!(view.top_left(), Position { x: -2, y: -1 });
+    view.set_zoom(2);
+    assert_eq!(view.zoom(), 2);
+    assert_eq!(view.top_left(), Position { x: -4, y: -2 });
+    assert_eq!(
+        view.rect().map(|r| r.bottom_right),
+        Some(Position { x: 3, y: 1 })
+    );
+    assert_eq!(view.to_screen(&Position { x: 1, y: 1 }), Some((1, 2)));
+    let cells: Vec<Position> = view.cells_at(1, 2).positions().collect();
+    assert_eq!(
+        cells,
+        vec![
+            Position { x: 0, y: 0 },
+            Position { x: 1, y: 0 },
+            Position { x: 0, y: 1 },
+            Position { x: 1, y: 1 },
+        ]
+    );
+    view.set_zoom(1);
+    assert_eq!(view.top_left(), Position { x: -2, y: -1 });
+    assert_eq!(quadrant_glyph(false, false, false, false), ' ');
+    assert_eq!(quadrant_glyph(true, true, false, false), '\u{2580}');
+    assert_eq!(quadrant_glyph(true, true, true, true), '\u{2588}');
+}
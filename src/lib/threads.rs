@@ -0,0 +1,61 @@
+//! How many threads the solutions which work in parallel may use.
+//! This is set once, from the `--threads` option or the
+//! `AOC_THREADS` environment variable; a count of 1 makes every
+//! solution run serially, which gives reproducible results.
+use std::sync::OnceLock;
+use std::thread;
+
+/// The environment variable which sets the thread count when
+/// `--threads` is not given.
+pub const THREADS_ENV_VAR: &str = "AOC_THREADS";
+
+static THREAD_COUNT: OnceLock<usize> = OnceLock::new();
+
+/// Use at most `n` threads for parallel work.  This also sizes the
+/// rayon thread pool, if the `rayon` feature is enabled, so it must
+/// be called before any parallel work starts.
+pub fn set_thread_count(n: usize) -> Result<(), String> {
+    if n == 0 {
+        return Err("the thread count must be positive".to_string());
+    }
+    THREAD_COUNT
+        .set(n)
+        .map_err(|_| "the thread count was already set".to_string())?;
+    #[cfg(feature = "rayon")]
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(n)
+        .build_global()
+        .map_err(|e| format!("failed to start {} threads: {}", n, e))?;
+    Ok(())
+}
+
+/// Set the thread count from `THREADS_ENV_VAR`, if it is set.
+pub fn set_thread_count_from_env() -> Result<(), String> {
+    match std::env::var(THREADS_ENV_VAR) {
+        Ok(value) => match value.trim().parse::<usize>() {
+            Ok(n) => set_thread_count(n),
+            Err(e) => Err(format!(
+                "bad value '{}' for {}: {}",
+                value, THREADS_ENV_VAR, e
+            )),
+        },
+        Err(_) => Ok(()),
+    }
+}
+
+/// The number of threads parallel work should be divided between:
+/// the count which was set, or else the number of CPUs.
+pub fn thread_count() -> usize {
+    match THREAD_COUNT.get() {
+        Some(n) => *n,
+        None => thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+    }
+}
+
+#[test]
+fn test_thread_count() {
+    assert!(thread_count() >= 1);
+    assert!(set_thread_count(0).is_err());
+}
@@ -0,0 +1,96 @@
+//! Measuring the resources used by a solution: the time it takes
+//! and, on Linux with the `rss` feature, the most memory it used.
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// The resources used by a piece of work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub elapsed: Duration,
+    /// The peak resident set size of the process, in bytes, while
+    /// the work was being done.  This is `None` where it cannot be
+    /// measured.
+    pub peak_rss: Option<u64>,
+}
+
+impl Display for Usage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3}s", self.elapsed.as_secs_f64())?;
+        if let Some(bytes) = self.peak_rss {
+            write!(f, ", peak RSS {:.1} MiB", bytes as f64 / (1024.0 * 1024.0))?;
+        }
+        Ok(())
+    }
+}
+
+/// Extract the peak resident set size, in bytes, from the content of
+/// `/proc/self/status`.
+#[cfg_attr(not(all(feature = "rss", target_os = "linux")), allow(dead_code))]
+fn parse_peak_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// The peak resident set size of this process, in bytes, since it
+/// started or since the last successful call to `reset_peak_rss`.
+#[cfg(all(feature = "rss", target_os = "linux"))]
+pub fn peak_rss() -> Option<u64> {
+    parse_peak_rss(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+#[cfg(not(all(feature = "rss", target_os = "linux")))]
+pub fn peak_rss() -> Option<u64> {
+    None
+}
+
+/// Start measuring the peak resident set size afresh, from the
+/// current size.  Returns false if this is not possible, in which
+/// case `peak_rss` gives the peak since the process started.
+#[cfg(all(feature = "rss", target_os = "linux"))]
+pub fn reset_peak_rss() -> bool {
+    // See proc(5): writing 5 to clear_refs resets the peak RSS.
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(all(feature = "rss", target_os = "linux")))]
+pub fn reset_peak_rss() -> bool {
+    false
+}
+
+/// Call `f` and return its result, with the time it took and (where
+/// this can be measured) the peak memory use while it ran.  Memory
+/// is measured for the whole process, so work done at the same time
+/// by other threads is included.
+pub fn measure<T, F: FnOnce() -> T>(f: F) -> (T, Usage) {
+    let reset = reset_peak_rss();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let usage = Usage {
+        elapsed,
+        peak_rss: if reset { peak_rss() } else { None },
+    };
+    (result, usage)
+}
+
+#[test]
+fn test_parse_peak_rss() {
+    let status = "Name:\tday16\nVmPeak:\t  20000 kB\nVmHWM:\t    1234 kB\nVmRSS:\t    1000 kB\n";
+    assert_eq!(parse_peak_rss(status), Some(1234 * 1024));
+    assert_eq!(parse_peak_rss("Name:\tday16\n"), None);
+}
+
+#[test]
+fn test_measure() {
+    let (answer, usage) = measure(|| 6 * 7);
+    assert_eq!(answer, 42);
+    let shown = usage.to_string();
+    assert!(shown.ends_with('s') || shown.contains("peak RSS"));
+}
@@ -0,0 +1,55 @@
+//! How much the programs say besides their answers.  This is set
+//! once, from the `-q` and `-v` options, and consulted by the
+//! `verbose!` and `warning!` macros.
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Verbosity {
+    /// Show only the answers.
+    Quiet,
+    /// Show the answers and any warnings.
+    Normal,
+    /// Also show how the answers were reached.
+    Verbose,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(level: Verbosity) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// Prints a diagnostic on standard error, if `-v` was given.
+#[macro_export]
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::verbosity::verbosity() >= $crate::verbosity::Verbosity::Verbose {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Prints a warning on standard error, unless `-q` was given.
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if $crate::verbosity::verbosity() >= $crate::verbosity::Verbosity::Normal {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+#[test]
+fn test_levels_are_ordered() {
+    assert!(Verbosity::Quiet < Verbosity::Normal);
+    assert!(Verbosity::Normal < Verbosity::Verbose);
+    assert_eq!(verbosity(), Verbosity::Normal);
+}